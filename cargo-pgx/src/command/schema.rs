@@ -340,6 +340,8 @@ pub(crate) fn generate_schema(
     let mut num_ords = 0_usize;
     let mut num_hashes = 0_usize;
     let mut num_aggregates = 0_usize;
+    let mut num_ts_parsers = 0_usize;
+    let mut num_ts_dictionaries = 0_usize;
     for func in &fns_to_call {
         if func.starts_with("__pgx_internals_schema_") {
             let schema = func
@@ -364,11 +366,15 @@ pub(crate) fn generate_schema(
             num_hashes += 1;
         } else if func.starts_with("__pgx_internals_aggregate_") {
             num_aggregates += 1;
+        } else if func.starts_with("__pgx_internals_ts_parser_") {
+            num_ts_parsers += 1;
+        } else if func.starts_with("__pgx_internals_ts_dictionary_") {
+            num_ts_dictionaries += 1;
         }
     }
 
     eprintln!(
-        "{} {} SQL entities: {} schemas ({} unique), {} functions, {} types, {} enums, {} sqls, {} ords, {} hashes, {} aggregates, {} triggers",
+        "{} {} SQL entities: {} schemas ({} unique), {} functions, {} types, {} enums, {} sqls, {} ords, {} hashes, {} aggregates, {} triggers, {} text search parsers, {} text search dictionaries",
         "  Discovered".bold().green(),
         fns_to_call.len().to_string().bold().cyan(),
         seen_schemas.iter().count().to_string().bold().cyan(),
@@ -381,6 +387,8 @@ pub(crate) fn generate_schema(
         num_hashes.to_string().bold().cyan(),
         num_aggregates.to_string().bold().cyan(),
         num_triggers.to_string().bold().cyan(),
+        num_ts_parsers.to_string().bold().cyan(),
+        num_ts_dictionaries.to_string().bold().cyan(),
     );
 
     tracing::debug!("Collecting {} SQL entities", fns_to_call.len());
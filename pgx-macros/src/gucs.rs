@@ -0,0 +1,301 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, Fields, GenericArgument, Lit, Meta, PathArguments, Token,
+    Type,
+};
+
+/// One `key = value` argument of an attribute, like `#[guc(...)]` or `#[pg_error(...)]`
+pub(crate) struct AttrArg {
+    pub(crate) key: Ident,
+    pub(crate) value: Expr,
+}
+
+impl Parse for AttrArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse()?;
+        input.parse::<Token![=]>()?;
+        Ok(AttrArg { key, value: input.parse()? })
+    }
+}
+
+pub(crate) fn parse_args(attrs: &[Attribute], name: &str) -> syn::Result<Vec<AttrArg>> {
+    let mut args = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident(name)) {
+        args.extend(attr.parse_args_with(Punctuated::<AttrArg, Token![,]>::parse_terminated)?);
+    }
+    Ok(args)
+}
+
+pub(crate) fn expect_str(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) => Ok(s.value()),
+        _ => Err(syn::Error::new(expr.span(), "expected a string literal")),
+    }
+}
+
+/// Qualify a bare identifier, like `Userset`, with `within`
+pub(crate) fn qualify(expr: &Expr, within: &TokenStream) -> TokenStream {
+    match expr {
+        Expr::Path(p) if p.qself.is_none() && p.path.segments.len() == 1 => {
+            quote! { #within::#p }
+        }
+        Expr::Binary(b) => {
+            let (left, op, right) = (qualify(&b.left, within), &b.op, qualify(&b.right, within));
+            quote! { #left #op #right }
+        }
+        Expr::Paren(p) => {
+            let inner = qualify(&p.expr, within);
+            quote! { (#inner) }
+        }
+        other => quote! { #other },
+    }
+}
+
+/// The first line of a field's doc comment, and the rest
+fn doc_comment(attrs: &[Attribute]) -> (Option<String>, Option<String>) {
+    let lines = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(syn::MetaNameValue { lit: Lit::Str(s), .. })) => {
+                Some(s.value().trim().to_string())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut lines = lines.into_iter();
+    let short = lines.next();
+    let long = lines.collect::<Vec<_>>().join(" ").trim().to_string();
+    (short, (!long.is_empty()).then_some(long))
+}
+
+enum GucKind {
+    Bool,
+    Int,
+    Float,
+    String,
+    Enum,
+}
+
+/// Types that could otherwise be mistaken for a `#[derive(PostgresGucEnum)]` enum
+const NOT_GUC_ENUMS: &[&str] = &[
+    "char", "str", "String", "Option", "i8", "i16", "i64", "i128", "isize", "u8", "u16", "u32",
+    "u64", "u128", "usize", "f32",
+];
+
+fn guc_kind(ty: &Type) -> syn::Result<GucKind> {
+    let unsupported = || {
+        syn::Error::new(
+            ty.span(),
+            "GUC fields must be `bool`, `i32`, `f64`, `Option<String>`, or a `#[derive(PostgresGucEnum)]` enum",
+        )
+    };
+    let segment = match ty {
+        Type::Path(p) if p.qself.is_none() => p.path.segments.last().ok_or_else(unsupported)?,
+        _ => return Err(unsupported()),
+    };
+    let is_string = |ty: &Type| match ty {
+        Type::Path(p) if p.qself.is_none() => {
+            p.path.segments.last().map_or(false, |s| s.ident == "String" && s.arguments.is_empty())
+        }
+        _ => false,
+    };
+
+    match (segment.ident.to_string().as_str(), &segment.arguments) {
+        ("bool", PathArguments::None) => Ok(GucKind::Bool),
+        ("i32", PathArguments::None) => Ok(GucKind::Int),
+        ("f64", PathArguments::None) => Ok(GucKind::Float),
+        ("Option", PathArguments::AngleBracketed(args))
+            if args.args.len() == 1
+                && matches!(args.args.first(), Some(GenericArgument::Type(inner)) if is_string(inner)) =>
+        {
+            Ok(GucKind::String)
+        }
+        (name, PathArguments::None) if !NOT_GUC_ENUMS.contains(&name) => Ok(GucKind::Enum),
+        _ => Err(unsupported()),
+    }
+}
+
+pub(crate) fn impl_postgres_gucs(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &ast.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    ast.span(),
+                    "#[derive(PostgresGucs)] requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "#[derive(PostgresGucs)] can only be applied to structs",
+            ))
+        }
+    };
+
+    let mut prefix = None;
+    for arg in parse_args(&ast.attrs, "gucs")? {
+        match arg.key.to_string().as_str() {
+            "prefix" => prefix = Some(expect_str(&arg.value)?),
+            _ => return Err(syn::Error::new(arg.key.span(), "unknown `gucs` argument")),
+        }
+    }
+    let prefix = prefix.ok_or_else(|| {
+        syn::Error::new(ast.span(), "#[derive(PostgresGucs)] requires `#[gucs(prefix = \"...\")]`")
+    })?;
+
+    let struct_name = &ast.ident;
+    let guc_context = quote! { ::pgx::guc::GucContext };
+    let guc_flags = quote! { ::pgx::guc::GucFlags };
+
+    let mut statics = TokenStream::new();
+    let mut defines = TokenStream::new();
+    let mut getters = TokenStream::new();
+
+    for field in fields {
+        let field_name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let setting = format_ident!("__PGX_GUC_{}", field_name.to_string().to_uppercase());
+        let (doc_short, doc_long) = doc_comment(&field.attrs);
+
+        let mut name = field_name.to_string();
+        let mut short_description = doc_short;
+        let mut long_description = doc_long;
+        let mut context = quote! { #guc_context::Userset };
+        let mut flags = quote! { #guc_flags::empty() };
+        let mut min = None;
+        let mut max = None;
+        let mut default = None;
+
+        for arg in parse_args(&field.attrs, "guc")? {
+            let value = &arg.value;
+            match arg.key.to_string().as_str() {
+                "name" => name = expect_str(value)?,
+                "short_description" => short_description = Some(expect_str(value)?),
+                "long_description" => long_description = Some(expect_str(value)?),
+                "context" => context = qualify(value, &guc_context),
+                "flags" => flags = qualify(value, &guc_flags),
+                "min" => min = Some(quote! { #value }),
+                "max" => max = Some(quote! { #value }),
+                "default" => default = Some(value.clone()),
+                _ => return Err(syn::Error::new(arg.key.span(), "unknown `guc` argument")),
+            }
+        }
+
+        let full_name = format!("{}.{}", prefix, name);
+        let short_description = short_description.unwrap_or_default();
+        let long_description = long_description.unwrap_or_default();
+        let hooks = quote! { ::pgx::guc::GucHooks::default() };
+
+        let (setting_ty, boot_value, define) = match guc_kind(ty)? {
+            GucKind::Bool => (
+                quote! { bool },
+                default.map_or(quote! { false }, |d| quote! { #d }),
+                quote! {
+                    ::pgx::guc::GucRegistry::define_bool_guc_with_hooks(
+                        #full_name, #short_description, #long_description, &#setting,
+                        #context, #flags, #hooks,
+                    );
+                },
+            ),
+            GucKind::Int => {
+                let min = min.unwrap_or(quote! { i32::MIN });
+                let max = max.unwrap_or(quote! { i32::MAX });
+                (
+                    quote! { i32 },
+                    default.map_or(quote! { 0 }, |d| quote! { #d }),
+                    quote! {
+                        ::pgx::guc::GucRegistry::define_int_guc_with_hooks(
+                            #full_name, #short_description, #long_description, &#setting,
+                            #min, #max, #context, #flags, #hooks,
+                        );
+                    },
+                )
+            }
+            GucKind::Float => {
+                let min = min.unwrap_or(quote! { f64::MIN });
+                let max = max.unwrap_or(quote! { f64::MAX });
+                (
+                    quote! { f64 },
+                    default.map_or(quote! { 0.0 }, |d| quote! { #d }),
+                    quote! {
+                        ::pgx::guc::GucRegistry::define_float_guc_with_hooks(
+                            #full_name, #short_description, #long_description, &#setting,
+                            #min, #max, #context, #flags, #hooks,
+                        );
+                    },
+                )
+            }
+            GucKind::String => (
+                quote! { Option<&'static str> },
+                default.map_or(quote! { None }, |d| quote! { Some(#d) }),
+                quote! {
+                    ::pgx::guc::GucRegistry::define_string_guc_with_hooks(
+                        #full_name, #short_description, #long_description, &#setting,
+                        #context, #flags, #hooks,
+                    );
+                },
+            ),
+            GucKind::Enum => {
+                let default = default.ok_or_else(|| {
+                    syn::Error::new(field.span(), "enum GUCs require `#[guc(default = ...)]`")
+                })?;
+                (
+                    quote! { #ty },
+                    qualify(&default, &quote! { #ty }),
+                    // anything else that made it this far must be an enum, so say so at the field
+                    quote_spanned! {ty.span()=>
+                        {
+                            fn assert_guc_enum<T: ::pgx::guc::GucEnum<T> + Copy>() {}
+                            assert_guc_enum::<#ty>();
+                        }
+                        ::pgx::guc::GucRegistry::define_enum_guc_with_hooks(
+                            #full_name, #short_description, #long_description, &#setting,
+                            #context, #flags, #hooks,
+                        );
+                    },
+                )
+            }
+        };
+
+        statics.extend(quote! {
+            static #setting: ::pgx::guc::GucSetting<#setting_ty> =
+                ::pgx::guc::GucSetting::<#setting_ty>::new(#boot_value);
+        });
+        defines.extend(define);
+        getters.extend(quote! { #field_name: #setting.get(), });
+    }
+
+    Ok(quote! {
+        const _: () = {
+            #statics
+
+            impl ::pgx::guc::GucGroup for #struct_name {
+                const PREFIX: &'static str = #prefix;
+
+                fn register() {
+                    #defines
+                    ::pgx::guc::GucRegistry::reserve_prefix(#prefix);
+                }
+
+                fn get() -> Self {
+                    #struct_name { #getters }
+                }
+            }
+        };
+    })
+}
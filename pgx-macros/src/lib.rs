@@ -17,15 +17,19 @@ use quote::{quote, ToTokens};
 use syn::spanned::Spanned;
 use syn::{parse_macro_input, Attribute, Data, DeriveInput, Item, ItemImpl};
 
+use gucs::impl_postgres_gucs;
 use operators::{impl_postgres_eq, impl_postgres_hash, impl_postgres_ord};
+use pg_error::impl_to_pg_error;
 use pgx_sql_entity_graph::{
     parse_extern_attributes, CodeEnrichment, ExtensionSql, ExtensionSqlFile, ExternArgs,
-    PgAggregate, PgExtern, PostgresEnum, PostgresType, Schema,
+    PgAggregate, PgExtern, PgTsDictionary, PgTsParser, PostgresEnum, PostgresType, Schema,
 };
 
 use crate::rewriter::PgGuardRewriter;
 
+mod gucs;
 mod operators;
+mod pg_error;
 mod rewriter;
 
 /// Declare a function as `#[pg_guard]` to indicate that it is called from a Postgres `extern "C"`
@@ -839,6 +843,87 @@ fn impl_postgres_type(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream>
     Ok(stream)
 }
 
+/**
+Generate a [`GucGroup`](pgx::guc::GucGroup) for a struct, defining one GUC per field under a common prefix.
+
+Fields may be `bool`, `i32`, `f64`, `Option<String>`, or a `#[derive(PostgresGucEnum)]` enum.
+Each field's GUC is named `prefix.field` and described by the field's doc comment, and can be
+customized with `#[guc(...)]`:
+
+* `name = "..."`: the GUC's name, after the prefix
+* `short_description = "..."`, `long_description = "..."`
+* `context = Suset`: a [`GucContext`](pgx::guc::GucContext), defaulting to `Userset`
+* `min = ...`, `max = ...`: bounds for `i32` and `f64` fields
+* `flags = UNIT_MS | REPORT`: [`GucFlags`](pgx::guc::GucFlags)
+* `default = ...`: the boot value.  Required for enums, where it's a variant name
+
+```rust,ignore
+use pgx::guc::*;
+
+#[derive(PostgresGucs)]
+#[gucs(prefix = "myext")]
+struct Config {
+    /// Enable the extension
+    enabled: bool,
+    /// How long to wait for a response
+    #[guc(min = 0, max = 60_000, default = 5_000, flags = UNIT_MS)]
+    timeout: i32,
+}
+
+#[pg_guard]
+pub extern "C" fn _PG_init() {
+    Config::register();
+}
+```
+*/
+#[proc_macro_derive(PostgresGucs, attributes(gucs, guc))]
+pub fn postgres_gucs(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    impl_postgres_gucs(ast).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/**
+Generate a [`ToPgError`](pgx::pg_sys::panic::ToPgError) implementation for an enum, so each variant
+can choose the SQLSTATE, level, detail, and hint it's raised with when returned from a
+`#[pg_extern]` function.  The enum must also implement `Display`, which provides the message.
+
+Variants are configured with `#[pg_error(...)]`, and settings on the enum itself apply to every
+variant that doesn't override them:
+
+* `code = ERRCODE_...`: a [`PgSqlErrorCode`](pgx::pg_sys::errcodes::PgSqlErrorCode), defaulting to
+  `ERRCODE_DATA_EXCEPTION`
+* `level = FATAL`: a [`PgLogLevel`](pgx::pg_sys::elog::PgLogLevel), defaulting to `ERROR`
+* `detail = "..."`, `hint = "..."`
+
+```rust,ignore
+use pgx::prelude::*;
+
+#[derive(thiserror::Error, ToPgError, Debug)]
+#[pg_error(code = ERRCODE_DATA_EXCEPTION)]
+enum AccountError {
+    #[error("account {0} does not exist")]
+    #[pg_error(code = ERRCODE_NO_DATA_FOUND)]
+    NotFound(i64),
+
+    #[error("insufficient funds")]
+    #[pg_error(code = ERRCODE_CHECK_VIOLATION, hint = "deposit more money")]
+    InsufficientFunds,
+}
+
+#[pg_extern]
+fn withdraw(account: i64, amount: i64) -> Result<i64, AccountError> {
+    Err(AccountError::InsufficientFunds)
+}
+```
+*/
+#[proc_macro_derive(ToPgError, attributes(pg_error))]
+pub fn to_pg_error(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    impl_to_pg_error(ast).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
 #[proc_macro_derive(PostgresGucEnum, attributes(hidden))]
 pub fn postgres_guc_enum(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as syn::DeriveInput);
@@ -1113,3 +1198,60 @@ pub fn pg_trigger(attrs: TokenStream, input: TokenStream) -> TokenStream {
         }
     }
 }
+
+/**
+Declare a `pgx::tsearch::TsParser` implementation on a type as a Postgres text search parser.
+
+Creates the parser's support functions and a `CREATE TEXT SEARCH PARSER` of the implementation's
+`NAME`, which defaults to the type's name in snake case.
+
+Review the `pgx::tsearch` documentation for use.
+*/
+#[proc_macro_attribute]
+pub fn pg_ts_parser(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(item_impl: ItemImpl) -> Result<TokenStream, syn::Error> {
+        let sql_graph_entity_item = PgTsParser::new(item_impl)?;
+
+        Ok(sql_graph_entity_item.to_token_stream().into())
+    }
+
+    let parsed_base = parse_macro_input!(item as syn::ItemImpl);
+    match wrapped(parsed_base) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a `pgx::tsearch::TsDictionary` implementation on a type as a Postgres text search dictionary.
+
+Creates the dictionary's support functions, a `CREATE TEXT SEARCH TEMPLATE`, and a
+`CREATE TEXT SEARCH DICTIONARY` using it, both of the implementation's `NAME`, which defaults to the
+type's name in snake case.
+
+Review the `pgx::tsearch` documentation for use.
+*/
+#[proc_macro_attribute]
+pub fn pg_ts_dictionary(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(item_impl: ItemImpl) -> Result<TokenStream, syn::Error> {
+        let sql_graph_entity_item = PgTsDictionary::new(item_impl)?;
+
+        Ok(sql_graph_entity_item.to_token_stream().into())
+    }
+
+    let parsed_base = parse_macro_input!(item as syn::ItemImpl);
+    match wrapped(parsed_base) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
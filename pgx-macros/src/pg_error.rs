@@ -0,0 +1,113 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::gucs::{expect_str, parse_args, qualify};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput};
+
+/// The `#[pg_error(...)]` settings of the enum, or of one of its variants
+#[derive(Default, Clone)]
+struct PgErrorAttrs {
+    code: Option<TokenStream>,
+    level: Option<TokenStream>,
+    detail: Option<String>,
+    hint: Option<String>,
+}
+
+impl PgErrorAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let codes = quote! { ::pgx::pg_sys::errcodes::PgSqlErrorCode };
+        let levels = quote! { ::pgx::pg_sys::elog::PgLogLevel };
+        let mut parsed = PgErrorAttrs::default();
+        for arg in parse_args(attrs, "pg_error")? {
+            match arg.key.to_string().as_str() {
+                "code" => parsed.code = Some(qualify(&arg.value, &codes)),
+                "level" => parsed.level = Some(qualify(&arg.value, &levels)),
+                "detail" => parsed.detail = Some(expect_str(&arg.value)?),
+                "hint" => parsed.hint = Some(expect_str(&arg.value)?),
+                _ => return Err(syn::Error::new(arg.key.span(), "unknown `pg_error` argument")),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Settings from `self`, falling back to `defaults`
+    fn or(self, defaults: &PgErrorAttrs) -> Self {
+        PgErrorAttrs {
+            code: self.code.or_else(|| defaults.code.clone()),
+            level: self.level.or_else(|| defaults.level.clone()),
+            detail: self.detail.or_else(|| defaults.detail.clone()),
+            hint: self.hint.or_else(|| defaults.hint.clone()),
+        }
+    }
+}
+
+fn optional_string(s: &Option<String>) -> TokenStream {
+    match s {
+        Some(s) => quote! { Some(#s.to_string()) },
+        None => quote! { None },
+    }
+}
+
+pub(crate) fn impl_to_pg_error(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let enum_data = match &ast.data {
+        Data::Enum(e) => e,
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "#[derive(ToPgError)] can only be applied to enums",
+            ))
+        }
+    };
+
+    let defaults = PgErrorAttrs::parse(&ast.attrs)?;
+    let mut code_arms = TokenStream::new();
+    let mut level_arms = TokenStream::new();
+    let mut detail_arms = TokenStream::new();
+    let mut hint_arms = TokenStream::new();
+
+    for variant in &enum_data.variants {
+        let ident = &variant.ident;
+        let attrs = PgErrorAttrs::parse(&variant.attrs)?.or(&defaults);
+        let code = attrs
+            .code
+            .unwrap_or(quote! { ::pgx::pg_sys::errcodes::PgSqlErrorCode::ERRCODE_DATA_EXCEPTION });
+        let level = attrs.level.unwrap_or(quote! { ::pgx::pg_sys::elog::PgLogLevel::ERROR });
+        let detail = optional_string(&attrs.detail);
+        let hint = optional_string(&attrs.hint);
+
+        code_arms.extend(quote! { Self::#ident { .. } => #code, });
+        level_arms.extend(quote! { Self::#ident { .. } => #level, });
+        detail_arms.extend(quote! { Self::#ident { .. } => #detail, });
+        hint_arms.extend(quote! { Self::#ident { .. } => #hint, });
+    }
+
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pgx::pg_sys::panic::ToPgError for #name #ty_generics #where_clause {
+            fn sql_error_code(&self) -> ::pgx::pg_sys::errcodes::PgSqlErrorCode {
+                match self { #code_arms }
+            }
+
+            fn level(&self) -> ::pgx::pg_sys::elog::PgLogLevel {
+                match self { #level_arms }
+            }
+
+            fn detail(&self) -> Option<String> {
+                match self { #detail_arms }
+            }
+
+            fn hint(&self) -> Option<String> {
+                match self { #hint_arms }
+            }
+        }
+    })
+}
@@ -25,6 +25,7 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "access/skey.h"
 #include "access/sysattr.h"
 #include "access/tableam.h"
+#include "access/tuptoaster.h"
 #include "access/xact.h"
 #include "catalog/dependency.h"
 #include "catalog/index.h"
@@ -42,6 +43,7 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "catalog/pg_tablespace.h"
 #include "catalog/pg_trigger.h"
 #include "catalog/pg_type.h"
+#include "commands/async.h"
 #include "commands/comment.h"
 #include "commands/dbcommands.h"
 #include "commands/defrem.h"
@@ -57,6 +59,9 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "executor/spi.h"
 #include "foreign/fdwapi.h"
 #include "foreign/foreign.h"
+#include "lib/dshash.h"
+#include "libpq/libpq-fs.h"
+#include "libpq/libpq.h"
 #include "mb/pg_wchar.h"
 #include "nodes/execnodes.h"
 #include "nodes/extensible.h"
@@ -79,8 +84,11 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "optimizer/restrictinfo.h"
 #include "optimizer/tlist.h"
 #include "parser/analyze.h"
+#include "parser/parse_collate.h"
+#include "parser/parse_expr.h"
 #include "parser/parse_func.h"
 #include "parser/parse_oper.h"
+#include "parser/parse_param.h"
 #include "parser/parse_type.h"
 #include "parser/parser.h"
 #include "parser/parsetree.h"
@@ -93,6 +101,8 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "storage/buffile.h"
 #include "storage/ipc.h"
 #include "storage/itemptr.h"
+#include "storage/large_object.h"
+#include "storage/lmgr.h"
 #include "storage/lwlock.h"
 #include "storage/procarray.h"
 #include "storage/spin.h"
@@ -108,6 +118,7 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "utils/fmgrprotos.h"
 #include "utils/geo_decls.h"
 #include "utils/guc.h"
+#include "utils/inval.h"
 #include "utils/json.h"
 #include "utils/jsonb.h"
 #include "utils/lsyscache.h"
@@ -430,6 +430,10 @@ pub const EOF: i32 = -1;
 pub const SEEK_SET: u32 = 0;
 pub const SEEK_CUR: u32 = 1;
 pub const SEEK_END: u32 = 2;
+pub const INV_WRITE: u32 = 131072;
+pub const INV_READ: u32 = 262144;
+pub const IFS_RDLOCK: u32 = 1;
+pub const IFS_WRLOCK: u32 = 2;
 pub const P_tmpdir: &[u8; 5usize] = b"/tmp\0";
 pub const _BITS_STDIO_LIM_H: u32 = 1;
 pub const L_tmpnam: u32 = 20;
@@ -9092,6 +9096,22 @@ extern "C" {
 extern "C" {
     pub fn pg_detoast_datum_packed(datum: *mut varlena) -> *mut varlena;
 }
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn toast_raw_datum_size(value: Datum) -> Size;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn toast_datum_size(value: Datum) -> Size;
+}
+pub const ToastCompressionId_TOAST_PGLZ_COMPRESSION_ID: ToastCompressionId = 0;
+pub const ToastCompressionId_TOAST_LZ4_COMPRESSION_ID: ToastCompressionId = 1;
+pub const ToastCompressionId_TOAST_INVALID_COMPRESSION_ID: ToastCompressionId = 2;
+pub type ToastCompressionId = ::std::os::raw::c_uint;
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn toast_get_compression_id(attr: *mut varlena) -> ToastCompressionId;
+}
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Pg_finfo_record {
@@ -12367,6 +12387,123 @@ extern "C" {
 }
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
+pub struct dshash_table {
+    _unused: [u8; 0],
+}
+pub type dshash_table_handle = dsa_pointer;
+pub type dshash_hash = uint32;
+pub type dshash_compare_function = ::std::option::Option<
+    unsafe extern "C" fn(
+        a: *const ::std::os::raw::c_void,
+        b: *const ::std::os::raw::c_void,
+        size: usize,
+        arg: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int,
+>;
+pub type dshash_hash_function = ::std::option::Option<
+    unsafe extern "C" fn(
+        v: *const ::std::os::raw::c_void,
+        size: usize,
+        arg: *mut ::std::os::raw::c_void,
+    ) -> dshash_hash,
+>;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct dshash_parameters {
+    pub key_size: usize,
+    pub entry_size: usize,
+    pub compare_function: dshash_compare_function,
+    pub hash_function: dshash_hash_function,
+    pub tranche_id: ::std::os::raw::c_int,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct dshash_table_item {
+    _unused: [u8; 0],
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_create(
+        area: *mut dsa_area,
+        params: *const dshash_parameters,
+        arg: *mut ::std::os::raw::c_void,
+    ) -> *mut dshash_table;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_attach(
+        area: *mut dsa_area,
+        params: *const dshash_parameters,
+        handle: dshash_table_handle,
+        arg: *mut ::std::os::raw::c_void,
+    ) -> *mut dshash_table;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_detach(hash_table: *mut dshash_table);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_get_hash_table_handle(hash_table: *mut dshash_table) -> dshash_table_handle;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_destroy(hash_table: *mut dshash_table);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_find(
+        hash_table: *mut dshash_table,
+        key: *const ::std::os::raw::c_void,
+        exclusive: bool,
+    ) -> *mut ::std::os::raw::c_void;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_find_or_insert(
+        hash_table: *mut dshash_table,
+        key: *const ::std::os::raw::c_void,
+        found: *mut bool,
+    ) -> *mut ::std::os::raw::c_void;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_delete_key(
+        hash_table: *mut dshash_table,
+        key: *const ::std::os::raw::c_void,
+    ) -> bool;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_delete_entry(hash_table: *mut dshash_table, entry: *mut ::std::os::raw::c_void);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_release_lock(hash_table: *mut dshash_table, entry: *mut ::std::os::raw::c_void);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_memcmp(
+        a: *const ::std::os::raw::c_void,
+        b: *const ::std::os::raw::c_void,
+        size: usize,
+        arg: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_memhash(
+        v: *const ::std::os::raw::c_void,
+        size: usize,
+        arg: *mut ::std::os::raw::c_void,
+    ) -> dshash_hash;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn dshash_dump(hash_table: *mut dshash_table);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
 pub struct TIDBitmap {
     _unused: [u8; 0],
 }
@@ -30473,6 +30610,42 @@ extern "C" {
     pub fn LockRelease(locktag: *const LOCKTAG, lockmode: LOCKMODE, sessionLock: bool) -> bool;
 }
 #[pgx_macros::pg_guard]
+extern "C" {
+    pub fn LockRelationOid(relid: Oid, lockmode: LOCKMODE);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn ConditionalLockRelationOid(relid: Oid, lockmode: LOCKMODE) -> bool;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn UnlockRelationOid(relid: Oid, lockmode: LOCKMODE);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn LockPage(relation: Relation, blkno: BlockNumber, lockmode: LOCKMODE);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn ConditionalLockPage(relation: Relation, blkno: BlockNumber, lockmode: LOCKMODE) -> bool;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn UnlockPage(relation: Relation, blkno: BlockNumber, lockmode: LOCKMODE);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn LockTuple(relation: Relation, tid: ItemPointer, lockmode: LOCKMODE);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn ConditionalLockTuple(relation: Relation, tid: ItemPointer, lockmode: LOCKMODE) -> bool;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn UnlockTuple(relation: Relation, tid: ItemPointer, lockmode: LOCKMODE);
+}
+#[pgx_macros::pg_guard]
 extern "C" {
     pub fn LockReleaseAll(lockmethodid: LOCKMETHODID, allLocks: bool);
 }
@@ -32467,6 +32640,26 @@ extern "C" {
     pub fn free_parsestate(pstate: *mut ParseState);
 }
 #[pgx_macros::pg_guard]
+extern "C" {
+    pub fn transformExpr(
+        pstate: *mut ParseState,
+        expr: *mut Node,
+        exprKind: ParseExprKind,
+    ) -> *mut Node;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn assign_expr_collations(pstate: *mut ParseState, expr: *mut Node);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn parse_fixed_parameters(
+        pstate: *mut ParseState,
+        paramTypes: *mut Oid,
+        numParams: ::std::os::raw::c_int,
+    );
+}
+#[pgx_macros::pg_guard]
 extern "C" {
     pub fn parser_errposition(
         pstate: *mut ParseState,
@@ -32915,6 +33108,83 @@ extern "C" {
 extern "C" {
     pub fn pg_largeobject_ownercheck(lobj_oid: Oid, roleid: Oid) -> bool;
 }
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct LargeObjectDesc {
+    pub id: Oid,
+    pub snapshot: Snapshot,
+    pub subid: SubTransactionId,
+    pub offset: uint64,
+    pub flags: bits32,
+}
+impl Default for LargeObjectDesc {
+    fn default() -> Self {
+        let mut s = ::std::mem::MaybeUninit::<Self>::uninit();
+        unsafe {
+            ::std::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
+            s.assume_init()
+        }
+    }
+}
+extern "C" {
+    pub static mut lo_compat_privileges: bool;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn close_lo_relation(isCommit: bool);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_create(lobjId: Oid) -> Oid;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_open(
+        lobjId: Oid,
+        flags: ::std::os::raw::c_int,
+        mcxt: MemoryContext,
+    ) -> *mut LargeObjectDesc;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_close(obj_desc: *mut LargeObjectDesc);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_drop(lobjId: Oid) -> ::std::os::raw::c_int;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_seek(
+        obj_desc: *mut LargeObjectDesc,
+        offset: int64,
+        whence: ::std::os::raw::c_int,
+    ) -> int64;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_tell(obj_desc: *mut LargeObjectDesc) -> int64;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_read(
+        obj_desc: *mut LargeObjectDesc,
+        buf: *mut ::std::os::raw::c_char,
+        nbytes: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_write(
+        obj_desc: *mut LargeObjectDesc,
+        buf: *const ::std::os::raw::c_char,
+        nbytes: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn inv_truncate(obj_desc: *mut LargeObjectDesc, len: int64);
+}
 #[pgx_macros::pg_guard]
 extern "C" {
     pub fn pg_namespace_ownercheck(nsp_oid: Oid, roleid: Oid) -> bool;
@@ -46447,6 +46717,64 @@ extern "C" {
 extern "C" {
     pub static mut whereToSendOutput: CommandDest;
 }
+extern "C" {
+    pub static mut notifyInterruptPending: sig_atomic_t;
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn Async_Notify(
+        channel: *const ::std::os::raw::c_char,
+        payload: *const ::std::os::raw::c_char,
+    );
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn Async_Listen(channel: *const ::std::os::raw::c_char);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn Async_Unlisten(channel: *const ::std::os::raw::c_char);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn Async_UnlistenAll();
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn ProcessCompletedNotifies();
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn ProcessNotifyInterrupt();
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PQcommMethods {
+    pub comm_reset: ::std::option::Option<unsafe extern "C" fn()>,
+    pub flush: ::std::option::Option<unsafe extern "C" fn() -> ::std::os::raw::c_int>,
+    pub flush_if_writable: ::std::option::Option<unsafe extern "C" fn() -> ::std::os::raw::c_int>,
+    pub is_send_pending: ::std::option::Option<unsafe extern "C" fn() -> bool>,
+    pub putmessage: ::std::option::Option<
+        unsafe extern "C" fn(
+            msgtype: ::std::os::raw::c_char,
+            s: *const ::std::os::raw::c_char,
+            len: usize,
+        ) -> ::std::os::raw::c_int,
+    >,
+    pub putmessage_noblock: ::std::option::Option<
+        unsafe extern "C" fn(
+            msgtype: ::std::os::raw::c_char,
+            s: *const ::std::os::raw::c_char,
+            len: usize,
+        ),
+    >,
+}
+extern "C" {
+    pub static mut PqCommMethods: *const PQcommMethods;
+}
+extern "C" {
+    pub static mut FrontendProtocol: ProtocolVersion;
+}
 extern "C" {
     pub static mut debug_query_string: *const ::std::os::raw::c_char;
 }
@@ -60779,6 +61107,55 @@ extern "C" {
         isNull: *mut bool,
     ) -> Datum;
 }
+pub type SyscacheCallbackFunction = ::std::option::Option<
+    unsafe extern "C" fn(arg: Datum, cacheid: ::std::os::raw::c_int, hashvalue: uint32),
+>;
+pub type RelcacheCallbackFunction =
+    ::std::option::Option<unsafe extern "C" fn(arg: Datum, relid: Oid)>;
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn AcceptInvalidationMessages();
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn CommandEndInvalidationMessages();
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn CacheInvalidateCatalog(catalogId: Oid);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn CacheInvalidateRelcache(relation: Relation);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn CacheInvalidateRelcacheAll();
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn CacheInvalidateRelcacheByRelid(relid: Oid);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn CacheRegisterSyscacheCallback(
+        cacheid: ::std::os::raw::c_int,
+        func: SyscacheCallbackFunction,
+        arg: Datum,
+    );
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn CacheRegisterRelcacheCallback(func: RelcacheCallbackFunction, arg: Datum);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn CallSyscacheCallbacks(cacheid: ::std::os::raw::c_int, hashvalue: uint32);
+}
+#[pgx_macros::pg_guard]
+extern "C" {
+    pub fn InvalidateSystemCaches();
+}
 #[pgx_macros::pg_guard]
 extern "C" {
     pub fn GetSysCacheHashValue(
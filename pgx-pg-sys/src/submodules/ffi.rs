@@ -147,6 +147,31 @@ unsafe fn pg_guard_ffi_boundary_impl<T, F: FnOnce() -> T>(f: F) -> T {
             let hint = errdata.hint.is_null().then(|| None).unwrap_or_else(|| {
                 Some(CStr::from_ptr(errdata.hint).to_string_lossy().to_string())
             });
+            let context = errdata.context.is_null().then(|| None).unwrap_or_else(|| {
+                Some(CStr::from_ptr(errdata.context).to_string_lossy().to_string())
+            });
+            let detail_log = errdata.detail_log.is_null().then(|| None).unwrap_or_else(|| {
+                Some(CStr::from_ptr(errdata.detail_log).to_string_lossy().to_string())
+            });
+            let position = (errdata.cursorpos > 0).then(|| errdata.cursorpos as u32);
+            let hide_statement = errdata.hide_stmt;
+            let schema_name = errdata.schema_name.is_null().then(|| None).unwrap_or_else(|| {
+                Some(CStr::from_ptr(errdata.schema_name).to_string_lossy().to_string())
+            });
+            let table_name = errdata.table_name.is_null().then(|| None).unwrap_or_else(|| {
+                Some(CStr::from_ptr(errdata.table_name).to_string_lossy().to_string())
+            });
+            let column_name = errdata.column_name.is_null().then(|| None).unwrap_or_else(|| {
+                Some(CStr::from_ptr(errdata.column_name).to_string_lossy().to_string())
+            });
+            let datatype_name =
+                errdata.datatype_name.is_null().then(|| None).unwrap_or_else(|| {
+                    Some(CStr::from_ptr(errdata.datatype_name).to_string_lossy().to_string())
+                });
+            let constraint_name =
+                errdata.constraint_name.is_null().then(|| None).unwrap_or_else(|| {
+                    Some(CStr::from_ptr(errdata.constraint_name).to_string_lossy().to_string())
+                });
             let funcname = errdata.funcname.is_null().then(|| None).unwrap_or_else(|| {
                 Some(CStr::from_ptr(errdata.funcname).to_string_lossy().to_string())
             });
@@ -171,6 +196,15 @@ unsafe fn pg_guard_ffi_boundary_impl<T, F: FnOnce() -> T>(f: F) -> T {
                     message,
                     detail,
                     hint,
+                    context,
+                    detail_log,
+                    position,
+                    hide_statement,
+                    schema_name,
+                    table_name,
+                    column_name,
+                    datatype_name,
+                    constraint_name,
                     location: ErrorReportLocation { file, funcname, line, col: 0 },
                 },
             }))
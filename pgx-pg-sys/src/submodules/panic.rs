@@ -58,6 +58,85 @@ where
     }
 }
 
+/// Describes how a Rust error type is raised as a Postgres `ERROR`
+///
+/// When a `#[pg_extern]` function returns `Result<T, E>` and `E` implements [`ToPgError`], an `Err`
+/// is raised using the [`PgSqlErrorCode`], level, detail, and hint `E` chooses, with `E`'s
+/// [`Display`] as the message.  Otherwise the error is raised as a
+/// [`PgSqlErrorCode::ERRCODE_DATA_EXCEPTION`], as with [`ErrorReportable`].
+///
+/// This is usually derived for an enum with `#[derive(ToPgError)]`.
+pub trait ToPgError: Display {
+    /// The SQLSTATE to raise, defaulting to [`PgSqlErrorCode::ERRCODE_DATA_EXCEPTION`]
+    fn sql_error_code(&self) -> PgSqlErrorCode {
+        PgSqlErrorCode::ERRCODE_DATA_EXCEPTION
+    }
+
+    /// The level to raise at, defaulting to [`PgLogLevel::ERROR`].  Levels below `ERROR` are
+    /// raised as `ERROR`, as the function can't return normally
+    fn level(&self) -> PgLogLevel {
+        PgLogLevel::ERROR
+    }
+
+    /// The error's `DETAIL` line, if any
+    fn detail(&self) -> Option<String> {
+        None
+    }
+
+    /// The error's `HINT` line, if any
+    fn hint(&self) -> Option<String> {
+        None
+    }
+
+    /// Build the [`ErrorReport`] this error is raised as
+    fn to_error_report(&self) -> ErrorReport {
+        let mut report = ErrorReport::with_location(
+            self.sql_error_code(),
+            self.to_string(),
+            ErrorReportLocation::default(),
+        );
+        report.detail = self.detail();
+        report.hint = self.hint();
+        report
+    }
+}
+
+/// Wraps a returned error so that `#[pg_extern]` can raise it with [`ToPgError`] when it's
+/// implemented, or with its [`Display`] implementation otherwise
+#[doc(hidden)]
+pub struct PgErrorReporter<E>(pub E);
+
+#[doc(hidden)]
+pub trait ReportToPgError {
+    fn report_error(&self) -> !;
+}
+
+impl<E: ToPgError> ReportToPgError for PgErrorReporter<E> {
+    fn report_error(&self) -> ! {
+        let level = self.0.level().max(PgLogLevel::ERROR);
+        self.0.to_error_report().report(level);
+        unreachable!()
+    }
+}
+
+#[doc(hidden)]
+pub trait ReportDisplayError {
+    fn report_error(&self) -> !;
+}
+
+impl<E: Any + Display> ReportDisplayError for &PgErrorReporter<E> {
+    fn report_error(&self) -> ! {
+        let any: &dyn Any = &self.0;
+        match any.downcast_ref::<ErrorReport>() {
+            Some(report) => report.clone().report(PgLogLevel::ERROR),
+            None => {
+                ereport!(ERROR, PgSqlErrorCode::ERRCODE_DATA_EXCEPTION, &format!("{}", self.0));
+            }
+        }
+        unreachable!()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ErrorReportLocation {
     pub(crate) file: String,
@@ -112,6 +191,15 @@ pub struct ErrorReport {
     pub(crate) message: String,
     pub(crate) hint: Option<String>,
     pub(crate) detail: Option<String>,
+    pub(crate) context: Option<String>,
+    pub(crate) detail_log: Option<String>,
+    pub(crate) position: Option<u32>,
+    pub(crate) hide_statement: bool,
+    pub(crate) schema_name: Option<String>,
+    pub(crate) table_name: Option<String>,
+    pub(crate) column_name: Option<String>,
+    pub(crate) datatype_name: Option<String>,
+    pub(crate) constraint_name: Option<String>,
     pub(crate) location: ErrorReportLocation,
 }
 
@@ -124,6 +212,9 @@ impl Display for ErrorReport {
         if let Some(detail) = &self.detail {
             write!(f, "\nDETAIL: {}", detail)?;
         }
+        if let Some(context) = &self.context {
+            write!(f, "\nCONTEXT: {}", context)?;
+        }
         write!(f, "\nLOCATION: {}", self.location)
     }
 }
@@ -188,9 +279,13 @@ impl ErrorReportWithLevel {
     }
 
     /// Returns the context message of this error report, if any
-    fn context_message(&self) -> Option<String> {
-        // NB:  holding this here for future use
-        None
+    pub fn context_message(&self) -> Option<&str> {
+        self.inner.context()
+    }
+
+    /// Returns the underlying [`ErrorReport`], for access to all of its fields
+    pub fn error_report(&self) -> &ErrorReport {
+        &self.inner
     }
 }
 
@@ -208,7 +303,22 @@ impl ErrorReport {
         let mut location: ErrorReportLocation = Location::caller().into();
         location.funcname = Some(funcname.to_string());
 
-        Self { sqlerrcode, message: message.into(), hint: None, detail: None, location }
+        Self {
+            sqlerrcode,
+            message: message.into(),
+            hint: None,
+            detail: None,
+            context: None,
+            detail_log: None,
+            position: None,
+            hide_statement: false,
+            schema_name: None,
+            table_name: None,
+            column_name: None,
+            datatype_name: None,
+            constraint_name: None,
+            location,
+        }
     }
 
     /// Create a [PgErrorReport] which can be raised via Rust's [std::panic::panic_any()] or as
@@ -220,7 +330,22 @@ impl ErrorReport {
         message: S,
         location: ErrorReportLocation,
     ) -> Self {
-        Self { sqlerrcode, message: message.into(), hint: None, detail: None, location }
+        Self {
+            sqlerrcode,
+            message: message.into(),
+            hint: None,
+            detail: None,
+            context: None,
+            detail_log: None,
+            position: None,
+            hide_statement: false,
+            schema_name: None,
+            table_name: None,
+            column_name: None,
+            datatype_name: None,
+            constraint_name: None,
+            location,
+        }
     }
 
     /// Set the `detail` property, whose default is `None`
@@ -235,6 +360,74 @@ impl ErrorReport {
         self
     }
 
+    /// Set the `context` property, whose default is `None`
+    pub fn set_context<S: Into<String>>(mut self, context: S) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Set the `detail_log` property, whose default is `None`.  This detail is only sent to the server
+    /// log, in place of `detail`
+    pub fn set_detail_log<S: Into<String>>(mut self, detail_log: S) -> Self {
+        self.detail_log = Some(detail_log.into());
+        self
+    }
+
+    /// Set the `position` property, whose default is `None`.  This is the 1-based character
+    /// position of the error in the query string (see `errposition()`)
+    pub fn set_position(mut self, position: u32) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Set the `hide_statement` property, whose default is `false`.  When `true`, the query string
+    /// isn't included in the server log's `STATEMENT` line (see `errhidestmt()`)
+    pub fn set_hide_statement(mut self, hide_statement: bool) -> Self {
+        self.hide_statement = hide_statement;
+        self
+    }
+
+    /// Set the `schema_name` property, whose default is `None`
+    pub fn set_schema_name<S: Into<String>>(mut self, schema_name: S) -> Self {
+        self.schema_name = Some(schema_name.into());
+        self
+    }
+
+    /// Set the `table_name` property, whose default is `None`
+    pub fn set_table_name<S: Into<String>>(mut self, table_name: S) -> Self {
+        self.table_name = Some(table_name.into());
+        self
+    }
+
+    /// Set the `column_name` property, whose default is `None`
+    pub fn set_column_name<S: Into<String>>(mut self, column_name: S) -> Self {
+        self.column_name = Some(column_name.into());
+        self
+    }
+
+    /// Set the `datatype_name` property, whose default is `None`
+    pub fn set_datatype_name<S: Into<String>>(mut self, datatype_name: S) -> Self {
+        self.datatype_name = Some(datatype_name.into());
+        self
+    }
+
+    /// Set the `constraint_name` property, whose default is `None`
+    pub fn set_constraint_name<S: Into<String>>(mut self, constraint_name: S) -> Self {
+        self.constraint_name = Some(constraint_name.into());
+        self
+    }
+
+    /// Set the source location reported for this error, in place of the caller's location
+    pub fn set_location(mut self, file: &str, line: u32, funcname: Option<&str>) -> Self {
+        self.location = ErrorReportLocation {
+            file: file.to_string(),
+            funcname: funcname.map(|s| s.to_string()),
+            line,
+            col: 0,
+        };
+        self
+    }
+
     /// Returns the error message of this error report
     pub fn message(&self) -> &str {
         &self.message
@@ -250,6 +443,51 @@ impl ErrorReport {
         self.hint.as_ref().map(|s| s.as_str())
     }
 
+    /// Returns the context message of this error report
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    /// Returns the server-log-only detail message of this error report
+    pub fn detail_log(&self) -> Option<&str> {
+        self.detail_log.as_deref()
+    }
+
+    /// Returns the position of the error in the query string, if known
+    pub fn position(&self) -> Option<u32> {
+        self.position
+    }
+
+    /// Returns whether the query string is hidden from the server log
+    pub fn hide_statement(&self) -> bool {
+        self.hide_statement
+    }
+
+    /// Returns the name of the schema associated with this error report
+    pub fn schema_name(&self) -> Option<&str> {
+        self.schema_name.as_deref()
+    }
+
+    /// Returns the name of the table associated with this error report
+    pub fn table_name(&self) -> Option<&str> {
+        self.table_name.as_deref()
+    }
+
+    /// Returns the name of the column associated with this error report
+    pub fn column_name(&self) -> Option<&str> {
+        self.column_name.as_deref()
+    }
+
+    /// Returns the name of the data type associated with this error report
+    pub fn datatype_name(&self) -> Option<&str> {
+        self.datatype_name.as_deref()
+    }
+
+    /// Returns the name of the constraint associated with this error report
+    pub fn constraint_name(&self) -> Option<&str> {
+        self.constraint_name.as_deref()
+    }
+
     /// Report this [PgErrorReport], which will ultimately be reported by Postgres at the specified [PgLogLevel]
     ///
     /// If the provided `level` is >= [`PgLogLevel::ERROR`] this function will not return.
@@ -292,6 +530,21 @@ impl CaughtError {
         // one to be thrown
         resume_unwind(Box::new(self))
     }
+    /// Add `context` as the outermost line of this error's `CONTEXT`.
+    ///
+    /// Errors raised by Postgres are left alone, as Postgres has already collected the context
+    /// that was active when they were raised.
+    pub fn add_context(&mut self, context: &str) {
+        match self {
+            CaughtError::PostgresError(_) => {}
+            CaughtError::ErrorReport(ereport) | CaughtError::RustPanic { ereport, .. } => {
+                ereport.inner.context = Some(match ereport.inner.context.take() {
+                    Some(inner) => format!("{}\n{}", inner, context),
+                    None => context.to_string(),
+                });
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -459,6 +712,13 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
         fn errdetail(fmt: *const ::std::os::raw::c_char, ...) -> ::std::os::raw::c_int;
         fn errhint(fmt: *const ::std::os::raw::c_char, ...) -> ::std::os::raw::c_int;
         fn errcontext_msg(fmt: *const ::std::os::raw::c_char, ...) -> ::std::os::raw::c_int;
+        fn errdetail_log(fmt: *const ::std::os::raw::c_char, ...) -> ::std::os::raw::c_int;
+        fn errposition(cursorpos: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+        fn errhidestmt(hide_stmt: bool) -> ::std::os::raw::c_int;
+        fn err_generic_string(
+            field: ::std::os::raw::c_int,
+            str_: *const ::std::os::raw::c_char,
+        ) -> ::std::os::raw::c_int;
     }
 
     /// do_ereport impl for postgres 13 and later
@@ -482,6 +742,16 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
                 let detail = ereport.detail().as_pg_cstr();
                 let hint = ereport.hint().as_pg_cstr();
                 let context = ereport.context_message().as_pg_cstr();
+                let detail_log = ereport.inner.detail_log().as_pg_cstr();
+                let position = ereport.inner.position();
+                let hide_statement = ereport.inner.hide_statement();
+                let object_names = [
+                    (crate::PG_DIAG_SCHEMA_NAME, ereport.inner.schema_name().as_pg_cstr()),
+                    (crate::PG_DIAG_TABLE_NAME, ereport.inner.table_name().as_pg_cstr()),
+                    (crate::PG_DIAG_COLUMN_NAME, ereport.inner.column_name().as_pg_cstr()),
+                    (crate::PG_DIAG_DATATYPE_NAME, ereport.inner.datatype_name().as_pg_cstr()),
+                    (crate::PG_DIAG_CONSTRAINT_NAME, ereport.inner.constraint_name().as_pg_cstr()),
+                ];
                 let lineno = ereport.line_number();
 
                 // SAFETY:  We know that `crate::ErrorContext` is a valid memory context pointer and one
@@ -507,6 +777,12 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
                 if !detail.is_null()  { errdetail(PERCENT_S.as_ptr(), detail);       pfree(detail.cast());  }
                 if !hint.is_null()    { errhint(PERCENT_S.as_ptr(), hint);           pfree(hint.cast());    }
                 if !context.is_null() { errcontext_msg(PERCENT_S.as_ptr(), context); pfree(context.cast()); }
+                if !detail_log.is_null() { errdetail_log(PERCENT_S.as_ptr(), detail_log); pfree(detail_log.cast()); }
+                if let Some(position) = position { errposition(position as _); }
+                if hide_statement { errhidestmt(true); }
+                for (field, name) in object_names {
+                    if !name.is_null() { err_generic_string(field as _, name); pfree(name.cast()); }
+                }
 
                 errfinish(file, lineno as _, funcname);
 
@@ -556,6 +832,16 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
                 let detail = ereport.detail().as_pg_cstr();
                 let hint = ereport.hint().as_pg_cstr();
                 let context = ereport.context_message().as_pg_cstr();
+                let detail_log = ereport.inner.detail_log().as_pg_cstr();
+                let position = ereport.inner.position();
+                let hide_statement = ereport.inner.hide_statement();
+                let object_names = [
+                    (crate::PG_DIAG_SCHEMA_NAME, ereport.inner.schema_name().as_pg_cstr()),
+                    (crate::PG_DIAG_TABLE_NAME, ereport.inner.table_name().as_pg_cstr()),
+                    (crate::PG_DIAG_COLUMN_NAME, ereport.inner.column_name().as_pg_cstr()),
+                    (crate::PG_DIAG_DATATYPE_NAME, ereport.inner.datatype_name().as_pg_cstr()),
+                    (crate::PG_DIAG_CONSTRAINT_NAME, ereport.inner.constraint_name().as_pg_cstr()),
+                ];
 
 
                 // do not leak the Rust `ErrorReportWithLocation` instance
@@ -572,6 +858,12 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
                 if !detail.is_null()  { errdetail(PERCENT_S.as_ptr(), detail);       pfree(detail.cast());  }
                 if !hint.is_null()    { errhint(PERCENT_S.as_ptr(), hint);           pfree(hint.cast());    }
                 if !context.is_null() { errcontext_msg(PERCENT_S.as_ptr(), context); pfree(context.cast()); }
+                if !detail_log.is_null() { errdetail_log(PERCENT_S.as_ptr(), detail_log); pfree(detail_log.cast()); }
+                if let Some(position) = position { errposition(position as _); }
+                if hide_statement { errhidestmt(true); }
+                for (field, name) in object_names {
+                    if !name.is_null() { err_generic_string(field as _, name); pfree(name.cast()); }
+                }
 
                 errfinish(0);
             }
@@ -32,6 +32,10 @@ pub use pg_extern::{NameMacro, PgExtern, PgExternArgument, PgOperator};
 pub use pg_trigger::attribute::PgTriggerAttribute;
 pub use pg_trigger::entity::PgTriggerEntity;
 pub use pg_trigger::PgTrigger;
+pub use pg_ts_dictionary::entity::PgTsDictionaryEntity;
+pub use pg_ts_dictionary::PgTsDictionary;
+pub use pg_ts_parser::entity::PgTsParserEntity;
+pub use pg_ts_parser::PgTsParser;
 pub use pgx_sql::PgxSql;
 pub use positioning_ref::PositioningRef;
 pub use postgres_enum::entity::PostgresEnumEntity;
@@ -58,6 +62,8 @@ pub(crate) mod mapping;
 pub mod metadata;
 pub(crate) mod pg_extern;
 pub(crate) mod pg_trigger;
+pub(crate) mod pg_ts_dictionary;
+pub(crate) mod pg_ts_parser;
 pub(crate) mod pgx_attribute;
 pub(crate) mod pgx_sql;
 pub mod positioning_ref;
@@ -103,6 +109,8 @@ pub enum SqlGraphEntity {
     Hash(PostgresHashEntity),
     Aggregate(PgAggregateEntity),
     Trigger(PgTriggerEntity),
+    TsParser(PgTsParserEntity),
+    TsDictionary(PgTsDictionaryEntity),
 }
 
 impl SqlGraphEntity {
@@ -136,6 +144,8 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Hash(item) => item.dot_identifier(),
             SqlGraphEntity::Aggregate(item) => item.dot_identifier(),
             SqlGraphEntity::Trigger(item) => item.dot_identifier(),
+            SqlGraphEntity::TsParser(item) => item.dot_identifier(),
+            SqlGraphEntity::TsDictionary(item) => item.dot_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.dot_identifier(),
         }
     }
@@ -152,6 +162,8 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Hash(item) => item.rust_identifier(),
             SqlGraphEntity::Aggregate(item) => item.rust_identifier(),
             SqlGraphEntity::Trigger(item) => item.rust_identifier(),
+            SqlGraphEntity::TsParser(item) => item.rust_identifier(),
+            SqlGraphEntity::TsDictionary(item) => item.rust_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.rust_identifier(),
         }
     }
@@ -168,6 +180,8 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Hash(item) => item.file(),
             SqlGraphEntity::Aggregate(item) => item.file(),
             SqlGraphEntity::Trigger(item) => item.file(),
+            SqlGraphEntity::TsParser(item) => item.file(),
+            SqlGraphEntity::TsDictionary(item) => item.file(),
             SqlGraphEntity::ExtensionRoot(item) => item.file(),
         }
     }
@@ -184,6 +198,8 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Hash(item) => item.line(),
             SqlGraphEntity::Aggregate(item) => item.line(),
             SqlGraphEntity::Trigger(item) => item.line(),
+            SqlGraphEntity::TsParser(item) => item.line(),
+            SqlGraphEntity::TsDictionary(item) => item.line(),
             SqlGraphEntity::ExtensionRoot(item) => item.line(),
         }
     }
@@ -246,6 +262,12 @@ impl ToSql for SqlGraphEntity {
             SqlGraphEntity::Trigger(item) => {
                 item.to_sql_config.to_sql(self, context).unwrap_or_else(|| item.to_sql(context))
             }
+            SqlGraphEntity::TsParser(item) => {
+                item.to_sql_config.to_sql(self, context).unwrap_or_else(|| item.to_sql(context))
+            }
+            SqlGraphEntity::TsDictionary(item) => {
+                item.to_sql_config.to_sql(self, context).unwrap_or_else(|| item.to_sql(context))
+            }
             SqlGraphEntity::ExtensionRoot(item) => item.to_sql(context),
         }
     }
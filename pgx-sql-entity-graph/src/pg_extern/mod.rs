@@ -420,11 +420,13 @@ impl PgExtern {
                        unsafe { ::pgx::fcinfo::pg_return_void() }
                     }
                 } else if retval_ty.result {
+                    let unwrapped =
+                        report_result(quote! { #result_ident }, self.func.sig.output.span());
                     if retval_ty.optional.is_some() {
                         // returning `Result<Option<T>>`
                         quote_spanned! {
                             self.func.sig.output.span() =>
-                                match ::pgx::datum::IntoDatum::into_datum(#result_ident) {
+                                match ::pgx::datum::IntoDatum::into_datum(#unwrapped) {
                                     Some(datum) => datum,
                                     None => unsafe { ::pgx::fcinfo::pg_return_null(#fcinfo_ident) },
                                 }
@@ -433,7 +435,7 @@ impl PgExtern {
                         // returning Result<T>
                         quote_spanned! {
                             self.func.sig.output.span() =>
-                                ::pgx::datum::IntoDatum::into_datum(#result_ident).unwrap_or_else(|| panic!("returned Datum was NULL"))
+                                ::pgx::datum::IntoDatum::into_datum(#unwrapped).unwrap_or_else(|| panic!("returned Datum was NULL"))
                         }
                     }
                 } else if retval_ty.resolved_ty == syn::parse_quote!(pg_sys::Datum)
@@ -481,15 +483,15 @@ impl PgExtern {
                         #func_name(#(#arg_pats),*)
                     }
                 } else if *result {
+                    let unwrapped =
+                        report_result(quote! { #func_name(#(#arg_pats),*) }, self.func.sig.span());
                     if *optional {
                         quote_spanned! { self.func.sig.span() =>
-                            use ::pgx::pg_sys::panic::ErrorReportable;
-                            #func_name(#(#arg_pats),*).report()
+                            #unwrapped
                         }
                     } else {
                         quote_spanned! { self.func.sig.span() =>
-                            use ::pgx::pg_sys::panic::ErrorReportable;
-                            Some(#func_name(#(#arg_pats),*).report())
+                            Some(#unwrapped)
                         }
                     }
                 } else {
@@ -523,11 +525,10 @@ impl PgExtern {
                         #func_name(#(#arg_pats),*)
                     }
                 } else if *result {
+                    let unwrapped =
+                        report_result(quote! { #func_name(#(#arg_pats),*) }, self.func.sig.span());
                     quote_spanned! { self.func.sig.span() =>
-                        {
-                            use ::pgx::pg_sys::panic::ErrorReportable;
-                            Some(#func_name(#(#arg_pats),*).report())
-                        }
+                        Some(#unwrapped)
                     }
                 } else {
                     quote_spanned! { self.func.sig.span() =>
@@ -557,6 +558,21 @@ impl PgExtern {
     }
 }
 
+/// Unwrap the `Result` produced by `expr`, raising an `Err` as a Postgres ERROR.  Errors which
+/// implement `ToPgError` choose their own SQLSTATE, level, detail, and hint.
+fn report_result(expr: TokenStream2, span: Span) -> TokenStream2 {
+    quote_spanned! { span =>
+        match #expr {
+            Ok(value) => value,
+            Err(e) => {
+                #[allow(unused_imports)]
+                use ::pgx::pg_sys::panic::{ReportDisplayError as _, ReportToPgError as _};
+                (&::pgx::pg_sys::panic::PgErrorReporter(e)).report_error()
+            }
+        }
+    }
+}
+
 impl ToEntityGraphTokens for PgExtern {
     fn to_entity_graph_tokens(&self) -> TokenStream2 {
         self.entity_tokens()
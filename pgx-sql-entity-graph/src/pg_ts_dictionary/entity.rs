@@ -0,0 +1,106 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+/*!
+
+`#[pg_ts_dictionary]` related entities for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+use crate::{PgxSql, SqlGraphEntity, SqlGraphIdentifier, ToSql, ToSqlConfigEntity};
+
+/// The output of a [`PgTsDictionary`](crate::PgTsDictionary) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PgTsDictionaryEntity {
+    pub name: &'static str,
+    pub function_prefix: &'static str,
+    pub options: &'static [(&'static str, &'static str)],
+    pub file: &'static str,
+    pub line: u32,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+impl From<PgTsDictionaryEntity> for SqlGraphEntity {
+    fn from(val: PgTsDictionaryEntity) -> Self {
+        SqlGraphEntity::TsDictionary(val)
+    }
+}
+
+impl ToSql for PgTsDictionaryEntity {
+    #[tracing::instrument(
+        level = "error",
+        skip(self, context),
+        fields(identifier = %self.rust_identifier()),
+    )]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let self_index = context.ts_dictionaries[self];
+        let schema = context.schema_prefix_for(&self_index);
+
+        let function = |kind: &str, args: &str| {
+            format!(
+                "CREATE FUNCTION {schema}\"{name}_{kind}\"({args}) RETURNS internal\n\
+                    \tSTRICT LANGUAGE c AS 'MODULE_PATHNAME', '{function_prefix}_{kind}_wrapper';\n",
+                schema = schema,
+                name = self.name,
+                kind = kind,
+                args = args,
+                function_prefix = self.function_prefix,
+            )
+        };
+        let options = self
+            .options
+            .iter()
+            .map(|(option, value)| format!(",\n\t{} = '{}'", option, value.replace('\'', "''")))
+            .collect::<String>();
+
+        let sql = format!(
+            "\n\
+            -- {file}:{line}\n\
+            -- {full_path}\n\
+            {init}\
+            {lexize}\
+            CREATE TEXT SEARCH TEMPLATE {schema}\"{name}\" (\n\
+                \tINIT = {schema}\"{name}_init\",\n\
+                \tLEXIZE = {schema}\"{name}_lexize\"\n\
+            );\n\
+            CREATE TEXT SEARCH DICTIONARY {schema}\"{name}\" (\n\
+                \tTEMPLATE = {schema}\"{name}\"{options}\n\
+            );",
+            file = self.file,
+            line = self.line,
+            full_path = self.full_path,
+            init = function("init", "internal"),
+            lexize = function("lexize", "internal, internal, internal, internal"),
+            schema = schema,
+            name = self.name,
+            options = options,
+        );
+        Ok(sql)
+    }
+}
+
+impl SqlGraphIdentifier for PgTsDictionaryEntity {
+    fn dot_identifier(&self) -> String {
+        format!("text search dictionary {}", self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
@@ -0,0 +1,144 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+/*!
+
+`#[pg_ts_dictionary]` related macro expansion for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+pub mod entity;
+
+use crate::enrich::{CodeEnrichment, ToEntityGraphTokens, ToRustCodeTokens};
+use crate::pg_ts_parser::impl_target_and_name;
+use crate::ToSqlConfig;
+use convert_case::{Case, Casing};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::ItemImpl;
+
+/// A parsed `#[pg_ts_dictionary]` item.
+///
+/// It should be used with [`syn::parse::Parse`] functions.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a [`PgTsDictionaryEntity`][crate::PgTsDictionaryEntity].
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_sql_entity_graph::PgTsDictionary;
+///
+/// # fn main() -> eyre::Result<()> {
+/// use pgx_sql_entity_graph::CodeEnrichment;
+/// let parsed: CodeEnrichment<PgTsDictionary> = parse_quote! {
+///     impl TsDictionary for UpperDictionary {
+///         fn init(options: &[(String, String)]) -> Self { todo!() }
+///         fn lexize(&self, token: &str) -> Option<Vec<String>> { todo!() }
+///     }
+/// };
+/// let sql_graph_entity_tokens = parsed.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgTsDictionary {
+    item_impl: ItemImpl,
+    target_ident: Ident,
+    name: syn::LitStr,
+    to_sql_config: ToSqlConfig,
+}
+
+impl PgTsDictionary {
+    pub fn new(mut item_impl: ItemImpl) -> Result<CodeEnrichment<Self>, syn::Error> {
+        let to_sql_config =
+            ToSqlConfig::from_attributes(item_impl.attrs.as_slice())?.unwrap_or_default();
+        let (target_ident, name) =
+            impl_target_and_name(&mut item_impl, "TsDictionary", "#[pg_ts_dictionary]")?;
+
+        Ok(CodeEnrichment(PgTsDictionary { item_impl, target_ident, name, to_sql_config }))
+    }
+
+    fn function_prefix(&self) -> String {
+        self.target_ident.to_string().to_case(Case::Snake)
+    }
+}
+
+impl ToEntityGraphTokens for PgTsDictionary {
+    fn to_entity_graph_tokens(&self) -> TokenStream2 {
+        let target_ident = &self.target_ident;
+        let function_prefix = self.function_prefix();
+        let sql_graph_entity_fn_name = syn::Ident::new(
+            &format!("__pgx_internals_ts_dictionary_{}", function_prefix),
+            target_ident.span(),
+        );
+        let name = &self.name;
+        let to_sql_config = &self.to_sql_config;
+
+        quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "Rust" fn #sql_graph_entity_fn_name() -> ::pgx::pgx_sql_entity_graph::SqlGraphEntity {
+                let submission = ::pgx::pgx_sql_entity_graph::PgTsDictionaryEntity {
+                    name: #name,
+                    function_prefix: #function_prefix,
+                    options: <#target_ident as ::pgx::tsearch::TsDictionary>::OPTIONS,
+                    file: file!(),
+                    line: line!(),
+                    full_path: ::core::any::type_name::<#target_ident>(),
+                    module_path: module_path!(),
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgx::pgx_sql_entity_graph::SqlGraphEntity::TsDictionary(submission)
+            }
+        }
+    }
+}
+
+impl ToRustCodeTokens for PgTsDictionary {
+    fn to_rust_code_tokens(&self) -> TokenStream2 {
+        let item_impl = &self.item_impl;
+        let target_ident = &self.target_ident;
+        let wrappers = ["init", "lexize"].into_iter().map(|kind| {
+            let wrapper_ident = Ident::new(
+                &format!("{}_{}_wrapper", self.function_prefix(), kind),
+                target_ident.span(),
+            );
+            let finfo_ident = Ident::new(&format!("pg_finfo_{}", wrapper_ident), target_ident.span());
+            let support_fn = Ident::new(&format!("ts_dictionary_{}", kind), target_ident.span());
+            quote! {
+                #[no_mangle]
+                #[doc(hidden)]
+                #[::pgx::pgx_macros::pg_guard]
+                pub unsafe extern "C" fn #wrapper_ident(fcinfo: ::pgx::pg_sys::FunctionCallInfo) -> ::pgx::pg_sys::Datum {
+                    ::pgx::tsearch::#support_fn::<#target_ident>(fcinfo)
+                }
+
+                #[no_mangle]
+                #[doc(hidden)]
+                pub extern "C" fn #finfo_ident() -> &'static ::pgx::pg_sys::Pg_finfo_record {
+                    const V1_API: ::pgx::pg_sys::Pg_finfo_record = ::pgx::pg_sys::Pg_finfo_record { api_version: 1 };
+                    &V1_API
+                }
+            }
+        });
+
+        quote! {
+            #item_impl
+            #( #wrappers )*
+        }
+    }
+}
+
+impl Parse for CodeEnrichment<PgTsDictionary> {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        PgTsDictionary::new(input.parse()?)
+    }
+}
@@ -0,0 +1,103 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+/*!
+
+`#[pg_ts_parser]` related entities for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+use crate::{PgxSql, SqlGraphEntity, SqlGraphIdentifier, ToSql, ToSqlConfigEntity};
+
+/// The output of a [`PgTsParser`](crate::PgTsParser) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PgTsParserEntity {
+    pub name: &'static str,
+    pub function_prefix: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+impl From<PgTsParserEntity> for SqlGraphEntity {
+    fn from(val: PgTsParserEntity) -> Self {
+        SqlGraphEntity::TsParser(val)
+    }
+}
+
+impl ToSql for PgTsParserEntity {
+    #[tracing::instrument(
+        level = "error",
+        skip(self, context),
+        fields(identifier = %self.rust_identifier()),
+    )]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let self_index = context.ts_parsers[self];
+        let schema = context.schema_prefix_for(&self_index);
+
+        let function = |kind: &str, args: &str, returns: &str| {
+            format!(
+                "CREATE FUNCTION {schema}\"{name}_{kind}\"({args}) RETURNS {returns}\n\
+                    \tSTRICT LANGUAGE c AS 'MODULE_PATHNAME', '{function_prefix}_{kind}_wrapper';\n",
+                schema = schema,
+                name = self.name,
+                kind = kind,
+                args = args,
+                returns = returns,
+                function_prefix = self.function_prefix,
+            )
+        };
+
+        let sql = format!(
+            "\n\
+            -- {file}:{line}\n\
+            -- {full_path}\n\
+            {start}\
+            {gettoken}\
+            {end}\
+            {lextype}\
+            CREATE TEXT SEARCH PARSER {schema}\"{name}\" (\n\
+                \tSTART = {schema}\"{name}_start\",\n\
+                \tGETTOKEN = {schema}\"{name}_gettoken\",\n\
+                \tEND = {schema}\"{name}_end\",\n\
+                \tLEXTYPES = {schema}\"{name}_lextype\"\n\
+            );",
+            file = self.file,
+            line = self.line,
+            full_path = self.full_path,
+            start = function("start", "internal, integer", "internal"),
+            gettoken = function("gettoken", "internal, internal, internal", "internal"),
+            end = function("end", "internal", "void"),
+            lextype = function("lextype", "internal", "internal"),
+            schema = schema,
+            name = self.name,
+        );
+        Ok(sql)
+    }
+}
+
+impl SqlGraphIdentifier for PgTsParserEntity {
+    fn dot_identifier(&self) -> String {
+        format!("text search parser {}", self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
@@ -0,0 +1,219 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+/*!
+
+`#[pg_ts_parser]` related macro expansion for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+pub mod entity;
+
+use crate::enrich::{CodeEnrichment, ToEntityGraphTokens, ToRustCodeTokens};
+use crate::ToSqlConfig;
+use convert_case::{Case, Casing};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{parse_quote, ItemImpl};
+
+/// A parsed `#[pg_ts_parser]` item.
+///
+/// It should be used with [`syn::parse::Parse`] functions.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a [`PgTsParserEntity`][crate::PgTsParserEntity].
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_sql_entity_graph::PgTsParser;
+///
+/// # fn main() -> eyre::Result<()> {
+/// use pgx_sql_entity_graph::CodeEnrichment;
+/// let parsed: CodeEnrichment<PgTsParser> = parse_quote! {
+///     impl TsParser for WordParser {
+///         const TOKEN_TYPES: &'static [TsTokenType] = &[TsTokenType::new(1, "word", "Word")];
+///         fn start(text: &str) -> Self { todo!() }
+///         fn next_token(&mut self) -> Option<(i32, &str)> { todo!() }
+///     }
+/// };
+/// let sql_graph_entity_tokens = parsed.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgTsParser {
+    item_impl: ItemImpl,
+    target_ident: Ident,
+    name: syn::LitStr,
+    to_sql_config: ToSqlConfig,
+}
+
+impl PgTsParser {
+    pub fn new(mut item_impl: ItemImpl) -> Result<CodeEnrichment<Self>, syn::Error> {
+        let to_sql_config =
+            ToSqlConfig::from_attributes(item_impl.attrs.as_slice())?.unwrap_or_default();
+        let (target_ident, name) =
+            impl_target_and_name(&mut item_impl, "TsParser", "#[pg_ts_parser]")?;
+
+        Ok(CodeEnrichment(PgTsParser { item_impl, target_ident, name, to_sql_config }))
+    }
+
+    fn function_prefix(&self) -> String {
+        self.target_ident.to_string().to_case(Case::Snake)
+    }
+}
+
+/// Find the type a text search `impl` is for, and its `NAME`, adding a `NAME` of the type's name
+/// in snake case if there isn't one.
+pub(crate) fn impl_target_and_name(
+    item_impl: &mut ItemImpl,
+    trait_name: &str,
+    macro_name: &str,
+) -> Result<(Ident, syn::LitStr), syn::Error> {
+    let implements_trait = match &item_impl.trait_ {
+        Some((_, path, _)) => path.segments.last().map_or(false, |last| last.ident == trait_name),
+        None => false,
+    };
+    if !implements_trait {
+        return Err(syn::Error::new(
+            item_impl.span(),
+            format!("`{}` only works with the `{}` trait.", macro_name, trait_name),
+        ));
+    }
+
+    let target_ident = match &*item_impl.self_ty {
+        syn::Type::Path(type_path) => match type_path.path.segments.last() {
+            Some(last) => last.ident.clone(),
+            None => {
+                return Err(syn::Error::new(
+                    type_path.span(),
+                    format!(
+                        "`{}` only works with types whose path have a final segment.",
+                        macro_name
+                    ),
+                ))
+            }
+        },
+        something_else => {
+            return Err(syn::Error::new(
+                something_else.span(),
+                format!("`{}` only works with types.", macro_name),
+            ))
+        }
+    };
+
+    let name_const = item_impl.items.iter().find_map(|item| match item {
+        syn::ImplItem::Const(item_const) if item_const.ident == "NAME" => Some(item_const),
+        _ => None,
+    });
+    let name = match name_const {
+        Some(item_const) => match &item_const.expr {
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(name), .. }) => name.clone(),
+            e => {
+                return Err(syn::Error::new(
+                    e.span(),
+                    format!("`NAME` must be a `&'static str` for {} implementations.", trait_name),
+                ))
+            }
+        },
+        None => {
+            let name = syn::LitStr::new(
+                &target_ident.to_string().to_case(Case::Snake),
+                target_ident.span(),
+            );
+            item_impl.items.push(parse_quote! {
+                const NAME: &'static str = #name;
+            });
+            name
+        }
+    };
+    // Roughly `pgx::pg_sys::NAMEDATALEN`, less the longest suffix of the generated functions
+    if name.value().len() >= 64 - "_gettoken".len() {
+        return Err(syn::Error::new(
+            name.span(),
+            format!("`NAME` `{}` is too long for Postgres, opt for a shorter one", name.value()),
+        ));
+    }
+
+    Ok((target_ident, name))
+}
+
+impl ToEntityGraphTokens for PgTsParser {
+    fn to_entity_graph_tokens(&self) -> TokenStream2 {
+        let target_ident = &self.target_ident;
+        let function_prefix = self.function_prefix();
+        let sql_graph_entity_fn_name = syn::Ident::new(
+            &format!("__pgx_internals_ts_parser_{}", function_prefix),
+            target_ident.span(),
+        );
+        let name = &self.name;
+        let to_sql_config = &self.to_sql_config;
+
+        quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "Rust" fn #sql_graph_entity_fn_name() -> ::pgx::pgx_sql_entity_graph::SqlGraphEntity {
+                let submission = ::pgx::pgx_sql_entity_graph::PgTsParserEntity {
+                    name: #name,
+                    function_prefix: #function_prefix,
+                    file: file!(),
+                    line: line!(),
+                    full_path: ::core::any::type_name::<#target_ident>(),
+                    module_path: module_path!(),
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgx::pgx_sql_entity_graph::SqlGraphEntity::TsParser(submission)
+            }
+        }
+    }
+}
+
+impl ToRustCodeTokens for PgTsParser {
+    fn to_rust_code_tokens(&self) -> TokenStream2 {
+        let item_impl = &self.item_impl;
+        let target_ident = &self.target_ident;
+        let wrappers = ["start", "gettoken", "end", "lextype"].into_iter().map(|kind| {
+            let wrapper_ident = Ident::new(
+                &format!("{}_{}_wrapper", self.function_prefix(), kind),
+                target_ident.span(),
+            );
+            let finfo_ident = Ident::new(&format!("pg_finfo_{}", wrapper_ident), target_ident.span());
+            let support_fn = Ident::new(&format!("ts_parser_{}", kind), target_ident.span());
+            quote! {
+                #[no_mangle]
+                #[doc(hidden)]
+                #[::pgx::pgx_macros::pg_guard]
+                pub unsafe extern "C" fn #wrapper_ident(fcinfo: ::pgx::pg_sys::FunctionCallInfo) -> ::pgx::pg_sys::Datum {
+                    ::pgx::tsearch::#support_fn::<#target_ident>(fcinfo)
+                }
+
+                #[no_mangle]
+                #[doc(hidden)]
+                pub extern "C" fn #finfo_ident() -> &'static ::pgx::pg_sys::Pg_finfo_record {
+                    const V1_API: ::pgx::pg_sys::Pg_finfo_record = ::pgx::pg_sys::Pg_finfo_record { api_version: 1 };
+                    &V1_API
+                }
+            }
+        });
+
+        quote! {
+            #item_impl
+            #( #wrappers )*
+        }
+    }
+}
+
+impl Parse for CodeEnrichment<PgTsParser> {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        PgTsParser::new(input.parse()?)
+    }
+}
@@ -31,6 +31,8 @@ use crate::extension_sql::entity::{ExtensionSqlEntity, SqlDeclaredEntity};
 use crate::extension_sql::SqlDeclared;
 use crate::pg_extern::entity::PgExternEntity;
 use crate::pg_trigger::entity::PgTriggerEntity;
+use crate::pg_ts_dictionary::entity::PgTsDictionaryEntity;
+use crate::pg_ts_parser::entity::PgTsParserEntity;
 use crate::positioning_ref::PositioningRef;
 use crate::postgres_enum::entity::PostgresEnumEntity;
 use crate::postgres_hash::entity::PostgresHashEntity;
@@ -77,6 +79,8 @@ pub struct PgxSql {
     pub hashes: HashMap<PostgresHashEntity, NodeIndex>,
     pub aggregates: HashMap<PgAggregateEntity, NodeIndex>,
     pub triggers: HashMap<PgTriggerEntity, NodeIndex>,
+    pub ts_parsers: HashMap<PgTsParserEntity, NodeIndex>,
+    pub ts_dictionaries: HashMap<PgTsDictionaryEntity, NodeIndex>,
     pub extension_name: String,
     pub versioned_so: bool,
 }
@@ -103,6 +107,8 @@ impl PgxSql {
         let mut hashes: Vec<PostgresHashEntity> = Vec::default();
         let mut aggregates: Vec<PgAggregateEntity> = Vec::default();
         let mut triggers: Vec<PgTriggerEntity> = Vec::default();
+        let mut ts_parsers: Vec<PgTsParserEntity> = Vec::default();
+        let mut ts_dictionaries: Vec<PgTsDictionaryEntity> = Vec::default();
         for entity in entities {
             match entity {
                 SqlGraphEntity::ExtensionRoot(input_control) => {
@@ -136,6 +142,12 @@ impl PgxSql {
                 SqlGraphEntity::Trigger(input_trigger) => {
                     triggers.push(input_trigger);
                 }
+                SqlGraphEntity::TsParser(input_ts_parser) => {
+                    ts_parsers.push(input_ts_parser);
+                }
+                SqlGraphEntity::TsDictionary(input_ts_dictionary) => {
+                    ts_dictionaries.push(input_ts_dictionary);
+                }
             }
         }
 
@@ -177,6 +189,10 @@ impl PgxSql {
             &mapped_types,
         )?;
         let mapped_triggers = initialize_triggers(&mut graph, root, bootstrap, finalize, triggers)?;
+        let mapped_ts_parsers =
+            initialize_ts_parsers(&mut graph, root, bootstrap, finalize, ts_parsers)?;
+        let mapped_ts_dictionaries =
+            initialize_ts_dictionaries(&mut graph, root, bootstrap, finalize, ts_dictionaries)?;
 
         // Now we can circle back and build up the edge sets.
         connect_schemas(&mut graph, &mapped_schemas, root);
@@ -188,6 +204,8 @@ impl PgxSql {
             &mapped_enums,
             &mapped_externs,
             &mapped_triggers,
+            &mapped_ts_parsers,
+            &mapped_ts_dictionaries,
         )?;
         connect_enums(&mut graph, &mapped_enums, &mapped_schemas);
         connect_types(&mut graph, &mapped_types, &mapped_schemas);
@@ -201,6 +219,8 @@ impl PgxSql {
             &mapped_builtin_types,
             &mapped_extension_sqls,
             &mapped_triggers,
+            &mapped_ts_parsers,
+            &mapped_ts_dictionaries,
         )?;
         connect_ords(
             &mut graph,
@@ -228,6 +248,8 @@ impl PgxSql {
             &mapped_externs,
         )?;
         connect_triggers(&mut graph, &mapped_triggers, &mapped_schemas);
+        connect_ts_parsers(&mut graph, &mapped_ts_parsers, &mapped_schemas);
+        connect_ts_dictionaries(&mut graph, &mapped_ts_dictionaries, &mapped_schemas);
 
         let this = Self {
             control: control,
@@ -241,6 +263,8 @@ impl PgxSql {
             hashes: mapped_hashes,
             aggregates: mapped_aggregates,
             triggers: mapped_triggers,
+            ts_parsers: mapped_ts_parsers,
+            ts_dictionaries: mapped_ts_dictionaries,
             graph: graph,
             graph_root: root,
             graph_bootstrap: bootstrap,
@@ -377,6 +401,14 @@ impl PgxSql {
                         "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFE4E0\", weight = 5, shape = \"diamond\"",
                         node.dot_identifier()
                     ),
+                    SqlGraphEntity::TsParser(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFE4E0\", weight = 5, shape = \"diamond\"",
+                        node.dot_identifier()
+                    ),
+                    SqlGraphEntity::TsDictionary(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFE4E0\", weight = 5, shape = \"diamond\"",
+                        node.dot_identifier()
+                    ),
                     SqlGraphEntity::CustomSql(_item) => format!(
                         "label = \"{}\", weight = 3, shape = \"signature\"",
                         node.dot_identifier()
@@ -551,6 +583,8 @@ pub fn find_positioning_ref_target<'a>(
     schemas: &'a HashMap<SchemaEntity, NodeIndex>,
     extension_sqls: &'a HashMap<ExtensionSqlEntity, NodeIndex>,
     triggers: &'a HashMap<PgTriggerEntity, NodeIndex>,
+    ts_parsers: &'a HashMap<PgTsParserEntity, NodeIndex>,
+    ts_dictionaries: &'a HashMap<PgTsDictionaryEntity, NodeIndex>,
 ) -> Option<&'a NodeIndex> {
     match positioning_ref {
         PositioningRef::FullPath(path) => {
@@ -589,6 +623,20 @@ pub fn find_positioning_ref_target<'a>(
                     return Some(&other_index);
                 }
             }
+            for (other, other_index) in ts_parsers {
+                if other.full_path.ends_with(&format!("::{}", last_segment))
+                    && other.module_path.ends_with(&module_path)
+                {
+                    return Some(&other_index);
+                }
+            }
+            for (other, other_index) in ts_dictionaries {
+                if other.full_path.ends_with(&format!("::{}", last_segment))
+                    && other.module_path.ends_with(&module_path)
+                {
+                    return Some(&other_index);
+                }
+            }
         }
         PositioningRef::Name(name) => {
             for (other, other_index) in extension_sqls {
@@ -610,6 +658,8 @@ fn connect_extension_sqls(
     enums: &HashMap<PostgresEnumEntity, NodeIndex>,
     externs: &HashMap<PgExternEntity, NodeIndex>,
     triggers: &HashMap<PgTriggerEntity, NodeIndex>,
+    ts_parsers: &HashMap<PgTsParserEntity, NodeIndex>,
+    ts_dictionaries: &HashMap<PgTsDictionaryEntity, NodeIndex>,
 ) -> eyre::Result<()> {
     for (item, &index) in extension_sqls {
         make_schema_connection(
@@ -630,6 +680,8 @@ fn connect_extension_sqls(
                 schemas,
                 extension_sqls,
                 triggers,
+                ts_parsers,
+                ts_dictionaries,
             ) {
                 tracing::debug!(from = %item.rust_identifier(), to = ?graph[*target].rust_identifier(), "Adding ExtensionSQL after positioning ref target");
                 graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
@@ -867,6 +919,8 @@ fn connect_externs(
     builtin_types: &HashMap<String, NodeIndex>,
     extension_sqls: &HashMap<ExtensionSqlEntity, NodeIndex>,
     triggers: &HashMap<PgTriggerEntity, NodeIndex>,
+    ts_parsers: &HashMap<PgTsParserEntity, NodeIndex>,
+    ts_dictionaries: &HashMap<PgTsDictionaryEntity, NodeIndex>,
 ) -> eyre::Result<()> {
     for (item, &index) in externs {
         let mut found_schema_declaration = false;
@@ -882,6 +936,8 @@ fn connect_externs(
                             schemas,
                             extension_sqls,
                             triggers,
+                            ts_parsers,
+                            ts_dictionaries,
                         ) {
                             tracing::debug!(from = %item.rust_identifier(), to = %graph[*target].rust_identifier(), "Adding Extern after positioning ref target");
                             graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
@@ -1501,6 +1557,80 @@ fn connect_triggers(
     }
 }
 
+#[tracing::instrument(level = "info", skip_all)]
+fn initialize_ts_parsers(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    ts_parsers: Vec<PgTsParserEntity>,
+) -> eyre::Result<HashMap<PgTsParserEntity, NodeIndex>> {
+    let mut mapped_ts_parsers = HashMap::default();
+    for item in ts_parsers {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+
+        mapped_ts_parsers.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_ts_parsers)
+}
+
+#[tracing::instrument(level = "info", skip_all)]
+fn connect_ts_parsers(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    ts_parsers: &HashMap<PgTsParserEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+) {
+    for (item, &index) in ts_parsers {
+        make_schema_connection(
+            graph,
+            "Text search parser",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            schemas,
+        );
+    }
+}
+
+#[tracing::instrument(level = "info", skip_all)]
+fn initialize_ts_dictionaries(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    ts_dictionaries: Vec<PgTsDictionaryEntity>,
+) -> eyre::Result<HashMap<PgTsDictionaryEntity, NodeIndex>> {
+    let mut mapped_ts_dictionaries = HashMap::default();
+    for item in ts_dictionaries {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+
+        mapped_ts_dictionaries.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_ts_dictionaries)
+}
+
+#[tracing::instrument(level = "info", skip_all)]
+fn connect_ts_dictionaries(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    ts_dictionaries: &HashMap<PgTsDictionaryEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+) {
+    for (item, &index) in ts_dictionaries {
+        make_schema_connection(
+            graph,
+            "Text search dictionary",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            schemas,
+        );
+    }
+}
+
 #[tracing::instrument(level = "info", skip_all, fields(rust_identifier))]
 fn make_schema_connection(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
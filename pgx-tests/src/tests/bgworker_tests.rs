@@ -6,8 +6,16 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
+use pgx::bgworker_pool::BackgroundWorkerPool;
 use pgx::prelude::*;
-use pgx::{FromDatum, IntoDatum, PgOid};
+use pgx::{FromDatum, IntoDatum, PgAtomic, PgOid};
+use std::sync::atomic::{AtomicI64, Ordering};
+
+pub static WORKER_POOL: BackgroundWorkerPool<i64> =
+    BackgroundWorkerPool::new("pgx_tests pool", "pgx_tests", "bgworker_pool_worker")
+        .workers_per_database(2)
+        .max_databases(1);
+pub static WORKER_POOL_SUM: PgAtomic<AtomicI64> = PgAtomic::new();
 
 #[pg_guard]
 #[no_mangle]
@@ -82,15 +90,149 @@ pub extern "C" fn bgworker_return_value(arg: pg_sys::Datum) {
     .expect("bgworker transaction failed");
 }
 
+#[pg_guard]
+#[no_mangle]
+/// Streams rows back to the launching backend through a shared memory queue
+pub extern "C" fn bgworker_shm_mq(arg: pg_sys::Datum) {
+    use pgx::bgworkers::*;
+    use pgx::shm_mq::*;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+
+    let mut sender = ShmMqSender::<(i64, String)>::attach(ShmMqHandle::from(arg));
+    for i in 1..=100 {
+        sender.send(&(i, format!("row {}", i))).expect("receiver detached");
+    }
+}
+
+#[pg_extern]
+fn bgworker_shm_mq_rows() -> TableIterator<'static, (name!(id, i64), name!(label, String))> {
+    use pgx::bgworkers::*;
+    use pgx::shm_mq::*;
+
+    // small enough that the worker has to wait for us to drain it
+    let mut receiver = ShmMqReceiver::<(i64, String)>::create(1024);
+    let worker = BackgroundWorkerBuilder::new("dynamic_bgworker_shm_mq")
+        .set_library("pgx_tests")
+        .set_function("bgworker_shm_mq")
+        .set_argument(Some(receiver.handle().into()))
+        .enable_shmem_access(None)
+        .set_notify_pid(unsafe { pg_sys::MyProcPid })
+        .load_dynamic();
+    receiver.attach_worker(&worker);
+
+    TableIterator::new(receiver)
+}
+
+#[pg_guard]
+#[no_mangle]
+/// Doubles each job with SPI and adds it to `WORKER_POOL_SUM`.  Negative jobs crash the worker.
+pub extern "C" fn bgworker_pool_worker(arg: pg_sys::Datum) {
+    WORKER_POOL.run_worker(arg, |job| {
+        if job < 0 {
+            panic!("negative job");
+        }
+        let doubled = Spi::get_one_with_args::<i64>(
+            "SELECT $1 * 2",
+            vec![(PgOid::BuiltIn(PgBuiltInOids::INT8OID), job.into_datum())],
+        )
+        .expect("SPI failed")
+        .unwrap();
+        WORKER_POOL_SUM.get().fetch_add(doubled, Ordering::SeqCst);
+    });
+}
+
+pgx::bgworker_pool_status!(bgworker_pool_status, WORKER_POOL);
+
+#[pg_guard]
+#[no_mangle]
+/// Exercises tasks, sockets, timers and SPI from other threads inside `run_async`
+pub extern "C" fn bgworker_async(_arg: pg_sys::Datum) {
+    use pgx::async_worker::*;
+    use pgx::bgworkers::*;
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    let value = BackgroundWorker::run_async(async {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        spawn(async move {
+            sleep(Duration::from_millis(10)).await;
+            b.write_all(b"x").unwrap();
+        });
+        readable(&a).await;
+
+        let spi = transaction_handle();
+        let thread =
+            std::thread::spawn(move || spi.run_blocking(|| Spi::get_one::<i32>("SELECT 40 + 1")));
+        while !thread.is_finished() {
+            sleep(Duration::from_millis(1)).await;
+        }
+        let from_thread = thread.join().unwrap().unwrap().unwrap().unwrap();
+
+        let from_future = transaction_handle()
+            .run(|| Spi::get_one::<i32>("SELECT 1"))
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        from_thread + from_future
+    })
+    .expect("async runtime stopped");
+
+    BackgroundWorker::transaction(|| {
+        Spi::run(&format!("CREATE TABLE tests.bgworker_async AS SELECT {} AS v;", value))
+    })
+    .expect("bgworker transaction failed");
+}
+
+#[pg_guard]
+#[no_mangle]
+/// Waits forever inside `run_async`, until it is terminated
+pub extern "C" fn bgworker_async_sigterm(_arg: pg_sys::Datum) {
+    use pgx::bgworkers::*;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    let stopped = BackgroundWorker::run_async(std::future::pending::<()>()).is_none();
+    BackgroundWorker::transaction(|| {
+        Spi::run(&format!(
+            "CREATE TABLE tests.bgworker_async_sigterm AS SELECT {} AS stopped;",
+            stopped
+        ))
+    })
+    .expect("bgworker transaction failed");
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
     #[allow(unused_imports)]
     use crate as pgx_tests;
 
+    use crate::tests::bgworker_tests::{WORKER_POOL, WORKER_POOL_SUM};
     use pgx::bgworkers::*;
     use pgx::prelude::*;
     use pgx::{pg_sys, IntoDatum};
+    use std::sync::atomic::Ordering;
+
+    /// Poll `f` every 10ms for up to 10 seconds, waiting for it to become true
+    fn wait_for(mut f: impl FnMut() -> bool) -> bool {
+        for _ in 0..1000 {
+            if f() {
+                return true;
+            }
+            unsafe { pg_sys::pg_usleep(10_000) };
+        }
+        false
+    }
 
     #[pg_test]
     fn test_dynamic_bgworker() {
@@ -155,4 +297,87 @@ mod tests {
 
         assert_eq!(Ok(Some(123)), Spi::get_one::<i32>("SELECT v FROM tests.bgworker_test_return;"));
     }
+
+    #[pg_test]
+    fn test_shm_mq_stream_from_bgworker() {
+        let (count, sum) = Spi::get_two::<i64, i64>(
+            "SELECT count(*), sum(id)::bigint FROM tests.bgworker_shm_mq_rows();",
+        )
+        .expect("SPI failed");
+        assert_eq!(count, Some(100));
+        assert_eq!(sum, Some(5050));
+
+        assert_eq!(
+            Ok(Some("row 42".to_string())),
+            Spi::get_one::<String>("SELECT label FROM tests.bgworker_shm_mq_rows() WHERE id = 42;")
+        );
+    }
+
+    #[pg_test]
+    fn test_bgworker_pool() {
+        assert_eq!(WORKER_POOL.start().expect("pool failed to start"), 2);
+        assert_eq!(WORKER_POOL.start().expect("pool failed to start"), 0);
+        assert_eq!(
+            Ok(Some(2)),
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM tests.bgworker_pool_status() WHERE pid IS NOT NULL"
+            )
+        );
+
+        for job in 1..=10 {
+            WORKER_POOL.submit(&job).expect("failed to submit job");
+        }
+        assert!(wait_for(|| WORKER_POOL_SUM.get().load(Ordering::SeqCst) == 110));
+        assert!(wait_for(
+            || WORKER_POOL.status().iter().map(|w| w.jobs_completed).sum::<u64>() == 10
+        ));
+        assert_eq!(WORKER_POOL.pending_jobs(), 0);
+
+        // a crashing job is counted as failed and its worker is restarted by the postmaster
+        WORKER_POOL.submit(&-1).expect("failed to submit job");
+        assert!(wait_for(|| WORKER_POOL
+            .status()
+            .iter()
+            .any(|w| w.restarts == 1 && w.jobs_failed == 1 && w.pid.is_some())));
+        WORKER_POOL.submit(&100).expect("failed to submit job");
+        assert!(wait_for(|| WORKER_POOL_SUM.get().load(Ordering::SeqCst) == 310));
+
+        assert_eq!(WORKER_POOL.stop(), 2);
+        assert!(wait_for(|| WORKER_POOL.status().is_empty()));
+        assert!(matches!(
+            WORKER_POOL.submit(&1),
+            Err(pgx::bgworker_pool::BackgroundWorkerPoolError::NotRunning)
+        ));
+    }
+
+    #[pg_test]
+    fn test_bgworker_run_async() {
+        let worker = BackgroundWorkerBuilder::new("dynamic_bgworker_async")
+            .set_library("pgx_tests")
+            .set_function("bgworker_async")
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pg_sys::MyProcPid })
+            .load_dynamic();
+        worker.wait_for_startup().expect("no PID from the worker");
+        worker.wait_for_shutdown().expect("aborted shutdown");
+
+        assert_eq!(Ok(Some(42)), Spi::get_one::<i32>("SELECT v FROM tests.bgworker_async;"));
+    }
+
+    #[pg_test]
+    fn test_bgworker_run_async_sigterm() {
+        let worker = BackgroundWorkerBuilder::new("dynamic_bgworker_async_sigterm")
+            .set_library("pgx_tests")
+            .set_function("bgworker_async_sigterm")
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pg_sys::MyProcPid })
+            .load_dynamic();
+        worker.wait_for_startup().expect("no PID from the worker");
+        worker.terminate().wait_for_shutdown().expect("aborted shutdown");
+
+        assert_eq!(
+            Ok(Some(true)),
+            Spi::get_one::<bool>("SELECT stopped FROM tests.bgworker_async_sigterm;")
+        );
+    }
 }
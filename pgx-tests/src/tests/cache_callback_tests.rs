@@ -0,0 +1,63 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{register_relcache_callback, register_syscache_callback};
+    use std::cell::{Cell, RefCell};
+
+    thread_local! {
+        static INVALIDATED: RefCell<Vec<Option<pg_sys::Oid>>> = RefCell::new(Vec::new());
+        static PROC_INVALIDATIONS: Cell<usize> = Cell::new(0);
+    }
+
+    fn oid(query: &str) -> pg_sys::Oid {
+        Spi::get_one::<pg_sys::Oid>(query).expect("SPI failed").expect("no oid")
+    }
+
+    #[pg_test]
+    fn test_relcache_callback() {
+        Spi::run("CREATE TABLE relcache_callback_test (id int)").expect("SPI failed");
+        let relid = oid("SELECT 'relcache_callback_test'::regclass::oid");
+
+        let receipt = register_relcache_callback(|relid| {
+            INVALIDATED.with(|invalidated| invalidated.borrow_mut().push(relid))
+        });
+        Spi::run("ALTER TABLE relcache_callback_test ADD COLUMN name text").expect("SPI failed");
+        let invalidated = INVALIDATED.with(|invalidated| invalidated.take());
+        assert!(invalidated.contains(&Some(relid)));
+
+        receipt.unregister_callback();
+        Spi::run("ALTER TABLE relcache_callback_test ADD COLUMN other text").expect("SPI failed");
+        assert!(INVALIDATED.with(|invalidated| invalidated.borrow().is_empty()));
+    }
+
+    #[pg_test]
+    fn test_syscache_callback() {
+        Spi::run("CREATE FUNCTION syscache_callback_test() RETURNS int LANGUAGE sql AS 'SELECT 1'")
+            .expect("SPI failed");
+
+        let receipt = register_syscache_callback(pg_sys::SysCacheIdentifier_PROCOID, |_| {
+            PROC_INVALIDATIONS.with(|count| count.set(count.get() + 1))
+        });
+        Spi::run("CREATE OR REPLACE FUNCTION syscache_callback_test() RETURNS int LANGUAGE sql AS 'SELECT 2'")
+            .expect("SPI failed");
+        assert!(PROC_INVALIDATIONS.with(|count| count.replace(0)) > 0);
+
+        receipt.unregister_callback();
+        Spi::run("CREATE OR REPLACE FUNCTION syscache_callback_test() RETURNS int LANGUAGE sql AS 'SELECT 3'")
+            .expect("SPI failed");
+        assert_eq!(PROC_INVALIDATIONS.with(|count| count.get()), 0);
+    }
+}
@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::ErrorContextGuard;
+
+    fn catch_context<F: FnOnce() + std::panic::UnwindSafe>(f: F) -> Option<String> {
+        PgTryBuilder::new(|| {
+            f();
+            unreachable!("no error was raised")
+        })
+        .catch_others(|e| match e {
+            pg_sys::panic::CaughtError::PostgresError(report)
+            | pg_sys::panic::CaughtError::ErrorReport(report)
+            | pg_sys::panic::CaughtError::RustPanic { ereport: report, .. } => {
+                report.context_message().map(|s| s.to_string())
+            }
+        })
+        .execute()
+    }
+
+    #[pg_test]
+    fn test_error_context_postgres_error() {
+        let stack = unsafe { pg_sys::error_context_stack };
+        let context = catch_context(|| {
+            pgx::error_context(
+                || format!("while processing row {}", 42),
+                || Spi::run("SELECT 1/0").expect("SPI failed"),
+            );
+        });
+        assert!(context.unwrap().contains("while processing row 42"));
+        assert_eq!(stack, unsafe { pg_sys::error_context_stack });
+    }
+
+    #[pg_test]
+    fn test_error_context_rust_error() {
+        let stack = unsafe { pg_sys::error_context_stack };
+        let context = catch_context(|| {
+            pgx::error_context(|| "outer", || pgx::error_context(|| "inner", || error!("oh no")));
+        });
+        assert_eq!(context.as_deref(), Some("inner\nouter"));
+        assert_eq!(stack, unsafe { pg_sys::error_context_stack });
+    }
+
+    #[pg_test]
+    fn test_error_context_no_error() {
+        let stack = unsafe { pg_sys::error_context_stack };
+        let result = pgx::error_context(|| "unused", || 42);
+        assert_eq!(result, 42);
+        assert_eq!(stack, unsafe { pg_sys::error_context_stack });
+    }
+
+    #[pg_test]
+    fn test_error_context_guard() {
+        let stack = unsafe { pg_sys::error_context_stack };
+        {
+            let _guard = unsafe { ErrorContextGuard::new(|| "in the guard") };
+            assert_ne!(stack, unsafe { pg_sys::error_context_stack });
+            let context = catch_context(|| Spi::run("SELECT 1/0").expect("SPI failed"));
+            assert!(context.unwrap().contains("in the guard"));
+        }
+        assert_eq!(stack, unsafe { pg_sys::error_context_stack });
+    }
+
+    #[pg_test]
+    fn test_error_context_guard_out_of_order() {
+        let stack = unsafe { pg_sys::error_context_stack };
+        let first = unsafe { ErrorContextGuard::new(|| "first") };
+        let second = unsafe { ErrorContextGuard::new(|| "second") };
+        drop(first);
+        let context = catch_context(|| Spi::run("SELECT 1/0").expect("SPI failed")).unwrap();
+        assert!(context.contains("second"));
+        assert!(!context.contains("first"));
+        drop(second);
+        assert_eq!(stack, unsafe { pg_sys::error_context_stack });
+    }
+}
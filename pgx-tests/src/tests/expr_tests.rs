@@ -0,0 +1,134 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{PgExpr, PgTupleDesc, PgTupleSlot, TryFromDatumError};
+
+    fn create_type() -> PgTupleDesc<'static> {
+        Spi::run("CREATE TYPE expr_dog AS (name text, age int)").expect("SPI failed");
+        PgTupleDesc::for_composite_type("expr_dog").unwrap()
+    }
+
+    #[pg_test]
+    fn test_expr_filter_columns() {
+        let tupdesc = create_type();
+        let mut filter = PgExpr::compile("age > 5 AND name LIKE 'B%'", &tupdesc, &[]).unwrap();
+        assert_eq!(filter.result_type(), pg_sys::BOOLOID);
+
+        let mut slot = PgTupleSlot::new(&tupdesc);
+        let dogs = [("Brandy", Some(10)), ("Nami", Some(12)), ("Bear", Some(2)), ("Bingo", None)];
+        let matches = dogs
+            .into_iter()
+            .map(|(name, age)| {
+                slot.store_datums([name.into_datum(), age.into_datum()]).unwrap();
+                filter.evaluate::<bool>(Some(&slot), &[]).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(matches, vec![Some(true), Some(false), Some(false), None]);
+    }
+
+    #[pg_test]
+    fn test_expr_result_value() {
+        let tupdesc = create_type();
+        let mut expr = PgExpr::compile("upper(name) || ' is ' || age", &tupdesc, &[]).unwrap();
+        assert_eq!(expr.result_type(), pg_sys::TEXTOID);
+
+        let mut slot = PgTupleSlot::new(&tupdesc);
+        slot.store_datums(["Brandy".into_datum(), 42.into_datum()]).unwrap();
+        assert_eq!(expr.evaluate::<String>(Some(&slot), &[]), Ok(Some("BRANDY is 42".to_string())));
+        assert!(matches!(
+            expr.evaluate::<i32>(Some(&slot), &[]),
+            Err(TryFromDatumError::IncompatibleTypes { .. })
+        ));
+    }
+
+    #[pg_test]
+    fn test_expr_params() {
+        let tupdesc = create_type();
+        let mut filter =
+            PgExpr::compile("age BETWEEN $1 AND $2", &tupdesc, &[pg_sys::INT4OID, pg_sys::INT4OID])
+                .unwrap();
+        assert_eq!(filter.param_types(), &[pg_sys::INT4OID, pg_sys::INT4OID]);
+
+        let mut slot = PgTupleSlot::new(&tupdesc);
+        slot.store_datums(["Brandy".into_datum(), 42.into_datum()]).unwrap();
+        assert_eq!(
+            filter.evaluate::<bool>(Some(&slot), &[40.into_datum(), 50.into_datum()]),
+            Ok(Some(true))
+        );
+        assert_eq!(
+            filter.evaluate::<bool>(Some(&slot), &[1.into_datum(), 10.into_datum()]),
+            Ok(Some(false))
+        );
+        assert_eq!(filter.evaluate::<bool>(Some(&slot), &[1.into_datum(), None]), Ok(None));
+    }
+
+    #[pg_test]
+    fn test_expr_without_slot() {
+        let tupdesc = create_type();
+        let mut expr = PgExpr::compile("$1 * 2", &tupdesc, &[pg_sys::INT8OID]).unwrap();
+        assert_eq!(expr.evaluate::<i64>(None, &[21i64.into_datum()]), Ok(Some(42)));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "division by zero")]
+    fn test_expr_planning_error() {
+        let tupdesc = create_type();
+        let _ = PgExpr::compile("age + 1 / 0", &tupdesc, &[]);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "expected 1 parameters but got 0")]
+    fn test_expr_wrong_param_count() {
+        let tupdesc = create_type();
+        let mut expr = PgExpr::compile("$1 + 1", &tupdesc, &[pg_sys::INT4OID]).unwrap();
+        let _ = expr.evaluate::<i32>(None, &[]);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "the tuple slot is empty")]
+    fn test_expr_empty_slot() {
+        let tupdesc = create_type();
+        let mut expr = PgExpr::compile("age + 1", &tupdesc, &[]).unwrap();
+        let slot = PgTupleSlot::new(&tupdesc);
+        let _ = expr.evaluate::<i32>(Some(&slot), &[]);
+    }
+
+    #[pg_test]
+    fn test_expr_compile_errors() {
+        let tupdesc = create_type();
+        let error = |sql: &str| PgExpr::compile(sql, &tupdesc, &[]).err().unwrap();
+
+        let unknown = error("age > 5 AND breed = 'lab'");
+        assert!(unknown.message().contains("breed"), "{}", unknown.message());
+        assert_eq!(unknown.position(), Some(13));
+
+        assert_eq!(error("age >").position(), Some(6));
+        assert_eq!(error("1 FROM pg_class").message(), "not a single scalar expression");
+        assert_eq!(error("1; SELECT 2").message(), "not a single scalar expression");
+        assert_eq!(
+            error("EXISTS (SELECT 1 FROM pg_class)").message(),
+            "cannot use subquery in expression"
+        );
+        assert!(error("count(*) > 1").message().contains("aggregate"));
+    }
+
+    #[pg_test]
+    fn test_expr_from_node() {
+        let mut expr = unsafe { PgExpr::from_node(pg_sys::makeBoolConst(true, false).cast(), &[]) };
+        assert_eq!(expr.result_type(), pg_sys::BOOLOID);
+        assert_eq!(expr.evaluate::<bool>(None, &[]), Ok(Some(true)));
+    }
+}
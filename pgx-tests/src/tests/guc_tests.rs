@@ -143,4 +143,207 @@ mod tests {
         Spi::run("SET test.enum = 'three'").expect("SPI failed");
         assert_eq!(GUC.get(), TestEnum::Three);
     }
+
+    #[pg_test]
+    fn test_int_guc_units() {
+        static TIMEOUT: GucSetting<i32> = GucSetting::new(1000);
+        GucRegistry::define_int_guc_with_hooks(
+            "test.timeout",
+            "test int guc in milliseconds",
+            "test int guc in milliseconds",
+            &TIMEOUT,
+            0,
+            i32::MAX,
+            GucContext::Userset,
+            GucFlags::UNIT_MS,
+            GucHooks::default(),
+        );
+        static CACHE_SIZE: GucSetting<i32> = GucSetting::new(64);
+        GucRegistry::define_int_guc_with_hooks(
+            "test.cache_size",
+            "test int guc in kilobytes",
+            "test int guc in kilobytes",
+            &CACHE_SIZE,
+            0,
+            i32::MAX,
+            GucContext::Userset,
+            GucFlags::UNIT_KB,
+            GucHooks::default(),
+        );
+
+        Spi::run("SET test.timeout = '5s'").expect("SPI failed");
+        assert_eq!(TIMEOUT.get(), 5000);
+        assert_eq!(Ok(Some("5s".to_string())), Spi::get_one::<String>("SHOW test.timeout"));
+
+        Spi::run("SET test.cache_size = '2MB'").expect("SPI failed");
+        assert_eq!(CACHE_SIZE.get(), 2048);
+    }
+
+    #[pg_test(error = "invalid value for parameter \"test.even\": 3")]
+    fn test_int_guc_check_hook() {
+        static GUC: GucSetting<i32> = GucSetting::new(2);
+        GucRegistry::define_int_guc_with_hooks(
+            "test.even",
+            "test int guc check hook",
+            "test int guc check hook",
+            &GUC,
+            0,
+            100,
+            GucContext::Userset,
+            GucFlags::default(),
+            GucHooks::new().check(|v| match v % 2 {
+                0 => Ok(()),
+                _ => Err(format!("{} is not even", v)),
+            }),
+        );
+
+        Spi::run("SET test.even = 4").expect("SPI failed");
+        assert_eq!(GUC.get(), 4);
+
+        Spi::run("SET test.even = 3").expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn test_string_guc_assign_and_show_hooks() {
+        use std::cell::RefCell;
+        thread_local! {
+            static ASSIGNED: RefCell<Vec<Option<String>>> = RefCell::new(Vec::new());
+        }
+        static GUC: GucSetting<Option<&'static str>> = GucSetting::new(Some("boot"));
+        GucRegistry::define_string_guc_with_hooks(
+            "test.hooked_string",
+            "test string guc hooks",
+            "test string guc hooks",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+            GucHooks::new()
+                .assign(|v| ASSIGNED.with(|a| a.borrow_mut().push(v)))
+                .show(|| format!("<{}>", GUC.get().unwrap_or_default())),
+        );
+
+        Spi::run("SET test.hooked_string = 'foo'").expect("SPI failed");
+        assert_eq!(GUC.get().unwrap(), "foo");
+        assert_eq!(
+            ASSIGNED.with(|a| a.borrow().clone()),
+            vec![Some("boot".to_string()), Some("foo".to_string())]
+        );
+        assert_eq!(
+            Ok(Some("<foo>".to_string())),
+            Spi::get_one::<String>("SHOW test.hooked_string")
+        );
+    }
+
+    #[pg_test]
+    fn test_string_guc_hooks_reject_invalid_utf8() {
+        static GUC: GucSetting<Option<&'static str>> = GucSetting::new(Some("valid"));
+        GucRegistry::define_string_guc_with_hooks(
+            "test.utf8_string",
+            "test string guc with invalid utf8",
+            "test string guc with invalid utf8",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+            GucHooks::new().assign(|_| ()),
+        );
+
+        let result = unsafe {
+            pg_sys::set_config_option(
+                b"test.utf8_string\0".as_ptr().cast(),
+                b"\xff\0".as_ptr().cast(),
+                pg_sys::GucContext_PGC_USERSET,
+                pg_sys::GucSource_PGC_S_SESSION,
+                pg_sys::GucAction_GUC_ACTION_SET,
+                true,
+                pg_sys::WARNING as _,
+                false,
+            )
+        };
+        assert_eq!(result, 0);
+        assert_eq!(GUC.get().as_deref(), Some("valid"));
+    }
+
+    #[pg_test(error = "invalid value for parameter \"test.hooked_enum\": \"Three\"")]
+    fn test_enum_guc_check_hook() {
+        #[derive(PostgresGucEnum, Clone, Copy, PartialEq, Debug)]
+        enum TestEnum {
+            One,
+            Two,
+            Three,
+        }
+        static GUC: GucSetting<TestEnum> = GucSetting::new(TestEnum::Two);
+        GucRegistry::define_enum_guc_with_hooks(
+            "test.hooked_enum",
+            "test enum guc check hook",
+            "test enum guc check hook",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+            GucHooks::new().check(|v| match v {
+                TestEnum::Three => Err("three is not allowed".into()),
+                _ => Ok(()),
+            }),
+        );
+
+        Spi::run("SET test.hooked_enum = 'one'").expect("SPI failed");
+        assert_eq!(GUC.get(), TestEnum::One);
+
+        Spi::run("SET test.hooked_enum = 'three'").expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn test_guc_group() {
+        #[derive(PostgresGucEnum, Clone, Copy, PartialEq, Debug)]
+        enum Mode {
+            Fast,
+            Safe,
+        }
+
+        #[derive(PostgresGucs, Debug, PartialEq)]
+        #[gucs(prefix = "test_gucs")]
+        struct Config {
+            /// enable the thing
+            enabled: bool,
+            /// how long to wait
+            #[guc(min = 0, max = 60_000, default = 1_000, flags = UNIT_MS)]
+            timeout: i32,
+            #[guc(name = "ratio", short_description = "a ratio", min = 0.0, max = 1.0)]
+            fraction: f64,
+            /// where to connect
+            #[guc(context = Suset, default = "localhost")]
+            host: Option<String>,
+            /// how to go
+            #[guc(default = Safe)]
+            mode: Mode,
+        }
+
+        Config::register();
+        assert_eq!(Config::PREFIX, "test_gucs");
+        assert_eq!(
+            Config::get(),
+            Config {
+                enabled: false,
+                timeout: 1_000,
+                fraction: 0.0,
+                host: Some("localhost".into()),
+                mode: Mode::Safe,
+            }
+        );
+
+        Spi::run("SET test_gucs.enabled = on").expect("SPI failed");
+        Spi::run("SET test_gucs.timeout = '5s'").expect("SPI failed");
+        Spi::run("SET test_gucs.ratio = 0.5").expect("SPI failed");
+        Spi::run("SET test_gucs.host = 'example.com'").expect("SPI failed");
+        Spi::run("SET test_gucs.mode = 'fast'").expect("SPI failed");
+        assert_eq!(
+            Config::get(),
+            Config {
+                enabled: true,
+                timeout: 5_000,
+                fraction: 0.5,
+                host: Some("example.com".into()),
+                mode: Mode::Fast,
+            }
+        );
+    }
 }
@@ -0,0 +1,185 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{AnyNumeric, JsonbBuilder, JsonbRef, JsonbValueRef, OwnedJsonb};
+
+    #[pg_extern]
+    fn jsonb_ref_first_tag<'a>(doc: JsonbRef<'a>) -> Option<&'a str> {
+        doc.get("tags")?.index(0)?.as_str()
+    }
+
+    #[pg_extern]
+    fn jsonb_ref_nested<'a>(doc: JsonbRef<'a>, key: &str) -> Option<JsonbRef<'a>> {
+        doc.get(key)?.as_object()
+    }
+
+    #[pg_extern]
+    fn jsonb_ref_summary(doc: JsonbRef<'_>) -> OwnedJsonb {
+        let mut builder = JsonbBuilder::new();
+        builder.begin_object();
+        builder.key("keys").begin_array();
+        for (key, _) in doc.entries() {
+            builder.push(key);
+        }
+        builder.end_array();
+        builder.key("count").push_number(doc.len());
+        builder.key("doc").push(doc);
+        builder.end_object();
+        builder.build()
+    }
+
+    fn doc() -> JsonbRef<'static> {
+        Spi::get_one::<JsonbRef>(
+            r#"SELECT '{"name": "pgx", "stars": 3000, "ratio": 0.5, "rust": true,
+                        "license": null, "tags": ["postgres", "rust"],
+                        "owner": {"login": "tcdi", "id": 42}}'::jsonb"#,
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    #[pg_test]
+    fn test_jsonb_ref_lookup() {
+        let doc = doc();
+        assert!(doc.is_object());
+        assert_eq!(doc.len(), 7);
+
+        assert_eq!(doc.get("name").and_then(|v| v.as_str()), Some("pgx"));
+        assert_eq!(doc.get("stars").and_then(|v| v.as_i64()), Some(3000));
+        assert_eq!(doc.get("ratio").and_then(|v| v.as_f64()), Some(0.5));
+        assert_eq!(doc.get("ratio").and_then(|v| v.as_i64()), None);
+        assert_eq!(doc.get("rust").and_then(|v| v.as_bool()), Some(true));
+        assert!(doc.get("license").unwrap().is_null());
+        assert!(doc.get("missing").is_none());
+        assert_eq!(doc.get("owner").and_then(|v| v.get("id")).and_then(|v| v.as_i64()), Some(42));
+
+        let tags = doc.get("tags").and_then(|v| v.as_array()).unwrap();
+        assert!(tags.is_array());
+        assert_eq!(tags.index(1).and_then(|v| v.as_str()), Some("rust"));
+        assert!(tags.index(2).is_none());
+        assert!(tags.get("rust").is_none());
+        assert!(doc.index(0).is_none());
+    }
+
+    #[pg_test]
+    fn test_jsonb_ref_iterators() {
+        let doc = doc();
+        let keys = doc.entries().map(|(key, _)| key).collect::<Vec<_>>();
+        // jsonb sorts keys by length, then bytes
+        assert_eq!(keys, vec!["name", "rust", "tags", "owner", "ratio", "stars", "license"]);
+
+        let tags = doc.get("tags").unwrap().as_array().unwrap();
+        let tags = tags.elements().map(|v| v.as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(tags, vec!["postgres", "rust"]);
+
+        assert_eq!(doc.elements().count(), 0);
+
+        // stopping early is fine
+        assert!(matches!(doc.entries().next(), Some(("name", JsonbValueRef::String("pgx")))));
+    }
+
+    #[pg_test]
+    fn test_jsonb_ref_scalar() {
+        let scalar = Spi::get_one::<JsonbRef>("SELECT '\"hello\"'::jsonb").unwrap().unwrap();
+        assert!(scalar.is_scalar());
+        assert!(!scalar.is_array());
+        assert_eq!(scalar.value().as_str(), Some("hello"));
+        assert_eq!(scalar.elements().count(), 0);
+        assert_eq!(scalar.to_string(), "\"hello\"");
+    }
+
+    #[pg_test]
+    fn test_jsonb_ref_from_sql() {
+        assert_eq!(
+            Spi::get_one::<&str>(
+                r#"SELECT tests.jsonb_ref_first_tag('{"tags": ["a", "b"]}'::jsonb)"#
+            ),
+            Ok(Some("a"))
+        );
+        assert_eq!(
+            Spi::get_one::<&str>(r#"SELECT tests.jsonb_ref_first_tag('{"tags": []}'::jsonb)"#),
+            Ok(None)
+        );
+        assert_eq!(
+            Spi::get_one::<bool>(
+                r#"SELECT tests.jsonb_ref_nested('{"a": {"b": [1, {"c": null}]}}'::jsonb, 'a')
+                        = '{"b": [1, {"c": null}]}'::jsonb"#
+            ),
+            Ok(Some(true))
+        );
+    }
+
+    #[pg_test]
+    fn test_jsonb_builder() {
+        let tag = String::from("rust");
+        let mut builder = JsonbBuilder::new();
+        builder.begin_object();
+        builder.key("name").push("pgx");
+        builder.key("stars").push_number(3000);
+        builder.key("ratio").push_number(AnyNumeric::try_from(0.5).unwrap());
+        builder.key("license").push(None::<&str>);
+        builder.key("tags").begin_array().push("postgres").push(tag.as_str()).end_array();
+        builder.key("owner").push(doc().get("owner").unwrap());
+        builder.key("rust").push(true);
+        builder.end_object();
+        let built = builder.build();
+
+        assert_eq!(built.to_string(), doc().to_string());
+        assert_eq!(
+            Spi::get_one_with_args::<bool>(
+                "SELECT $1 = (SELECT tests.jsonb_ref_summary($1) -> 'doc')",
+                vec![(PgOid::BuiltIn(PgBuiltInOids::JSONBOID), built.into_datum())],
+            ),
+            Ok(Some(true))
+        );
+    }
+
+    #[pg_test]
+    fn test_jsonb_builder_scalars() {
+        let mut builder = JsonbBuilder::new();
+        builder.push_number(42);
+        let built = builder.build();
+        assert!(built.as_jsonb_ref().is_scalar());
+        assert_eq!(built.to_string(), "42");
+
+        let mut builder = JsonbBuilder::new();
+        builder.begin_array().end_array();
+        assert_eq!(builder.build().to_string(), "[]");
+
+        assert_eq!(
+            Spi::get_one::<JsonbRef>(r#"SELECT tests.jsonb_ref_summary('{"b": 1, "a": 2}')"#)
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            r#"{"doc": {"a": 2, "b": 1}, "keys": ["a", "b"], "count": 2}"#
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "expected a jsonb key")]
+    fn test_jsonb_builder_missing_key() {
+        let mut builder = JsonbBuilder::new();
+        builder.begin_object().push(true);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "incomplete jsonb document")]
+    fn test_jsonb_builder_incomplete() {
+        let mut builder = JsonbBuilder::new();
+        builder.begin_array().push(true);
+        builder.build();
+    }
+}
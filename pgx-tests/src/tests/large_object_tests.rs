@@ -0,0 +1,130 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{LargeObjectMode, PgLargeObject};
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    #[pg_test]
+    fn test_large_object_roundtrip() {
+        let mut lo = PgLargeObject::create();
+        lo.write_all(b"hello, world").unwrap();
+        assert_eq!(lo.stream_position().unwrap(), 12);
+
+        assert_eq!(lo.seek(SeekFrom::Start(7)).unwrap(), 7);
+        let mut world = String::new();
+        lo.read_to_string(&mut world).unwrap();
+        assert_eq!(world, "world");
+
+        assert_eq!(lo.seek(SeekFrom::End(-5)).unwrap(), 7);
+        lo.write_all(b"there").unwrap();
+        assert_eq!(lo.seek(SeekFrom::Current(-12)).unwrap(), 0);
+        let mut contents = Vec::new();
+        lo.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"hello, there");
+
+        // and SQL sees the same thing
+        let oid = lo.oid();
+        drop(lo);
+        assert_eq!(
+            Spi::get_one::<Vec<u8>>(&format!("SELECT lo_get({})", oid.as_u32())),
+            Ok(Some(b"hello, there".to_vec()))
+        );
+    }
+
+    #[pg_test]
+    fn test_large_object_streaming() {
+        // spans many of pg_largeobject's 2kB pages
+        let data = (0..1024 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        let mut lo = PgLargeObject::create();
+        std::io::copy(&mut data.as_slice(), &mut lo).unwrap();
+        let oid = lo.oid();
+        drop(lo);
+
+        let mut lo = PgLargeObject::open(oid, LargeObjectMode::Read);
+        let mut copied = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            match lo.read(&mut buf).unwrap() {
+                0 => break,
+                n => copied.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(copied, data);
+    }
+
+    #[pg_test]
+    fn test_large_object_truncate() {
+        let mut lo = PgLargeObject::create();
+        lo.write_all(b"hello, world").unwrap();
+        lo.truncate(5);
+        assert_eq!(lo.seek(SeekFrom::End(0)).unwrap(), 5);
+
+        lo.truncate(8);
+        lo.rewind().unwrap();
+        let mut contents = Vec::new();
+        lo.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"hello\0\0\0");
+    }
+
+    #[pg_test]
+    fn test_large_object_from_sql() {
+        let oid =
+            Spi::get_one::<pg_sys::Oid>("SELECT lo_from_bytea(0, 'from sql')").unwrap().unwrap();
+        let mut lo = PgLargeObject::open(oid, LargeObjectMode::Read);
+        let mut contents = String::new();
+        lo.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "from sql");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "was not opened for writing")]
+    fn test_large_object_read_only() {
+        let oid = PgLargeObject::create().oid();
+        let mut lo = PgLargeObject::open(oid, LargeObjectMode::Read);
+        let _ = lo.write(b"nope");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "cannot execute lo_create() in a read-only transaction")]
+    fn test_large_object_read_only_transaction() {
+        Spi::run("SET transaction_read_only = on").expect("SPI failed");
+        let _ = PgLargeObject::create();
+    }
+
+    #[pg_test]
+    fn test_large_object_unlink() {
+        let oid = PgLargeObject::create().oid();
+        let exists = || {
+            Spi::get_one::<bool>(&format!(
+                "SELECT EXISTS(SELECT 1 FROM pg_largeobject_metadata WHERE oid = {})",
+                oid.as_u32()
+            ))
+        };
+        assert_eq!(exists(), Ok(Some(true)));
+
+        PgLargeObject::unlink(oid);
+        assert_eq!(exists(), Ok(Some(false)));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "does not exist")]
+    fn test_large_object_open_missing() {
+        let oid = PgLargeObject::create().oid();
+        PgLargeObject::unlink(oid);
+        let _ = PgLargeObject::open(oid, LargeObjectMode::Read);
+    }
+}
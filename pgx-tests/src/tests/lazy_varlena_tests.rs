@@ -0,0 +1,146 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{PgLazyVarlena, ToastCompression};
+
+    #[pg_extern]
+    fn lazy_prefix<'a>(document: PgLazyVarlena<'a, str>, len: i32) -> &'a [u8] {
+        document.slice(0, len as usize)
+    }
+
+    #[pg_extern]
+    fn lazy_slice<'a>(document: PgLazyVarlena<'a, [u8]>, offset: i32, len: i32) -> &'a [u8] {
+        document.slice(offset as usize, len as usize)
+    }
+
+    #[pg_extern]
+    fn lazy_sizes<'a>(document: PgLazyVarlena<'a, str>) -> Vec<i64> {
+        vec![document.raw_size() as i64, document.stored_size() as i64]
+    }
+
+    #[pg_extern]
+    fn lazy_is_external<'a>(document: PgLazyVarlena<'a, str>) -> bool {
+        document.is_external()
+    }
+
+    #[pg_extern]
+    fn lazy_is_pglz<'a>(document: PgLazyVarlena<'a, str>) -> bool {
+        document.compression() == Some(ToastCompression::Pglz)
+    }
+
+    #[pg_extern]
+    fn lazy_passthrough<'a>(document: PgLazyVarlena<'a, str>) -> PgLazyVarlena<'a, str> {
+        document
+    }
+
+    fn create_documents() {
+        Spi::run(
+            "CREATE TABLE lazy_docs (id int, doc text);
+             ALTER TABLE lazy_docs ALTER COLUMN doc SET STORAGE EXTERNAL;
+             INSERT INTO lazy_docs VALUES
+                (1, 'small'),
+                (2, (SELECT string_agg(md5(i::text), '') FROM generate_series(1, 10000) i));",
+        )
+        .expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn test_lazy_varlena_slice() {
+        create_documents();
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT bool_and(tests.lazy_prefix(doc, 40) = convert_to(left(doc, 40), 'UTF8')) \
+                 FROM lazy_docs"
+            ),
+            Ok(Some(true))
+        );
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT tests.lazy_slice(convert_to(doc, 'UTF8'), 200000, 32) = convert_to(substr(doc, 200001, 32), 'UTF8') \
+                 FROM lazy_docs WHERE id = 2"
+            ),
+            Ok(Some(true))
+        );
+
+        // slices are clipped to the end of the value
+        assert_eq!(
+            Spi::get_one::<&[u8]>("SELECT tests.lazy_slice('abcdefg'::bytea, 4, 100)"),
+            Ok(Some(b"efg".as_slice()))
+        );
+        assert_eq!(
+            Spi::get_one::<&[u8]>("SELECT tests.lazy_slice('abcdefg'::bytea, 10, 1)"),
+            Ok(Some(b"".as_slice()))
+        );
+    }
+
+    #[pg_test]
+    fn test_lazy_varlena_sizes() {
+        create_documents();
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT bool_and(tests.lazy_sizes(doc) = ARRAY[octet_length(doc), pg_column_size(doc)]::bigint[]) \
+                 FROM lazy_docs"
+            ),
+            Ok(Some(true))
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<i64>>("SELECT tests.lazy_sizes(doc) FROM lazy_docs WHERE id = 2"),
+            Ok(Some(vec![320000, 320000]))
+        );
+    }
+
+    #[pg_test]
+    fn test_lazy_varlena_storage() {
+        create_documents();
+        Spi::run("INSERT INTO lazy_docs VALUES (3, repeat('pgx', 100000))").expect("SPI failed");
+        Spi::run("ALTER TABLE lazy_docs ALTER COLUMN doc SET STORAGE EXTENDED")
+            .expect("SPI failed");
+        Spi::run("INSERT INTO lazy_docs VALUES (4, repeat('pgx', 100000))").expect("SPI failed");
+
+        let storage = |id: i32| {
+            Spi::get_two::<bool, bool>(&format!(
+                "SELECT tests.lazy_is_external(doc), tests.lazy_is_pglz(doc) FROM lazy_docs WHERE id = {}",
+                id
+            ))
+        };
+        assert_eq!(storage(1), Ok((Some(false), Some(false))));
+        assert_eq!(storage(2), Ok((Some(true), Some(false))));
+        assert_eq!(storage(3), Ok((Some(true), Some(false))));
+        // compressed, whether or not it then fit inline
+        assert_eq!(storage(4).map(|(_, pglz)| pglz), Ok(Some(true)));
+        assert_eq!(
+            Spi::get_one::<&[u8]>("SELECT tests.lazy_prefix(doc, 9) FROM lazy_docs WHERE id = 4"),
+            Ok(Some(b"pgxpgxpgx".as_slice()))
+        );
+    }
+
+    #[pg_test]
+    fn test_lazy_varlena_detoast() {
+        create_documents();
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT bool_and(tests.lazy_passthrough(doc) = doc) FROM lazy_docs"
+            ),
+            Ok(Some(true))
+        );
+
+        let text = "hello".into_datum().unwrap();
+        let lazy = unsafe { PgLazyVarlena::<str>::from_datum(text, false) }.unwrap();
+        assert_eq!(lazy.detoast(), "hello");
+        assert_eq!(lazy.slice(1, 3), b"ell");
+        assert_eq!(lazy.compression(), None);
+    }
+}
@@ -0,0 +1,128 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{LockMode, PgAdvisoryLock, PgPageLock, PgRelation, PgRelationLock, PgTupleLock};
+
+    /// The modes this backend holds locks of `locktype` in, for locks matching `filter`
+    fn held_locks(locktype: &str, filter: &str) -> Vec<String> {
+        Spi::get_one::<Vec<String>>(&format!(
+            "SELECT coalesce(array_agg(mode ORDER BY mode), '{{}}') FROM pg_locks \
+             WHERE pid = pg_backend_pid() AND locktype = '{}' AND {}",
+            locktype, filter
+        ))
+        .unwrap()
+        .unwrap()
+    }
+
+    fn create_table() -> PgRelation {
+        Spi::run("CREATE TABLE locked_table (id int); INSERT INTO locked_table VALUES (1)")
+            .expect("SPI failed");
+        PgRelation::open_with_name_and_share_lock("locked_table").unwrap()
+    }
+
+    #[pg_test]
+    fn test_lock_mode_order() {
+        assert!(LockMode::AccessShare < LockMode::RowExclusive);
+        assert!(LockMode::Exclusive < LockMode::AccessExclusive);
+        assert_eq!(pg_sys::LOCKMODE::from(LockMode::AccessShare), 1);
+        assert_eq!(pg_sys::LOCKMODE::from(LockMode::AccessExclusive), 8);
+    }
+
+    #[pg_test]
+    fn test_relation_lock() {
+        let relation = create_table();
+        let filter = format!("relation = {}", relation.oid().as_u32());
+
+        let lock = PgRelationLock::lock(relation.oid(), LockMode::ShareRowExclusive);
+        assert_eq!(lock.relid(), relation.oid());
+        assert!(held_locks("relation", &filter).contains(&"ShareRowExclusiveLock".to_string()));
+        drop(lock);
+        assert!(!held_locks("relation", &filter).contains(&"ShareRowExclusiveLock".to_string()));
+
+        // our own locks never conflict with each other
+        let lock = PgRelationLock::try_lock(relation.oid(), LockMode::Exclusive).unwrap();
+        assert_eq!(lock.mode(), LockMode::Exclusive);
+        lock.hold_until_end_of_xact();
+        assert!(held_locks("relation", &filter).contains(&"ExclusiveLock".to_string()));
+    }
+
+    #[pg_test]
+    fn test_page_and_tuple_locks() {
+        let relation = create_table();
+        let filter = format!("relation = {}", relation.oid().as_u32());
+
+        let page = PgPageLock::lock(&relation, 0, LockMode::Exclusive);
+        assert_eq!(page.blkno(), 0);
+        assert_eq!(held_locks("page", &filter), vec!["ExclusiveLock"]);
+        drop(page);
+        assert!(held_locks("page", &filter).is_empty());
+
+        let mut tid = pg_sys::ItemPointerData::default();
+        pgx::item_pointer_set_all(&mut tid, 0, 1);
+        let tuple = PgTupleLock::try_lock(&relation, tid, LockMode::Share).unwrap();
+        assert_eq!(held_locks("tuple", &filter), vec!["ShareLock"]);
+        drop(tuple);
+        assert!(held_locks("tuple", &filter).is_empty());
+    }
+
+    #[pg_test]
+    fn test_session_advisory_lock() {
+        let filter = "classid = 0 AND objid = 42 AND objsubid = 1";
+
+        let lock = PgAdvisoryLock::lock(42i64);
+        assert!(!lock.is_shared());
+        assert_eq!(held_locks("advisory", filter), vec!["ExclusiveLock"]);
+        drop(lock);
+        assert!(held_locks("advisory", filter).is_empty());
+
+        let shared = PgAdvisoryLock::try_lock_shared(42i64).unwrap();
+        let again = PgAdvisoryLock::lock_shared(42i64);
+        assert!(shared.is_shared());
+        assert_eq!(held_locks("advisory", filter), vec!["ShareLock"]);
+        drop(shared);
+        drop(again);
+        assert!(held_locks("advisory", filter).is_empty());
+    }
+
+    #[pg_test]
+    fn test_advisory_lock_keys() {
+        let lock = PgAdvisoryLock::try_lock((7, 8)).unwrap();
+        assert_eq!(lock.key(), pgx::AdvisoryLockKey::Int4Pair(7, 8));
+        assert_eq!(
+            held_locks("advisory", "classid = 7 AND objid = 8 AND objsubid = 2"),
+            vec!["ExclusiveLock"]
+        );
+
+        // the same lock SQL's pg_advisory_lock() functions see
+        assert_eq!(Spi::get_one::<bool>("SELECT pg_try_advisory_lock(7, 8)"), Ok(Some(true)));
+        assert_eq!(Spi::get_one::<bool>("SELECT pg_advisory_unlock(7, 8)"), Ok(Some(true)));
+    }
+
+    #[pg_test]
+    fn test_xact_advisory_lock() {
+        let filter = "classid = 0 AND objid = 43 AND objsubid = 1";
+
+        PgAdvisoryLock::xact_lock(43i64);
+        assert!(PgAdvisoryLock::try_xact_lock_shared(43i64));
+        assert_eq!(held_locks("advisory", filter), vec!["ExclusiveLock", "ShareLock"]);
+
+        // transaction-level locks can't be released by the session-level unlock functions
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT pg_advisory_unlock_shared(43::bigint)"),
+            Ok(Some(false))
+        );
+    }
+}
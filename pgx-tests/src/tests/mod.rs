@@ -13,11 +13,14 @@ mod array_tests;
 mod attributes_tests;
 mod bgworker_tests;
 mod bytea_tests;
+mod cache_callback_tests;
 mod cfg_tests;
 mod datetime_tests;
 mod default_arg_value_tests;
 mod derive_pgtype_lifetimes;
 mod enum_type_tests;
+mod error_context_tests;
+mod expr_tests;
 mod fcinfo_tests;
 mod from_into_datum_tests;
 mod guc_tests;
@@ -27,11 +30,18 @@ mod hooks_tests;
 mod inet_tests;
 mod internal_tests;
 mod json_tests;
+mod jsonb_ref_tests;
+mod lazy_varlena_tests;
+mod large_object_tests;
 mod lifetime_tests;
+mod lock_tests;
 mod log_tests;
 mod memcxt_tests;
 mod name_tests;
+mod node_tests;
+mod notify_tests;
 mod numeric_tests;
+mod parser_tests;
 mod pg_extern_tests;
 mod pg_guard_tests;
 mod pg_try_tests;
@@ -40,14 +50,21 @@ mod pgx_module_qualification;
 mod postgres_type_tests;
 mod range_tests;
 mod result_tests;
+mod scan_tests;
 mod schema_tests;
 mod shmem_tests;
+mod snapshot_tests;
 mod spi_tests;
 mod srf_tests;
 mod struct_type_tests;
+mod syscache_tests;
+mod tracing_tests;
 mod trigger_tests;
+mod tsearch_tests;
+mod tuptable_tests;
 mod uuid_tests;
 mod variadic_tests;
+mod wait_event_tests;
 mod xact_callback_tests;
 mod xid64_tests;
 mod zero_datum_edge_cases;
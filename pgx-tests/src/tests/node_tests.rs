@@ -0,0 +1,161 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{expression_tree_walker, query_tree_mutator, query_tree_walker, NodeRef, PgList};
+
+    fn analyze(sql: &str) -> *mut pg_sys::Query {
+        let sql = std::ffi::CString::new(sql).unwrap();
+        unsafe {
+            let stmt = PgList::<pg_sys::RawStmt>::from_pg(pg_sys::pg_parse_query(sql.as_ptr()))
+                .head()
+                .expect("no statement");
+            #[cfg(feature = "pg15")]
+            let query = pg_sys::parse_analyze_fixedparams(
+                stmt,
+                sql.as_ptr(),
+                std::ptr::null(),
+                0,
+                std::ptr::null_mut(),
+            );
+            #[cfg(not(feature = "pg15"))]
+            let query = pg_sys::parse_analyze(
+                stmt,
+                sql.as_ptr(),
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+            );
+            query
+        }
+    }
+
+    #[pg_test]
+    fn test_node_ref_query() {
+        let query = analyze("SELECT relname, 42 FROM pg_class WHERE oid > 10 AND relname = 'x'");
+        let query = unsafe { NodeRef::from_pg(query.cast()) }.unwrap();
+        assert!(matches!(query, NodeRef::Query(_)));
+        assert_eq!(query.tag(), pg_sys::NodeTag_T_Query);
+
+        let rtable = query.range_table();
+        assert_eq!(rtable.len(), 1);
+        assert_eq!(rtable[0].relid, pg_sys::RelationRelationId);
+
+        let targets = query.target_list();
+        assert_eq!(targets.len(), 2);
+        let relname = NodeRef::TargetEntry(targets[0]).expr().unwrap();
+        assert!(matches!(relname, NodeRef::Var(var) if var.vartype == pg_sys::NAMEOID));
+        let constant = NodeRef::TargetEntry(targets[1]).expr().unwrap();
+        assert!(matches!(constant, NodeRef::Const(c) if c.consttype == pg_sys::INT4OID));
+
+        let quals = match query {
+            NodeRef::Query(query) => unsafe { NodeRef::from_pg((*query.jointree).quals) },
+            _ => unreachable!(),
+        };
+        let quals = quals.expect("no WHERE clause");
+        assert!(
+            matches!(quals, NodeRef::BoolExpr(expr) if expr.boolop == pg_sys::BoolExprType_AND_EXPR)
+        );
+        let args = quals.args();
+        assert_eq!(args.len(), 2);
+        assert!(args.iter().all(|arg| matches!(arg, NodeRef::OpExpr(_))));
+        assert!(matches!(args[0].args()[0], NodeRef::Var(_)));
+    }
+
+    #[pg_test]
+    fn test_expression_tree_walker() {
+        let query = analyze(
+            "SELECT upper(relname) FROM pg_class \
+             WHERE EXISTS (SELECT 1 FROM pg_type WHERE typrelid = pg_class.oid)",
+        );
+
+        let mut relations = Vec::new();
+        let (mut vars, mut funcs, mut sublinks) = (0, 0, 0);
+        let found = unsafe {
+            query_tree_walker(query, |node| {
+                match node {
+                    NodeRef::RangeTblEntry(rte) => relations.push(rte.relid),
+                    NodeRef::Var(_) => vars += 1,
+                    NodeRef::FuncExpr(_) => funcs += 1,
+                    NodeRef::SubLink(_) => sublinks += 1,
+                    _ => {}
+                }
+                false
+            })
+        };
+        assert!(!found);
+        // the sub-query is walked before the outer query's range table
+        assert_eq!(relations, vec![pg_sys::TypeRelationId, pg_sys::RelationRelationId]);
+        assert_eq!(vars, 3);
+        assert_eq!(funcs, 1);
+        assert_eq!(sublinks, 1);
+    }
+
+    #[pg_test]
+    fn test_expression_tree_walker_stops() {
+        let query = analyze("SELECT 1, 2, 3");
+        let mut seen = Vec::new();
+        let found = unsafe {
+            expression_tree_walker(query.cast(), |node| match node {
+                NodeRef::Const(c) => {
+                    seen.push(i32::from_datum(c.constvalue, c.constisnull).unwrap());
+                    seen.len() == 2
+                }
+                _ => false,
+            })
+        };
+        assert!(found);
+        assert_eq!(seen, vec![1, 2]);
+    }
+
+    #[pg_test]
+    fn test_query_tree_mutator() {
+        let query = analyze("SELECT 1 + 1 WHERE EXISTS (SELECT 1)");
+        let mutated = unsafe {
+            query_tree_mutator(query, |node| match node {
+                NodeRef::Const(c) if c.consttype == pg_sys::INT4OID => Some(
+                    pg_sys::makeConst(
+                        pg_sys::INT4OID,
+                        -1,
+                        pg_sys::InvalidOid,
+                        4,
+                        2.into(),
+                        false,
+                        true,
+                    )
+                    .cast(),
+                ),
+                _ => None,
+            })
+        };
+        assert_ne!(mutated, query);
+
+        let constants = |query: *mut pg_sys::Query| {
+            let mut values = Vec::new();
+            unsafe {
+                query_tree_walker(query, |node| {
+                    if let NodeRef::Const(c) = node {
+                        values.push(i32::from_datum(c.constvalue, c.constisnull).unwrap());
+                    }
+                    false
+                });
+            }
+            values
+        };
+        // the original query is left alone
+        assert_eq!(constants(query), vec![1, 1, 1]);
+        assert_eq!(constants(mutated), vec![2, 2, 2]);
+    }
+}
@@ -0,0 +1,99 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::prelude::*;
+
+#[pg_guard]
+#[no_mangle]
+/// Listens to a channel, notifies it, and records the notification it then receives
+pub extern "C" fn bgworker_notify(_arg: pg_sys::Datum) {
+    use pgx::bgworkers::*;
+    use pgx::notify::*;
+    use std::time::Duration;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    let mut listener = PgListener::new();
+    listener.listen("pgx_tests_notify").expect("invalid channel");
+    assert_eq!(listener.channels(), &["pgx_tests_notify".to_string()]);
+
+    BackgroundWorker::transaction(|| notify("pgx_tests_notify", "hello").expect("invalid payload"));
+    let notification = listener
+        .notifications(Some(Duration::from_secs(10)))
+        .next()
+        .expect("no notification received");
+    let from_self = notification.sender_pid == unsafe { pg_sys::MyProcPid };
+
+    BackgroundWorker::transaction(|| {
+        Spi::run(&format!(
+            "CREATE TABLE tests.bgworker_notify AS SELECT '{}' AS channel, '{}' AS payload, {} AS from_self;",
+            notification.channel, notification.payload, from_self
+        ))
+    })
+    .expect("bgworker transaction failed");
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::bgworkers::*;
+    use pgx::notify::*;
+    use pgx::prelude::*;
+
+    #[pg_test]
+    fn test_notify() {
+        assert_eq!(notify("pgx_tests_channel", "payload"), Ok(()));
+        assert_eq!(notify("pgx_tests_channel", ""), Ok(()));
+        assert_eq!(notify("pgx_tests_channel", &"x".repeat(MAX_PAYLOAD_LENGTH)), Ok(()));
+    }
+
+    #[pg_test]
+    fn test_notify_errors() {
+        assert_eq!(notify("", "payload"), Err(NotifyError::EmptyChannel));
+        assert_eq!(
+            notify(&"c".repeat(MAX_CHANNEL_LENGTH + 1), "payload"),
+            Err(NotifyError::ChannelTooLong(MAX_CHANNEL_LENGTH + 1))
+        );
+        assert_eq!(
+            notify("pgx_tests_channel", &"x".repeat(MAX_PAYLOAD_LENGTH + 1)),
+            Err(NotifyError::PayloadTooLong(MAX_PAYLOAD_LENGTH + 1))
+        );
+        assert_eq!(notify("pgx_tests_channel", "pay\0load"), Err(NotifyError::ContainsNul));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "PgListener can only be used from a registered background worker")]
+    fn test_listener_outside_bgworker() {
+        let _ = PgListener::new();
+    }
+
+    #[pg_test]
+    fn test_bgworker_listener() {
+        let worker = BackgroundWorkerBuilder::new("dynamic_bgworker_notify")
+            .set_library("pgx_tests")
+            .set_function("bgworker_notify")
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pg_sys::MyProcPid })
+            .load_dynamic();
+        worker.wait_for_startup().expect("no PID from the worker");
+        worker.wait_for_shutdown().expect("aborted shutdown");
+
+        assert_eq!(
+            Spi::get_three::<String, String, bool>(
+                "SELECT channel, payload, from_self FROM tests.bgworker_notify;"
+            ),
+            Ok((Some("pgx_tests_notify".to_string()), Some("hello".to_string()), Some(true)))
+        );
+    }
+}
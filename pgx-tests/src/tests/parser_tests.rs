@@ -0,0 +1,151 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::parser::{parse, RawNodeKind, SourceLocation};
+    use pgx::prelude::*;
+
+    #[pg_test]
+    fn test_parse_statements() {
+        let sql = "SELECT 1;\n  DELETE FROM foo WHERE id = 2;\nUPDATE foo SET x = 1";
+        let stmts = parse(sql).expect("failed to parse");
+        assert_eq!(stmts.len(), 3);
+
+        assert_eq!(stmts[0].sql(), "SELECT 1");
+        assert!(matches!(stmts[0].node().kind(), RawNodeKind::SelectStmt(_)));
+
+        assert_eq!(stmts[1].sql(), "DELETE FROM foo WHERE id = 2");
+        assert!(matches!(stmts[1].node().kind(), RawNodeKind::DeleteStmt(_)));
+
+        assert_eq!(stmts[2].sql(), "UPDATE foo SET x = 1");
+        assert!(matches!(stmts[2].node().kind(), RawNodeKind::UpdateStmt(_)));
+        assert_eq!(stmts[1].location(), SourceLocation { offset: 12, line: 2, column: 3 });
+        assert_eq!(stmts[2].location(), SourceLocation { offset: 42, line: 3, column: 1 });
+    }
+
+    #[pg_test]
+    fn test_parse_syntax_error() {
+        let sql = "SELECT 1;\nSELEC 2";
+        let error = parse(sql).expect_err("parsed invalid SQL");
+        assert!(error.message().contains("syntax error"));
+
+        let location = SourceLocation::from_position(sql, error.position().unwrap()).unwrap();
+        assert_eq!((location.line, location.column), (2, 1));
+
+        // the transaction is still usable
+        assert_eq!(Spi::get_one::<i32>("SELECT 1"), Ok(Some(1)));
+    }
+
+    #[pg_test]
+    fn test_parse_node_locations() {
+        let sql = "SELECT a,\n       upper(b)\n  FROM t";
+        let stmts = parse(sql).unwrap();
+
+        let mut columns = Vec::new();
+        let mut functions = Vec::new();
+        stmts[0].node().walk(|node| {
+            match node.kind() {
+                RawNodeKind::ColumnRef(_) => columns.push(node.location().unwrap()),
+                RawNodeKind::FuncCall(_) => functions.push(node.location().unwrap()),
+                _ => {}
+            }
+            false
+        });
+        let line_columns = |locations: Vec<SourceLocation>| {
+            locations.iter().map(|location| (location.line, location.column)).collect::<Vec<_>>()
+        };
+        assert_eq!(line_columns(columns), vec![(1, 8), (2, 14)]);
+        assert_eq!(line_columns(functions), vec![(2, 8)]);
+    }
+
+    #[pg_test]
+    fn test_walk_utility_statements() {
+        for sql in [
+            "CREATE TABLE t (a int DEFAULT 1)",
+            "ALTER TABLE t ADD COLUMN b text",
+            "COPY t FROM STDIN",
+        ] {
+            let stmts = parse(sql).unwrap();
+            let mut tags = Vec::new();
+            stmts[0].node().walk(|node| {
+                tags.push(node.tag());
+                false
+            });
+            assert_eq!(tags, vec![stmts[0].node().tag()], "{}", sql);
+        }
+
+        // the transaction is still usable
+        assert_eq!(Spi::get_one::<i32>("SELECT 1"), Ok(Some(1)));
+    }
+
+    #[pg_test]
+    fn test_parse_children() {
+        let stmts = parse("SELECT a, b FROM t WHERE a > 1").unwrap();
+        let node = stmts[0].node();
+        let select = match node.kind() {
+            RawNodeKind::SelectStmt(select) => select,
+            _ => panic!("not a SELECT"),
+        };
+        let targets = unsafe { node.children(select.targetList) };
+        assert_eq!(targets.len(), 2);
+        assert!(targets.iter().all(|target| matches!(target.kind(), RawNodeKind::ResTarget(_))));
+
+        let from = unsafe { node.children(select.fromClause) };
+        match from[0].kind() {
+            RawNodeKind::RangeVar(range_var) => unsafe {
+                assert_eq!(std::ffi::CStr::from_ptr(range_var.relname).to_str(), Ok("t"));
+            },
+            _ => panic!("not a RangeVar"),
+        }
+
+        let qual = unsafe { node.child(select.whereClause) }.unwrap();
+        assert!(matches!(qual.kind(), RawNodeKind::A_Expr(_)));
+    }
+
+    #[pg_test]
+    fn test_deparse() {
+        let deparse = |sql: &str| parse(sql).unwrap()[0].deparse();
+
+        assert_eq!(deparse("select 1").as_deref(), Some("SELECT 1"));
+        assert_eq!(
+            deparse("select a, t.b as \"B\" from s.t where a > 1 and not b is null order by a desc")
+                .as_deref(),
+            Some("SELECT a, t.b AS \"B\" FROM s.t WHERE ((a > 1) AND (NOT (b IS NULL))) ORDER BY a DESC")
+        );
+        assert_eq!(
+            deparse("select count(*), count(distinct x), 'it''s'::text from t group by y having -sum(z) < 0.5")
+                .as_deref(),
+            Some("SELECT count(*), count(DISTINCT x), ('it''s')::text FROM t GROUP BY y HAVING ((- sum(z)) < 0.5)")
+        );
+        assert_eq!(
+            deparse("select * from only t1 x where exists (select 1 from t2) limit 10 offset 5")
+                .as_deref(),
+            Some("SELECT * FROM ONLY t1 AS x WHERE EXISTS (SELECT 1 FROM t2) LIMIT 10 OFFSET 5")
+        );
+        assert_eq!(
+            deparse("select a from t1 union all select b from t2 order by 1").as_deref(),
+            Some("(SELECT a FROM t1) UNION ALL (SELECT b FROM t2) ORDER BY 1")
+        );
+
+        // the deparsed SQL parses back to the same thing
+        let sql = "SELECT DISTINCT ON (a) a, (b)::pg_catalog.int4 FROM t WHERE (a = 'x')";
+        let deparsed = deparse(sql).unwrap();
+        assert_eq!(deparsed, sql);
+        assert_eq!(deparse(&deparsed).as_deref(), Some(sql));
+
+        // unsupported statements
+        assert_eq!(deparse("DELETE FROM t"), None);
+        assert_eq!(deparse("SELECT row_number() OVER () FROM t"), None);
+    }
+}
@@ -61,6 +61,27 @@ fn get_relation_name(oid: pg_sys::Oid) -> String {
     .execute()
 }
 
+#[pg_extern]
+fn raise_full_error_report() {
+    pgx::pg_sys::panic::ErrorReport::new(
+        PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+        "value is out of range",
+        function_name!(),
+    )
+    .set_detail("the value must be positive")
+    .set_detail_log("the value was -1")
+    .set_hint("use a positive value")
+    .set_context("while checking the value")
+    .set_position(8)
+    .set_hide_statement(true)
+    .set_schema_name("public")
+    .set_table_name("accounts")
+    .set_column_name("balance")
+    .set_datatype_name("numeric")
+    .set_constraint_name("accounts_balance_check")
+    .report(PgLogLevel::ERROR);
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pg_schema]
 mod tests {
@@ -328,4 +349,30 @@ mod tests {
         // really just testing that the finally block ran
         assert_eq!(true, finally.load(Ordering::SeqCst));
     }
+
+    #[pg_test]
+    fn test_error_report_fields_survive_errfinish() {
+        let report = PgTryBuilder::new(|| {
+            Spi::run("SELECT raise_full_error_report()").ok();
+            unreachable!("raise_full_error_report() didn't raise an error")
+        })
+        .catch_others(|e| match e {
+            pg_sys::panic::CaughtError::PostgresError(report) => report.error_report().clone(),
+            _ => panic!("expected a Postgres error, got {:?}", e),
+        })
+        .execute();
+
+        assert_eq!(report.message(), "value is out of range");
+        assert_eq!(report.detail(), Some("the value must be positive"));
+        assert_eq!(report.detail_log(), Some("the value was -1"));
+        assert_eq!(report.hint(), Some("use a positive value"));
+        assert!(report.context().unwrap().starts_with("while checking the value"));
+        assert_eq!(report.position(), Some(8));
+        assert!(report.hide_statement());
+        assert_eq!(report.schema_name(), Some("public"));
+        assert_eq!(report.table_name(), Some("accounts"));
+        assert_eq!(report.column_name(), Some("balance"));
+        assert_eq!(report.datatype_name(), Some("numeric"));
+        assert_eq!(report.constraint_name(), Some("accounts_balance_check"));
+    }
 }
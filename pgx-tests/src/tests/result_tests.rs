@@ -68,6 +68,47 @@ mod tests {
         Err(pgx::spi::Error::InvalidPosition)
     }
 
+    #[derive(thiserror::Error, ToPgError, Debug)]
+    #[pg_error(hint = "check the account number")]
+    enum AccountError {
+        #[error("account {0} does not exist")]
+        #[pg_error(code = ERRCODE_NO_DATA_FOUND)]
+        NotFound(i64),
+
+        #[error("insufficient funds")]
+        #[pg_error(code = ERRCODE_CHECK_VIOLATION, detail = "balance would be negative", hint = "deposit more money")]
+        InsufficientFunds { balance: i64 },
+
+        #[error("account is locked")]
+        Locked,
+    }
+
+    #[pg_extern]
+    fn withdraw(account: i64, amount: i64) -> Result<i64, AccountError> {
+        match account {
+            1 => Err(AccountError::InsufficientFunds { balance: 100 - amount }),
+            2 => Err(AccountError::Locked),
+            _ => Err(AccountError::NotFound(account)),
+        }
+    }
+
+    #[pg_extern]
+    fn withdraw_all(account: i64) -> Result<SetOfIterator<'static, i64>, AccountError> {
+        Err(AccountError::NotFound(account))
+    }
+
+    fn catch_error_report(query: &str) -> pgx::pg_sys::panic::ErrorReportWithLevel {
+        PgTryBuilder::new(|| {
+            Spi::run(query).ok();
+            unreachable!("{} didn't raise an error", query)
+        })
+        .catch_others(|e| match e {
+            pg_sys::panic::CaughtError::PostgresError(report) => report,
+            _ => panic!("expected a Postgres error, got {:?}", e),
+        })
+        .execute()
+    }
+
     #[pg_test(error = "No such file or directory (os error 2)")]
     fn test_return_io_error() -> Result<(), std::io::Error> {
         std::fs::read("/tmp/i-sure-hope-this-doest-exist.pgx-tests::test_result_result").map(|_| ())
@@ -147,4 +188,32 @@ mod tests {
     fn test_return_result_set_of_error() -> Result<(), spi::Error> {
         Spi::run("SELECT * FROM tests.return_result_set_of_error()")
     }
+
+    #[pg_test]
+    fn test_to_pg_error_fields() {
+        let report = catch_error_report("SELECT tests.withdraw(1, 500)");
+        assert_eq!(report.sql_error_code(), PgSqlErrorCode::ERRCODE_CHECK_VIOLATION);
+        assert_eq!(report.message(), "insufficient funds");
+        assert_eq!(report.detail(), Some("balance would be negative"));
+        assert_eq!(report.hint(), Some("deposit more money"));
+    }
+
+    #[pg_test]
+    fn test_to_pg_error_defaults() {
+        let report = catch_error_report("SELECT tests.withdraw(2, 500)");
+        assert_eq!(report.sql_error_code(), PgSqlErrorCode::ERRCODE_DATA_EXCEPTION);
+        assert_eq!(report.message(), "account is locked");
+        assert_eq!(report.detail(), None);
+        assert_eq!(report.hint(), Some("check the account number"));
+
+        let report = catch_error_report("SELECT tests.withdraw(3, 500)");
+        assert_eq!(report.sql_error_code(), PgSqlErrorCode::ERRCODE_NO_DATA_FOUND);
+        assert_eq!(report.message(), "account 3 does not exist");
+    }
+
+    #[pg_test]
+    fn test_to_pg_error_set_of() {
+        let report = catch_error_report("SELECT * FROM tests.withdraw_all(3)");
+        assert_eq!(report.sql_error_code(), PgSqlErrorCode::ERRCODE_NO_DATA_FOUND);
+    }
 }
@@ -0,0 +1,82 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{PgRelation, PgSnapshot, ScanKey};
+
+    fn create_table() -> (PgRelation, PgRelation) {
+        Spi::run(
+            "CREATE TABLE scan_test (id bigint, name text);
+             CREATE INDEX scan_test_id_idx ON scan_test (id);
+             INSERT INTO scan_test SELECT i, 'row ' || i FROM generate_series(1, 100) i;",
+        )
+        .expect("SPI failed");
+        let table = PgRelation::open_with_name_and_share_lock("scan_test").unwrap();
+        let index = PgRelation::open_with_name_and_share_lock("scan_test_id_idx").unwrap();
+        (table, index)
+    }
+
+    fn ids<'a>(rows: impl Iterator<Item = PgHeapTuple<'a, AllocatedByRust>>) -> Vec<i64> {
+        rows.map(|row| row.get_by_name::<i64>("id").unwrap().unwrap()).collect()
+    }
+
+    #[pg_test]
+    fn test_heap_scan() {
+        let (table, _) = create_table();
+        let snapshot = PgSnapshot::transaction();
+
+        let mut all = ids(table.heap_scan(&snapshot, &[]));
+        all.sort();
+        assert_eq!(all, (1..=100).collect::<Vec<_>>());
+
+        // the column is a bigint, but we can compare it to an i32
+        let rows = table.heap_scan(&snapshot, &[ScanKey::equal(1, 42)]).collect::<Vec<_>>();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_by_name::<String>("name"), Ok(Some("row 42".to_string())));
+
+        let mut some = ids(table
+            .heap_scan(&snapshot, &[ScanKey::greater(1, 90i64), ScanKey::less_or_equal(1, 95i64)]));
+        some.sort();
+        assert_eq!(some, vec![91, 92, 93, 94, 95]);
+
+        let rows = table.heap_scan(&snapshot, &[ScanKey::equal(2, "row 7")]);
+        assert_eq!(ids(rows), vec![7]);
+    }
+
+    #[pg_test]
+    fn test_index_scan() {
+        let (table, index) = create_table();
+        let snapshot = PgSnapshot::transaction();
+
+        let rows = table.index_scan(
+            &index,
+            &snapshot,
+            &[ScanKey::greater_or_equal(1, 10i64), ScanKey::less(1, 15i64)],
+        );
+        // index scans return rows in index order
+        assert_eq!(ids(rows), vec![10, 11, 12, 13, 14]);
+
+        let rows = table.index_scan(&index, &snapshot, &[ScanKey::equal(1, 1000i64)]);
+        assert_eq!(rows.count(), 0);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "is not an index on")]
+    fn test_index_scan_wrong_table() {
+        let (table, _) = create_table();
+        let snapshot = PgSnapshot::transaction();
+        let _ = table.index_scan(&table, &snapshot, &[]);
+    }
+}
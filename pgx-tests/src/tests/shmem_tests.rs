@@ -7,17 +7,25 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use pgx::prelude::*;
-use pgx::{pg_shmem_init, PgAtomic, PgLwLock, PgSharedMemoryInitialization};
+use pgx::{
+    pg_shmem_init, PgAtomic, PgDsHashMap, PgLwLock, PgSharedHashMap, PgSharedMemoryInitialization,
+};
 use std::sync::atomic::AtomicBool;
 
 static ATOMIC: PgAtomic<AtomicBool> = PgAtomic::new();
 static LWLOCK: PgLwLock<bool> = PgLwLock::new();
+static HASH_MAP: PgSharedHashMap<u64, i64> = PgSharedHashMap::new(4);
+static DSHASH_MAP: PgDsHashMap<u64, i64> = PgDsHashMap::new();
 
 #[pg_guard]
 pub extern "C" fn _PG_init() {
     // This ensures that this functionality works across PostgreSQL versions
     pg_shmem_init!(ATOMIC);
     pg_shmem_init!(LWLOCK);
+    pg_shmem_init!(HASH_MAP);
+    pg_shmem_init!(DSHASH_MAP);
+    pg_shmem_init!(crate::tests::bgworker_tests::WORKER_POOL);
+    pg_shmem_init!(crate::tests::bgworker_tests::WORKER_POOL_SUM);
 }
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
@@ -25,8 +33,9 @@ mod tests {
     #[allow(unused_imports)]
     use crate as pgx_tests;
 
-    use crate::tests::shmem_tests::LWLOCK;
+    use crate::tests::shmem_tests::{DSHASH_MAP, HASH_MAP, LWLOCK};
     use pgx::prelude::*;
+    use pgx::PgSharedHashMapError;
 
     #[pg_test]
     #[should_panic(expected = "cache lookup failed for type 0")]
@@ -52,4 +61,92 @@ mod tests {
         });
         let _lock = LWLOCK.exclusive();
     }
+
+    #[pg_test]
+    pub fn test_shared_hash_map() {
+        HASH_MAP.exclusive().clear();
+
+        assert_eq!(HASH_MAP.insert(1, 10), Ok(None));
+        assert_eq!(HASH_MAP.insert(1, 11), Ok(Some(10)));
+        assert_eq!(HASH_MAP.get(&1), Some(11));
+        assert_eq!(HASH_MAP.get(&2), None);
+        assert_eq!(HASH_MAP.len(), 1);
+
+        assert_eq!(HASH_MAP.remove(&1), Some(11));
+        assert_eq!(HASH_MAP.remove(&1), None);
+        assert!(HASH_MAP.is_empty());
+    }
+
+    #[pg_test]
+    pub fn test_shared_hash_map_capacity() {
+        let mut map = HASH_MAP.exclusive();
+        map.clear();
+
+        for i in 0..4 {
+            assert_eq!(map.insert(i, i as i64), Ok(None));
+        }
+        assert_eq!(map.insert(4, 4), Err(PgSharedHashMapError::HashMapFull));
+
+        // replacing an existing key doesn't need more room
+        assert_eq!(map.insert(3, 30), Ok(Some(3)));
+
+        map.retain(|k, _| k % 2 == 0);
+        let mut entries = map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, vec![(0, 0), (2, 2)]);
+    }
+
+    #[pg_test]
+    pub fn test_shared_hash_map_iter_early_exit() {
+        HASH_MAP.exclusive().clear();
+        HASH_MAP.insert(1, 1).unwrap();
+        HASH_MAP.insert(2, 2).unwrap();
+
+        // dropping a partially consumed iterator must terminate the scan
+        assert!(HASH_MAP.share().iter().next().is_some());
+        assert_eq!(HASH_MAP.entries().len(), 2);
+    }
+
+    #[pg_test]
+    pub fn test_dshash_map() {
+        assert_eq!(DSHASH_MAP.insert(42, 1), None);
+        assert_eq!(DSHASH_MAP.get(&42), Some(1));
+        DSHASH_MAP.upsert(42, |v| (v.copied().unwrap_or_default() + 1, ()));
+        assert_eq!(DSHASH_MAP.insert(42, 5), Some(2));
+        assert!(DSHASH_MAP.contains_key(&42));
+        assert_eq!(DSHASH_MAP.remove(&42), Some(5));
+        assert_eq!(DSHASH_MAP.get(&42), None);
+    }
+
+    #[pg_test]
+    pub fn test_dshash_map_upsert_unwind() {
+        // a panic computing a new key's value leaves no entry behind
+        let _res = std::panic::catch_unwind(|| {
+            DSHASH_MAP.upsert(77, |_| -> (i64, ()) { panic!("get out") })
+        });
+        assert_eq!(DSHASH_MAP.get(&77), None);
+
+        // and one updating an existing key leaves it as it was, and unlocked
+        DSHASH_MAP.insert(77, 7);
+        let _res = std::panic::catch_unwind(|| {
+            DSHASH_MAP.upsert(77, |_| -> (i64, ()) { panic!("get out") })
+        });
+        assert_eq!(DSHASH_MAP.insert(77, 8), Some(7));
+        assert_eq!(DSHASH_MAP.remove(&77), Some(8));
+    }
+
+    #[cfg(feature = "pg15")]
+    #[pg_test]
+    pub fn test_dshash_map_iter() {
+        for i in 100..110 {
+            DSHASH_MAP.insert(i, i as i64 * 2);
+        }
+        let mut entries =
+            DSHASH_MAP.entries().into_iter().filter(|(k, _)| *k >= 100).collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, (100..110).map(|i| (i, i as i64 * 2)).collect::<Vec<_>>());
+        for i in 100..110 {
+            DSHASH_MAP.remove(&i);
+        }
+    }
 }
@@ -0,0 +1,64 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{PgRelation, PgSnapshot};
+
+    #[pg_test]
+    fn test_snapshot_accessors() {
+        let active = PgSnapshot::active().expect("no active snapshot");
+        assert!(active.xmin() <= active.xmax());
+
+        let latest = PgSnapshot::latest();
+        assert!(latest.xmin() <= latest.xmax());
+        assert!(active.xmin() <= latest.xmin());
+
+        let transaction = PgSnapshot::transaction();
+        let clone = transaction.clone();
+        assert_eq!(clone.xmin(), transaction.xmin());
+        assert_eq!(clone.xmax(), transaction.xmax());
+        assert_eq!(clone.command_id(), transaction.command_id());
+    }
+
+    #[pg_test]
+    fn test_snapshot_visibility() {
+        Spi::run("CREATE TABLE snapshot_test (id int)").expect("SPI failed");
+        let before = PgSnapshot::transaction();
+        Spi::run("INSERT INTO snapshot_test SELECT generate_series(1, 10)").expect("SPI failed");
+        let after = PgSnapshot::transaction();
+        assert!(before.command_id() < after.command_id());
+
+        // rows inserted by a later command aren't visible to the older snapshot
+        let table = PgRelation::open_with_name_and_share_lock("snapshot_test").unwrap();
+        assert_eq!(table.heap_scan(&before, &[]).count(), 0);
+        assert_eq!(table.heap_scan(&after, &[]).count(), 10);
+    }
+
+    #[pg_test]
+    fn test_snapshot_with_active() {
+        let snapshot = PgSnapshot::transaction();
+        let command_id = snapshot.with_active(|| {
+            assert_eq!(Spi::get_one::<i32>("SELECT 1"), Ok(Some(1)));
+            PgSnapshot::active().expect("no active snapshot").command_id()
+        });
+        assert_eq!(command_id, snapshot.command_id());
+    }
+
+    #[pg_test]
+    fn test_snapshot_export() {
+        let id = PgSnapshot::transaction().export();
+        assert!(!id.is_empty());
+    }
+}
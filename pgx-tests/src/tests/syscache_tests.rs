@@ -0,0 +1,120 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::syscache::{ProcKind, ProcVolatility, TypeKind};
+    use pgx::{PgAttribute, PgClass, PgNamespace, PgOperator, PgProc, PgType};
+
+    fn oid(query: &str) -> pg_sys::Oid {
+        Spi::get_one::<pg_sys::Oid>(query).expect("SPI failed").expect("no oid")
+    }
+
+    #[pg_test]
+    fn test_pg_proc_lookup() {
+        let proc = PgProc::lookup(oid("SELECT 'pg_catalog.left(text, int)'::regprocedure::oid"))
+            .expect("left() not found");
+        assert_eq!(proc.name(), "left");
+        assert_eq!(PgNamespace::lookup(proc.namespace_oid()).unwrap().name(), "pg_catalog");
+        assert_eq!(proc.kind(), ProcKind::Function);
+        assert_eq!(proc.volatility(), ProcVolatility::Immutable);
+        assert!(proc.is_strict());
+        assert!(!proc.returns_set());
+        assert_eq!(proc.return_type(), pg_sys::TEXTOID);
+        assert_eq!(proc.arg_types(), &[pg_sys::TEXTOID, pg_sys::INT4OID]);
+        assert_eq!(proc.source().as_deref(), Some("text_left"));
+    }
+
+    #[pg_test]
+    fn test_pg_proc_arg_names() {
+        Spi::run(
+            "CREATE FUNCTION syscache_add(a int, b int) RETURNS int LANGUAGE sql AS 'SELECT a + b'",
+        )
+        .expect("SPI failed");
+        let proc = PgProc::lookup(oid("SELECT 'syscache_add'::regproc::oid")).unwrap();
+        assert_eq!(proc.arg_names(), Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(proc.source().as_deref(), Some("SELECT a + b"));
+        assert_eq!(proc.volatility(), ProcVolatility::Volatile);
+    }
+
+    #[pg_test]
+    fn test_pg_proc_lookup_missing() {
+        assert!(PgProc::lookup(pg_sys::InvalidOid).is_none());
+    }
+
+    #[pg_test]
+    fn test_pg_type_lookup() {
+        let int4 = PgType::lookup(pg_sys::INT4OID).expect("int4 not found");
+        assert_eq!(int4.name(), "int4");
+        assert_eq!(int4.typlen(), 4);
+        assert!(int4.is_by_value());
+        assert_eq!(int4.kind(), TypeKind::Base);
+        assert_eq!(int4.category(), 'N');
+        assert_eq!(int4.array_type(), Some(pg_sys::INT4ARRAYOID));
+        assert_eq!(int4.element_type(), None);
+
+        let text_array = PgType::lookup(pg_sys::TEXTARRAYOID).expect("text[] not found");
+        assert_eq!(text_array.typlen(), -1);
+        assert_eq!(text_array.element_type(), Some(pg_sys::TEXTOID));
+        assert_eq!(text_array.category(), 'A');
+    }
+
+    #[pg_test]
+    fn test_pg_class_and_attribute_lookup() {
+        Spi::run("CREATE TABLE syscache_test (id int NOT NULL, name text DEFAULT 'x')")
+            .expect("SPI failed");
+        let class = PgClass::lookup(oid("SELECT 'syscache_test'::regclass::oid")).unwrap();
+        assert_eq!(class.name(), "syscache_test");
+        assert!(class.is_table());
+        assert_eq!(class.kind(), 'r');
+        assert_eq!(class.persistence(), 'p');
+        assert_eq!(class.natts(), 2);
+
+        let id = class.attribute(1).expect("id not found");
+        assert_eq!(id.name(), "id");
+        assert_eq!(id.type_oid(), pg_sys::INT4OID);
+        assert!(id.is_not_null());
+        assert!(!id.has_default());
+        assert_eq!(id.collation(), None);
+
+        let name = PgAttribute::lookup(class.oid(), 2).expect("name not found");
+        assert_eq!(name.name(), "name");
+        assert_eq!(name.relation_oid(), class.oid());
+        assert!(name.has_default());
+        assert!(name.collation().is_some());
+
+        assert!(class.attribute(3).is_none());
+    }
+
+    #[pg_test]
+    fn test_pg_namespace_lookup() {
+        let public = PgNamespace::lookup_by_name("public").expect("public not found");
+        assert_eq!(public.name(), "public");
+        assert_eq!(PgNamespace::lookup(public.oid()).unwrap().name(), "public");
+        assert!(PgNamespace::lookup_by_name("no_such_schema").is_none());
+    }
+
+    #[pg_test]
+    fn test_pg_operator_lookup() {
+        let op = PgOperator::lookup(oid("SELECT 'pg_catalog.+(int4, int4)'::regoperator::oid"))
+            .expect("+ not found");
+        assert_eq!(op.name(), "+");
+        assert_eq!(op.kind(), 'b');
+        assert_eq!(op.left_type(), Some(pg_sys::INT4OID));
+        assert_eq!(op.right_type(), Some(pg_sys::INT4OID));
+        assert_eq!(op.result_type(), pg_sys::INT4OID);
+        assert_eq!(op.commutator(), Some(op.oid()));
+        assert_eq!(PgProc::lookup(op.function()).unwrap().name(), "int4pl");
+    }
+}
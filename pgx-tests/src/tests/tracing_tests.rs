@@ -0,0 +1,103 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use core::ffi::CStr;
+    use pgx::prelude::*;
+    use pgx::tracing::PgLogLayer;
+    use std::cell::{Cell, RefCell};
+    use std::fmt::{Debug, Formatter};
+    use tracing::Level;
+
+    thread_local! {
+        static CAPTURED: RefCell<Vec<(String, Option<String>)>> = RefCell::new(Vec::new());
+        static FORMATTED: Cell<bool> = Cell::new(false);
+    }
+
+    #[pg_guard]
+    extern "C" fn capture_log(edata: *mut pg_sys::ErrorData) {
+        unsafe {
+            let edata = &*edata;
+            let message = CStr::from_ptr(edata.message).to_string_lossy().into_owned();
+            let context = (!edata.context.is_null())
+                .then(|| CStr::from_ptr(edata.context).to_string_lossy().into_owned());
+            CAPTURED.with(|c| c.borrow_mut().push((message, context)));
+        }
+    }
+
+    /// Run `f` with `layer` as the tracing subscriber, returning the messages it sent to the server log
+    fn capture<F: FnOnce()>(layer: PgLogLayer, f: F) -> Vec<(String, Option<String>)> {
+        unsafe {
+            let prev = pg_sys::emit_log_hook;
+            pg_sys::emit_log_hook = Some(capture_log);
+            tracing::subscriber::with_default(layer.subscriber(), f);
+            pg_sys::emit_log_hook = prev;
+        }
+        CAPTURED.with(|c| c.take())
+    }
+
+    struct Flag;
+    impl Debug for Flag {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            FORMATTED.with(|formatted| formatted.set(true));
+            write!(f, "flag")
+        }
+    }
+
+    #[pg_test]
+    fn test_tracing_event_with_span_context() {
+        Spi::run("SET log_min_messages TO warning").expect("SPI failed");
+        let captured = capture(PgLogLayer::new(), || {
+            let _outer = tracing::info_span!("outer", file = "data.csv").entered();
+            let _inner = tracing::info_span!("row", n = 42).entered();
+            tracing::warn!(column = "id", "bad value");
+        });
+
+        assert_eq!(captured.len(), 1);
+        let (message, context) = &captured[0];
+        assert_eq!(message, "bad value column=id");
+        assert!(context.as_deref().unwrap().starts_with("row n=42\nouter file=data.csv"));
+    }
+
+    #[pg_test]
+    fn test_tracing_level_mapping() {
+        Spi::run("SET log_min_messages TO warning").expect("SPI failed");
+        let captured = capture(PgLogLayer::new(), || {
+            tracing::error!("an error");
+            tracing::info!("some info");
+            tracing::debug!("some debugging");
+        });
+        assert_eq!(
+            captured.iter().map(|(m, _)| m.as_str()).collect::<Vec<_>>(),
+            vec!["an error", "some info"]
+        );
+
+        let layer = PgLogLayer::new().level(Level::INFO, PgLogLevel::DEBUG1);
+        let captured = capture(layer, || tracing::info!("some info"));
+        assert!(captured.is_empty());
+    }
+
+    #[pg_test]
+    fn test_tracing_filtered_events_are_not_formatted() {
+        Spi::run("SET log_min_messages TO warning").expect("SPI failed");
+        Spi::run("SET client_min_messages TO notice").expect("SPI failed");
+        capture(PgLogLayer::new(), || tracing::debug!(flag = ?Flag, "filtered"));
+        assert!(!FORMATTED.with(|f| f.get()));
+
+        Spi::run("SET log_min_messages TO debug1").expect("SPI failed");
+        let captured = capture(PgLogLayer::new(), || tracing::debug!(flag = ?Flag, "emitted"));
+        assert!(FORMATTED.with(|f| f.get()));
+        assert_eq!(captured[0].0, "emitted flag=flag");
+    }
+}
@@ -0,0 +1,320 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::{TsPosition, TsQuery, TsQueryNode, TsVector, TsWeight};
+
+    pub struct WordParser {
+        text: String,
+        offset: usize,
+    }
+
+    #[pg_ts_parser]
+    impl TsParser for WordParser {
+        const TOKEN_TYPES: &'static [TsTokenType] =
+            &[TsTokenType::new(1, "word", "Word"), TsTokenType::new(2, "number", "Number")];
+
+        fn start(text: &str) -> Self {
+            WordParser { text: text.to_string(), offset: 0 }
+        }
+
+        fn next_token(&mut self) -> Option<(i32, &str)> {
+            let rest = &self.text[self.offset..];
+            let start = rest.find(|c: char| !c.is_whitespace())?;
+            let len = rest[start..].find(char::is_whitespace).unwrap_or(rest.len() - start);
+            self.offset += start + len;
+
+            let token = &rest[start..start + len];
+            let token_type = if token.chars().all(|c| c.is_ascii_digit()) { 2 } else { 1 };
+            Some((token_type, token))
+        }
+    }
+
+    pub struct UpperDictionary {
+        stopword: String,
+    }
+
+    #[pg_ts_dictionary]
+    impl TsDictionary for UpperDictionary {
+        const OPTIONS: &'static [(&'static str, &'static str)] = &[("stopword", "the")];
+
+        fn init(options: &[(String, String)]) -> Self {
+            let mut stopword = String::new();
+            for (option, value) in options {
+                match option.as_str() {
+                    "stopword" => stopword = value.to_lowercase(),
+                    other => panic!("unrecognized upper_dictionary option: {}", other),
+                }
+            }
+            UpperDictionary { stopword }
+        }
+
+        fn lexize(&self, token: &str) -> Option<Vec<String>> {
+            if token.chars().all(|c| c.is_ascii_digit()) {
+                None
+            } else if token.to_lowercase() == self.stopword {
+                Some(vec![])
+            } else {
+                Some(vec![token.to_uppercase()])
+            }
+        }
+    }
+
+    #[pg_extern]
+    fn tsvector_roundtrip(vector: TsVector) -> TsVector {
+        vector
+    }
+
+    #[pg_extern]
+    fn tsquery_roundtrip(query: TsQuery) -> TsQuery {
+        query
+    }
+
+    #[pg_extern]
+    fn fat_cat_tsvector() -> TsVector {
+        let mut vector = TsVector::new();
+        vector.insert_position("fat", 2, TsWeight::A);
+        vector.insert_position("cat", 3, TsWeight::D);
+        vector.insert("rat");
+        vector
+    }
+
+    #[pg_test]
+    fn test_tsvector_from_sql() {
+        let vector = Spi::get_one::<TsVector>("SELECT 'fat:2A,4 cat:3 rat it''s'::tsvector")
+            .expect("SPI failed")
+            .expect("tsvector was null");
+
+        assert_eq!(vector.len(), 4);
+        assert_eq!(
+            vector.iter().map(|lexeme| lexeme.lexeme()).collect::<Vec<_>>(),
+            vec!["cat", "fat", "it's", "rat"]
+        );
+        assert_eq!(
+            vector.get("fat").unwrap().positions(),
+            &[
+                TsPosition { position: 2, weight: TsWeight::A },
+                TsPosition { position: 4, weight: TsWeight::D }
+            ]
+        );
+        assert!(vector.get("rat").unwrap().positions().is_empty());
+        assert!(!vector.contains("dog"));
+        assert_eq!(vector.to_string(), "'cat':3 'fat':2A,4 'it''s' 'rat'");
+    }
+
+    #[pg_test]
+    fn test_tsvector_roundtrip() {
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT tests.tsvector_roundtrip(to_tsvector('english', 'The quick brown foxes jumped over the lazy dogs')) \
+                    = to_tsvector('english', 'The quick brown foxes jumped over the lazy dogs');"
+            ),
+            Ok(Some(true))
+        );
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT tests.tsvector_roundtrip(''::tsvector) = ''::tsvector;"),
+            Ok(Some(true))
+        );
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT tests.fat_cat_tsvector() = 'fat:2A cat:3 rat'::tsvector;"),
+            Ok(Some(true))
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SELECT tests.fat_cat_tsvector()::text;"),
+            Ok(Some(fat_cat_tsvector().to_string()))
+        );
+    }
+
+    #[pg_test]
+    fn test_tsvector_insert_position() {
+        let mut vector = TsVector::new();
+        vector.insert_position("cat", 3, TsWeight::C);
+        vector.insert_position("cat", 1, TsWeight::D);
+        vector.insert_position("cat", 3, TsWeight::A);
+        vector.insert_position("cat", 20000, TsWeight::D);
+        assert_eq!(vector.to_string(), "'cat':1,3A,16383");
+
+        assert!(vector.remove("cat"));
+        assert!(!vector.remove("cat"));
+        assert!(vector.is_empty());
+    }
+
+    #[pg_test]
+    fn test_tsquery_from_sql() {
+        let query = Spi::get_one::<TsQuery>(
+            "SELECT to_tsquery('simple', 'fat & (cat | rat:*B) & !dog & (big <2> mouse)');",
+        )
+        .expect("SPI failed")
+        .expect("tsquery was null");
+
+        let expected = TsQueryNode::lexeme("fat")
+            & (TsQueryNode::lexeme("cat")
+                | TsQueryNode::Lexeme {
+                    lexeme: "rat".to_string(),
+                    weights: vec![TsWeight::B],
+                    prefix: true,
+                })
+            & !TsQueryNode::lexeme("dog")
+            & TsQueryNode::lexeme("big").phrase(TsQueryNode::lexeme("mouse"), 2);
+        assert_eq!(query.root(), Some(&expected));
+        assert_eq!(
+            Some(query.to_string()),
+            Spi::get_one::<String>(
+                "SELECT to_tsquery('simple', 'fat & (cat | rat:*B) & !dog & (big <2> mouse)')::text;",
+            )
+            .expect("SPI failed")
+        );
+    }
+
+    #[pg_test]
+    fn test_tsquery_roundtrip() {
+        for query in [
+            "'fat' & 'rat'",
+            "'fat' | 'rat' & 'cat'",
+            "( 'fat' | 'rat' ) & !'cat'",
+            "!( 'fat' | 'rat' )",
+            "'fat' <-> 'rat' <2> 'cat'",
+            "'fat' <-> ( 'rat' <-> 'cat' )",
+            "'fat':AC & 'ca':*",
+            "'it''s' & 'back\\\\slash'",
+        ] {
+            assert_eq!(
+                Spi::get_one::<bool>(&format!(
+                    "SELECT tests.tsquery_roundtrip($${}$$::tsquery) = $${}$$::tsquery;",
+                    query, query
+                )),
+                Ok(Some(true)),
+                "{}",
+                query
+            );
+
+            let parsed = Spi::get_one::<TsQuery>(&format!("SELECT $${}$$::tsquery;", query))
+                .expect("SPI failed")
+                .expect("tsquery was null");
+            let text = Spi::get_one::<String>(&format!("SELECT $${}$$::tsquery::text;", query))
+                .expect("SPI failed")
+                .expect("tsquery was null");
+            assert_eq!(parsed.to_string(), text);
+        }
+    }
+
+    #[pg_test]
+    fn test_empty_tsquery() {
+        let query = Spi::get_one::<TsQuery>("SELECT to_tsquery('english', 'the');")
+            .expect("SPI failed")
+            .expect("tsquery was null");
+        assert!(query.is_empty());
+        assert_eq!(query.to_string(), "");
+        assert_eq!(
+            Spi::get_one::<i32>(
+                "SELECT numnode(tests.tsquery_roundtrip(to_tsquery('english', 'the')));"
+            ),
+            Ok(Some(0))
+        );
+    }
+
+    #[pg_test]
+    fn test_ts_parser() {
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT string_agg(tokid || ':' || alias || ':' || description, ',' ORDER BY tokid) \
+                    FROM ts_token_type('tests.word_parser');"
+            ),
+            Ok(Some("1:word:Word,2:number:Number".to_string()))
+        );
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT string_agg(tokid || ':' || token, ',') \
+                    FROM ts_parse('tests.word_parser', '  hello 42  world ');"
+            ),
+            Ok(Some("1:hello,2:42,1:world".to_string()))
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "text search parser does not support headline creation")]
+    fn test_ts_parser_no_headline() {
+        Spi::run(
+            "CREATE TEXT SEARCH CONFIGURATION tests.headline_words (PARSER = tests.word_parser);",
+        )
+        .expect("SPI failed");
+        let _ = Spi::get_one::<String>(
+            "SELECT ts_headline('tests.headline_words', 'hello world', 'hello'::tsquery);",
+        );
+    }
+
+    #[pg_test]
+    fn test_ts_dictionary() {
+        assert_eq!(
+            Spi::get_one::<Vec<String>>("SELECT ts_lexize('tests.upper_dictionary', 'Hello');"),
+            Ok(Some(vec!["HELLO".to_string()]))
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<String>>("SELECT ts_lexize('tests.upper_dictionary', 'The');"),
+            Ok(Some(vec![]))
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<String>>("SELECT ts_lexize('tests.upper_dictionary', '42');"),
+            Ok(None)
+        );
+
+        Spi::run(
+            "CREATE TEXT SEARCH DICTIONARY tests.upper_no_fox \
+                (TEMPLATE = tests.upper_dictionary, stopword = 'fox');",
+        )
+        .expect("SPI failed");
+        assert_eq!(
+            Spi::get_one::<Vec<String>>("SELECT ts_lexize('tests.upper_no_fox', 'Fox');"),
+            Ok(Some(vec![]))
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<String>>("SELECT ts_lexize('tests.upper_no_fox', 'the');"),
+            Ok(Some(vec!["THE".to_string()]))
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "unrecognized upper_dictionary option: color")]
+    fn test_ts_dictionary_bad_option() {
+        Spi::run(
+            "CREATE TEXT SEARCH DICTIONARY tests.upper_color \
+                (TEMPLATE = tests.upper_dictionary, color = 'red');",
+        )
+        .expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn test_ts_configuration() {
+        Spi::run(
+            "CREATE TEXT SEARCH CONFIGURATION tests.words (PARSER = tests.word_parser); \
+            ALTER TEXT SEARCH CONFIGURATION tests.words ADD MAPPING FOR word WITH tests.upper_dictionary; \
+            ALTER TEXT SEARCH CONFIGURATION tests.words ADD MAPPING FOR number WITH simple;",
+        )
+        .expect("SPI failed");
+
+        let vector =
+            Spi::get_one::<TsVector>("SELECT to_tsvector('tests.words', 'the quick fox 42');")
+                .expect("SPI failed")
+                .expect("tsvector was null");
+        assert_eq!(vector.to_string(), "'42':4 'FOX':3 'QUICK':2");
+
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT to_tsvector('tests.words', 'the quick fox') @@ to_tsquery('tests.words', 'quick & fox');"
+            ),
+            Ok(Some(true))
+        );
+    }
+}
@@ -0,0 +1,114 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::heap_tuple::PgHeapTupleError;
+    use pgx::prelude::*;
+    use pgx::{PgTupleDesc, PgTupleSlot, TryFromDatumError};
+    use std::num::NonZeroUsize;
+
+    fn attno(i: usize) -> NonZeroUsize {
+        NonZeroUsize::new(i).unwrap()
+    }
+
+    fn create_type() -> PgTupleDesc<'static> {
+        Spi::run("CREATE TYPE slot_dog AS (name text, age int)").expect("SPI failed");
+        PgTupleDesc::for_composite_type("slot_dog").unwrap()
+    }
+
+    #[pg_test]
+    fn test_slot_store_datums() {
+        let tupdesc = create_type();
+        let mut slot = PgTupleSlot::new(&tupdesc);
+        assert!(slot.is_empty());
+        assert_eq!(slot.natts(), 2);
+
+        slot.store_datums(["Brandy".into_datum(), None]).unwrap();
+        assert!(!slot.is_empty());
+        assert_eq!(slot.get::<String>(attno(1)), Ok(Some("Brandy".to_string())));
+        assert_eq!(slot.get::<i32>(attno(2)), Ok(None));
+        assert_eq!(slot.get_datum(attno(2)), None);
+
+        slot.store_datums(["Nami".into_datum(), 3.into_datum()]).unwrap();
+        assert_eq!(slot.get_by_name::<String>("name"), Ok(Some("Nami".to_string())));
+        assert_eq!(slot.get_by_name::<i32>("age"), Ok(Some(3)));
+
+        assert_eq!(
+            slot.get_by_name::<i32>("breed"),
+            Err(TryFromDatumError::NoSuchAttributeName("breed".to_string()))
+        );
+        assert_eq!(
+            slot.get::<i32>(attno(3)),
+            Err(TryFromDatumError::NoSuchAttributeNumber(attno(3)))
+        );
+        assert!(matches!(
+            slot.get::<String>(attno(2)),
+            Err(TryFromDatumError::IncompatibleTypes { .. })
+        ));
+
+        assert_eq!(
+            slot.store_datums([3.into_datum()]),
+            Err(PgHeapTupleError::IncorrectAttributeCount(1, 2))
+        );
+
+        slot.clear();
+        assert!(slot.is_empty());
+        assert!(slot.to_heap_tuple().is_none());
+    }
+
+    #[pg_test]
+    fn test_slot_heap_tuple_round_trip() {
+        let tupdesc = create_type();
+        let mut dog = PgHeapTuple::new_composite_type("slot_dog").unwrap();
+        dog.set_by_name("name", "Brandy").unwrap();
+        dog.set_by_name("age", 42).unwrap();
+
+        let mut slot = PgTupleSlot::new(&tupdesc);
+        slot.store_heap_tuple(&dog).unwrap();
+        // the slot holds a copy
+        drop(dog);
+        assert_eq!(slot.get::<i32>(attno(2)), Ok(Some(42)));
+        assert_eq!(slot.get::<String>(attno(1)), Ok(Some("Brandy".to_string())));
+
+        let mut copy = slot.to_heap_tuple().unwrap();
+        copy.set_by_name("age", 43).unwrap();
+        assert_eq!(copy.get_by_name::<i32>("age"), Ok(Some(43)));
+        assert_eq!(copy.get_by_name::<String>("name"), Ok(Some("Brandy".to_string())));
+        assert_eq!(slot.get::<i32>(attno(2)), Ok(Some(42)));
+    }
+
+    #[pg_test]
+    fn test_slot_from_pg() {
+        let tupdesc = create_type();
+        let ptr = PgTupleSlot::new(&tupdesc).into_pg();
+        {
+            let mut slot = unsafe { PgTupleSlot::from_pg(ptr) };
+            slot.store_datums(["Brandy".into_datum(), 42.into_datum()]).unwrap();
+        }
+
+        // dropping the wrapper left the slot, and its tuple, alone
+        let slot = unsafe { PgTupleSlot::from_pg(ptr) };
+        assert_eq!(slot.get::<i32>(attno(2)), Ok(Some(42)));
+        drop(slot);
+        unsafe { pg_sys::ExecDropSingleTupleTableSlot(ptr) };
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "cannot get attributes from an empty slot")]
+    fn test_slot_get_empty() {
+        let tupdesc = create_type();
+        let slot = PgTupleSlot::new(&tupdesc);
+        let _ = slot.get::<i32>(attno(2));
+    }
+}
@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::prelude::*;
+    use pgx::wait_event::{WaitEventSetBuilder, WaitEventSource, WaitEvents};
+    use std::io::{Read, Write};
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    #[pg_test]
+    fn test_wait_event_set_timeout() {
+        let (a, _b) = UnixStream::pair().unwrap();
+        let mut set = WaitEventSetBuilder::new()
+            .latch()
+            .postmaster_death()
+            .socket(&a, WaitEvents::SOCKET_READABLE)
+            .build();
+        assert!(set.wait(Some(Duration::from_millis(10))).is_empty());
+    }
+
+    #[pg_test]
+    fn test_wait_event_set_socket() {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        let mut set = WaitEventSetBuilder::new()
+            .latch()
+            .socket(&a, WaitEvents::SOCKET_READABLE)
+            .socket(&b, WaitEvents::SOCKET_READABLE)
+            .build();
+
+        b.write_all(b"ping").unwrap();
+        let events = set.wait(Some(Duration::from_secs(10)));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].position, 1);
+        assert_eq!(events[0].source, WaitEventSource::Socket(a.as_raw_fd()));
+        assert!(events[0].events.contains(WaitEvents::SOCKET_READABLE));
+
+        let mut buf = [0u8; 4];
+        a.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+        assert!(set.wait(Some(Duration::from_millis(10))).is_empty());
+
+        // an idle socket is always writeable
+        set.modify_socket(2, WaitEvents::SOCKET_WRITEABLE);
+        let events = set.wait(Some(Duration::from_secs(10)));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].source, WaitEventSource::Socket(b.as_raw_fd()));
+        assert!(events[0].events.contains(WaitEvents::SOCKET_WRITEABLE));
+    }
+
+    #[pg_test]
+    fn test_wait_event_set_same_socket() {
+        let (a, _b) = UnixStream::pair().unwrap();
+        let mut set = WaitEventSetBuilder::new()
+            .socket(&a, WaitEvents::SOCKET_READABLE)
+            .socket(&a, WaitEvents::SOCKET_WRITEABLE)
+            .build();
+
+        let events = set.wait(Some(Duration::from_secs(10)));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].position, 0);
+        assert!(events[0].events.contains(WaitEvents::SOCKET_WRITEABLE));
+    }
+
+    #[pg_test]
+    fn test_wait_event_set_latch() {
+        let mut set = WaitEventSetBuilder::new().latch().build();
+        unsafe { pg_sys::SetLatch(pg_sys::MyLatch) };
+        let events = set.wait(None);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].source, WaitEventSource::Latch);
+        assert!(events[0].events.contains(WaitEvents::LATCH_SET));
+
+        // the latch was reset for us
+        assert!(set.wait(Some(Duration::from_millis(10))).is_empty());
+    }
+}
@@ -0,0 +1,561 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A single-threaded async executor for background workers
+//!
+//! [`BackgroundWorker::run_async`] drives a future on the worker's main thread, so futures can
+//! call into Postgres directly.  The executor sleeps on a
+//! [`WaitEventSet`](crate::wait_event::WaitEventSet) holding the worker's latch, postmaster death
+//! and any sockets futures are waiting on, which means SIGTERM (and anything else that sets the
+//! latch) is noticed promptly.  Wakers never touch the latch: they wake the main thread through a
+//! socket pair in the same set, so they can be used from any thread.
+//!
+//! Code running on other threads must never call into Postgres.  Instead it can use a
+//! [`TransactionHandle`] to send closures back to the main thread, where they are run inside
+//! [`BackgroundWorker::transaction`].
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use pgx::async_worker::{readable, transaction_handle};
+//! use pgx::bgworkers::*;
+//! use pgx::prelude::*;
+//! use std::io::Read;
+//! use std::os::unix::net::UnixListener;
+//!
+//! #[pg_guard]
+//! #[no_mangle]
+//! pub extern "C" fn socket_worker_main(_arg: pg_sys::Datum) {
+//!     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+//!     BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+//!
+//!     BackgroundWorker::run_async(async {
+//!         let listener = UnixListener::bind("/tmp/my_worker.sock").unwrap();
+//!         listener.set_nonblocking(true).unwrap();
+//!         let spi = transaction_handle();
+//!         loop {
+//!             readable(&listener).await;
+//!             let Ok((mut stream, _)) = listener.accept() else { continue };
+//!             let mut message = String::new();
+//!             stream.read_to_string(&mut message).unwrap();
+//!             spi.run(move || Spi::run(&format!("NOTIFY my_channel, '{}'", message)))
+//!                 .await
+//!                 .expect("worker stopped")
+//!                 .expect("NOTIFY failed");
+//!         }
+//!     });
+//! }
+//! ```
+use crate::bgworkers::BackgroundWorker;
+use crate::pg_sys;
+use crate::wait_event::{WaitEventSet, WaitEventSetBuilder, WaitEventSource, WaitEvents};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+/// The task id of the future passed to [`BackgroundWorker::run_async`]
+const MAIN_TASK: usize = usize::MAX;
+
+/// Errors returned when the async runtime is no longer there to run a closure
+#[derive(thiserror::Error, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AsyncWorkerError {
+    #[error("the background worker's async runtime has stopped")]
+    Stopped,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+/// The sending half of a self-pipe the main thread waits on.  Unlike `SetLatch()`, which pgx only
+/// lets the main thread call, writing to it is fine from any thread.
+#[derive(Clone)]
+struct Wakeup(Arc<UnixStream>);
+
+impl Wakeup {
+    fn wake(&self) {
+        // the socket is non-blocking, and if its buffer is full the main thread has plenty of
+        // wakeups waiting for it already
+        let _ = (&*self.0).write(&[1]);
+    }
+}
+
+/// Read everything written to the receiving half of the self-pipe, so it stops being readable
+fn drain_wakeups(mut receiver: &UnixStream) {
+    let mut buf = [0u8; 64];
+    loop {
+        match receiver.read(&mut buf) {
+            Ok(0) => break,
+            Ok(_) => continue,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    }
+}
+
+/// Ids of tasks that have been woken, shared with every [`Waker`]
+struct ReadyQueue {
+    ready: Mutex<VecDeque<usize>>,
+    wakeup: Wakeup,
+}
+
+struct TaskWaker {
+    id: usize,
+    queue: Arc<ReadyQueue>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.queue.ready.lock().unwrap().push_back(self.id);
+        self.queue.wakeup.wake();
+    }
+}
+
+struct IoInterest {
+    fd: RawFd,
+    events: WaitEvents,
+    fired: Rc<Cell<bool>>,
+    waker: Waker,
+}
+
+/// A socket in the runtime's [`WaitEventSet`], with the events of every interest in it merged
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct SocketWait {
+    fd: RawFd,
+    /// The socket's device and inode numbers, so a socket that was closed and replaced by another
+    /// with the same descriptor isn't mistaken for the one in the set
+    identity: Option<(libc::dev_t, libc::ino_t)>,
+    events: WaitEvents,
+}
+
+impl SocketWait {
+    fn identity(fd: RawFd) -> Option<(libc::dev_t, libc::ino_t)> {
+        let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+        unsafe {
+            if libc::fstat(fd, stat.as_mut_ptr()) == 0 {
+                let stat = stat.assume_init();
+                Some((stat.st_dev, stat.st_ino))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// The [`WaitEventSet`] the runtime sleeps on, which is kept for as long as the same sockets are
+/// waited on, since Postgres can't remove sockets from a set
+struct IoWaitSet {
+    set: WaitEventSet,
+    /// the sockets after the latch, postmaster death and wakeup members, sorted by descriptor
+    sockets: Vec<SocketWait>,
+}
+
+impl IoWaitSet {
+    /// The position of the first of `sockets` in the set
+    const FIRST_SOCKET: usize = 3;
+
+    fn new(wakeup: &UnixStream, sockets: Vec<SocketWait>) -> Self {
+        let set = sockets
+            .iter()
+            .fold(
+                WaitEventSetBuilder::new()
+                    .latch()
+                    .postmaster_death()
+                    .socket(wakeup, WaitEvents::SOCKET_READABLE),
+                |set, socket| set.socket(&socket.fd, socket.events),
+            )
+            .build();
+        IoWaitSet { set, sockets }
+    }
+
+    /// Wait for `sockets` with the existing set if it holds the same ones, only changing the
+    /// events they wait for
+    fn update(this: &mut Option<IoWaitSet>, wakeup: &UnixStream, sockets: Vec<SocketWait>) {
+        match this {
+            Some(current)
+                if current.sockets.len() == sockets.len()
+                    && current
+                        .sockets
+                        .iter()
+                        .zip(&sockets)
+                        .all(|(a, b)| a.fd == b.fd && a.identity == b.identity) =>
+            {
+                for (i, (old, new)) in current.sockets.iter_mut().zip(sockets).enumerate() {
+                    if old.events != new.events {
+                        current.set.modify_socket(Self::FIRST_SOCKET + i, new.events);
+                        old.events = new.events;
+                    }
+                }
+            }
+            _ => {
+                // free the old set before creating its replacement
+                *this = None;
+                *this = Some(IoWaitSet::new(wakeup, sockets));
+            }
+        }
+    }
+}
+
+struct Runtime {
+    queue: Arc<ReadyQueue>,
+    tasks: RefCell<Vec<Option<Task>>>,
+    spawned: RefCell<Vec<Task>>,
+    timers: RefCell<Vec<(Instant, Waker)>>,
+    io: RefCell<Vec<IoInterest>>,
+    jobs: mpsc::Sender<Job>,
+}
+
+thread_local! {
+    static RUNTIME: RefCell<Option<Rc<Runtime>>> = const { RefCell::new(None) };
+}
+
+fn with_runtime<R>(f: impl FnOnce(&Runtime) -> R) -> R {
+    let runtime = RUNTIME.with(|rt| rt.borrow().clone());
+    f(&runtime.expect("not running inside `BackgroundWorker::run_async()`"))
+}
+
+/// Unsets the thread-local runtime when `run_async()` returns or unwinds
+struct RuntimeGuard;
+
+impl Drop for RuntimeGuard {
+    fn drop(&mut self) {
+        RUNTIME.with(|rt| rt.borrow_mut().take());
+    }
+}
+
+impl BackgroundWorker {
+    /// Drive `future` to completion on this background worker's main thread.
+    ///
+    /// Returns `None`, dropping `future` and every task [`spawn`]ed from it, if the worker receives
+    /// a SIGTERM (see [`BackgroundWorker::attach_signal_handlers`]) or the postmaster dies before
+    /// it completes.  Futures run on the main thread and may call into Postgres, but they must not
+    /// block.  Use [`readable`], [`writable`] and [`sleep`] to wait instead.
+    pub fn run_async<F: Future>(future: F) -> Option<F::Output> {
+        unsafe {
+            assert!(!pg_sys::MyBgworkerEntry.is_null(), "BackgroundWorker associated functions can only be called from a registered background worker");
+        }
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (wakeup_sender, wakeup_receiver) =
+            UnixStream::pair().expect("failed to create the async runtime's wakeup socket pair");
+        wakeup_sender.set_nonblocking(true).expect("failed to make the wakeup socket non-blocking");
+        wakeup_receiver
+            .set_nonblocking(true)
+            .expect("failed to make the wakeup socket non-blocking");
+        let queue = Arc::new(ReadyQueue {
+            ready: Mutex::new(VecDeque::from([MAIN_TASK])),
+            wakeup: Wakeup(Arc::new(wakeup_sender)),
+        });
+        let runtime = Rc::new(Runtime {
+            queue: queue.clone(),
+            tasks: RefCell::new(Vec::new()),
+            spawned: RefCell::new(Vec::new()),
+            timers: RefCell::new(Vec::new()),
+            io: RefCell::new(Vec::new()),
+            jobs,
+        });
+        RUNTIME.with(|rt| {
+            assert!(rt.borrow().is_none(), "`BackgroundWorker::run_async()` cannot be nested");
+            *rt.borrow_mut() = Some(runtime.clone());
+        });
+        let _guard = RuntimeGuard;
+
+        let main_waker = Waker::from(Arc::new(TaskWaker { id: MAIN_TASK, queue: queue.clone() }));
+        let mut future = Box::pin(future);
+        let mut io_set = None::<IoWaitSet>;
+
+        loop {
+            // poll everything that has been woken since we last looked
+            let ready = std::mem::take(&mut *queue.ready.lock().unwrap());
+            for id in ready {
+                if id == MAIN_TASK {
+                    if let Poll::Ready(output) =
+                        future.as_mut().poll(&mut Context::from_waker(&main_waker))
+                    {
+                        return Some(output);
+                    }
+                    continue;
+                }
+
+                let task = runtime.tasks.borrow_mut().get_mut(id).and_then(Option::take);
+                if let Some(mut task) = task {
+                    let waker = Waker::from(Arc::new(TaskWaker { id, queue: queue.clone() }));
+                    if task.as_mut().poll(&mut Context::from_waker(&waker)).is_pending() {
+                        runtime.tasks.borrow_mut()[id] = Some(task);
+                    }
+                }
+            }
+
+            // give newly spawned tasks a slot and their first poll
+            for task in runtime.spawned.take() {
+                let mut tasks = runtime.tasks.borrow_mut();
+                let id = match tasks.iter().position(Option::is_none) {
+                    Some(id) => {
+                        tasks[id] = Some(task);
+                        id
+                    }
+                    None => {
+                        tasks.push(Some(task));
+                        tasks.len() - 1
+                    }
+                };
+                queue.ready.lock().unwrap().push_back(id);
+            }
+
+            // closures sent from other threads
+            while let Ok(job) = job_receiver.try_recv() {
+                BackgroundWorker::transaction(AssertUnwindSafe(job));
+            }
+
+            // wake expired timers
+            let now = Instant::now();
+            let mut next_deadline = None::<Instant>;
+            runtime.timers.borrow_mut().retain(|(deadline, waker)| {
+                if *deadline <= now {
+                    waker.wake_by_ref();
+                    false
+                } else {
+                    next_deadline = Some(next_deadline.map_or(*deadline, |d| d.min(*deadline)));
+                    true
+                }
+            });
+
+            if !queue.ready.lock().unwrap().is_empty() {
+                continue;
+            }
+
+            // nothing left to do until the latch is set, a waker or socket is ready, or a timer
+            // expires
+            let mut sockets = Vec::<SocketWait>::new();
+            for interest in runtime.io.borrow().iter() {
+                match sockets.iter_mut().find(|socket| socket.fd == interest.fd) {
+                    Some(socket) => socket.events |= interest.events,
+                    None => sockets.push(SocketWait {
+                        fd: interest.fd,
+                        identity: SocketWait::identity(interest.fd),
+                        events: interest.events,
+                    }),
+                }
+            }
+            sockets.sort_by_key(|socket| socket.fd);
+            IoWaitSet::update(&mut io_set, &wakeup_receiver, sockets);
+            let set = &mut io_set.as_mut().unwrap().set;
+            let timeout = next_deadline.map(|d| d.saturating_duration_since(Instant::now()));
+
+            for event in set.wait(timeout) {
+                match event.source {
+                    WaitEventSource::PostmasterDeath => return None,
+                    WaitEventSource::Latch => {
+                        if BackgroundWorker::sigterm_received() {
+                            return None;
+                        }
+                    }
+                    WaitEventSource::Socket(fd) if fd == wakeup_receiver.as_raw_fd() => {
+                        drain_wakeups(&wakeup_receiver)
+                    }
+                    WaitEventSource::Socket(fd) => {
+                        runtime.io.borrow_mut().retain(|interest| {
+                            if interest.fd == fd && interest.events.intersects(event.events) {
+                                interest.fired.set(true);
+                                interest.waker.wake_by_ref();
+                                false
+                            } else {
+                                true
+                            }
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Run `future` concurrently with the others on this worker's async runtime.  It is dropped if
+/// the runtime stops before it completes.
+///
+/// # Panics
+///
+/// If not called from within [`BackgroundWorker::run_async`]
+pub fn spawn<F: Future<Output = ()> + 'static>(future: F) {
+    with_runtime(|rt| {
+        rt.spawned.borrow_mut().push(Box::pin(future));
+        rt.queue.wakeup.wake();
+    })
+}
+
+/// Get a [`TransactionHandle`] for the running async runtime
+///
+/// # Panics
+///
+/// If not called from within [`BackgroundWorker::run_async`]
+pub fn transaction_handle() -> TransactionHandle {
+    with_runtime(|rt| TransactionHandle { jobs: rt.jobs.clone(), wakeup: rt.queue.wakeup.clone() })
+}
+
+/// Wait until `duration` has passed
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep { deadline: Instant::now() + duration }
+}
+
+/// Future returned by [`sleep`]
+pub struct Sleep {
+    deadline: Instant,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        with_runtime(|rt| rt.timers.borrow_mut().push((self.deadline, cx.waker().clone())));
+        Poll::Pending
+    }
+}
+
+/// Wait until `socket` can be read from without blocking
+pub fn readable<S: AsRawFd + ?Sized>(socket: &S) -> Readiness {
+    Readiness { fd: socket.as_raw_fd(), events: WaitEvents::SOCKET_READABLE, fired: None }
+}
+
+/// Wait until `socket` can be written to without blocking
+pub fn writable<S: AsRawFd + ?Sized>(socket: &S) -> Readiness {
+    Readiness { fd: socket.as_raw_fd(), events: WaitEvents::SOCKET_WRITEABLE, fired: None }
+}
+
+/// Future returned by [`readable`] and [`writable`]
+pub struct Readiness {
+    fd: RawFd,
+    events: WaitEvents,
+    fired: Option<Rc<Cell<bool>>>,
+}
+
+impl Future for Readiness {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if matches!(&self.fired, Some(fired) if fired.get()) {
+            return Poll::Ready(());
+        }
+
+        let fired = self.fired.get_or_insert_with(Default::default).clone();
+        let (fd, events) = (self.fd, self.events);
+        with_runtime(|rt| {
+            let mut io = rt.io.borrow_mut();
+            match io.iter_mut().find(|i| Rc::ptr_eq(&i.fired, &fired)) {
+                Some(interest) => interest.waker = cx.waker().clone(),
+                None => io.push(IoInterest { fd, events, fired, waker: cx.waker().clone() }),
+            }
+        });
+        Poll::Pending
+    }
+}
+
+impl Drop for Readiness {
+    fn drop(&mut self) {
+        if let Some(fired) = &self.fired {
+            RUNTIME.with(|rt| {
+                if let Some(rt) = rt.borrow().as_ref() {
+                    rt.io.borrow_mut().retain(|i| !Rc::ptr_eq(&i.fired, fired));
+                }
+            });
+        }
+    }
+}
+
+/// A handle, usable from any thread, for running closures inside a
+/// [`BackgroundWorker::transaction`] on the worker's main thread
+#[derive(Clone)]
+pub struct TransactionHandle {
+    jobs: mpsc::Sender<Job>,
+    wakeup: Wakeup,
+}
+
+impl TransactionHandle {
+    /// Run `f` in a transaction on the main thread, returning a future that resolves to its result.
+    /// If `f` panics or raises an `ERROR` the background worker exits.
+    pub fn run<F, R>(&self, f: F) -> TransactionFuture<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result, receiver) = mpsc::sync_channel(1);
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let job_waker = waker.clone();
+        let job: Job = Box::new(move || {
+            let _ = result.send(f());
+            if let Some(waker) = job_waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        });
+        // if the runtime is gone `receiver` is disconnected and the future says so
+        if self.jobs.send(job).is_ok() {
+            self.wakeup.wake();
+        }
+        TransactionFuture { receiver, waker }
+    }
+
+    /// Like [`TransactionHandle::run`], but blocks the calling thread until `f` has run.  This must
+    /// not be called from the main thread, which would then never get to run `f`.
+    pub fn run_blocking<F, R>(&self, f: F) -> Result<R, AsyncWorkerError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result, receiver) = mpsc::sync_channel(1);
+        self.jobs
+            .send(Box::new(move || {
+                let _ = result.send(f());
+            }))
+            .map_err(|_| AsyncWorkerError::Stopped)?;
+        self.wakeup.wake();
+        receiver.recv().map_err(|_| AsyncWorkerError::Stopped)
+    }
+}
+
+/// Future returned by [`TransactionHandle::run`]
+pub struct TransactionFuture<R> {
+    receiver: mpsc::Receiver<R>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl<R> Future for TransactionFuture<R> {
+    type Output = Result<R, AsyncWorkerError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.receiver.try_recv() {
+            Ok(r) => return Poll::Ready(Ok(r)),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                return Poll::Ready(Err(AsyncWorkerError::Stopped))
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+        }
+
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+        // the job may have finished before our waker was stored
+        match self.receiver.try_recv() {
+            Ok(r) => Poll::Ready(Ok(r)),
+            Err(mpsc::TryRecvError::Disconnected) => Poll::Ready(Err(AsyncWorkerError::Stopped)),
+            Err(mpsc::TryRecvError::Empty) => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,721 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A supervised pool of dynamic background workers that process jobs submitted from any backend
+//!
+//! A [`BackgroundWorkerPool`] keeps a fixed number of workers running per database.  Jobs are
+//! serialized with CBOR into a queue in Postgres shared memory, and the next idle worker for the
+//! submitting backend's database is woken up to run it inside a transaction.
+//!
+//! Workers are registered with a restart interval, so a worker that crashes (for example because
+//! its job raised an `ERROR`) is restarted by the postmaster and resumes taking jobs.  The job that
+//! was running when the worker crashed is counted as failed and is not retried.
+//!
+//! > Extensions that use a pool **must** be loaded via `postgresql.conf`'s
+//! > `shared_preload_libraries` configuration setting.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use pgx::bgworker_pool::BackgroundWorkerPool;
+//! use pgx::prelude::*;
+//! use pgx::{pg_shmem_init, PgSharedMemoryInitialization};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Refresh {
+//!     view: String,
+//! }
+//!
+//! static POOL: BackgroundWorkerPool<Refresh> =
+//!     BackgroundWorkerPool::new("refresh pool", "my_extension", "refresh_worker_main")
+//!         .workers_per_database(2);
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     pg_shmem_init!(POOL);
+//! }
+//!
+//! #[pg_guard]
+//! #[no_mangle]
+//! pub extern "C" fn refresh_worker_main(arg: pg_sys::Datum) {
+//!     POOL.run_worker(arg, |job| {
+//!         Spi::run(&format!("REFRESH MATERIALIZED VIEW {}", job.view)).expect("refresh failed");
+//!     });
+//! }
+//!
+//! #[pg_extern]
+//! fn refresh_later(view: String) {
+//!     POOL.start().expect("failed to start the refresh pool");
+//!     POOL.submit(&Refresh { view }).expect("failed to submit the job");
+//! }
+//!
+//! // SELECT * FROM refresh_pool_status();
+//! pgx::bgworker_pool_status!(refresh_pool_status, POOL);
+//! ```
+use crate::bgworkers::{
+    BackgroundWorker, BackgroundWorkerBuilder, BackgroundWorkerStatus, Pid, SignalWakeFlags,
+};
+use crate::lwlock::release_unless_elog_unwinding;
+use crate::pg_sys;
+use crate::shmem::PgSharedMemoryInitialization;
+use once_cell::sync::OnceCell;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How long an idle worker sleeps on its latch before checking the queue again on its own
+const IDLE_NAPTIME: Duration = Duration::from_secs(10);
+
+/// Errors that can occur when starting a pool or submitting jobs to it
+#[derive(thiserror::Error, Debug)]
+pub enum BackgroundWorkerPoolError {
+    /// No workers are running for the current database.  Call [`BackgroundWorkerPool::start`] first
+    #[error("background worker pool is not running in this database")]
+    NotRunning,
+
+    /// Every group of workers is already assigned to another database
+    #[error("background worker pool has no free workers for another database")]
+    NoFreeWorkers,
+
+    /// The job queue already holds its maximum number of jobs
+    #[error("background worker pool job queue is full")]
+    QueueFull,
+
+    /// The serialized job doesn't fit in a queue entry
+    #[error("serialized job is {size} bytes, but the pool only allows {max} bytes")]
+    JobTooLarge { size: usize, max: usize },
+
+    /// A worker could not be started
+    #[error("background worker failed to start: {0:?}")]
+    StartupFailed(BackgroundWorkerStatus),
+
+    /// The job could not be serialized
+    #[error(transparent)]
+    Codec(#[from] serde_cbor::Error),
+}
+
+/// The state of one worker in a [`BackgroundWorkerPool`]
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BackgroundWorkerPoolState {
+    /// No worker is running in this slot
+    Stopped,
+    /// The worker has been registered but hasn't attached to the pool yet
+    Starting,
+    /// The worker is waiting for a job
+    Idle,
+    /// The worker is running a job
+    Busy,
+}
+
+impl BackgroundWorkerPoolState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackgroundWorkerPoolState::Stopped => "stopped",
+            BackgroundWorkerPoolState::Starting => "starting",
+            BackgroundWorkerPoolState::Idle => "idle",
+            BackgroundWorkerPoolState::Busy => "busy",
+        }
+    }
+}
+
+/// A snapshot of one worker's status, as returned by [`BackgroundWorkerPool::status`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BackgroundWorkerPoolWorker {
+    /// The worker's slot in the pool, which is also its `bgw_main_arg`
+    pub slot: usize,
+    /// The database the worker is connected to
+    pub database: pg_sys::Oid,
+    /// The worker's process id, if it is running
+    pub pid: Option<Pid>,
+    pub state: BackgroundWorkerPoolState,
+    /// Jobs this slot has run to completion
+    pub jobs_completed: u64,
+    /// Jobs that were running when this slot's worker crashed, or that could not be decoded
+    pub jobs_failed: u64,
+    /// How many times the postmaster has restarted this slot's worker
+    pub restarts: u64,
+}
+
+#[repr(C)]
+struct PoolHeader {
+    next_seq: u64,
+}
+
+#[repr(C)]
+struct WorkerSlot {
+    database: pg_sys::Oid,
+    user: pg_sys::Oid,
+    state: BackgroundWorkerPoolState,
+    stop_requested: bool,
+    pid: Pid,
+    /// `MyLatch` of the worker, which lives in its `PGPROC` and is therefore shared
+    latch: *mut pg_sys::Latch,
+    /// Sequence number of the job being run, or zero
+    current_job: u64,
+    jobs_completed: u64,
+    jobs_failed: u64,
+    restarts: u64,
+}
+
+impl WorkerSlot {
+    /// The worker's latch, unless the worker has exited.  A worker that crashed leaves its latch
+    /// behind, and the `PGPROC` it's in may have been taken over by another process since.
+    fn live_latch(&self) -> Option<*mut pg_sys::Latch> {
+        let live = !self.latch.is_null() && unsafe { (*self.latch).owner_pid } == self.pid;
+        live.then_some(self.latch)
+    }
+}
+
+#[repr(C)]
+struct JobSlot {
+    /// Submission order of the job in this slot, or zero if the slot is free
+    seq: u64,
+    database: pg_sys::Oid,
+    len: usize,
+}
+
+struct PoolShared {
+    header: *mut PoolHeader,
+    workers: *mut WorkerSlot,
+    jobs: *mut JobSlot,
+    payloads: *mut u8,
+    lock: *mut pg_sys::LWLock,
+}
+
+unsafe impl Send for PoolShared {}
+unsafe impl Sync for PoolShared {}
+
+/// A pool of dynamic background workers that run jobs of type `J`
+///
+/// The pool is declared as a `static`, configured with its `const` builder methods, and passed to
+/// [`pg_shmem_init!()`](crate::pg_shmem_init) from `_PG_init()`.  The extension must also export the
+/// function named in [`BackgroundWorkerPool::new`], which hands control to
+/// [`BackgroundWorkerPool::run_worker`].
+pub struct BackgroundWorkerPool<J> {
+    name: &'static str,
+    library: &'static str,
+    function: &'static str,
+    workers_per_database: usize,
+    max_databases: usize,
+    queue_capacity: usize,
+    max_job_size: usize,
+    restart_time: Duration,
+    shmem_name: OnceCell<&'static str>,
+    shared: OnceCell<PoolShared>,
+    __marker: PhantomData<fn(J) -> J>,
+}
+
+impl<J> BackgroundWorkerPool<J>
+where
+    J: Serialize + DeserializeOwned,
+{
+    /// Create a new pool whose workers are named after `name` and start by calling `function` in
+    /// the shared library `library`.  By default the pool runs 4 workers in each of up to 4
+    /// databases, queues up to 64 jobs of at most 1kB each, and restarts crashed workers after
+    /// one second.
+    pub const fn new(name: &'static str, library: &'static str, function: &'static str) -> Self {
+        BackgroundWorkerPool {
+            name,
+            library,
+            function,
+            workers_per_database: 4,
+            max_databases: 4,
+            queue_capacity: 64,
+            max_job_size: 1024,
+            restart_time: Duration::from_secs(1),
+            shmem_name: OnceCell::new(),
+            shared: OnceCell::new(),
+            __marker: PhantomData,
+        }
+    }
+
+    /// How many workers to keep running in each database the pool is started in
+    pub const fn workers_per_database(mut self, workers: usize) -> Self {
+        self.workers_per_database = workers;
+        self
+    }
+
+    /// How many databases the pool can be started in at the same time
+    pub const fn max_databases(mut self, databases: usize) -> Self {
+        self.max_databases = databases;
+        self
+    }
+
+    /// How many submitted jobs can wait for a worker, across all databases
+    pub const fn queue_capacity(mut self, jobs: usize) -> Self {
+        self.queue_capacity = jobs;
+        self
+    }
+
+    /// The largest serialized job, in bytes, that can be submitted
+    pub const fn max_job_size(mut self, bytes: usize) -> Self {
+        self.max_job_size = bytes;
+        self
+    }
+
+    /// How long the postmaster waits before restarting a crashed worker.  Postgres only honors
+    /// whole seconds.
+    pub const fn restart_time(mut self, restart_time: Duration) -> Self {
+        self.restart_time = restart_time;
+        self
+    }
+
+    /// Get the name of the shared memory segment (and `LWLock` tranche) backing this pool
+    pub fn get_name(&self) -> &'static str {
+        self.shmem_name.get_or_init(|| Box::leak(Uuid::new_v4().to_string().into_boxed_str()))
+    }
+
+    /// Make sure `workers_per_database` workers are running for the current database, launching
+    /// any that aren't and waiting for them to start.  Returns how many workers were launched.
+    ///
+    /// This must be called from a regular backend.  Calling it again is cheap, and relaunches
+    /// workers that have exited.
+    pub fn start(&'static self) -> Result<usize, BackgroundWorkerPoolError> {
+        let (database, user) = unsafe { (pg_sys::MyDatabaseId, pg_sys::GetUserId()) };
+
+        let launch = {
+            let mut guard = self.exclusive();
+            let n = self.workers_per_database;
+            let mut free = None;
+            let mut ours = None;
+            for (g, group) in guard.workers().chunks(n).enumerate() {
+                if group.iter().any(|w| w.state != BackgroundWorkerPoolState::Stopped) {
+                    if group[0].database == database {
+                        ours = Some(g);
+                        break;
+                    }
+                } else if free.is_none() {
+                    free = Some(g);
+                }
+            }
+
+            let g = match (ours, free) {
+                (Some(g), _) => g,
+                (None, Some(g)) => {
+                    for slot in &mut guard.workers_mut()[g * n..(g + 1) * n] {
+                        slot.database = database;
+                        slot.user = user;
+                        slot.jobs_completed = 0;
+                        slot.jobs_failed = 0;
+                        slot.restarts = 0;
+                    }
+                    g
+                }
+                (None, None) => return Err(BackgroundWorkerPoolError::NoFreeWorkers),
+            };
+
+            let mut launch = Vec::new();
+            for index in g * n..(g + 1) * n {
+                let slot = &mut guard.workers_mut()[index];
+                if slot.state == BackgroundWorkerPoolState::Stopped {
+                    slot.state = BackgroundWorkerPoolState::Starting;
+                    slot.stop_requested = false;
+                    slot.current_job = 0;
+                    launch.push(index);
+                }
+            }
+            launch
+        };
+
+        for (i, &index) in launch.iter().enumerate() {
+            let worker = BackgroundWorkerBuilder::new(&format!("{} worker {}", self.name, index))
+                .set_type(self.name)
+                .set_library(self.library)
+                .set_function(self.function)
+                .set_argument(Some(pg_sys::Datum::from(index)))
+                .enable_spi_access()
+                .set_restart_time(Some(self.restart_time))
+                .set_notify_pid(unsafe { pg_sys::MyProcPid })
+                .load_dynamic();
+
+            if let Err(status) = worker.wait_for_startup() {
+                // nothing from here on was started, so give the slots back
+                let mut guard = self.exclusive();
+                for &index in &launch[i..] {
+                    guard.workers_mut()[index].state = BackgroundWorkerPoolState::Stopped;
+                }
+                return Err(BackgroundWorkerPoolError::StartupFailed(status));
+            }
+        }
+
+        Ok(launch.len())
+    }
+
+    /// Ask every worker running for the current database to exit once it finishes its current
+    /// job.  Returns how many workers were asked to stop.
+    pub fn stop(&self) -> usize {
+        let database = unsafe { pg_sys::MyDatabaseId };
+        let latches = {
+            let mut guard = self.exclusive();
+            guard
+                .workers_mut()
+                .iter_mut()
+                .filter(|w| w.database == database && w.state != BackgroundWorkerPoolState::Stopped)
+                .map(|w| {
+                    w.stop_requested = true;
+                    w.live_latch()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for &latch in latches.iter().flatten() {
+            unsafe { pg_sys::SetLatch(latch) }
+        }
+        latches.len()
+    }
+
+    /// Queue `job` for one of the current database's workers and wake an idle worker
+    pub fn submit(&self, job: &J) -> Result<(), BackgroundWorkerPoolError> {
+        let database = unsafe { pg_sys::MyDatabaseId };
+        let payload = serde_cbor::to_vec(job)?;
+        if payload.len() > self.max_job_size {
+            return Err(BackgroundWorkerPoolError::JobTooLarge {
+                size: payload.len(),
+                max: self.max_job_size,
+            });
+        }
+
+        let latch = {
+            let mut guard = self.exclusive();
+            let mut running = guard.workers().iter().filter(|w| {
+                w.database == database
+                    && w.state != BackgroundWorkerPoolState::Stopped
+                    && !w.stop_requested
+            });
+            if running.clone().next().is_none() {
+                return Err(BackgroundWorkerPoolError::NotRunning);
+            }
+            let latch = running
+                .find(|w| w.state == BackgroundWorkerPoolState::Idle)
+                .and_then(WorkerSlot::live_latch);
+
+            let Some(index) = guard.jobs().iter().position(|j| j.seq == 0) else {
+                return Err(BackgroundWorkerPoolError::QueueFull);
+            };
+            let seq = guard.next_seq();
+            guard.payload_mut(index)[..payload.len()].copy_from_slice(&payload);
+            let slot = &mut guard.jobs_mut()[index];
+            slot.seq = seq;
+            slot.database = database;
+            slot.len = payload.len();
+            latch
+        };
+
+        // workers that are busy will look at the queue again once their job is done
+        if let Some(latch) = latch {
+            unsafe { pg_sys::SetLatch(latch) }
+        }
+        Ok(())
+    }
+
+    /// How many jobs submitted from the current database are waiting for a worker
+    pub fn pending_jobs(&self) -> usize {
+        let database = unsafe { pg_sys::MyDatabaseId };
+        self.share().jobs().iter().filter(|j| j.seq != 0 && j.database == database).count()
+    }
+
+    /// Report on every worker slot that is in use, across all databases
+    pub fn status(&self) -> Vec<BackgroundWorkerPoolWorker> {
+        self.share()
+            .workers()
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.state != BackgroundWorkerPoolState::Stopped)
+            .map(|(slot, w)| BackgroundWorkerPoolWorker {
+                slot,
+                database: w.database,
+                pid: (w.pid != 0).then_some(w.pid),
+                state: w.state,
+                jobs_completed: w.jobs_completed,
+                jobs_failed: w.jobs_failed,
+                restarts: w.restarts,
+            })
+            .collect()
+    }
+
+    /// The main loop of a pool worker, to be called from the background worker function named in
+    /// [`BackgroundWorkerPool::new`] with the argument Postgres passed to it.
+    ///
+    /// Each job is run with `handler` inside [`BackgroundWorker::transaction`].  If the handler
+    /// panics or raises an `ERROR` the worker exits and is restarted by the postmaster.  Returns
+    /// when the worker receives a SIGTERM or is asked to [`stop`](BackgroundWorkerPool::stop).
+    pub fn run_worker<F: FnMut(J)>(&self, arg: pg_sys::Datum, mut handler: F) {
+        BackgroundWorker::attach_signal_handlers(
+            SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM,
+        );
+        let index = arg.value();
+
+        let (database, user) = {
+            let mut guard = self.exclusive();
+            let slot = &mut guard.workers_mut()[index];
+            if slot.state != BackgroundWorkerPoolState::Starting {
+                // we were restarted by the postmaster after crashing, so the latch and pid are
+                // those of the crashed worker until we replace them below
+                slot.restarts += 1;
+                if slot.current_job != 0 {
+                    slot.jobs_failed += 1;
+                    slot.current_job = 0;
+                }
+            }
+            slot.state = BackgroundWorkerPoolState::Idle;
+            unsafe {
+                slot.pid = pg_sys::MyProcPid;
+                slot.latch = pg_sys::MyLatch;
+            }
+            (slot.database, slot.user)
+        };
+
+        unsafe {
+            pg_sys::BackgroundWorkerInitializeConnectionByOid(database, user, 0);
+        }
+
+        loop {
+            match self.take_job(index) {
+                Some(Ok(job)) => {
+                    BackgroundWorker::transaction(AssertUnwindSafe(|| handler(job)));
+                    self.finish_job(index, true);
+                    if BackgroundWorker::sigterm_received() {
+                        break;
+                    }
+                }
+                Some(Err(e)) => {
+                    crate::warning!("{}: could not decode job: {}", self.name, e);
+                    self.finish_job(index, false);
+                }
+                None => {
+                    if !BackgroundWorker::wait_latch(Some(IDLE_NAPTIME)) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut guard = self.exclusive();
+        let slot = &mut guard.workers_mut()[index];
+        slot.state = BackgroundWorkerPoolState::Stopped;
+        slot.pid = 0;
+        slot.latch = std::ptr::null_mut();
+    }
+
+    /// Remove the oldest job for our database from the queue and mark us as busy with it.  Returns
+    /// `None` if there is no job, or if we've been asked to stop.
+    fn take_job(&self, index: usize) -> Option<Result<J, serde_cbor::Error>> {
+        let payload = {
+            let mut guard = self.exclusive();
+            let (database, stop_requested) = {
+                let slot = &guard.workers()[index];
+                (slot.database, slot.stop_requested)
+            };
+            if stop_requested {
+                return None;
+            }
+
+            let (job, seq) = guard
+                .jobs()
+                .iter()
+                .enumerate()
+                .filter(|(_, j)| j.seq != 0 && j.database == database)
+                .min_by_key(|(_, j)| j.seq)
+                .map(|(i, j)| (i, j.seq))?;
+            let len = guard.jobs()[job].len;
+            let payload = guard.payload_mut(job)[..len].to_vec();
+            guard.jobs_mut()[job].seq = 0;
+
+            let slot = &mut guard.workers_mut()[index];
+            slot.state = BackgroundWorkerPoolState::Busy;
+            slot.current_job = seq;
+            payload
+        };
+        Some(serde_cbor::from_slice(&payload))
+    }
+
+    fn finish_job(&self, index: usize, completed: bool) {
+        let mut guard = self.exclusive();
+        let slot = &mut guard.workers_mut()[index];
+        if completed {
+            slot.jobs_completed += 1;
+        } else {
+            slot.jobs_failed += 1;
+        }
+        slot.current_job = 0;
+        slot.state = BackgroundWorkerPoolState::Idle;
+    }
+}
+
+impl<J> BackgroundWorkerPool<J> {
+    fn total_workers(&self) -> usize {
+        self.workers_per_database * self.max_databases
+    }
+
+    /// Offsets of the worker slots, job slots and job payloads from the start of the segment
+    fn layout(&self) -> (usize, usize, usize, usize) {
+        fn align(offset: usize) -> usize {
+            (offset + 7) & !7
+        }
+        let workers = align(std::mem::size_of::<PoolHeader>());
+        let jobs = align(workers + self.total_workers() * std::mem::size_of::<WorkerSlot>());
+        let payloads = align(jobs + self.queue_capacity * std::mem::size_of::<JobSlot>());
+        let size = payloads + self.queue_capacity * self.max_job_size;
+        (workers, jobs, payloads, size)
+    }
+
+    fn shared(&self) -> &PoolShared {
+        self.shared.get().expect("BackgroundWorkerPool was not initialized")
+    }
+
+    fn share(&self) -> PoolGuard<'_, J> {
+        unsafe {
+            pg_sys::LWLockAcquire(self.shared().lock, pg_sys::LWLockMode_LW_SHARED);
+        }
+        PoolGuard { pool: self, shared: self.shared() }
+    }
+
+    fn exclusive(&self) -> PoolGuard<'_, J> {
+        unsafe {
+            pg_sys::LWLockAcquire(self.shared().lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+        }
+        PoolGuard { pool: self, shared: self.shared() }
+    }
+}
+
+impl<J> PgSharedMemoryInitialization for BackgroundWorkerPool<J>
+where
+    J: Serialize + DeserializeOwned,
+{
+    fn pg_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.get_name()).expect("CString::new failed");
+            pg_sys::RequestAddinShmemSpace(self.layout().3);
+            pg_sys::RequestNamedLWLockTranche(name.as_ptr(), 1);
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.get_name()).expect("CString::new failed");
+            let (workers, jobs, payloads, size) = self.layout();
+            let addin_shmem_init_lock: *mut pg_sys::LWLock =
+                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+            pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+
+            let mut found = false;
+            let base = pg_sys::ShmemInitStruct(name.as_ptr(), size, &mut found) as *mut u8;
+            if !found {
+                // all-zeros is an empty header, stopped workers and free job slots
+                std::ptr::write_bytes(base, 0, size);
+                (*base.cast::<PoolHeader>()).next_seq = 1;
+            }
+            let lock = &mut (*pg_sys::GetNamedLWLockTranche(name.as_ptr())).lock as *mut _;
+
+            self.shared
+                .set(PoolShared {
+                    header: base.cast(),
+                    workers: base.add(workers).cast(),
+                    jobs: base.add(jobs).cast(),
+                    payloads: base.add(payloads),
+                    lock,
+                })
+                .ok()
+                .expect("BackgroundWorkerPool was already initialized");
+            pg_sys::LWLockRelease(addin_shmem_init_lock);
+        }
+    }
+}
+
+/// Holds the pool's `LWLock` and provides access to the shared state it protects
+struct PoolGuard<'a, J> {
+    pool: &'a BackgroundWorkerPool<J>,
+    shared: &'a PoolShared,
+}
+
+impl<J> PoolGuard<'_, J> {
+    fn workers(&self) -> &[WorkerSlot] {
+        unsafe { std::slice::from_raw_parts(self.shared.workers, self.pool.total_workers()) }
+    }
+
+    fn workers_mut(&mut self) -> &mut [WorkerSlot] {
+        unsafe { std::slice::from_raw_parts_mut(self.shared.workers, self.pool.total_workers()) }
+    }
+
+    fn jobs(&self) -> &[JobSlot] {
+        unsafe { std::slice::from_raw_parts(self.shared.jobs, self.pool.queue_capacity) }
+    }
+
+    fn jobs_mut(&mut self) -> &mut [JobSlot] {
+        unsafe { std::slice::from_raw_parts_mut(self.shared.jobs, self.pool.queue_capacity) }
+    }
+
+    fn payload_mut(&mut self, job: usize) -> &mut [u8] {
+        let size = self.pool.max_job_size;
+        unsafe { std::slice::from_raw_parts_mut(self.shared.payloads.add(job * size), size) }
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        unsafe {
+            let header = &mut *self.shared.header;
+            header.next_seq += 1;
+            header.next_seq - 1
+        }
+    }
+}
+
+impl<J> Drop for PoolGuard<'_, J> {
+    fn drop(&mut self) {
+        unsafe { release_unless_elog_unwinding(self.shared.lock) }
+    }
+}
+
+/// Define a set-returning SQL function named `$name` that reports on every worker slot in use in
+/// the [`BackgroundWorkerPool`] `$pool`, like [`BackgroundWorkerPool::status`]
+///
+/// ```rust,no_run
+/// # use pgx::bgworker_pool::BackgroundWorkerPool;
+/// # static POOL: BackgroundWorkerPool<String> =
+/// #     BackgroundWorkerPool::new("pool", "my_extension", "pool_worker_main");
+/// // SELECT slot, pid, state, jobs_completed FROM my_pool_status();
+/// pgx::bgworker_pool_status!(my_pool_status, POOL);
+/// ```
+#[macro_export]
+macro_rules! bgworker_pool_status {
+    ($name:ident, $pool:path) => {
+        // `#[pg_extern]` re-parses the function from its source text, where `$crate` can't be
+        // resolved, so this names the crate as extensions depend on it
+        #[::pgx::pg_extern]
+        fn $name() -> ::pgx::iter::TableIterator<
+            'static,
+            (
+                ::pgx::name!(slot, i64),
+                ::pgx::name!(database, ::pgx::pg_sys::Oid),
+                ::pgx::name!(pid, Option<i32>),
+                ::pgx::name!(state, &'static str),
+                ::pgx::name!(jobs_completed, i64),
+                ::pgx::name!(jobs_failed, i64),
+                ::pgx::name!(restarts, i64),
+            ),
+        > {
+            ::pgx::iter::TableIterator::new($pool.status().into_iter().map(|w| {
+                (
+                    w.slot as i64,
+                    w.database,
+                    w.pid,
+                    w.state.as_str(),
+                    w.jobs_completed as i64,
+                    w.jobs_failed as i64,
+                    w.restarts as i64,
+                )
+            }))
+        }
+    };
+}
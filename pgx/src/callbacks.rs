@@ -7,7 +7,8 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-//! Provides safe wrappers around Postgres' "Transaction" and "Sub Transaction" hook system
+//! Provides safe wrappers around Postgres' "Transaction" and "Sub Transaction" hook system, and
+//! its relcache and syscache invalidation callbacks
 
 use crate as pgx; // for #[pg_guard] support from within ourself
 use crate::pg_sys;
@@ -375,3 +376,188 @@ where
 
     SubXactCallbackReceipt(wrapped_func)
 }
+
+/// Registering a relcache invalidation callback returns a `RelcacheCallbackReceipt` that can be
+/// used to unregister the callback if it later becomes unnecessary
+pub struct RelcacheCallbackReceipt(Rc<RefCell<Option<RelcacheCallbackWrapper>>>);
+
+impl RelcacheCallbackReceipt {
+    /// Consumes this `RelcacheCallbackReceipt` and unregisters the registered callback it represents
+    pub fn unregister_callback(self) {
+        self.0.replace(None);
+    }
+}
+
+struct RelcacheCallbackWrapper(
+    Box<dyn Fn(Option<pg_sys::Oid>) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static>,
+);
+
+type RelcacheCallbackList = Vec<Rc<RefCell<Option<RelcacheCallbackWrapper>>>>;
+
+/// Register a closure to be called whenever a relation's relcache entry is invalidated, such as
+/// after DDL on the relation, in this or any other backend.
+///
+/// The closure is given the oid of the invalidated relation, or `None` if every relation's entry
+/// was invalidated at once.  Multiple closures can be registered, and they are called in the order
+/// in which they were registered.
+///
+/// Unlike transaction callbacks, relcache callbacks remain registered for the life of the backend,
+/// or until they're unregistered through the returned `RelcacheCallbackReceipt`.
+///
+/// ## Examples
+///
+/// Forget what's been cached about a relation when it changes:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::cell::RefCell;
+/// use std::collections::HashMap;
+///
+/// thread_local! {
+///     static COLUMN_COUNTS: RefCell<HashMap<pg_sys::Oid, usize>> = RefCell::new(HashMap::new());
+/// }
+///
+/// register_relcache_callback(|relid| {
+///     COLUMN_COUNTS.with(|counts| match relid {
+///         Some(relid) => { counts.borrow_mut().remove(&relid); }
+///         None => counts.borrow_mut().clear(),
+///     })
+/// });
+/// ```
+///
+/// ## Safety
+///
+/// Invalidation callbacks can run at almost any point where Postgres processes invalidation
+/// messages, including while it's in the middle of other catalog access.  They should only mark
+/// cached data as stale, without accessing the catalogs themselves, and must not `panic!()` or
+/// `ereport(ERROR)`.
+pub fn register_relcache_callback<F>(f: F) -> RelcacheCallbackReceipt
+where
+    F: Fn(Option<pg_sys::Oid>) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    // our list of relcache callbacks.  Postgres has a small, fixed number of slots for relcache
+    // callbacks, so we register a single callback the first time through and dispatch from it
+    static mut RELCACHE_HOOKS: Option<RelcacheCallbackList> = None;
+
+    #[pg_guard]
+    unsafe extern "C" fn callback(_arg: pg_sys::Datum, relid: pg_sys::Oid) {
+        // copy the list, as a hook could register another hook while we're iterating it
+        let hooks = RELCACHE_HOOKS.clone().unwrap_or_default();
+        let relid = if relid == pg_sys::InvalidOid { None } else { Some(relid) };
+        for hook in hooks.iter() {
+            if let Some(hook) = hook.borrow().as_ref() {
+                (hook.0)(relid)
+            }
+        }
+    }
+
+    let hooks = unsafe {
+        (*std::ptr::addr_of_mut!(RELCACHE_HOOKS)).get_or_insert_with(|| {
+            pg_sys::CacheRegisterRelcacheCallback(Some(callback), pg_sys::Datum::from(0));
+            Vec::new()
+        })
+    };
+
+    // forget about any hooks that have been unregistered
+    hooks.retain(|hook| hook.borrow().is_some());
+
+    let wrapped_func = Rc::new(RefCell::new(Some(RelcacheCallbackWrapper(Box::new(f)))));
+    hooks.push(Rc::clone(&wrapped_func));
+
+    RelcacheCallbackReceipt(wrapped_func)
+}
+
+/// Registering a syscache invalidation callback returns a `SyscacheCallbackReceipt` that can be
+/// used to unregister the callback if it later becomes unnecessary
+pub struct SyscacheCallbackReceipt(Rc<RefCell<Option<SyscacheCallbackWrapper>>>);
+
+impl SyscacheCallbackReceipt {
+    /// Consumes this `SyscacheCallbackReceipt` and unregisters the registered callback it represents
+    pub fn unregister_callback(self) {
+        self.0.replace(None);
+    }
+}
+
+struct SyscacheCallbackWrapper(
+    Box<dyn Fn(Option<u32>) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static>,
+);
+
+type SyscacheCallbackMap =
+    HashMap<pg_sys::SysCacheIdentifier, Vec<Rc<RefCell<Option<SyscacheCallbackWrapper>>>>>;
+
+/// Register a closure to be called whenever an entry in the syscache identified by `cache_id`,
+/// such as `pg_sys::SysCacheIdentifier_PROCOID`, is invalidated.
+///
+/// The closure is given the hash value of the invalidated entry, as computed by
+/// `pg_sys::GetSysCacheHashValue()`, or `None` if every entry in the cache was invalidated at
+/// once.  Multiple closures can be registered per cache, and they are called in the order in
+/// which they were registered.
+///
+/// Syscache callbacks remain registered for the life of the backend, or until they're
+/// unregistered through the returned `SyscacheCallbackReceipt`.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// register_syscache_callback(pg_sys::SysCacheIdentifier_PROCOID, |_hashvalue| {
+///     // a function was created, altered, or dropped.  Forget what we know about functions
+/// });
+/// ```
+///
+/// ## Safety
+///
+/// As with [`register_relcache_callback`], the closure should only mark cached data as stale,
+/// without accessing the catalogs itself, and must not `panic!()` or `ereport(ERROR)`.
+pub fn register_syscache_callback<F>(
+    cache_id: pg_sys::SysCacheIdentifier,
+    f: F,
+) -> SyscacheCallbackReceipt
+where
+    F: Fn(Option<u32>) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    static mut SYSCACHE_HOOKS: Option<SyscacheCallbackMap> = None;
+
+    #[pg_guard]
+    unsafe extern "C" fn callback(
+        _arg: pg_sys::Datum,
+        cache_id: ::std::os::raw::c_int,
+        hashvalue: u32,
+    ) {
+        // copy the list, as a hook could register another hook while we're iterating it
+        let hooks = SYSCACHE_HOOKS
+            .as_ref()
+            .and_then(|hooks| hooks.get(&(cache_id as pg_sys::SysCacheIdentifier)))
+            .cloned()
+            .unwrap_or_default();
+        let hashvalue = if hashvalue == 0 { None } else { Some(hashvalue) };
+        for hook in hooks.iter() {
+            if let Some(hook) = hook.borrow().as_ref() {
+                (hook.0)(hashvalue)
+            }
+        }
+    }
+
+    let hooks =
+        unsafe { (*std::ptr::addr_of_mut!(SYSCACHE_HOOKS)).get_or_insert_with(HashMap::new) };
+    let entry = hooks.entry(cache_id).or_insert_with(|| {
+        // the first callback for this cache, so register our single dispatching callback for it
+        unsafe {
+            pg_sys::CacheRegisterSyscacheCallback(
+                cache_id as _,
+                Some(callback),
+                pg_sys::Datum::from(0),
+            );
+        }
+        Vec::new()
+    });
+
+    // forget about any hooks that have been unregistered
+    entry.retain(|hook| hook.borrow().is_some());
+
+    let wrapped_func = Rc::new(RefCell::new(Some(SyscacheCallbackWrapper(Box::new(f)))));
+    entry.push(Rc::clone(&wrapped_func));
+
+    SyscacheCallbackReceipt(wrapped_func)
+}
@@ -0,0 +1,753 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Zero-copy access to `jsonb` values, and building new ones, without going through
+//! `serde_json::Value`
+
+use crate::{
+    pg_sys, varsize_any, AllocatedByRust, AnyNumeric, FromDatum, IntoDatum, PgBox, PgMemoryContexts,
+};
+use core::ffi::CStr;
+use pgx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// A `jsonb` document, or an object or array within one, borrowed from its binary representation
+///
+/// Unlike [`crate::JsonB`], nothing is parsed up front.  Lookups walk the binary `JsonbContainer`
+/// directly, and strings and numbers are borrowed from it.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use pgx::prelude::*;
+/// use pgx::JsonbRef;
+///
+/// #[pg_extern]
+/// fn first_tag<'a>(doc: JsonbRef<'a>) -> Option<&'a str> {
+///     doc.get("tags")?.index(0)?.as_str()
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct JsonbRef<'a> {
+    container: NonNull<pg_sys::JsonbContainer>,
+    /// the size of the container, in bytes
+    len: usize,
+    /// the whole `jsonb` varlena, if this is its root container
+    root: Option<NonNull<pg_sys::Jsonb>>,
+    __marker: PhantomData<&'a pg_sys::Jsonb>,
+}
+
+impl<'a> JsonbRef<'a> {
+    /// Borrow the root container of a detoasted `jsonb` varlena
+    ///
+    /// ## Safety
+    ///
+    /// `jsonb` must be a valid, non-null, and detoasted `Jsonb *`, and it must outlive `'a`.
+    pub unsafe fn from_jsonb(jsonb: *mut pg_sys::Jsonb) -> Self {
+        let jsonb = NonNull::new(jsonb).expect("jsonb pointer is null");
+        JsonbRef {
+            container: NonNull::from(&mut (*jsonb.as_ptr()).root),
+            len: varsize_any(jsonb.as_ptr().cast()) - pg_sys::VARHDRSZ,
+            root: Some(jsonb),
+            __marker: PhantomData,
+        }
+    }
+
+    fn header(&self) -> u32 {
+        unsafe { self.container.as_ref().header }
+    }
+
+    /// Is this a JSON object?
+    pub fn is_object(&self) -> bool {
+        self.header() & pg_sys::JB_FOBJECT != 0
+    }
+
+    /// Is this a JSON array?
+    pub fn is_array(&self) -> bool {
+        self.header() & pg_sys::JB_FARRAY != 0 && !self.is_scalar()
+    }
+
+    /// Is this a document that's just a single scalar, such as `'42'::jsonb`?
+    pub fn is_scalar(&self) -> bool {
+        self.header() & pg_sys::JB_FSCALAR != 0
+    }
+
+    /// The number of keys in an object, or elements in an array.  A scalar document has one.
+    pub fn len(&self) -> usize {
+        (self.header() & pg_sys::JB_CMASK) as usize
+    }
+
+    /// Is this an empty object or array?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This document, or the scalar it consists of
+    pub fn value(&self) -> JsonbValueRef<'a> {
+        if self.is_scalar() {
+            unsafe { self.ith(0) }.expect("scalar jsonb has no value")
+        } else if self.is_object() {
+            JsonbValueRef::Object(*self)
+        } else {
+            JsonbValueRef::Array(*self)
+        }
+    }
+
+    /// The value of `key`, if this is an object that has it
+    pub fn get(&self, key: &str) -> Option<JsonbValueRef<'a>> {
+        if !self.is_object() {
+            return None;
+        }
+        let mut key_value = JsonbValueRef::String(key).to_jsonb_value();
+        unsafe {
+            take_value(pg_sys::findJsonbValueFromContainer(
+                self.container.as_ptr(),
+                pg_sys::JB_FOBJECT,
+                &mut key_value,
+            ))
+        }
+    }
+
+    /// The element at `index`, if this is an array that long
+    pub fn index(&self, index: usize) -> Option<JsonbValueRef<'a>> {
+        if !self.is_array() {
+            return None;
+        }
+        unsafe { self.ith(u32::try_from(index).ok()?) }
+    }
+
+    unsafe fn ith(&self, index: u32) -> Option<JsonbValueRef<'a>> {
+        take_value(pg_sys::getIthJsonbValueFromContainer(self.container.as_ptr(), index))
+    }
+
+    /// Iterate over the elements of an array, in order.  Nothing is returned for objects or
+    /// scalars.
+    pub fn elements(&self) -> JsonbElements<'a> {
+        JsonbElements(JsonbIter::new(self, self.is_array()))
+    }
+
+    /// Iterate over the keys and values of an object, in the order `jsonb` stores them.  Nothing
+    /// is returned for arrays or scalars.
+    pub fn entries(&self) -> JsonbEntries<'a> {
+        JsonbEntries(JsonbIter::new(self, self.is_object()))
+    }
+
+    /// The underlying `JsonbContainer`
+    pub fn as_ptr(&self) -> *mut pg_sys::JsonbContainer {
+        self.container.as_ptr()
+    }
+
+    fn binary_value(&self) -> pg_sys::JsonbValue {
+        pg_sys::JsonbValue {
+            type_: pg_sys::jbvType_jbvBinary,
+            val: pg_sys::JsonbValue__bindgen_ty_1 {
+                binary: pg_sys::JsonbValue__bindgen_ty_1__bindgen_ty_4 {
+                    len: self.len as i32,
+                    data: self.container.as_ptr(),
+                },
+            },
+        }
+    }
+}
+
+/// Formats the value as `jsonb_out()` would
+impl Display for JsonbRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let text = unsafe {
+            let cstr = pg_sys::JsonbToCString(
+                std::ptr::null_mut(),
+                self.container.as_ptr(),
+                self.len as i32,
+            );
+            let text = CStr::from_ptr(cstr).to_string_lossy().into_owned();
+            pg_sys::pfree(cstr.cast());
+            text
+        };
+        f.pad(&text)
+    }
+}
+
+impl Debug for JsonbRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JsonbRef({})", self)
+    }
+}
+
+/// Convert, and free, a `JsonbValue` Postgres allocated for us
+unsafe fn take_value<'a>(value: *mut pg_sys::JsonbValue) -> Option<JsonbValueRef<'a>> {
+    if value.is_null() {
+        return None;
+    }
+    let result = JsonbValueRef::from_jsonb_value(&*value);
+    pg_sys::pfree(value.cast());
+    Some(result)
+}
+
+/// A value within a `jsonb` document, borrowed from it
+#[derive(Debug, Clone)]
+pub enum JsonbValueRef<'a> {
+    Null,
+    Bool(bool),
+    Number(JsonbNumber<'a>),
+    String(&'a str),
+    Array(JsonbRef<'a>),
+    Object(JsonbRef<'a>),
+}
+
+impl<'a> JsonbValueRef<'a> {
+    unsafe fn from_jsonb_value(value: &pg_sys::JsonbValue) -> Self {
+        match value.type_ {
+            pg_sys::jbvType_jbvNull => JsonbValueRef::Null,
+            pg_sys::jbvType_jbvBool => JsonbValueRef::Bool(value.val.boolean),
+            pg_sys::jbvType_jbvNumeric => JsonbValueRef::Number(JsonbNumber {
+                // numerics within a jsonb are never toasted, so this doesn't copy
+                numeric: AnyNumeric::from_datum(pg_sys::Datum::from(value.val.numeric), false)
+                    .expect("jsonb numeric is null"),
+                __marker: PhantomData,
+            }),
+            pg_sys::jbvType_jbvString => {
+                let string = value.val.string;
+                let bytes =
+                    std::slice::from_raw_parts(string.val as *const u8, string.len as usize);
+                JsonbValueRef::String(std::str::from_utf8_unchecked(bytes))
+            }
+            pg_sys::jbvType_jbvBinary => {
+                let binary = value.val.binary;
+                let container = JsonbRef {
+                    container: NonNull::new(binary.data).expect("jsonb container is null"),
+                    len: binary.len as usize,
+                    root: None,
+                    __marker: PhantomData,
+                };
+                if container.is_object() {
+                    JsonbValueRef::Object(container)
+                } else {
+                    JsonbValueRef::Array(container)
+                }
+            }
+            other => panic!("unexpected jsonb value type: {}", other),
+        }
+    }
+
+    fn to_jsonb_value(&self) -> pg_sys::JsonbValue {
+        let mut value = pg_sys::JsonbValue::default();
+        match self {
+            JsonbValueRef::Null => value.type_ = pg_sys::jbvType_jbvNull,
+            JsonbValueRef::Bool(b) => {
+                value.type_ = pg_sys::jbvType_jbvBool;
+                value.val.boolean = *b;
+            }
+            JsonbValueRef::Number(n) => {
+                value.type_ = pg_sys::jbvType_jbvNumeric;
+                value.val.numeric = n.numeric.inner;
+            }
+            JsonbValueRef::String(s) => {
+                value.type_ = pg_sys::jbvType_jbvString;
+                value.val.string = pg_sys::JsonbValue__bindgen_ty_1__bindgen_ty_1 {
+                    len: s.len().try_into().expect("jsonb string too long"),
+                    val: s.as_ptr() as *mut std::os::raw::c_char,
+                };
+            }
+            JsonbValueRef::Array(c) | JsonbValueRef::Object(c) => value = c.binary_value(),
+        }
+        value
+    }
+
+    /// Is this a JSON `null`?
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonbValueRef::Null)
+    }
+
+    /// The boolean, if this is one
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonbValueRef::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The string, if this is one
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            JsonbValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The number, if this is one
+    pub fn as_number(&self) -> Option<JsonbNumber<'a>> {
+        match self {
+            JsonbValueRef::Number(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
+    /// The number as an `i64`, if this is one that's an integer that fits
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64()
+    }
+
+    /// The number as an `f64`, if this is one
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number()?.as_f64()
+    }
+
+    /// The object, if this is one
+    pub fn as_object(&self) -> Option<JsonbRef<'a>> {
+        match self {
+            JsonbValueRef::Object(o) => Some(*o),
+            _ => None,
+        }
+    }
+
+    /// The array, if this is one
+    pub fn as_array(&self) -> Option<JsonbRef<'a>> {
+        match self {
+            JsonbValueRef::Array(a) => Some(*a),
+            _ => None,
+        }
+    }
+
+    /// The value of `key`, if this is an object that has it
+    pub fn get(&self, key: &str) -> Option<JsonbValueRef<'a>> {
+        self.as_object()?.get(key)
+    }
+
+    /// The element at `index`, if this is an array that long
+    pub fn index(&self, index: usize) -> Option<JsonbValueRef<'a>> {
+        self.as_array()?.index(index)
+    }
+}
+
+impl From<bool> for JsonbValueRef<'_> {
+    fn from(b: bool) -> Self {
+        JsonbValueRef::Bool(b)
+    }
+}
+
+impl<'a> From<&'a str> for JsonbValueRef<'a> {
+    fn from(s: &'a str) -> Self {
+        JsonbValueRef::String(s)
+    }
+}
+
+impl<'a> From<JsonbNumber<'a>> for JsonbValueRef<'a> {
+    fn from(n: JsonbNumber<'a>) -> Self {
+        JsonbValueRef::Number(n)
+    }
+}
+
+impl<'a> From<JsonbRef<'a>> for JsonbValueRef<'a> {
+    fn from(jsonb: JsonbRef<'a>) -> Self {
+        jsonb.value()
+    }
+}
+
+impl<'a, T> From<Option<T>> for JsonbValueRef<'a>
+where
+    T: Into<JsonbValueRef<'a>>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map_or(JsonbValueRef::Null, Into::into)
+    }
+}
+
+/// A number within a `jsonb` document, borrowed from it
+pub struct JsonbNumber<'a> {
+    /// doesn't own the number, which belongs to the document
+    numeric: AnyNumeric,
+    __marker: PhantomData<&'a pg_sys::NumericData>,
+}
+
+impl Clone for JsonbNumber<'_> {
+    fn clone(&self) -> Self {
+        JsonbNumber { numeric: self.numeric.copy(), __marker: PhantomData }
+    }
+}
+
+impl JsonbNumber<'_> {
+    /// Copy the number into an [`AnyNumeric`] in the `CurrentMemoryContext`
+    pub fn to_numeric(&self) -> AnyNumeric {
+        self.numeric.clone()
+    }
+
+    /// The number as an `i64`, if it's an integer that fits
+    pub fn as_i64(&self) -> Option<i64> {
+        if self.numeric.floor() != self.numeric {
+            return None;
+        }
+        i64::try_from(self.numeric.copy()).ok()
+    }
+
+    /// The number as an `f64`, which might lose precision
+    pub fn as_f64(&self) -> Option<f64> {
+        f64::try_from(self.numeric.copy()).ok()
+    }
+}
+
+impl Display for JsonbNumber<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.numeric, f)
+    }
+}
+
+impl Debug for JsonbNumber<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JsonbNumber({})", self)
+    }
+}
+
+struct JsonbIter<'a> {
+    iter: *mut pg_sys::JsonbIterator,
+    __marker: PhantomData<&'a pg_sys::Jsonb>,
+}
+
+impl<'a> JsonbIter<'a> {
+    fn new(container: &JsonbRef<'a>, iterate: bool) -> Self {
+        let mut iter = JsonbIter { iter: std::ptr::null_mut(), __marker: PhantomData };
+        if iterate {
+            iter.iter = unsafe { pg_sys::JsonbIteratorInit(container.container.as_ptr()) };
+            // skip past WJB_BEGIN_ARRAY or WJB_BEGIN_OBJECT
+            iter.next_token(&mut pg_sys::JsonbValue::default());
+        }
+        iter
+    }
+
+    /// The next token at the top level of the container, with nested containers returned as
+    /// binary values rather than descended into
+    fn next_token(&mut self, value: &mut pg_sys::JsonbValue) -> pg_sys::JsonbIteratorToken {
+        if self.iter.is_null() {
+            // Postgres frees, and nulls out, the iterator once it's done
+            return pg_sys::JsonbIteratorToken_WJB_DONE;
+        }
+        unsafe { pg_sys::JsonbIteratorNext(&mut self.iter, value, true) }
+    }
+}
+
+impl Drop for JsonbIter<'_> {
+    fn drop(&mut self) {
+        if !self.iter.is_null() {
+            unsafe { pg_sys::pfree(self.iter.cast()) }
+        }
+    }
+}
+
+/// An iterator over the elements of a `jsonb` array
+pub struct JsonbElements<'a>(JsonbIter<'a>);
+
+impl<'a> Iterator for JsonbElements<'a> {
+    type Item = JsonbValueRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut value = pg_sys::JsonbValue::default();
+        match self.0.next_token(&mut value) {
+            pg_sys::JsonbIteratorToken_WJB_ELEM => {
+                Some(unsafe { JsonbValueRef::from_jsonb_value(&value) })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An iterator over the keys and values of a `jsonb` object
+pub struct JsonbEntries<'a>(JsonbIter<'a>);
+
+impl<'a> Iterator for JsonbEntries<'a> {
+    type Item = (&'a str, JsonbValueRef<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut key = pg_sys::JsonbValue::default();
+        if self.0.next_token(&mut key) != pg_sys::JsonbIteratorToken_WJB_KEY {
+            return None;
+        }
+        let mut value = pg_sys::JsonbValue::default();
+        let token = self.0.next_token(&mut value);
+        assert_eq!(token, pg_sys::JsonbIteratorToken_WJB_VALUE, "jsonb key without a value");
+
+        unsafe {
+            let key =
+                JsonbValueRef::from_jsonb_value(&key).as_str().expect("jsonb key is not a string");
+            Some((key, JsonbValueRef::from_jsonb_value(&value)))
+        }
+    }
+}
+
+enum Frame {
+    Array,
+    Object { expecting_key: bool },
+}
+
+/// Builds a new `jsonb` document, value by value, with Postgres' own `pushJsonbValue()`
+///
+/// Strings and values borrowed from other documents are only copied when the document is built,
+/// so they must outlive the builder.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use pgx::{JsonbBuilder, JsonbRef, OwnedJsonb};
+///
+/// fn tagged<'a>(doc: JsonbRef<'a>, tag: &'a str) -> OwnedJsonb {
+///     let mut builder = JsonbBuilder::new();
+///     builder.begin_object();
+///     builder.key("tag").push(tag);
+///     builder.key("count").push_number(doc.len());
+///     builder.key("doc").push(doc);
+///     builder.end_object();
+///     builder.build()
+/// }
+/// ```
+///
+/// ## Panics
+///
+/// If values, keys, and the ends of objects and arrays are pushed out of order
+pub struct JsonbBuilder<'a> {
+    state: *mut pg_sys::JsonbParseState,
+    frames: Vec<Frame>,
+    result: Option<pg_sys::JsonbValue>,
+    /// numbers converted from Rust, which must live until the document is built
+    numbers: Vec<AnyNumeric>,
+    __marker: PhantomData<&'a str>,
+}
+
+impl<'a> JsonbBuilder<'a> {
+    pub fn new() -> Self {
+        JsonbBuilder {
+            state: std::ptr::null_mut(),
+            frames: Vec::new(),
+            result: None,
+            numbers: Vec::new(),
+            __marker: PhantomData,
+        }
+    }
+
+    /// Start an object, as a value of its own
+    pub fn begin_object(&mut self) -> &mut Self {
+        self.value_token();
+        self.push_token(pg_sys::JsonbIteratorToken_WJB_BEGIN_OBJECT, None);
+        self.frames.push(Frame::Object { expecting_key: true });
+        self
+    }
+
+    /// End the current object
+    pub fn end_object(&mut self) -> &mut Self {
+        match self.frames.pop() {
+            Some(Frame::Object { expecting_key: true }) => {}
+            Some(Frame::Object { expecting_key: false }) => panic!("jsonb key without a value"),
+            _ => panic!("not building a jsonb object"),
+        }
+        self.end_container(pg_sys::JsonbIteratorToken_WJB_END_OBJECT)
+    }
+
+    /// Start an array, as a value of its own
+    pub fn begin_array(&mut self) -> &mut Self {
+        self.value_token();
+        self.push_token(pg_sys::JsonbIteratorToken_WJB_BEGIN_ARRAY, None);
+        self.frames.push(Frame::Array);
+        self
+    }
+
+    /// End the current array
+    pub fn end_array(&mut self) -> &mut Self {
+        match self.frames.pop() {
+            Some(Frame::Array) => {}
+            _ => panic!("not building a jsonb array"),
+        }
+        self.end_container(pg_sys::JsonbIteratorToken_WJB_END_ARRAY)
+    }
+
+    /// Push the next key of the current object, whose value must be pushed next
+    pub fn key(&mut self, key: &'a str) -> &mut Self {
+        match self.frames.last_mut() {
+            Some(Frame::Object { expecting_key }) if *expecting_key => *expecting_key = false,
+            _ => panic!("not expecting a jsonb key"),
+        }
+        let mut value = JsonbValueRef::String(key).to_jsonb_value();
+        self.push_token(pg_sys::JsonbIteratorToken_WJB_KEY, Some(&mut value));
+        self
+    }
+
+    /// Push a value: the next element of an array, the value of an object's key, or the whole
+    /// document
+    pub fn push(&mut self, value: impl Into<JsonbValueRef<'a>>) -> &mut Self {
+        let mut value = value.into().to_jsonb_value();
+        match self.value_token() {
+            Some(token) => {
+                self.push_token(token, Some(&mut value));
+            }
+            // JsonbValueToJsonb() takes care of scalar documents, and copying containers
+            None => self.result = Some(value),
+        }
+        self
+    }
+
+    /// Push a number converted from Rust, like with [`JsonbBuilder::push()`]
+    pub fn push_number(&mut self, number: impl Into<AnyNumeric>) -> &mut Self {
+        let number = number.into();
+        let value =
+            JsonbValueRef::Number(JsonbNumber { numeric: number.copy(), __marker: PhantomData });
+        self.numbers.push(number);
+        self.push(value)
+    }
+
+    /// Build the document, in the `CurrentMemoryContext`
+    ///
+    /// ## Panics
+    ///
+    /// If the document is incomplete
+    pub fn build(mut self) -> OwnedJsonb {
+        assert!(self.frames.is_empty(), "incomplete jsonb document");
+        let mut result = self.result.take().expect("empty jsonb document");
+        unsafe {
+            OwnedJsonb { jsonb: PgBox::<_>::from_rust(pg_sys::JsonbValueToJsonb(&mut result)) }
+        }
+    }
+
+    /// The token to push the next value with, or `None` if it's the whole document
+    fn value_token(&mut self) -> Option<pg_sys::JsonbIteratorToken> {
+        assert!(self.result.is_none(), "jsonb document is already complete");
+        match self.frames.last_mut() {
+            None => None,
+            Some(Frame::Array) => Some(pg_sys::JsonbIteratorToken_WJB_ELEM),
+            Some(Frame::Object { expecting_key }) => {
+                assert!(!*expecting_key, "expected a jsonb key");
+                *expecting_key = true;
+                Some(pg_sys::JsonbIteratorToken_WJB_VALUE)
+            }
+        }
+    }
+
+    fn end_container(&mut self, token: pg_sys::JsonbIteratorToken) -> &mut Self {
+        let result = self.push_token(token, None);
+        if self.frames.is_empty() {
+            self.result = Some(unsafe { *result });
+        }
+        self
+    }
+
+    fn push_token(
+        &mut self,
+        token: pg_sys::JsonbIteratorToken,
+        value: Option<&mut pg_sys::JsonbValue>,
+    ) -> *mut pg_sys::JsonbValue {
+        let value = value.map_or(std::ptr::null_mut(), |value| value as *mut _);
+        unsafe { pg_sys::pushJsonbValue(&mut self.state, token, value) }
+    }
+}
+
+impl Default for JsonbBuilder<'_> {
+    fn default() -> Self {
+        JsonbBuilder::new()
+    }
+}
+
+/// A `jsonb` document built by a [`JsonbBuilder`], which is `pfree`d when dropped, unless it's
+/// returned to Postgres
+pub struct OwnedJsonb {
+    jsonb: PgBox<pg_sys::Jsonb, AllocatedByRust>,
+}
+
+impl OwnedJsonb {
+    /// Borrow the document, to read it like any other
+    pub fn as_jsonb_ref(&self) -> JsonbRef<'_> {
+        unsafe { JsonbRef::from_jsonb(self.jsonb.as_ptr()) }
+    }
+}
+
+impl Display for OwnedJsonb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.as_jsonb_ref(), f)
+    }
+}
+
+impl Debug for OwnedJsonb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OwnedJsonb({})", self)
+    }
+}
+
+impl<'a> FromDatum for JsonbRef<'a> {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null || datum.is_null() {
+            None
+        } else {
+            let jsonb = pg_sys::pg_detoast_datum(datum.cast_mut_ptr()) as *mut pg_sys::Jsonb;
+            Some(JsonbRef::from_jsonb(jsonb))
+        }
+    }
+
+    unsafe fn from_datum_in_memory_context(
+        mut memory_context: PgMemoryContexts,
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null || datum.is_null() {
+            None
+        } else {
+            memory_context.switch_to(|_| {
+                let jsonb =
+                    pg_sys::pg_detoast_datum_copy(datum.cast_mut_ptr()) as *mut pg_sys::Jsonb;
+                Some(JsonbRef::from_jsonb(jsonb))
+            })
+        }
+    }
+}
+
+/// A document's root is returned as-is, and a nested object or array is copied into a document
+/// of its own
+impl IntoDatum for JsonbRef<'_> {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let jsonb = match self.root {
+            Some(root) => root.as_ptr(),
+            None => unsafe { pg_sys::JsonbValueToJsonb(&mut self.binary_value()) },
+        };
+        Some(jsonb.into())
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::JSONBOID
+    }
+}
+
+impl IntoDatum for OwnedJsonb {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.jsonb.into_pg().into())
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::JSONBOID
+    }
+}
+
+unsafe impl SqlTranslatable for JsonbRef<'_> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("jsonb"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("jsonb")))
+    }
+}
+
+unsafe impl SqlTranslatable for OwnedJsonb {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("jsonb"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("jsonb")))
+    }
+}
@@ -0,0 +1,212 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{
+    pg_sys, varatt_is_1b_e, varlena_to_byte_slice, void_mut_ptr, FromDatum, IntoDatum,
+    PgMemoryContexts,
+};
+use pgx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// How a TOASTed value is compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastCompression {
+    Pglz,
+    Lz4,
+}
+
+/// A `text` or `bytea` value that's left TOASTed until it's asked for
+///
+/// Accepting `&str`, `String`, `&[u8]`, or `Vec<u8>` detoasts the entire value before the function
+/// is even called.  A `PgLazyVarlena<str>` (mapped to `text`) or `PgLazyVarlena<[u8]>` (mapped to
+/// `bytea`) can instead report the value's sizes and compression, and fetch just a slice of it with
+/// [`PgLazyVarlena::slice()`].
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use pgx::prelude::*;
+/// use pgx::PgLazyVarlena;
+///
+/// #[pg_extern]
+/// fn is_pdf<'a>(document: PgLazyVarlena<'a, [u8]>) -> bool {
+///     document.slice(0, 5) == b"%PDF-"
+/// }
+/// ```
+///
+/// Slices are cheapest for values stored uncompressed, with `ALTER TABLE ... SET STORAGE EXTERNAL`,
+/// as Postgres then only fetches the TOAST chunks covering the slice.  Compressed values must be
+/// decompressed up to the end of the slice.
+pub struct PgLazyVarlena<'a, T: ?Sized = [u8]> {
+    varlena: NonNull<pg_sys::varlena>,
+    __marker: PhantomData<&'a T>,
+}
+
+impl<'a, T: ?Sized> PgLazyVarlena<'a, T> {
+    /// Wrap a possibly TOASTed `varlena *` without detoasting it
+    ///
+    /// ## Safety
+    ///
+    /// `varlena` must be a valid, non-null `varlena *` of the type `T` is mapped to, and it must
+    /// outlive `'a`.
+    pub unsafe fn from_ptr(varlena: *mut pg_sys::varlena) -> Self {
+        PgLazyVarlena {
+            varlena: NonNull::new(varlena).expect("varlena pointer is null"),
+            __marker: PhantomData,
+        }
+    }
+
+    /// The size, in bytes, of the value once it's detoasted, like `octet_length()`
+    pub fn raw_size(&self) -> usize {
+        unsafe { pg_sys::toast_raw_datum_size(self.datum()) - pg_sys::VARHDRSZ }
+    }
+
+    /// The size, in bytes, the value takes up as stored, which might be compressed, like
+    /// `pg_column_size()`
+    pub fn stored_size(&self) -> usize {
+        unsafe { pg_sys::toast_datum_size(self.datum()) }
+    }
+
+    /// Is the value stored out-of-line, in its table's TOAST table?
+    pub fn is_external(&self) -> bool {
+        unsafe { varatt_is_1b_e(self.varlena.as_ptr()) }
+    }
+
+    /// How the value is compressed, if it is, like `pg_column_compression()`
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    pub fn compression(&self) -> Option<ToastCompression> {
+        match unsafe { pg_sys::toast_get_compression_id(self.varlena.as_ptr()) } {
+            pg_sys::ToastCompressionId_TOAST_PGLZ_COMPRESSION_ID => Some(ToastCompression::Pglz),
+            pg_sys::ToastCompressionId_TOAST_LZ4_COMPRESSION_ID => Some(ToastCompression::Lz4),
+            _ => None,
+        }
+    }
+
+    /// How the value is compressed, if it is.  Before Postgres 14, that's always with pglz.
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+    pub fn compression(&self) -> Option<ToastCompression> {
+        let ptr = self.varlena.as_ptr();
+        let compressed = unsafe {
+            if varatt_is_1b_e(ptr) {
+                // only on-disk TOAST pointers can point to a compressed value
+                crate::vartag_external(ptr) == pg_sys::vartag_external_VARTAG_ONDISK as u8 && {
+                    let toast = std::ptr::read_unaligned(
+                        crate::vardata_1b_e(ptr) as *const pg_sys::varatt_external
+                    );
+                    toast.va_extsize < toast.va_rawsize - pg_sys::VARHDRSZ as i32
+                }
+            } else {
+                crate::varatt_is_b8_c(ptr)
+            }
+        };
+        compressed.then(|| ToastCompression::Pglz)
+    }
+
+    /// Fetch, and decompress, only the `len` bytes of the value starting at byte `offset`
+    ///
+    /// The slice is shorter than `len` if the value ends first, and empty if it ends before
+    /// `offset`.  For `text`, the slice's boundaries might fall within a multibyte character.
+    pub fn slice(&self, offset: usize, len: usize) -> &'a [u8] {
+        let offset = i32::try_from(offset).unwrap_or(i32::MAX);
+        let len = i32::try_from(len).unwrap_or(i32::MAX);
+        unsafe {
+            let slice = pg_sys::pg_detoast_datum_slice(self.varlena.as_ptr(), offset, len);
+            varlena_to_byte_slice(slice)
+        }
+    }
+
+    /// The underlying, possibly TOASTed, `varlena *`
+    pub fn as_ptr(&self) -> *mut pg_sys::varlena {
+        self.varlena.as_ptr()
+    }
+
+    fn datum(&self) -> pg_sys::Datum {
+        pg_sys::Datum::from(self.varlena.as_ptr())
+    }
+}
+
+impl<'a> PgLazyVarlena<'a, str> {
+    /// Detoast the entire value, as `&str` would have
+    pub fn detoast(&self) -> &'a str {
+        unsafe {
+            let varlena = pg_sys::pg_detoast_datum_packed(self.varlena.as_ptr());
+            crate::text_to_rust_str_unchecked(varlena)
+        }
+    }
+}
+
+impl<'a> PgLazyVarlena<'a, [u8]> {
+    /// Detoast the entire value, as `&[u8]` would have
+    pub fn detoast(&self) -> &'a [u8] {
+        unsafe {
+            let varlena = pg_sys::pg_detoast_datum_packed(self.varlena.as_ptr());
+            varlena_to_byte_slice(varlena)
+        }
+    }
+}
+
+impl<'a, T: ?Sized> FromDatum for PgLazyVarlena<'a, T> {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null || datum.is_null() {
+            None
+        } else {
+            Some(PgLazyVarlena::from_ptr(datum.cast_mut_ptr()))
+        }
+    }
+
+    /// Copies the value as it is, so a TOAST pointer stays a pointer, and must not outlive the
+    /// current transaction
+    unsafe fn from_datum_in_memory_context(
+        mut memory_context: PgMemoryContexts,
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null || datum.is_null() {
+            None
+        } else {
+            let ptr = datum.cast_mut_ptr::<pg_sys::varlena>();
+            let copy = memory_context.copy_ptr_into(ptr as void_mut_ptr, crate::varsize_any(ptr));
+            Some(PgLazyVarlena::from_ptr(copy.cast()))
+        }
+    }
+}
+
+/// Returns the value as-is, without detoasting it
+impl<'a, T: ?Sized> IntoDatum for PgLazyVarlena<'a, T>
+where
+    &'a T: IntoDatum,
+{
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.datum())
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        <&'a T as IntoDatum>::type_oid()
+    }
+}
+
+unsafe impl<'a, T: ?Sized> SqlTranslatable for PgLazyVarlena<'a, T>
+where
+    &'a T: SqlTranslatable,
+{
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        <&'a T as SqlTranslatable>::argument_sql()
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        <&'a T as SqlTranslatable>::return_sql()
+    }
+}
@@ -20,6 +20,8 @@ mod internal;
 mod into;
 mod item_pointer_data;
 mod json;
+mod jsonb_ref;
+mod lazy_varlena;
 pub mod numeric;
 pub mod numeric_support;
 #[deny(unsafe_op_in_unsafe_fn)]
@@ -28,6 +30,8 @@ mod time;
 mod time_stamp;
 mod time_stamp_with_timezone;
 mod time_with_timezone;
+mod tsquery;
+mod tsvector;
 mod tuples;
 mod uuid;
 mod varlena;
@@ -45,6 +49,8 @@ pub use internal::*;
 pub use into::*;
 pub use item_pointer_data::*;
 pub use json::*;
+pub use jsonb_ref::*;
+pub use lazy_varlena::*;
 pub use numeric::{AnyNumeric, Numeric};
 use once_cell::sync::Lazy;
 pub use range::*;
@@ -52,6 +58,8 @@ use std::any::TypeId;
 pub use time_stamp::*;
 pub use time_stamp_with_timezone::*;
 pub use time_with_timezone::*;
+pub use tsquery::*;
+pub use tsvector::*;
 pub use tuples::*;
 pub use varlena::*;
 
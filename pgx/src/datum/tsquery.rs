@@ -0,0 +1,307 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::datum::tsvector::write_quoted_lexeme;
+use crate::{direct_function_call_as_datum, pg_sys, set_varsize, FromDatum, IntoDatum, TsWeight};
+use pgx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use std::ffi::CString;
+use std::fmt::{Display, Formatter};
+
+/// A node of a [`TsQuery`]'s operator tree
+///
+/// Nodes can be combined with `&`, `|`, and `!`, like in a `tsquery`'s text.
+///
+/// ```rust,no_run
+/// use pgx::{TsQuery, TsQueryNode};
+///
+/// let query = TsQuery::new(
+///     (TsQueryNode::lexeme("fat") | TsQueryNode::prefix("cat")) & !TsQueryNode::lexeme("rat"),
+/// );
+/// assert_eq!(query.to_string(), "( 'fat' | 'cat':* ) & !'rat'");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TsQueryNode {
+    /// Matches a lexeme.  If `weights` isn't empty, only at positions with one of those weights,
+    /// and if `prefix` is true, any lexeme starting with `lexeme`.
+    Lexeme { lexeme: String, weights: Vec<TsWeight>, prefix: bool },
+    /// `!operand`
+    Not(Box<TsQueryNode>),
+    /// `left & right`
+    And(Box<TsQueryNode>, Box<TsQueryNode>),
+    /// `left | right`
+    Or(Box<TsQueryNode>, Box<TsQueryNode>),
+    /// `left <distance> right`, where `right` follows `left` by exactly `distance` positions.
+    /// `<->` is a distance of 1.
+    Phrase(Box<TsQueryNode>, Box<TsQueryNode>, u16),
+}
+
+impl TsQueryNode {
+    pub fn lexeme(lexeme: &str) -> Self {
+        TsQueryNode::Lexeme { lexeme: lexeme.to_string(), weights: Vec::new(), prefix: false }
+    }
+
+    /// Matches any lexeme starting with `prefix`, like `'prefix':*`
+    pub fn prefix(prefix: &str) -> Self {
+        TsQueryNode::Lexeme { lexeme: prefix.to_string(), weights: Vec::new(), prefix: true }
+    }
+
+    /// `self <-> next`
+    pub fn followed_by(self, next: TsQueryNode) -> Self {
+        self.phrase(next, 1)
+    }
+
+    /// `self <distance> next`
+    pub fn phrase(self, next: TsQueryNode, distance: u16) -> Self {
+        TsQueryNode::Phrase(Box::new(self), Box::new(next), distance)
+    }
+
+    /// `tsearch_op_priority`, which decides where `tsqueryout()` puts parentheses
+    fn priority(&self) -> i32 {
+        match self {
+            TsQueryNode::Lexeme { .. } => i32::MAX,
+            TsQueryNode::Not(_) => 4,
+            TsQueryNode::Phrase(..) => 3,
+            TsQueryNode::And(..) => 2,
+            TsQueryNode::Or(..) => 1,
+        }
+    }
+
+    /// Write the node the same way `tsqueryout()`'s `infix()` does
+    fn write_infix(
+        &self,
+        f: &mut Formatter<'_>,
+        parent_priority: i32,
+        right_phrase_op: bool,
+    ) -> std::fmt::Result {
+        let priority = self.priority();
+        let (left, right, op) = match self {
+            TsQueryNode::Lexeme { lexeme, weights, prefix } => {
+                write_quoted_lexeme(f, lexeme)?;
+                if *prefix || !weights.is_empty() {
+                    f.write_str(":")?;
+                    if *prefix {
+                        f.write_str("*")?;
+                    }
+                    for weight in [TsWeight::A, TsWeight::B, TsWeight::C, TsWeight::D] {
+                        if weights.contains(&weight) {
+                            write!(f, "{}", weight.as_char())?;
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            TsQueryNode::Not(operand) => {
+                let parenthesize = priority < parent_priority;
+                if parenthesize {
+                    f.write_str("( ")?;
+                }
+                f.write_str("!")?;
+                operand.write_infix(f, priority, false)?;
+                if parenthesize {
+                    f.write_str(" )")?;
+                }
+                return Ok(());
+            }
+            TsQueryNode::And(left, right) => (left, right, " & ".to_string()),
+            TsQueryNode::Or(left, right) => (left, right, " | ".to_string()),
+            TsQueryNode::Phrase(left, right, 1) => (left, right, " <-> ".to_string()),
+            TsQueryNode::Phrase(left, right, distance) => {
+                (left, right, format!(" <{}> ", distance))
+            }
+        };
+
+        let is_phrase = matches!(self, TsQueryNode::Phrase(..));
+        // phrase operators depend on their order
+        let parenthesize = priority < parent_priority || (is_phrase && right_phrase_op);
+        if parenthesize {
+            f.write_str("( ")?;
+        }
+        left.write_infix(f, priority, false)?;
+        f.write_str(&op)?;
+        right.write_infix(f, priority, is_phrase)?;
+        if parenthesize {
+            f.write_str(" )")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::ops::BitAnd for TsQueryNode {
+    type Output = TsQueryNode;
+
+    fn bitand(self, rhs: TsQueryNode) -> Self::Output {
+        TsQueryNode::And(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl std::ops::BitOr for TsQueryNode {
+    type Output = TsQueryNode;
+
+    fn bitor(self, rhs: TsQueryNode) -> Self::Output {
+        TsQueryNode::Or(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl std::ops::Not for TsQueryNode {
+    type Output = TsQueryNode;
+
+    fn not(self) -> Self::Output {
+        TsQueryNode::Not(Box::new(self))
+    }
+}
+
+/// A `tsquery`, a tree of lexemes combined with `&`, `|`, `!`, and phrase operators
+///
+/// A query can be empty, like the one `to_tsquery()` returns for a query made only of stop words.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TsQuery {
+    root: Option<TsQueryNode>,
+}
+
+impl TsQuery {
+    pub fn new(root: TsQueryNode) -> Self {
+        TsQuery { root: Some(root) }
+    }
+
+    /// A query without any lexemes, which matches nothing
+    pub fn empty() -> Self {
+        TsQuery::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn root(&self) -> Option<&TsQueryNode> {
+        self.root.as_ref()
+    }
+
+    pub fn into_root(self) -> Option<TsQueryNode> {
+        self.root
+    }
+}
+
+impl From<TsQueryNode> for TsQuery {
+    fn from(root: TsQueryNode) -> Self {
+        TsQuery::new(root)
+    }
+}
+
+/// Formats the query the way Postgres' `tsquery` output function does
+impl Display for TsQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.root {
+            Some(root) => root.write_infix(f, -1, false),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The size of a `TSQueryData` before its `QueryItem`s, `HDRSIZETQ`
+const HDRSIZETQ: usize = std::mem::size_of::<i32>() * 2;
+
+/// Decode the item at `index`.  Items are stored in prefix order: an operator is followed by its
+/// right operand, and `left` items after it is its left operand.
+unsafe fn decode_item(
+    items: *const pg_sys::QueryItem,
+    operands: *const u8,
+    index: usize,
+) -> TsQueryNode {
+    let item = items.add(index);
+    if (*item).type_ as u32 == pg_sys::QI_VAL {
+        let operand = (*item).qoperand;
+        let lexeme = std::slice::from_raw_parts(
+            operands.add(operand.distance() as usize),
+            operand.length() as usize,
+        );
+        let weights = [TsWeight::A, TsWeight::B, TsWeight::C, TsWeight::D]
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| operand.weight & (1 << (3 - i)) != 0)
+            .map(|(_, weight)| weight)
+            .collect();
+        return TsQueryNode::Lexeme {
+            lexeme: String::from_utf8_lossy(lexeme).into_owned(),
+            weights,
+            prefix: operand.prefix,
+        };
+    }
+
+    let operator = (*item).qoperator;
+    let right = Box::new(decode_item(items, operands, index + 1));
+    if operator.oper as u32 == pg_sys::OP_NOT {
+        return TsQueryNode::Not(right);
+    }
+    let left = Box::new(decode_item(items, operands, index + operator.left as usize));
+    match operator.oper as u32 {
+        pg_sys::OP_AND => TsQueryNode::And(left, right),
+        pg_sys::OP_OR => TsQueryNode::Or(left, right),
+        pg_sys::OP_PHRASE => TsQueryNode::Phrase(left, right, operator.distance as u16),
+        other => panic!("unrecognized tsquery operator: {}", other),
+    }
+}
+
+impl FromDatum for TsQuery {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null || datum.is_null() {
+            return None;
+        }
+
+        let query = pg_sys::pg_detoast_datum(datum.cast_mut_ptr()) as *mut pg_sys::TSQueryData;
+        let size = (*query).size as usize;
+        if size == 0 {
+            return Some(TsQuery::empty());
+        }
+        // `GETQUERY()` and `GETOPERAND()`
+        let items = (*query).data.as_ptr() as *const pg_sys::QueryItem;
+        let operands = items.add(size) as *const u8;
+        Some(TsQuery::new(decode_item(items, operands, 0)))
+    }
+}
+
+/// Non-empty queries are built by Postgres' `tsquery` input function from the query's text.
+///
+/// ## Panics
+///
+/// If Postgres can't parse the query, such as when a lexeme is empty
+impl IntoDatum for TsQuery {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        if self.is_empty() {
+            unsafe {
+                let query = pg_sys::palloc0(HDRSIZETQ) as *mut pg_sys::TSQueryData;
+                set_varsize(query.cast(), HDRSIZETQ as i32);
+                return Some(pg_sys::Datum::from(query));
+            }
+        }
+
+        let cstr = CString::new(self.to_string()).expect("tsquery contains a NUL byte");
+        unsafe {
+            direct_function_call_as_datum(pg_sys::tsqueryin, vec![cstr.as_c_str().into_datum()])
+        }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::TSQUERYOID
+    }
+}
+
+unsafe impl SqlTranslatable for TsQuery {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("tsquery"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("tsquery")))
+    }
+}
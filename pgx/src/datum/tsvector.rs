@@ -0,0 +1,359 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{pg_sys, set_varsize, FromDatum, IntoDatum};
+use pgx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use std::fmt::{Display, Formatter};
+
+/// The weight of a lexeme's position, or of a lexeme in a [`TsQuery`](crate::TsQuery)
+///
+/// `A` is the highest weight and `D`, the default, the lowest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TsWeight {
+    A,
+    B,
+    C,
+    #[default]
+    D,
+}
+
+impl TsWeight {
+    /// The weight's two bits in a `WordEntryPos`
+    pub(crate) fn bits(self) -> u16 {
+        match self {
+            TsWeight::A => 3,
+            TsWeight::B => 2,
+            TsWeight::C => 1,
+            TsWeight::D => 0,
+        }
+    }
+
+    pub(crate) fn from_bits(bits: u16) -> Self {
+        match bits & 3 {
+            3 => TsWeight::A,
+            2 => TsWeight::B,
+            1 => TsWeight::C,
+            _ => TsWeight::D,
+        }
+    }
+
+    pub(crate) fn as_char(self) -> char {
+        match self {
+            TsWeight::A => 'A',
+            TsWeight::B => 'B',
+            TsWeight::C => 'C',
+            TsWeight::D => 'D',
+        }
+    }
+}
+
+/// A lexeme's position in a document, counted in words from 1, and its weight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TsPosition {
+    pub position: u16,
+    pub weight: TsWeight,
+}
+
+/// One of a [`TsVector`]'s lexemes, and the positions it occurs at, if any
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TsLexeme {
+    lexeme: String,
+    positions: Vec<TsPosition>,
+}
+
+impl TsLexeme {
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    /// The lexeme's positions, in ascending order
+    pub fn positions(&self) -> &[TsPosition] {
+        &self.positions
+    }
+
+    fn add_position(&mut self, position: u16, weight: TsWeight) {
+        match self.positions.binary_search_by_key(&position, |p| p.position) {
+            // like Postgres, a duplicated position keeps its highest weight
+            Ok(i) => {
+                if weight.bits() > self.positions[i].weight.bits() {
+                    self.positions[i].weight = weight;
+                }
+            }
+            Err(i) if self.positions.len() < pg_sys::MAXNUMPOS as usize => {
+                self.positions.insert(i, TsPosition { position, weight })
+            }
+            Err(_) => (),
+        }
+    }
+}
+
+/// A `tsvector`, a sorted set of distinct lexemes, each with the positions it occurs at
+///
+/// Lexemes are sorted and positions are kept as Postgres does, so a `TsVector` built in Rust is
+/// the same value as one built by `to_tsvector()` or parsed from text.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgx::{TsVector, TsWeight};
+///
+/// let mut vector = TsVector::new();
+/// vector.insert_position("fat", 2, TsWeight::A);
+/// vector.insert_position("cat", 3, TsWeight::D);
+/// vector.insert("rat");
+/// assert_eq!(vector.to_string(), "'cat':3 'fat':2A 'rat'");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TsVector {
+    lexemes: Vec<TsLexeme>,
+}
+
+impl TsVector {
+    pub fn new() -> Self {
+        TsVector::default()
+    }
+
+    /// The number of distinct lexemes, like SQL's `length(tsvector)`
+    pub fn len(&self) -> usize {
+        self.lexemes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lexemes.is_empty()
+    }
+
+    /// Iterate over the lexemes, in the order Postgres sorts them
+    pub fn iter(&self) -> std::slice::Iter<'_, TsLexeme> {
+        self.lexemes.iter()
+    }
+
+    pub fn get(&self, lexeme: &str) -> Option<&TsLexeme> {
+        self.find(lexeme).ok().map(|i| &self.lexemes[i])
+    }
+
+    pub fn contains(&self, lexeme: &str) -> bool {
+        self.find(lexeme).is_ok()
+    }
+
+    /// Add `lexeme`, without a position, if it's not already in the vector
+    ///
+    /// ## Panics
+    ///
+    /// If `lexeme` is empty or longer than `MAXSTRLEN` bytes
+    pub fn insert(&mut self, lexeme: &str) -> &mut Self {
+        self.entry(lexeme);
+        self
+    }
+
+    /// Add `lexeme` at `position`, with `weight`
+    ///
+    /// Like Postgres, positions past `MAXENTRYPOS - 1` are clamped to it, and a lexeme's positions
+    /// past the first `MAXNUMPOS` are ignored.
+    ///
+    /// ## Panics
+    ///
+    /// If `position` is zero, or `lexeme` is empty or longer than `MAXSTRLEN` bytes
+    pub fn insert_position(&mut self, lexeme: &str, position: u16, weight: TsWeight) -> &mut Self {
+        assert!(position > 0, "tsvector positions start at 1");
+        let position = position.min(pg_sys::MAXENTRYPOS as u16 - 1);
+        self.entry(lexeme).add_position(position, weight);
+        self
+    }
+
+    /// Remove `lexeme`, like SQL's `ts_delete()`, returning if it was in the vector
+    pub fn remove(&mut self, lexeme: &str) -> bool {
+        match self.find(lexeme) {
+            Ok(i) => {
+                self.lexemes.remove(i);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Postgres orders lexemes by their bytes, shorter lexemes first, which is how `str` compares
+    fn find(&self, lexeme: &str) -> Result<usize, usize> {
+        self.lexemes.binary_search_by(|l| l.lexeme.as_str().cmp(lexeme))
+    }
+
+    fn entry(&mut self, lexeme: &str) -> &mut TsLexeme {
+        assert!(!lexeme.is_empty(), "tsvector lexemes cannot be empty");
+        assert!(
+            lexeme.len() <= pg_sys::MAXSTRLEN as usize,
+            "word is too long ({} bytes, max {} bytes)",
+            lexeme.len(),
+            pg_sys::MAXSTRLEN
+        );
+        let i = match self.find(lexeme) {
+            Ok(i) => i,
+            Err(i) => {
+                self.lexemes
+                    .insert(i, TsLexeme { lexeme: lexeme.to_string(), positions: Vec::new() });
+                i
+            }
+        };
+        &mut self.lexemes[i]
+    }
+}
+
+impl<'a> IntoIterator for &'a TsVector {
+    type Item = &'a TsLexeme;
+    type IntoIter = std::slice::Iter<'a, TsLexeme>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lexemes.iter()
+    }
+}
+
+/// Quote a lexeme the way `tsvectorout()` and `tsqueryout()` do
+pub(crate) fn write_quoted_lexeme(f: &mut Formatter<'_>, lexeme: &str) -> std::fmt::Result {
+    f.write_str("'")?;
+    for c in lexeme.chars() {
+        match c {
+            '\'' => f.write_str("''")?,
+            '\\' => f.write_str("\\\\")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("'")
+}
+
+/// Formats the vector the way Postgres' `tsvector` output function does
+impl Display for TsVector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, lexeme) in self.lexemes.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write_quoted_lexeme(f, &lexeme.lexeme)?;
+            for (j, position) in lexeme.positions.iter().enumerate() {
+                f.write_str(if j == 0 { ":" } else { "," })?;
+                write!(f, "{}", position.position)?;
+                if position.weight != TsWeight::D {
+                    write!(f, "{}", position.weight.as_char())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The size of a `TSVectorData` before its `WordEntry`s, `DATAHDRSIZE`
+const DATAHDRSIZE: usize = std::mem::size_of::<i32>() * 2;
+
+impl FromDatum for TsVector {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null || datum.is_null() {
+            return None;
+        }
+
+        let vector = pg_sys::pg_detoast_datum(datum.cast_mut_ptr()) as *mut pg_sys::TSVectorData;
+        let size = (*vector).size as usize;
+        let entries = (*vector).entries.as_ptr();
+        // `STRPTR()`, where the lexemes and their positions start
+        let strptr = entries.add(size) as *const u8;
+
+        let mut lexemes = Vec::with_capacity(size);
+        for i in 0..size {
+            let entry = *entries.add(i);
+            let (pos, len) = (entry.pos() as usize, entry.len() as usize);
+            let lexeme = std::slice::from_raw_parts(strptr.add(pos), len);
+
+            let mut positions = Vec::new();
+            if entry.haspos() != 0 {
+                // `_POSVECPTR()`, the positions start at the next even offset after the lexeme
+                let posvec = strptr.add((pos + len + 1) & !1) as *const u16;
+                let npos = posvec.read_unaligned() as usize;
+                for j in 1..=npos {
+                    let word_entry_pos = posvec.add(j).read_unaligned();
+                    positions.push(TsPosition {
+                        position: word_entry_pos & 0x3fff,
+                        weight: TsWeight::from_bits(word_entry_pos >> 14),
+                    });
+                }
+            }
+
+            lexemes
+                .push(TsLexeme { lexeme: String::from_utf8_lossy(lexeme).into_owned(), positions });
+        }
+
+        Some(TsVector { lexemes })
+    }
+}
+
+impl IntoDatum for TsVector {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let mut entries = Vec::with_capacity(self.lexemes.len());
+        let mut data = Vec::<u8>::new();
+        for lexeme in &self.lexemes {
+            let pos = data.len();
+            data.extend_from_slice(lexeme.lexeme.as_bytes());
+            if !lexeme.positions.is_empty() {
+                if data.len() % 2 == 1 {
+                    data.push(0);
+                }
+                data.extend_from_slice(&(lexeme.positions.len() as u16).to_ne_bytes());
+                for position in &lexeme.positions {
+                    let word_entry_pos = position.weight.bits() << 14 | position.position;
+                    data.extend_from_slice(&word_entry_pos.to_ne_bytes());
+                }
+            }
+            entries.push(pg_sys::WordEntry {
+                _bitfield_align_1: [],
+                _bitfield_1: pg_sys::WordEntry::new_bitfield_1(
+                    !lexeme.positions.is_empty() as u32,
+                    lexeme.lexeme.len() as u32,
+                    pos as u32,
+                ),
+            });
+        }
+        if data.len() > pg_sys::MAXSTRPOS as usize {
+            panic!(
+                "string is too long for tsvector ({} bytes, max {} bytes)",
+                data.len(),
+                pg_sys::MAXSTRPOS
+            );
+        }
+
+        let entries_size = entries.len() * std::mem::size_of::<pg_sys::WordEntry>();
+        let total = DATAHDRSIZE + entries_size + data.len();
+        unsafe {
+            let vector = pg_sys::palloc0(total) as *mut pg_sys::TSVectorData;
+            set_varsize(vector.cast(), total as i32);
+            (*vector).size = entries.len() as i32;
+            let entries_ptr = (*vector).entries.as_mut_ptr();
+            std::ptr::copy_nonoverlapping(entries.as_ptr(), entries_ptr, entries.len());
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                entries_ptr.add(entries.len()) as *mut u8,
+                data.len(),
+            );
+            Some(pg_sys::Datum::from(vector))
+        }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::TSVECTOROID
+    }
+}
+
+unsafe impl SqlTranslatable for TsVector {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("tsvector"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("tsvector")))
+    }
+}
@@ -0,0 +1,138 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Add `CONTEXT` lines to errors and messages raised while some code runs
+//!
+//! This is a safe wrapper around Postgres' `error_context_stack`.  While an [`ErrorContextGuard`]
+//! is alive, every error, warning, or other message Postgres reports gets a `CONTEXT` line built
+//! by the guard's closure.  The closure is only called when a message is actually reported.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use pgx::prelude::*;
+//!
+//! # fn process(row: usize) {}
+//! for row in 0..100 {
+//!     pgx::error_context(
+//!         || format!("while processing row {} of file {}", row, "data.csv"),
+//!         || process(row),
+//!     );
+//! }
+//! ```
+use crate as pgx; // for #[pg_guard] support from within ourself
+use crate::pg_sys::{self, AsPgCStr, PgTryBuilder};
+use pgx_macros::pg_guard;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::AssertUnwindSafe;
+
+/// Run `body` with `message` providing a `CONTEXT` line for anything reported while it runs
+///
+/// The context is removed when `body` returns, or if it raises an error or panics.  Errors raised
+/// from Rust within `body`, such as with `error!()` or `panic!()`, also get the context.
+pub fn error_context<M, S, F, R>(message: M, body: F) -> R
+where
+    M: Fn() -> S,
+    S: AsRef<str>,
+    F: FnOnce() -> R,
+{
+    let context = AssertUnwindSafe(&message);
+    PgTryBuilder::new(AssertUnwindSafe(|| {
+        // SAFETY: the guard is dropped before this closure returns or unwinds
+        let _guard = unsafe { ErrorContextGuard::new(&message) };
+        body()
+    }))
+    .catch_others(move |mut error| {
+        // errors raised from Rust aren't reported until they reach the `#[pg_guard]`
+        // boundary, long after our guard is gone, so they carry the context with them
+        error.add_context((*context)().as_ref());
+        error.rethrow()
+    })
+    .execute()
+}
+
+/// Pushes an entry onto Postgres' `error_context_stack`, which is removed when the guard is dropped
+///
+/// Prefer [`error_context()`], which also adds the context to errors raised from Rust.
+pub struct ErrorContextGuard<'a> {
+    entry: Box<ErrorContextEntry<'a>>,
+    // the stack belongs to this backend's main thread
+    _not_send: PhantomData<*const ()>,
+}
+
+struct ErrorContextEntry<'a> {
+    callback: pg_sys::ErrorContextCallback,
+    message: Box<dyn Fn() -> String + 'a>,
+}
+
+impl<'a> ErrorContextGuard<'a> {
+    /// Push `message` onto the `error_context_stack`
+    ///
+    /// # Safety
+    ///
+    /// The guard must be dropped before the Postgres function that was running when it was
+    /// created returns, and it must not be leaked.  Otherwise Postgres is left with a pointer to
+    /// freed memory on its `error_context_stack`.
+    pub unsafe fn new<M, S>(message: M) -> Self
+    where
+        M: Fn() -> S + 'a,
+        S: AsRef<str>,
+    {
+        let mut entry = Box::new(ErrorContextEntry {
+            callback: pg_sys::ErrorContextCallback {
+                previous: pg_sys::error_context_stack,
+                callback: Some(error_context_callback),
+                arg: std::ptr::null_mut(),
+            },
+            message: Box::new(move || message().as_ref().to_string()),
+        });
+
+        // the entry is boxed, so these pointers stay valid for as long as the guard exists
+        entry.callback.arg = (&mut entry.message as *mut Box<dyn Fn() -> String + 'a>).cast();
+        pg_sys::error_context_stack = &mut entry.callback;
+        ErrorContextGuard { entry, _not_send: PhantomData }
+    }
+}
+
+impl Drop for ErrorContextGuard<'_> {
+    fn drop(&mut self) {
+        let entry: *mut pg_sys::ErrorContextCallback = &mut self.entry.callback;
+        unsafe {
+            // unlink our entry wherever it is, as guards aren't necessarily dropped in the reverse
+            // order they're created.  When unwinding from a Postgres ERROR, the stack has already
+            // been reset to where it was when we called into Postgres, which might be below our
+            // entry, and then there's nothing to do
+            let mut link: *mut *mut pg_sys::ErrorContextCallback =
+                std::ptr::addr_of_mut!(pg_sys::error_context_stack);
+            while !(*link).is_null() {
+                if *link == entry {
+                    *link = (*entry).previous;
+                    break;
+                }
+                link = &mut (**link).previous;
+            }
+        }
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn error_context_callback(arg: *mut c_void) {
+    // errcontext_msg() is excluded from the generated bindings, just like the rest of the
+    // ereport machinery, so we declare it ourselves
+    extern "C" {
+        fn errcontext_msg(fmt: *const c_char, ...) -> c_int;
+    }
+
+    let message = &*(arg as *const Box<dyn Fn() -> String>);
+    let message = message().as_pg_cstr();
+    pg_sys::set_errcontext_domain(std::ptr::null());
+    errcontext_msg(b"%s\0".as_ptr().cast(), message);
+    pg_sys::pfree(message.cast());
+}
@@ -0,0 +1,373 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Compile SQL expressions once and evaluate them against many tuples, without going through SPI
+use crate as pgx; // for #[pg_guard] support from within ourself
+use crate::nodes::{expression_tree_walker, NodeRef};
+use crate::parser::{self, RawNodeKind};
+use crate::pg_sys::panic::{CaughtError, ErrorReport};
+use crate::{
+    pg_sys, FromDatum, IntoDatum, PgList, PgMemoryContexts, PgSqlErrorCode, PgTryBuilder,
+    PgTupleDesc, PgTupleSlot, TryFromDatumError,
+};
+use core::ffi::CStr;
+use pgx_macros::pg_guard;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// Expressions are parsed as the target of a `SELECT`, so error positions are shifted back by this
+const SELECT_PREFIX: &str = "SELECT ";
+
+/// A compiled scalar expression, ready to be evaluated against a [`PgTupleSlot`]
+///
+/// The expression is planned and compiled by Postgres' executor, exactly as a `WHERE` clause would
+/// be, so evaluating it is as fast as Postgres' own filtering.  Everything it needs is allocated
+/// in its own `EState`, which is freed when the `PgExpr` is dropped.
+///
+/// Parameters are referenced as `$1`, `$2`, etc, and bound each time the expression is evaluated.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgx::prelude::*;
+/// use pgx::{PgExpr, PgTupleDesc, PgTupleSlot};
+///
+/// let tupdesc = PgTupleDesc::for_composite_type("dog").unwrap();
+/// let mut filter =
+///     PgExpr::compile("age > $1 AND name LIKE 'B%'", &tupdesc, &[pg_sys::INT4OID]).unwrap();
+///
+/// let mut slot = PgTupleSlot::new(&tupdesc);
+/// slot.store_datums(["Brandy".into_datum(), 42.into_datum()]).unwrap();
+///
+/// let matches = filter.evaluate::<bool>(Some(&slot), &[10.into_datum()]);
+/// assert_eq!(matches, Ok(Some(true)));
+/// ```
+pub struct PgExpr<'a> {
+    estate: NonNull<pg_sys::EState>,
+    state: NonNull<pg_sys::ExprState>,
+    econtext: NonNull<pg_sys::ExprContext>,
+    param_types: Vec<pg_sys::Oid>,
+    result_type: pg_sys::Oid,
+    uses_columns: bool,
+    _tupdesc: PhantomData<&'a pg_sys::TupleDescData>,
+}
+
+impl<'a> PgExpr<'a> {
+    /// Compile the SQL expression `sql`, whose column references name attributes of `tupdesc` and
+    /// whose parameters `$1`, `$2`, etc, have the types `param_types`
+    ///
+    /// Anything allowed in a `WHERE` clause is allowed, except sub-queries.
+    ///
+    /// Syntax errors, unknown columns or functions, and other problems with `sql` are returned
+    /// rather than raised.  Their [`ErrorReport::position()`] is the 1-based character position
+    /// in `sql` of the problem.
+    ///
+    /// Errors raised while planning the expression, which evaluates its constant parts (such as
+    /// the division by zero in `1 / 0`), are raised as usual.
+    pub fn compile(
+        sql: &str,
+        tupdesc: &'a PgTupleDesc<'_>,
+        param_types: &[pg_sys::Oid],
+    ) -> Result<Self, ErrorReport> {
+        unsafe {
+            let estate = pg_sys::CreateExecutorState();
+
+            // the parse trees, along with the compiled expression, belong to the EState
+            let expr = PgMemoryContexts::For((*estate).es_query_cxt)
+                .switch_to(|_| transform(sql, tupdesc, param_types));
+            match expr {
+                // planning folds the expression's constants, which runs its functions, so errors
+                // from here on can't be caught without a subtransaction and are raised as usual
+                Ok(expr) => Ok(PgExpr::prepare(estate, expr, param_types)),
+                Err(e) => {
+                    pg_sys::FreeExecutorState(estate);
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// Compile an already analyzed expression tree, such as one from a `Query`, whose parameters
+    /// `$1`, `$2`, etc, have the types `param_types`
+    ///
+    /// The tree is copied as it's compiled, so it needn't outlive the returned `PgExpr`.
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe as we cannot guarantee the provided `expr` is a valid expression
+    /// tree, nor that its `Var`s reference attributes of the slots it'll be evaluated against
+    pub unsafe fn from_node(expr: *mut pg_sys::Expr, param_types: &[pg_sys::Oid]) -> Self {
+        PgExpr::prepare(pg_sys::CreateExecutorState(), expr, param_types)
+    }
+
+    unsafe fn prepare(
+        estate: *mut pg_sys::EState,
+        expr: *mut pg_sys::Expr,
+        param_types: &[pg_sys::Oid],
+    ) -> Self {
+        let uses_columns =
+            expression_tree_walker(expr.cast(), |node| matches!(node, NodeRef::Var(_)));
+        let result_type = pg_sys::exprType(expr.cast());
+        let state = pg_sys::ExecPrepareExpr(expr, estate);
+        let econtext = pg_sys::CreateExprContext(estate);
+
+        if !param_types.is_empty() {
+            let size = std::mem::size_of::<pg_sys::ParamListInfoData>()
+                + param_types.len() * std::mem::size_of::<pg_sys::ParamExternData>();
+            let params = pg_sys::MemoryContextAllocZero((*estate).es_query_cxt, size)
+                as pg_sys::ParamListInfo;
+            (*params).numParams = param_types.len() as _;
+            (*econtext).ecxt_param_list_info = params;
+        }
+
+        PgExpr {
+            estate: NonNull::new(estate).expect("CreateExecutorState returned NULL"),
+            state: NonNull::new(state).expect("ExecPrepareExpr returned NULL"),
+            econtext: NonNull::new(econtext).expect("CreateExprContext returned NULL"),
+            param_types: param_types.to_vec(),
+            result_type,
+            uses_columns,
+            _tupdesc: PhantomData,
+        }
+    }
+
+    /// The type of the values the expression evaluates to
+    pub fn result_type(&self) -> pg_sys::Oid {
+        self.result_type
+    }
+
+    /// The types of the expression's parameters
+    pub fn param_types(&self) -> &[pg_sys::Oid] {
+        &self.param_types
+    }
+
+    /// Evaluate the expression against the tuple in `slot`, with `params` bound to its parameters
+    ///
+    /// The value is computed in memory that's reset the next time the expression is evaluated, so
+    /// `T` should be a type that copies the value out of the Datum.  Errors raised by the
+    /// expression itself, such as a division by zero, are raised as usual.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`TryFromDatumError::IncompatibleTypes`] if `T` isn't compatible with the
+    /// expression's [`result_type()`][PgExpr::result_type]
+    ///
+    /// ## Panics
+    ///
+    /// If the number of `params` isn't the number of parameter types the expression was compiled
+    /// with, or if the expression references columns and no `slot`, or an empty one, is given
+    pub fn evaluate<T: FromDatum + IntoDatum>(
+        &mut self,
+        slot: Option<&PgTupleSlot<'_>>,
+        params: &[Option<pg_sys::Datum>],
+    ) -> Result<Option<T>, TryFromDatumError> {
+        assert_eq!(
+            params.len(),
+            self.param_types.len(),
+            "expected {} parameters but got {}",
+            self.param_types.len(),
+            params.len()
+        );
+        if self.uses_columns {
+            match slot {
+                None => panic!("expression references columns, but no tuple slot was provided"),
+                Some(slot) if slot.is_empty() => {
+                    panic!("expression references columns, but the tuple slot is empty")
+                }
+                Some(_) => {}
+            }
+        }
+
+        unsafe {
+            let state = self.state.as_ptr();
+            let econtext = self.econtext.as_ptr();
+
+            let param_list = (*econtext).ecxt_param_list_info;
+            if !param_list.is_null() {
+                let externs = (*param_list).params.as_mut_slice(params.len());
+                for ((param, datum), ptype) in externs.iter_mut().zip(params).zip(&self.param_types)
+                {
+                    param.value = datum.unwrap_or(pg_sys::Datum::from(0));
+                    param.isnull = datum.is_none();
+                    param.pflags = pg_sys::PARAM_FLAG_CONST as _;
+                    param.ptype = *ptype;
+                }
+            }
+
+            (*econtext).ecxt_scantuple = slot.map_or(std::ptr::null_mut(), |slot| slot.as_ptr());
+            pg_sys::MemoryContextReset((*econtext).ecxt_per_tuple_memory);
+
+            let mut is_null = false;
+            let evalfunc = (*state).evalfunc.expect("ExprState has no evalfunc");
+            let datum = PgMemoryContexts::For((*econtext).ecxt_per_tuple_memory).switch_to(|_| {
+                pg_sys::ffi::pg_guard_ffi_boundary(|| evalfunc(state, econtext, &mut is_null))
+            });
+
+            T::try_from_datum_in_memory_context(
+                PgMemoryContexts::CurrentMemoryContext,
+                datum,
+                is_null,
+                self.result_type,
+            )
+        }
+    }
+
+    /// Returns the underlying `pg_sys::ExprState`
+    pub fn as_ptr(&self) -> *mut pg_sys::ExprState {
+        self.state.as_ptr()
+    }
+}
+
+impl Drop for PgExpr<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::FreeExecutorState(self.estate.as_ptr());
+        }
+    }
+}
+
+/// Parse and analyze `sql` into an expression tree, in the `CurrentMemoryContext`
+unsafe fn transform(
+    sql: &str,
+    tupdesc: &PgTupleDesc<'_>,
+    param_types: &[pg_sys::Oid],
+) -> Result<*mut pg_sys::Expr, ErrorReport> {
+    let source = format!("{}{}", SELECT_PREFIX, sql);
+    let stmts = parser::parse(&source).map_err(unprefix_position)?;
+    let target = match stmts.as_slice() {
+        [stmt] => match stmt.node().kind() {
+            RawNodeKind::SelectStmt(select) if is_bare_select(select) => {
+                PgList::<pg_sys::ResTarget>::from_pg(select.targetList).get_ptr(0).unwrap()
+            }
+            _ => return Err(not_an_expression()),
+        },
+        _ => return Err(not_an_expression()),
+    };
+
+    // `parse()` already made sure `source` has no NUL bytes
+    let c_source = CString::new(source.as_str()).unwrap();
+    PgTryBuilder::new(|| {
+        let pstate = pg_sys::make_parsestate(std::ptr::null_mut());
+        (*pstate).p_sourcetext = c_source.as_ptr();
+        (*pstate).p_pre_columnref_hook = Some(resolve_column);
+        (*pstate).p_ref_hook_state = tupdesc as *const PgTupleDesc as *mut _;
+
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        pg_sys::parse_fixed_parameters(
+            pstate,
+            param_types.as_ptr() as *mut _,
+            param_types.len() as _,
+        );
+        #[cfg(feature = "pg15")]
+        pg_sys::setup_parse_fixed_parameters(pstate, param_types.as_ptr(), param_types.len() as _);
+
+        let expr =
+            pg_sys::transformExpr(pstate, (*target).val, pg_sys::ParseExprKind_EXPR_KIND_WHERE);
+        if (*pstate).p_hasSubLinks {
+            return Err(ErrorReport::new(
+                PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                "cannot use subquery in expression",
+                pg_sys::function_name!(),
+            ));
+        }
+        pg_sys::assign_expr_collations(pstate, expr);
+        pg_sys::free_parsestate(pstate);
+
+        Ok(expr.cast())
+    })
+    .catch_others(caught_error_report)
+    .execute()
+}
+
+/// Return a caught Postgres error, with its position made relative to the expression
+fn caught_error_report<T>(e: CaughtError) -> Result<T, ErrorReport> {
+    match e {
+        CaughtError::PostgresError(ereport) => {
+            Err(unprefix_position(ereport.error_report().clone()))
+        }
+        e => e.rethrow(),
+    }
+}
+
+/// Is `select` just a target list, as `SELECT <expr>` parses to?
+fn is_bare_select(select: &pg_sys::SelectStmt) -> bool {
+    let targets = unsafe { PgList::<pg_sys::Node>::from_pg(select.targetList).len() };
+    targets == 1
+        && select.op == pg_sys::SetOperation_SETOP_NONE
+        && select.distinctClause.is_null()
+        && select.intoClause.is_null()
+        && select.fromClause.is_null()
+        && select.whereClause.is_null()
+        && select.groupClause.is_null()
+        && select.havingClause.is_null()
+        && select.windowClause.is_null()
+        && select.sortClause.is_null()
+        && select.limitOffset.is_null()
+        && select.limitCount.is_null()
+        && select.lockingClause.is_null()
+        && select.withClause.is_null()
+}
+
+fn not_an_expression() -> ErrorReport {
+    ErrorReport::new(
+        PgSqlErrorCode::ERRCODE_SYNTAX_ERROR,
+        "not a single scalar expression",
+        pg_sys::function_name!(),
+    )
+}
+
+/// Make an error's position relative to the expression rather than the `SELECT` it's parsed as
+fn unprefix_position(report: ErrorReport) -> ErrorReport {
+    match report.position() {
+        Some(position) => {
+            let position = position.saturating_sub(SELECT_PREFIX.len() as u32).max(1);
+            report.set_position(position)
+        }
+        None => report,
+    }
+}
+
+/// A `p_pre_columnref_hook` that resolves unqualified column names to attributes of the
+/// `PgTupleDesc` in `p_ref_hook_state`
+#[pg_guard]
+unsafe extern "C" fn resolve_column(
+    pstate: *mut pg_sys::ParseState,
+    cref: *mut pg_sys::ColumnRef,
+) -> *mut pg_sys::Node {
+    let fields = PgList::<pg_sys::Node>::from_pg((*cref).fields);
+    if fields.len() != 1 {
+        // let Postgres complain about it
+        return std::ptr::null_mut();
+    }
+    let name = match parser::string_value(fields.get_ptr(0).unwrap()) {
+        Some(name) => CStr::from_ptr(name),
+        None => return std::ptr::null_mut(),
+    };
+
+    let tupdesc = &*((*pstate).p_ref_hook_state as *const PgTupleDesc);
+    let att = tupdesc
+        .iter()
+        .find(|att| !att.is_dropped() && name.to_str().map_or(false, |name| att.name() == name));
+    match att {
+        Some(att) => {
+            let var = pg_sys::makeVar(
+                1 as _,
+                att.attnum,
+                att.atttypid,
+                att.atttypmod,
+                att.attcollation,
+                0,
+            );
+            (*var).location = (*cref).location;
+            var.cast()
+        }
+        None => std::ptr::null_mut(),
+    }
+}
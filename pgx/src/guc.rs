@@ -8,10 +8,17 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! Provides a safe interface into Postgres' Configuration System (GUC)
+use crate as pgx; // for #[pg_guard] support from within ourself
 use crate::{pg_sys, PgMemoryContexts};
-pub use ::pgx_macros::PostgresGucEnum;
+pub use ::pgx_macros::{PostgresGucEnum, PostgresGucs};
 use core::ffi::CStr;
-use std::cell::Cell;
+use pgx_macros::pg_guard;
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::{Entry, HashMap};
+use std::os::raw::{c_char, c_int, c_void};
+use std::rc::Rc;
+use std::str::Utf8Error;
 
 pub enum GucContext {
     /// cannot be set by the user at all, but only through
@@ -60,6 +67,54 @@ where
     unsafe fn config_matrix(&self) -> *const pg_sys::config_enum_entry;
 }
 
+/// A group of GUCs registered together under a common prefix
+///
+/// This is implemented by `#[derive(PostgresGucs)]`, which turns each field of a struct into a GUC
+/// named `prefix.field`.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use pgx::guc::*;
+///
+/// #[derive(PostgresGucEnum, Clone, Copy, PartialEq, Debug)]
+/// enum Mode {
+///     Fast,
+///     Safe,
+/// }
+///
+/// #[derive(PostgresGucs)]
+/// #[gucs(prefix = "myext")]
+/// struct Config {
+///     /// Enable the extension
+///     enabled: bool,
+///     /// How long to wait for a response
+///     #[guc(min = 0, max = 60_000, default = 5_000, flags = UNIT_MS)]
+///     timeout: i32,
+///     /// Where to send requests
+///     #[guc(context = Suset, default = "localhost")]
+///     host: Option<String>,
+///     #[guc(short_description = "How to process requests", default = Safe)]
+///     mode: Mode,
+/// }
+///
+/// // in `_PG_init()`
+/// Config::register();
+///
+/// // anywhere else
+/// let config = Config::get();
+/// ```
+pub trait GucGroup: Sized {
+    /// The prefix every GUC in the group is named under
+    const PREFIX: &'static str;
+
+    /// Define every GUC in the group and reserve [`GucGroup::PREFIX`].  Call this from `_PG_init()`
+    fn register();
+
+    /// A snapshot of the current value of every GUC in the group
+    fn get() -> Self;
+}
+
 pub struct GucSetting<T> {
     value: Cell<T>,
     char_p: Cell<*mut std::os::raw::c_char>,
@@ -144,14 +199,373 @@ where
     }
 }
 
+bitflags! {
+    /// Flags that change how a GUC is parsed and displayed.
+    ///
+    /// The `UNIT_*` flags declare the unit an integer or float GUC is stored in, which lets users
+    /// write values in any compatible unit, such as `SET myext.timeout = '5s'` for a GUC declared
+    /// with [`GucFlags::UNIT_MS`].
+    #[derive(Default)]
+    pub struct GucFlags: i32 {
+        /// input can be a list
+        const LIST_INPUT = pg_sys::GUC_LIST_INPUT as i32;
+        /// double-quote list elements
+        const LIST_QUOTE = pg_sys::GUC_LIST_QUOTE as i32;
+        /// exclude from SHOW ALL
+        const NO_SHOW_ALL = pg_sys::GUC_NO_SHOW_ALL as i32;
+        /// exclude from RESET ALL
+        const NO_RESET_ALL = pg_sys::GUC_NO_RESET_ALL as i32;
+        /// auto-report changes to client
+        const REPORT = pg_sys::GUC_REPORT as i32;
+        /// not in postgresql.conf.sample
+        const NOT_IN_SAMPLE = pg_sys::GUC_NOT_IN_SAMPLE as i32;
+        /// can't set in postgresql.conf
+        const DISALLOW_IN_FILE = pg_sys::GUC_DISALLOW_IN_FILE as i32;
+        /// show only to superusers
+        const SUPERUSER_ONLY = pg_sys::GUC_SUPERUSER_ONLY as i32;
+        /// limit string to NAMEDATALEN-1
+        const IS_NAME = pg_sys::GUC_IS_NAME as i32;
+        /// can't set if security restricted
+        const NOT_WHILE_SEC_REST = pg_sys::GUC_NOT_WHILE_SEC_REST as i32;
+        /// can't set in postgresql.auto.conf
+        const DISALLOW_IN_AUTO_FILE = pg_sys::GUC_DISALLOW_IN_AUTO_FILE as i32;
+        /// value is in kilobytes
+        const UNIT_KB = pg_sys::GUC_UNIT_KB as i32;
+        /// value is in blocks
+        const UNIT_BLOCKS = pg_sys::GUC_UNIT_BLOCKS as i32;
+        /// value is in xlog blocks
+        const UNIT_XBLOCKS = pg_sys::GUC_UNIT_XBLOCKS as i32;
+        /// value is in megabytes
+        const UNIT_MB = pg_sys::GUC_UNIT_MB as i32;
+        /// value is in bytes
+        const UNIT_BYTE = pg_sys::GUC_UNIT_BYTE as i32;
+        /// value is in milliseconds
+        const UNIT_MS = pg_sys::GUC_UNIT_MS as i32;
+        /// value is in seconds
+        const UNIT_S = pg_sys::GUC_UNIT_S as i32;
+        /// value is in minutes
+        const UNIT_MIN = pg_sys::GUC_UNIT_MIN as i32;
+    }
+}
+
+/// Rust closures Postgres calls when a GUC is set or shown
+///
+/// * `check` validates a proposed value.  Returning an `Err` rejects the value, and the message is
+///   reported as the error's `DETAIL` (see `GUC_check_errdetail()`)
+/// * `assign` is called with the new value just before it's assigned, for side effects
+/// * `show` formats the current value for `SHOW`
+///
+/// Hooks for string GUCs receive an `Option<String>` and hooks for enum GUCs receive the enum.
+/// Values of string GUCs that aren't valid UTF-8 are rejected before they reach any hook.
+///
+/// Postgres' hooks are plain function pointers without a user-data argument, so every closure is
+/// called through a function generated for its type.  A closure that captures anything can't be
+/// the hook of more than one GUC.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use pgx::guc::*;
+///
+/// static TIMEOUT: GucSetting<i32> = GucSetting::new(5000);
+///
+/// GucRegistry::define_int_guc_with_hooks(
+///     "myext.timeout",
+///     "request timeout",
+///     "how long to wait for a response",
+///     &TIMEOUT,
+///     0,
+///     i32::MAX,
+///     GucContext::Userset,
+///     GucFlags::UNIT_MS,
+///     GucHooks::new()
+///         .check(|ms| if ms % 1000 == 0 { Ok(()) } else { Err("must be whole seconds".into()) }),
+/// );
+/// ```
+pub struct GucHooks<T: GucHookValue> {
+    check: T::CheckHook,
+    assign: T::AssignHook,
+    show: pg_sys::GucShowHook,
+}
+
+impl<T: GucHookValue> Default for GucHooks<T> {
+    fn default() -> Self {
+        GucHooks { check: Default::default(), assign: Default::default(), show: None }
+    }
+}
+
+impl<T: GucHookValue> GucHooks<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validate new values before they're assigned
+    pub fn check<F: Fn(T) -> Result<(), String> + 'static>(mut self, check: F) -> Self {
+        register_hook(check);
+        self.check = T::check_hook::<F>();
+        self
+    }
+
+    /// Act on new values as they're assigned
+    pub fn assign<F: Fn(T) + 'static>(mut self, assign: F) -> Self {
+        register_hook(assign);
+        self.assign = T::assign_hook::<F>();
+        self
+    }
+
+    /// Format the current value for `SHOW`
+    pub fn show<F: Fn() -> String + 'static>(mut self, show: F) -> Self {
+        register_hook(show);
+        self.show = Some(show_hook::<F>);
+        self
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The types of value [`GucHooks`] can receive: `bool`, `i32`, `f64`, `Option<String>`, and
+/// [`GucEnum`]s
+pub trait GucHookValue: sealed::Sealed + Sized + 'static {
+    #[doc(hidden)]
+    type CheckHook: Copy + Default;
+    #[doc(hidden)]
+    type AssignHook: Copy + Default;
+
+    #[doc(hidden)]
+    fn check_hook<F: Fn(Self) -> Result<(), String> + 'static>() -> Self::CheckHook;
+    #[doc(hidden)]
+    fn assign_hook<F: Fn(Self) + 'static>() -> Self::AssignHook;
+}
+
+macro_rules! guc_hook_value {
+    ($ty:ty, $check_hook:ty, $assign_hook:ty, $check:ident, $assign:ident) => {
+        impl sealed::Sealed for $ty {}
+        impl GucHookValue for $ty {
+            type CheckHook = $check_hook;
+            type AssignHook = $assign_hook;
+
+            fn check_hook<F: Fn(Self) -> Result<(), String> + 'static>() -> Self::CheckHook {
+                Some($check::<F>)
+            }
+
+            fn assign_hook<F: Fn(Self) + 'static>() -> Self::AssignHook {
+                Some($assign::<F>)
+            }
+        }
+    };
+}
+
+guc_hook_value!(bool, pg_sys::GucBoolCheckHook, pg_sys::GucBoolAssignHook, check_bool, assign_bool);
+guc_hook_value!(i32, pg_sys::GucIntCheckHook, pg_sys::GucIntAssignHook, check_int, assign_int);
+guc_hook_value!(f64, pg_sys::GucRealCheckHook, pg_sys::GucRealAssignHook, check_real, assign_real);
+guc_hook_value!(
+    Option<String>,
+    pg_sys::GucStringCheckHook,
+    pg_sys::GucStringAssignHook,
+    check_string,
+    assign_string
+);
+
+// enum hooks see the same ordinals as int hooks
+impl<T: GucEnum<T> + Copy + 'static> sealed::Sealed for T {}
+impl<T: GucEnum<T> + Copy + 'static> GucHookValue for T {
+    type CheckHook = pg_sys::GucIntCheckHook;
+    type AssignHook = pg_sys::GucIntAssignHook;
+
+    fn check_hook<F: Fn(Self) -> Result<(), String> + 'static>() -> Self::CheckHook {
+        Some(check_enum::<T, F>)
+    }
+
+    fn assign_hook<F: Fn(Self) + 'static>() -> Self::AssignHook {
+        Some(assign_enum::<T, F>)
+    }
+}
+
+thread_local! {
+    /// Every hook closure, by its type
+    static GUC_HOOKS: RefCell<HashMap<TypeId, Rc<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+fn register_hook<F: 'static>(hook: F) {
+    GUC_HOOKS.with(|registry| match registry.borrow_mut().entry(TypeId::of::<F>()) {
+        Entry::Vacant(entry) => {
+            entry.insert(Rc::new(hook));
+        }
+        // every value of a type without any state behaves the same
+        Entry::Occupied(_) if std::mem::size_of::<F>() == 0 => {}
+        Entry::Occupied(_) => panic!("a closure can't be the hook of more than one GUC"),
+    })
+}
+
+fn hook<F: 'static>() -> Rc<F> {
+    let hook = GUC_HOOKS.with(|registry| registry.borrow()[&TypeId::of::<F>()].clone());
+    hook.downcast().expect("GUC hook has the wrong type")
+}
+
+fn run_check(result: Result<(), String>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(detail) => {
+            unsafe {
+                pg_sys::GUC_check_errdetail_string =
+                    PgMemoryContexts::CurrentMemoryContext.pstrdup(&detail);
+            }
+            false
+        }
+    }
+}
+
+unsafe fn string_value(value: *const c_char) -> Result<Option<String>, Utf8Error> {
+    if value.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(CStr::from_ptr(value).to_str()?.to_owned()))
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_bool<F: Fn(bool) -> Result<(), String> + 'static>(
+    newval: *mut bool,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    run_check(hook::<F>()(*newval))
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_int<F: Fn(i32) -> Result<(), String> + 'static>(
+    newval: *mut c_int,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    run_check(hook::<F>()(*newval))
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_real<F: Fn(f64) -> Result<(), String> + 'static>(
+    newval: *mut f64,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    run_check(hook::<F>()(*newval))
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_string<F: Fn(Option<String>) -> Result<(), String> + 'static>(
+    newval: *mut *mut c_char,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    match string_value(*newval) {
+        Ok(value) => run_check(hook::<F>()(value)),
+        Err(_) => run_check(Err("The value is not valid UTF-8.".into())),
+    }
+}
+
+/// Keeps values that aren't valid UTF-8 from string GUCs with an `assign` hook but no `check` hook
+#[pg_guard]
+unsafe extern "C" fn check_string_utf8(
+    newval: *mut *mut c_char,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    match string_value(*newval) {
+        Ok(_) => true,
+        Err(_) => run_check(Err("The value is not valid UTF-8.".into())),
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_enum<T: GucEnum<T> + Copy, F: Fn(T) -> Result<(), String> + 'static>(
+    newval: *mut c_int,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    run_check(hook::<F>()(T::from_ordinal(*newval)))
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_bool<F: Fn(bool) + 'static>(newval: bool, _extra: *mut c_void) {
+    hook::<F>()(newval)
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_int<F: Fn(i32) + 'static>(newval: c_int, _extra: *mut c_void) {
+    hook::<F>()(newval)
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_real<F: Fn(f64) + 'static>(newval: f64, _extra: *mut c_void) {
+    hook::<F>()(newval)
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_string<F: Fn(Option<String>) + 'static>(
+    newval: *const c_char,
+    _extra: *mut c_void,
+) {
+    // the check hook has already made sure the value is valid UTF-8
+    let value = (!newval.is_null()).then(|| CStr::from_ptr(newval).to_string_lossy().into_owned());
+    hook::<F>()(value)
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_enum<T: GucEnum<T> + Copy, F: Fn(T) + 'static>(
+    newval: c_int,
+    _extra: *mut c_void,
+) {
+    hook::<F>()(T::from_ordinal(newval))
+}
+
+#[pg_guard]
+unsafe extern "C" fn show_hook<F: Fn() -> String + 'static>() -> *const c_char {
+    PgMemoryContexts::CurrentMemoryContext.pstrdup(&hook::<F>()())
+}
+
 pub struct GucRegistry {}
 impl GucRegistry {
+    /// Reserve `prefix` for this extension's GUCs.  Setting an unknown GUC under `prefix` is an
+    /// error on Postgres 15 (`MarkGUCPrefixReserved`), and a warning on earlier versions
+    /// (`EmitWarningsOnPlaceholders`).  Call this after defining the GUCs.
+    pub fn reserve_prefix(prefix: &str) {
+        unsafe {
+            let prefix = PgMemoryContexts::TopMemoryContext.pstrdup(prefix);
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+            pg_sys::EmitWarningsOnPlaceholders(prefix);
+            #[cfg(feature = "pg15")]
+            pg_sys::MarkGUCPrefixReserved(prefix);
+        }
+    }
+
     pub fn define_bool_guc(
         name: &str,
         short_description: &str,
         long_description: &str,
         setting: &GucSetting<bool>,
         context: GucContext,
+    ) {
+        Self::define_bool_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            GucFlags::default(),
+            GucHooks::default(),
+        )
+    }
+
+    /// Like [`GucRegistry::define_bool_guc`], but with [`GucFlags`] and [`GucHooks`]
+    pub fn define_bool_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<bool>,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<bool>,
     ) {
         unsafe {
             pg_sys::DefineCustomBoolVariable(
@@ -161,10 +575,10 @@ impl GucRegistry {
                 setting.as_ptr(),
                 setting.get(),
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                hooks.check,
+                hooks.assign,
+                hooks.show,
             )
         }
     }
@@ -177,6 +591,32 @@ impl GucRegistry {
         min_value: i32,
         max_value: i32,
         context: GucContext,
+    ) {
+        Self::define_int_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            context,
+            GucFlags::default(),
+            GucHooks::default(),
+        )
+    }
+
+    /// Like [`GucRegistry::define_int_guc`], but with [`GucFlags`] and [`GucHooks`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn define_int_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<i32>,
+        min_value: i32,
+        max_value: i32,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<i32>,
     ) {
         unsafe {
             pg_sys::DefineCustomIntVariable(
@@ -188,10 +628,10 @@ impl GucRegistry {
                 min_value,
                 max_value,
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                hooks.check,
+                hooks.assign,
+                hooks.show,
             )
         }
     }
@@ -203,6 +643,31 @@ impl GucRegistry {
         setting: &GucSetting<Option<&'static str>>,
         context: GucContext,
     ) {
+        Self::define_string_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            GucFlags::default(),
+            GucHooks::default(),
+        )
+    }
+
+    /// Like [`GucRegistry::define_string_guc`], but with [`GucFlags`] and [`GucHooks`]
+    pub fn define_string_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<Option<&'static str>>,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<Option<String>>,
+    ) {
+        let check = match (hooks.check, hooks.assign) {
+            (None, Some(_)) => Some(check_string_utf8 as _),
+            (check, _) => check,
+        };
         unsafe {
             let boot_value = match setting.value.get() {
                 Some(s) => PgMemoryContexts::TopMemoryContext.pstrdup(s),
@@ -216,10 +681,10 @@ impl GucRegistry {
                 setting.as_ptr(),
                 boot_value,
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                check,
+                hooks.assign,
+                hooks.show,
             )
         }
     }
@@ -232,6 +697,32 @@ impl GucRegistry {
         min_value: f64,
         max_value: f64,
         context: GucContext,
+    ) {
+        Self::define_float_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            context,
+            GucFlags::default(),
+            GucHooks::default(),
+        )
+    }
+
+    /// Like [`GucRegistry::define_float_guc`], but with [`GucFlags`] and [`GucHooks`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn define_float_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<f64>,
+        min_value: f64,
+        max_value: f64,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<f64>,
     ) {
         unsafe {
             pg_sys::DefineCustomRealVariable(
@@ -243,10 +734,10 @@ impl GucRegistry {
                 min_value,
                 max_value,
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                hooks.check,
+                hooks.assign,
+                hooks.show,
             )
         }
     }
@@ -269,11 +760,40 @@ impl GucRegistry {
                 setting.value.get().to_ordinal(),
                 setting.value.get().config_matrix(),
                 context as isize as u32,
-                0,
+                GucFlags::default().bits(),
                 None,
                 None,
                 None,
             )
         }
     }
+
+    /// Like [`GucRegistry::define_enum_guc`], but with [`GucFlags`] and [`GucHooks`]
+    pub fn define_enum_guc_with_hooks<T>(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<T>,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<T>,
+    ) where
+        T: GucEnum<T> + Copy + 'static,
+    {
+        unsafe {
+            pg_sys::DefineCustomEnumVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                setting.value.get().to_ordinal(),
+                setting.value.get().config_matrix(),
+                context as isize as u32,
+                flags.bits(),
+                hooks.check,
+                hooks.assign,
+                hooks.show,
+            )
+        }
+    }
 }
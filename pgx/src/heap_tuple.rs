@@ -171,6 +171,17 @@ impl<'a> PgHeapTuple<'a, AllocatedByRust> {
         }
     }
 
+    /// Wrap a [pg_sys::HeapTuple] that was allocated for us, such as a copy, as owned by Rust
+    pub(crate) unsafe fn from_rust_heap_tuple(
+        tupdesc: PgTupleDesc<'a>,
+        heap_tuple: pg_sys::HeapTuple,
+    ) -> Self {
+        Self {
+            tuple: PgBox::<pg_sys::HeapTupleData, AllocatedByRust>::from_rust(heap_tuple),
+            tupdesc,
+        }
+    }
+
     /// Create a new [PgHeapTuple] from a [PgTupleDesc] from an iterator of Datums.
     ///
     /// ## Errors
@@ -359,6 +370,12 @@ impl<'a, AllocatedBy: WhoAllocated> PgHeapTuple<'a, AllocatedBy> {
         self.tuple.into_pg()
     }
 
+    /// The wrapped [`pg_sys::HeapTupleData`], which is still owned by this [`PgHeapTuple`]
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *mut pg_sys::HeapTupleData {
+        self.tuple.as_ptr()
+    }
+
     /// Returns the number of attributes in this [`PgHeapTuple`].
     #[inline]
     pub fn len(&self) -> usize {
@@ -0,0 +1,220 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Streaming access to Postgres' large objects
+//!
+//! A [`PgLargeObject`] implements [`std::io::Read`], [`std::io::Write`], and [`std::io::Seek`]
+//! directly on top of Postgres' `inv_api`, the same functions behind SQL's `lo_read()`,
+//! `lo_write()`, and friends, so objects far larger than a `bytea` can hold can be copied through
+//! a small buffer with [`std::io::copy()`].
+//!
+//! ```rust,no_run
+//! use pgx::PgLargeObject;
+//! use std::io::{Read, Seek, SeekFrom, Write};
+//!
+//! let mut lo = PgLargeObject::create();
+//! lo.write_all(b"hello, world").unwrap();
+//! lo.seek(SeekFrom::Start(7)).unwrap();
+//!
+//! let mut world = String::new();
+//! lo.read_to_string(&mut world).unwrap();
+//! assert_eq!(world, "world");
+//!
+//! let oid = lo.oid();
+//! drop(lo);
+//! PgLargeObject::unlink(oid);
+//! ```
+use crate::{
+    ereport, pg_sys, register_xact_callback, PgLogLevel, PgSqlErrorCode, PgXactCallbackEvent,
+};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::raw::{c_char, c_int};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How a [`PgLargeObject`] is opened, like the `mode` argument of SQL's `lo_open()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LargeObjectMode {
+    /// Reads see the object as of the current snapshot, like `INV_READ`
+    Read,
+    /// Writes are allowed, and reads see them, like `INV_WRITE`
+    Write,
+    /// The same as [`LargeObjectMode::Write`], like `INV_READ | INV_WRITE`
+    ReadWrite,
+}
+
+impl From<LargeObjectMode> for c_int {
+    fn from(mode: LargeObjectMode) -> Self {
+        (match mode {
+            LargeObjectMode::Read => pg_sys::INV_READ,
+            LargeObjectMode::Write => pg_sys::INV_WRITE,
+            LargeObjectMode::ReadWrite => pg_sys::INV_READ | pg_sys::INV_WRITE,
+        }) as c_int
+    }
+}
+
+/// An open large object, which is closed when dropped
+///
+/// Postgres raises an error for reads, writes, or seeks it doesn't allow, such as writing to an
+/// object opened with [`LargeObjectMode::Read`] or seeking to a negative offset.
+///
+/// A `PgLargeObject` must not outlive the transaction it was opened in.
+pub struct PgLargeObject {
+    desc: NonNull<pg_sys::LargeObjectDesc>,
+}
+
+impl PgLargeObject {
+    /// Create a new, empty large object, and open it for reading and writing
+    ///
+    /// Postgres raises an error if the transaction is read-only.
+    pub fn create() -> Self {
+        let oid = unsafe {
+            pg_sys::PreventCommandIfReadOnly(b"lo_create()\0".as_ptr().cast());
+            pg_sys::inv_create(pg_sys::InvalidOid)
+        };
+        PgLargeObject::open(oid, LargeObjectMode::ReadWrite)
+    }
+
+    /// Open the existing large object `oid`
+    ///
+    /// Postgres raises an error if it doesn't exist, if the current user isn't allowed to access it
+    /// in `mode`, or if it's opened for writing in a read-only transaction.  Opening it with
+    /// [`LargeObjectMode::Read`] requires an active snapshot to read it as of, which SQL functions
+    /// always have.
+    pub fn open(oid: pg_sys::Oid, mode: LargeObjectMode) -> Self {
+        unsafe {
+            if mode == LargeObjectMode::Read {
+                if !pg_sys::ActiveSnapshotSet() {
+                    ereport!(
+                        PgLogLevel::ERROR,
+                        PgSqlErrorCode::ERRCODE_INVALID_TRANSACTION_STATE,
+                        "cannot open a large object for reading without an active snapshot"
+                    );
+                }
+            } else {
+                pg_sys::PreventCommandIfReadOnly(b"lo_open(INV_WRITE)\0".as_ptr().cast());
+            }
+        }
+        close_relation_at_end_of_xact();
+        let desc = unsafe { pg_sys::inv_open(oid, mode.into(), pg_sys::TopTransactionContext) };
+        PgLargeObject { desc: NonNull::new(desc).expect("inv_open returned a null descriptor") }
+    }
+
+    /// Delete the large object `oid`, like SQL's `lo_unlink()`
+    ///
+    /// Postgres raises an error if it doesn't exist, if the current user doesn't own it, or if the
+    /// transaction is read-only.
+    pub fn unlink(oid: pg_sys::Oid) {
+        unsafe {
+            pg_sys::PreventCommandIfReadOnly(b"lo_unlink()\0".as_ptr().cast());
+            if !pg_sys::lo_compat_privileges
+                && !pg_sys::pg_largeobject_ownercheck(oid, pg_sys::GetUserId())
+            {
+                ereport!(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+                    format!("must be owner of large object {}", oid.as_u32())
+                );
+            }
+            pg_sys::inv_drop(oid);
+        }
+    }
+
+    /// The oid of this large object
+    pub fn oid(&self) -> pg_sys::Oid {
+        unsafe { self.desc.as_ref().id }
+    }
+
+    /// Truncate, or zero-extend, this large object to `len` bytes, like SQL's `lo_truncate64()`
+    ///
+    /// The current position isn't changed.
+    pub fn truncate(&mut self, len: u64) {
+        let len = i64::try_from(len).expect("large object length out of range");
+        unsafe {
+            pg_sys::PreventCommandIfReadOnly(b"lo_truncate()\0".as_ptr().cast());
+            pg_sys::inv_truncate(self.desc.as_ptr(), len)
+        }
+    }
+
+    /// A pointer to the underlying `LargeObjectDesc`
+    pub fn as_ptr(&self) -> *mut pg_sys::LargeObjectDesc {
+        self.desc.as_ptr()
+    }
+}
+
+impl Read for PgLargeObject {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(i32::MAX as usize) as c_int;
+        let read =
+            unsafe { pg_sys::inv_read(self.desc.as_ptr(), buf.as_mut_ptr() as *mut c_char, len) };
+        Ok(read as usize)
+    }
+}
+
+impl Write for PgLargeObject {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(i32::MAX as usize) as c_int;
+        let written = unsafe {
+            pg_sys::PreventCommandIfReadOnly(b"lo_write()\0".as_ptr().cast());
+            pg_sys::inv_write(self.desc.as_ptr(), buf.as_ptr() as *const c_char, len)
+        };
+        Ok(written as usize)
+    }
+
+    /// Writes go straight to `pg_largeobject`, so there's nothing to flush
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for PgLargeObject {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (
+                i64::try_from(offset).map_err(|_| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, "offset out of range")
+                })?,
+                pg_sys::SEEK_SET,
+            ),
+            SeekFrom::Current(offset) => (offset, pg_sys::SEEK_CUR),
+            SeekFrom::End(offset) => (offset, pg_sys::SEEK_END),
+        };
+        let position = unsafe { pg_sys::inv_seek(self.desc.as_ptr(), offset, whence as c_int) };
+        Ok(position as u64)
+    }
+}
+
+impl Drop for PgLargeObject {
+    fn drop(&mut self) {
+        unsafe { pg_sys::inv_close(self.desc.as_ptr()) }
+    }
+}
+
+static CLOSE_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// `inv_api` caches `pg_largeobject` and its index for the rest of the transaction, and leaves it
+/// to SQL's `lo_*()` functions to close them when it ends.  We have to do the same, once per
+/// transaction, for when those functions aren't used.
+fn close_relation_at_end_of_xact() {
+    if !CLOSE_REGISTERED.swap(true, Ordering::Relaxed) {
+        // before the transaction's resource owner complains about the leaked relations
+        register_xact_callback(PgXactCallbackEvent::PreCommit, || unsafe {
+            pg_sys::close_lo_relation(true);
+            CLOSE_REGISTERED.store(false, Ordering::Relaxed)
+        });
+        register_xact_callback(PgXactCallbackEvent::PrePrepare, || unsafe {
+            pg_sys::close_lo_relation(true);
+            CLOSE_REGISTERED.store(false, Ordering::Relaxed)
+        });
+        register_xact_callback(PgXactCallbackEvent::Abort, || unsafe {
+            pg_sys::close_lo_relation(false);
+            CLOSE_REGISTERED.store(false, Ordering::Relaxed)
+        });
+    }
+}
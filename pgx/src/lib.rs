@@ -37,11 +37,16 @@ pub mod prelude;
 
 pub mod aggregate;
 pub mod array;
+pub mod async_worker;
 pub mod atomics;
+pub mod bgworker_pool;
 pub mod bgworkers;
 pub mod callbacks;
 pub mod datum;
 pub mod enum_helper;
+pub mod error_context;
+#[cfg(feature = "cshim")]
+pub mod expr;
 pub mod fcinfo;
 pub mod ffi;
 pub mod guc;
@@ -52,25 +57,40 @@ pub mod htup;
 pub mod inoutfuncs;
 pub mod itemptr;
 pub mod iter;
+pub mod large_object;
 #[cfg(feature = "cshim")]
 pub mod list;
+pub mod lock;
 pub mod lwlock;
 pub mod memcxt;
 pub mod misc;
 #[cfg(feature = "cshim")]
 pub mod namespace;
 pub mod nodes;
+pub mod notify;
+#[cfg(feature = "cshim")]
+pub mod parser;
 pub mod pgbox;
 pub mod rel;
+pub mod scan;
+pub mod shm_mq;
 pub mod shmem;
+pub mod shmem_hash;
+pub mod snapshot;
 pub mod spi;
 #[cfg(feature = "cshim")]
 pub mod spinlock;
 pub mod srf;
 pub mod stringinfo;
+pub mod syscache;
+pub mod tracing;
 pub mod trigger_support;
+#[cfg(feature = "cshim")]
+pub mod tsearch;
 pub mod tupdesc;
+pub mod tuptable;
 pub mod varlena;
+pub mod wait_event;
 pub mod wrappers;
 pub mod xid;
 
@@ -86,6 +106,9 @@ pub use atomics::*;
 pub use callbacks::*;
 pub use datum::*;
 pub use enum_helper::*;
+pub use error_context::*;
+#[cfg(feature = "cshim")]
+pub use expr::*;
 pub use fcinfo::*;
 pub use guc::*;
 #[cfg(feature = "cshim")]
@@ -93,8 +116,10 @@ pub use hooks::*;
 pub use htup::*;
 pub use inoutfuncs::*;
 pub use itemptr::*;
+pub use large_object::*;
 #[cfg(feature = "cshim")]
 pub use list::*;
+pub use lock::*;
 pub use lwlock::*;
 pub use memcxt::*;
 #[cfg(feature = "cshim")]
@@ -102,11 +127,18 @@ pub use namespace::*;
 pub use nodes::*;
 pub use pgbox::*;
 pub use rel::*;
+pub use scan::*;
 pub use shmem::*;
+pub use shmem_hash::*;
+pub use snapshot::*;
 pub use spi::Spi; // only Spi.  We don't want the top-level namespace polluted with spi::Result and spi::Error
 pub use stringinfo::*;
+pub use syscache::{PgAttribute, PgClass, PgNamespace, PgOperator, PgProc, PgType};
 pub use trigger_support::*;
+#[cfg(feature = "cshim")]
+pub use tsearch::*;
 pub use tupdesc::*;
+pub use tuptable::*;
 pub use varlena::*;
 pub use wrappers::*;
 pub use xid::*;
@@ -118,6 +150,7 @@ pub use pg_sys::elog::PgLogLevel;
 pub use pg_sys::errcodes::PgSqlErrorCode;
 pub use pg_sys::oids::PgOid;
 pub use pg_sys::panic::pgx_extern_c_guard;
+pub use pg_sys::panic::ToPgError;
 pub use pg_sys::pg_try::PgTryBuilder;
 pub use pg_sys::utils::name_data_to_str;
 pub use pg_sys::PgBuiltInOids;
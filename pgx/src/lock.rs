@@ -0,0 +1,355 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Safe wrappers around Postgres' heavyweight lock manager: relation, page, tuple, and advisory
+//! locks
+//!
+//! Unlike a [`crate::PgLwLock`], these are the locks that show up in `pg_locks`, participate in
+//! deadlock detection, and are normally held until the end of the transaction.  The guards here
+//! release their lock as soon as they're dropped, unless told to hold it until the end of the
+//! transaction, which is what Postgres itself usually does.
+use crate::{pg_sys, PgRelation};
+
+/// The lock modes of Postgres' heavyweight locks, from weakest to strongest
+///
+/// See [Table-Level Locks](https://www.postgresql.org/docs/current/explicit-locking.html#LOCKING-TABLES)
+/// for what each mode conflicts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LockMode {
+    AccessShare,
+    RowShare,
+    RowExclusive,
+    ShareUpdateExclusive,
+    Share,
+    ShareRowExclusive,
+    Exclusive,
+    AccessExclusive,
+}
+
+impl From<LockMode> for pg_sys::LOCKMODE {
+    fn from(mode: LockMode) -> Self {
+        (match mode {
+            LockMode::AccessShare => pg_sys::AccessShareLock,
+            LockMode::RowShare => pg_sys::RowShareLock,
+            LockMode::RowExclusive => pg_sys::RowExclusiveLock,
+            LockMode::ShareUpdateExclusive => pg_sys::ShareUpdateExclusiveLock,
+            LockMode::Share => pg_sys::ShareLock,
+            LockMode::ShareRowExclusive => pg_sys::ShareRowExclusiveLock,
+            LockMode::Exclusive => pg_sys::ExclusiveLock,
+            LockMode::AccessExclusive => pg_sys::AccessExclusiveLock,
+        }) as pg_sys::LOCKMODE
+    }
+}
+
+/// A lock on a relation, by its oid, which is released when dropped
+///
+/// This is what `LOCK TABLE` takes, and what [`PgRelation::with_lock()`] takes as it opens a
+/// relation.
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct PgRelationLock {
+    relid: pg_sys::Oid,
+    mode: LockMode,
+}
+
+impl PgRelationLock {
+    /// Lock the relation `relid`, waiting for any conflicting locks to be released
+    pub fn lock(relid: pg_sys::Oid, mode: LockMode) -> Self {
+        unsafe {
+            pg_sys::LockRelationOid(relid, mode.into());
+        }
+        PgRelationLock { relid, mode }
+    }
+
+    /// Lock the relation `relid`, returning `None` rather than waiting if a conflicting lock is
+    /// held by another backend
+    pub fn try_lock(relid: pg_sys::Oid, mode: LockMode) -> Option<Self> {
+        unsafe { pg_sys::ConditionalLockRelationOid(relid, mode.into()) }
+            .then(|| PgRelationLock { relid, mode })
+    }
+
+    /// The locked relation's oid
+    pub fn relid(&self) -> pg_sys::Oid {
+        self.relid
+    }
+
+    /// The mode the relation is locked in
+    pub fn mode(&self) -> LockMode {
+        self.mode
+    }
+
+    /// Keep the lock until the end of the transaction, rather than releasing it now
+    pub fn hold_until_end_of_xact(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for PgRelationLock {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::UnlockRelationOid(self.relid, self.mode.into());
+        }
+    }
+}
+
+/// A lock on one page of a relation, which is released when dropped
+///
+/// Postgres uses page locks sparingly, such as in hash and GIN indexes, so they're mostly useful
+/// to index access methods implemented with pgx.
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct PgPageLock<'a> {
+    relation: &'a PgRelation,
+    blkno: pg_sys::BlockNumber,
+    mode: LockMode,
+}
+
+impl<'a> PgPageLock<'a> {
+    /// Lock page `blkno` of `relation`, waiting for any conflicting locks to be released
+    pub fn lock(relation: &'a PgRelation, blkno: pg_sys::BlockNumber, mode: LockMode) -> Self {
+        unsafe {
+            pg_sys::LockPage(relation.as_ptr(), blkno, mode.into());
+        }
+        PgPageLock { relation, blkno, mode }
+    }
+
+    /// Lock page `blkno` of `relation`, returning `None` rather than waiting if a conflicting lock
+    /// is held by another backend
+    pub fn try_lock(
+        relation: &'a PgRelation,
+        blkno: pg_sys::BlockNumber,
+        mode: LockMode,
+    ) -> Option<Self> {
+        unsafe { pg_sys::ConditionalLockPage(relation.as_ptr(), blkno, mode.into()) }
+            .then(|| PgPageLock { relation, blkno, mode })
+    }
+
+    /// The locked page's block number
+    pub fn blkno(&self) -> pg_sys::BlockNumber {
+        self.blkno
+    }
+
+    /// The mode the page is locked in
+    pub fn mode(&self) -> LockMode {
+        self.mode
+    }
+
+    /// Keep the lock until the end of the transaction, rather than releasing it now
+    pub fn hold_until_end_of_xact(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for PgPageLock<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::UnlockPage(self.relation.as_ptr(), self.blkno, self.mode.into());
+        }
+    }
+}
+
+/// A lock on one tuple of a relation, by its `ctid`, which is released when dropped
+///
+/// This is the lock Postgres takes, along with marking the tuple itself, to queue up backends
+/// waiting on a `SELECT ... FOR UPDATE` or an `UPDATE` of the same row.
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct PgTupleLock<'a> {
+    relation: &'a PgRelation,
+    tid: pg_sys::ItemPointerData,
+    mode: LockMode,
+}
+
+impl<'a> PgTupleLock<'a> {
+    /// Lock the tuple at `tid` in `relation`, waiting for any conflicting locks to be released
+    pub fn lock(relation: &'a PgRelation, tid: pg_sys::ItemPointerData, mode: LockMode) -> Self {
+        let mut tid = tid;
+        unsafe {
+            pg_sys::LockTuple(relation.as_ptr(), &mut tid, mode.into());
+        }
+        PgTupleLock { relation, tid, mode }
+    }
+
+    /// Lock the tuple at `tid` in `relation`, returning `None` rather than waiting if a
+    /// conflicting lock is held by another backend
+    pub fn try_lock(
+        relation: &'a PgRelation,
+        tid: pg_sys::ItemPointerData,
+        mode: LockMode,
+    ) -> Option<Self> {
+        let mut tid = tid;
+        unsafe { pg_sys::ConditionalLockTuple(relation.as_ptr(), &mut tid, mode.into()) }
+            .then(|| PgTupleLock { relation, tid, mode })
+    }
+
+    /// The locked tuple's `ctid`
+    pub fn tid(&self) -> pg_sys::ItemPointerData {
+        self.tid
+    }
+
+    /// The mode the tuple is locked in
+    pub fn mode(&self) -> LockMode {
+        self.mode
+    }
+
+    /// Keep the lock until the end of the transaction, rather than releasing it now
+    pub fn hold_until_end_of_xact(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for PgTupleLock<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::UnlockTuple(self.relation.as_ptr(), &mut self.tid, self.mode.into());
+        }
+    }
+}
+
+/// The key of an advisory lock, which is either one `bigint` or two `int`s, exactly as with
+/// Postgres' `pg_advisory_lock()` functions
+///
+/// The two kinds of keys never conflict with each other, even if their bits are the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdvisoryLockKey {
+    Int8(i64),
+    Int4Pair(i32, i32),
+}
+
+impl From<i64> for AdvisoryLockKey {
+    fn from(key: i64) -> Self {
+        AdvisoryLockKey::Int8(key)
+    }
+}
+
+impl From<(i32, i32)> for AdvisoryLockKey {
+    fn from((key1, key2): (i32, i32)) -> Self {
+        AdvisoryLockKey::Int4Pair(key1, key2)
+    }
+}
+
+impl AdvisoryLockKey {
+    /// The `LOCKTAG` Postgres' `SET_LOCKTAG_ADVISORY()` macro makes for this key in the current
+    /// database
+    fn locktag(self) -> pg_sys::LOCKTAG {
+        let (key1, key2, keytype) = match self {
+            AdvisoryLockKey::Int8(key) => ((key >> 32) as u32, key as u32, 1),
+            AdvisoryLockKey::Int4Pair(key1, key2) => (key1 as u32, key2 as u32, 2),
+        };
+        pg_sys::LOCKTAG {
+            locktag_field1: unsafe { pg_sys::MyDatabaseId }.as_u32(),
+            locktag_field2: key1,
+            locktag_field3: key2,
+            locktag_field4: keytype,
+            locktag_type: pg_sys::LockTagType_LOCKTAG_ADVISORY as _,
+            locktag_lockmethodid: pg_sys::USER_LOCKMETHOD as _,
+        }
+    }
+}
+
+/// A session-level advisory lock, which is released when dropped
+///
+/// Advisory locks are application-defined locks on arbitrary keys, shared with SQL's
+/// `pg_advisory_lock()` family of functions.  A session-level lock is not released at the end of
+/// the transaction, so the guard can outlive it, such as in a background worker.  Transaction-level
+/// locks are taken with [`PgAdvisoryLock::xact_lock()`] and friends, and can't be released early.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgx::PgAdvisoryLock;
+///
+/// const REFRESH_LOCK: i64 = 0x70677821;
+///
+/// match PgAdvisoryLock::try_lock(REFRESH_LOCK) {
+///     Some(_guard) => { /* refresh, while no other backend can */ }
+///     None => { /* another backend is already refreshing */ }
+/// }
+/// ```
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct PgAdvisoryLock {
+    key: AdvisoryLockKey,
+    mode: pg_sys::LOCKMODE,
+}
+
+impl PgAdvisoryLock {
+    /// Take an exclusive session-level lock on `key`, like `pg_advisory_lock()`
+    pub fn lock<K: Into<AdvisoryLockKey>>(key: K) -> Self {
+        PgAdvisoryLock::session_lock(key.into(), pg_sys::ExclusiveLock, false).unwrap()
+    }
+
+    /// Take a shared session-level lock on `key`, like `pg_advisory_lock_shared()`
+    pub fn lock_shared<K: Into<AdvisoryLockKey>>(key: K) -> Self {
+        PgAdvisoryLock::session_lock(key.into(), pg_sys::ShareLock, false).unwrap()
+    }
+
+    /// Take an exclusive session-level lock on `key` if it's available, like
+    /// `pg_try_advisory_lock()`
+    pub fn try_lock<K: Into<AdvisoryLockKey>>(key: K) -> Option<Self> {
+        PgAdvisoryLock::session_lock(key.into(), pg_sys::ExclusiveLock, true)
+    }
+
+    /// Take a shared session-level lock on `key` if it's available, like
+    /// `pg_try_advisory_lock_shared()`
+    pub fn try_lock_shared<K: Into<AdvisoryLockKey>>(key: K) -> Option<Self> {
+        PgAdvisoryLock::session_lock(key.into(), pg_sys::ShareLock, true)
+    }
+
+    /// Take an exclusive transaction-level lock on `key`, like `pg_advisory_xact_lock()`
+    pub fn xact_lock<K: Into<AdvisoryLockKey>>(key: K) {
+        acquire(key.into(), pg_sys::ExclusiveLock, false, false);
+    }
+
+    /// Take a shared transaction-level lock on `key`, like `pg_advisory_xact_lock_shared()`
+    pub fn xact_lock_shared<K: Into<AdvisoryLockKey>>(key: K) {
+        acquire(key.into(), pg_sys::ShareLock, false, false);
+    }
+
+    /// Take an exclusive transaction-level lock on `key` if it's available, like
+    /// `pg_try_advisory_xact_lock()`, returning whether it was
+    pub fn try_xact_lock<K: Into<AdvisoryLockKey>>(key: K) -> bool {
+        acquire(key.into(), pg_sys::ExclusiveLock, false, true)
+    }
+
+    /// Take a shared transaction-level lock on `key` if it's available, like
+    /// `pg_try_advisory_xact_lock_shared()`, returning whether it was
+    pub fn try_xact_lock_shared<K: Into<AdvisoryLockKey>>(key: K) -> bool {
+        acquire(key.into(), pg_sys::ShareLock, false, true)
+    }
+
+    /// The locked key
+    pub fn key(&self) -> AdvisoryLockKey {
+        self.key
+    }
+
+    /// Is this a shared lock, rather than an exclusive one?
+    pub fn is_shared(&self) -> bool {
+        self.mode == pg_sys::ShareLock as pg_sys::LOCKMODE
+    }
+
+    fn session_lock(key: AdvisoryLockKey, mode: u32, dont_wait: bool) -> Option<Self> {
+        acquire(key, mode, true, dont_wait)
+            .then(|| PgAdvisoryLock { key, mode: mode as pg_sys::LOCKMODE })
+    }
+}
+
+impl Drop for PgAdvisoryLock {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::LockRelease(&self.key.locktag(), self.mode, true);
+        }
+    }
+}
+
+/// Acquire an advisory lock, returning whether it was
+fn acquire(key: AdvisoryLockKey, mode: u32, session: bool, dont_wait: bool) -> bool {
+    let result = unsafe {
+        pg_sys::LockAcquire(&key.locktag(), mode as pg_sys::LOCKMODE, session, dont_wait)
+    };
+    result != pg_sys::LockAcquireResult_LOCKACQUIRE_NOT_AVAIL
+}
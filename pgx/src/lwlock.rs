@@ -176,7 +176,7 @@ impl<T> Drop for PgLwLockExclusiveGuard<'_, T> {
 /// on (sub)transaction abort anyway.
 ///
 /// SAFETY: the given lock must be valid
-unsafe fn release_unless_elog_unwinding(lock: *mut pg_sys::LWLock) {
+pub(crate) unsafe fn release_unless_elog_unwinding(lock: *mut pg_sys::LWLock) {
     // SAFETY: mut static access is ok from a single (main) thread.
     if pg_sys::InterruptHoldoffCount > 0 {
         pg_sys::LWLockRelease(lock);
@@ -9,7 +9,11 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Helper functions and such for Postgres' various query tree `Node`s
 
+use crate as pgx; // for #[pg_guard] support from within ourself
 use crate::pg_sys;
+#[cfg(feature = "cshim")]
+use crate::PgList;
+use pgx_macros::pg_guard;
 
 /// #define IsA(nodeptr,_type_)            (nodeTag(nodeptr) == T_##_type_)
 #[inline]
@@ -38,3 +42,266 @@ pub unsafe fn node_to_string<'a>(nodeptr: *mut pg_sys::Node) -> Option<&'a str>
         }
     }
 }
+
+/// A borrowed, typed view of a [pg_sys::Node], for the node types extensions most often inspect
+/// from hooks such as `planner` and `post_parse_analyze`
+///
+/// Nodes of any other type are [`NodeRef::Other`], and can still be checked with [`NodeRef::tag()`]
+/// and cast through [`NodeRef::as_ptr()`].
+#[derive(Debug, Clone, Copy)]
+pub enum NodeRef<'a> {
+    Query(&'a pg_sys::Query),
+    RangeTblEntry(&'a pg_sys::RangeTblEntry),
+    TargetEntry(&'a pg_sys::TargetEntry),
+    Var(&'a pg_sys::Var),
+    Const(&'a pg_sys::Const),
+    OpExpr(&'a pg_sys::OpExpr),
+    FuncExpr(&'a pg_sys::FuncExpr),
+    BoolExpr(&'a pg_sys::BoolExpr),
+    SubLink(&'a pg_sys::SubLink),
+    Other(&'a pg_sys::Node),
+}
+
+impl<'a> NodeRef<'a> {
+    /// View `nodeptr` by its `NodeTag`, returning `None` if it's NULL
+    ///
+    /// ### Safety
+    ///
+    /// We cannot guarantee the provided `nodeptr` is a valid pointer to a `Node`, nor that the node
+    /// outlives `'a`
+    pub unsafe fn from_pg(nodeptr: *mut pg_sys::Node) -> Option<Self> {
+        let node = nodeptr.as_ref()?;
+        Some(match node.type_ {
+            pg_sys::NodeTag_T_Query => NodeRef::Query(&*nodeptr.cast()),
+            pg_sys::NodeTag_T_RangeTblEntry => NodeRef::RangeTblEntry(&*nodeptr.cast()),
+            pg_sys::NodeTag_T_TargetEntry => NodeRef::TargetEntry(&*nodeptr.cast()),
+            pg_sys::NodeTag_T_Var => NodeRef::Var(&*nodeptr.cast()),
+            pg_sys::NodeTag_T_Const => NodeRef::Const(&*nodeptr.cast()),
+            pg_sys::NodeTag_T_OpExpr => NodeRef::OpExpr(&*nodeptr.cast()),
+            pg_sys::NodeTag_T_FuncExpr => NodeRef::FuncExpr(&*nodeptr.cast()),
+            pg_sys::NodeTag_T_BoolExpr => NodeRef::BoolExpr(&*nodeptr.cast()),
+            pg_sys::NodeTag_T_SubLink => NodeRef::SubLink(&*nodeptr.cast()),
+            _ => NodeRef::Other(node),
+        })
+    }
+
+    /// The node's `NodeTag`
+    pub fn tag(&self) -> pg_sys::NodeTag {
+        unsafe { (*self.as_ptr()).type_ }
+    }
+
+    /// The underlying [pg_sys::Node]
+    pub fn as_ptr(&self) -> *mut pg_sys::Node {
+        match self {
+            NodeRef::Query(node) => *node as *const _ as *mut _,
+            NodeRef::RangeTblEntry(node) => *node as *const _ as *mut _,
+            NodeRef::TargetEntry(node) => *node as *const _ as *mut _,
+            NodeRef::Var(node) => *node as *const _ as *mut _,
+            NodeRef::Const(node) => *node as *const _ as *mut _,
+            NodeRef::OpExpr(node) => *node as *const _ as *mut _,
+            NodeRef::FuncExpr(node) => *node as *const _ as *mut _,
+            NodeRef::BoolExpr(node) => *node as *const _ as *mut _,
+            NodeRef::SubLink(node) => *node as *const _ as *mut _,
+            NodeRef::Other(node) => *node as *const _ as *mut _,
+        }
+    }
+
+    /// The arguments of an `OpExpr`, `FuncExpr`, or `BoolExpr`, and no nodes for anything else
+    #[cfg(feature = "cshim")]
+    pub fn args(&self) -> Vec<NodeRef<'a>> {
+        let args = match self {
+            NodeRef::OpExpr(expr) => expr.args,
+            NodeRef::FuncExpr(expr) => expr.args,
+            NodeRef::BoolExpr(expr) => expr.args,
+            _ => return Vec::new(),
+        };
+        unsafe { node_list(args) }
+    }
+
+    /// The expression a `TargetEntry` computes
+    pub fn expr(&self) -> Option<NodeRef<'a>> {
+        match self {
+            NodeRef::TargetEntry(entry) => unsafe { NodeRef::from_pg(entry.expr.cast()) },
+            _ => None,
+        }
+    }
+
+    /// The range table of a `Query`, and no entries for anything else
+    #[cfg(feature = "cshim")]
+    pub fn range_table(&self) -> Vec<&'a pg_sys::RangeTblEntry> {
+        match self {
+            NodeRef::Query(query) => unsafe {
+                PgList::<pg_sys::RangeTblEntry>::from_pg(query.rtable)
+                    .iter_ptr()
+                    .map(|rte| &*rte)
+                    .collect()
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// The target list of a `Query`, and no entries for anything else
+    #[cfg(feature = "cshim")]
+    pub fn target_list(&self) -> Vec<&'a pg_sys::TargetEntry> {
+        match self {
+            NodeRef::Query(query) => unsafe {
+                PgList::<pg_sys::TargetEntry>::from_pg(query.targetList)
+                    .iter_ptr()
+                    .map(|entry| &*entry)
+                    .collect()
+            },
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// View each node in a `List` of nodes
+///
+/// ### Safety
+///
+/// We cannot guarantee the provided `list` is a valid `List` of `Node` pointers, nor that its nodes
+/// outlive `'a`
+#[cfg(feature = "cshim")]
+pub unsafe fn node_list<'a>(list: *mut pg_sys::List) -> Vec<NodeRef<'a>> {
+    PgList::<pg_sys::Node>::from_pg(list)
+        .iter_ptr()
+        .filter_map(|node| NodeRef::from_pg(node))
+        .collect()
+}
+
+type Walker<'a> = dyn FnMut(NodeRef<'_>) -> bool + 'a;
+type Mutator<'a> = dyn FnMut(NodeRef<'_>) -> Option<*mut pg_sys::Node> + 'a;
+
+/// Call `f` on `node` and on every node below it, stopping as soon as `f` returns `true`
+///
+/// Unlike Postgres' `expression_tree_walker()`, `f` doesn't need to recurse itself.  Sub-queries,
+/// from `SubLink`s or a `Query`'s range table, are walked too, and `f` is also called on each
+/// `RangeTblEntry` of a walked `Query`.
+///
+/// Returns `true` if `f` did.
+///
+/// ### Safety
+///
+/// We cannot guarantee the provided `node` is a valid pointer to an expression or `Query` tree
+pub unsafe fn expression_tree_walker<F: FnMut(NodeRef<'_>) -> bool>(
+    node: *mut pg_sys::Node,
+    mut f: F,
+) -> bool {
+    let mut f: &mut Walker = &mut f;
+    walk_node(node, (&mut f as *mut &mut Walker).cast())
+}
+
+/// Call `f` on every node in `query`, as [`expression_tree_walker()`] does, stopping as soon as `f`
+/// returns `true`
+///
+/// ### Safety
+///
+/// We cannot guarantee the provided `query` is a valid pointer to a `Query`
+pub unsafe fn query_tree_walker<F: FnMut(NodeRef<'_>) -> bool>(
+    query: *mut pg_sys::Query,
+    f: F,
+) -> bool {
+    expression_tree_walker(query.cast(), f)
+}
+
+#[pg_guard]
+unsafe extern "C" fn walk_node(node: *mut pg_sys::Node, context: crate::void_mut_ptr) -> bool {
+    let node = match NodeRef::from_pg(node) {
+        Some(node) => node,
+        None => return false,
+    };
+    let f = &mut *context.cast::<&mut Walker>();
+
+    match node {
+        NodeRef::Query(query) => {
+            f(node)
+                || pg_sys::query_tree_walker(
+                    query as *const _ as *mut _,
+                    Some(walk_node),
+                    context,
+                    EXAMINE_RTES as _,
+                )
+        }
+        // `query_tree_walker()` walks the contents of each entry itself
+        NodeRef::RangeTblEntry(_) => f(node),
+        // Lists aren't interesting on their own
+        NodeRef::Other(_) if node.tag() == pg_sys::NodeTag_T_List => {
+            pg_sys::expression_tree_walker(node.as_ptr(), Some(walk_node), context)
+        }
+        _ => f(node) || pg_sys::expression_tree_walker(node.as_ptr(), Some(walk_node), context),
+    }
+}
+
+#[cfg(feature = "pg11")]
+const EXAMINE_RTES: u32 = pg_sys::QTW_EXAMINE_RTES;
+#[cfg(not(feature = "pg11"))]
+const EXAMINE_RTES: u32 = pg_sys::QTW_EXAMINE_RTES_BEFORE;
+
+/// Return a modified copy of the expression tree `node`, replacing each node `f` returns
+/// `Some(replacement)` for
+///
+/// Nodes `f` returns `None` for are copied, and the nodes below them are passed to `f` as well.
+/// Sub-queries from `SubLink`s are mutated too.  The copy is allocated in the
+/// `CurrentMemoryContext`.
+///
+/// ### Safety
+///
+/// We cannot guarantee the provided `node` is a valid pointer to an expression tree, nor that
+/// the replacement nodes `f` returns are valid where they're placed
+pub unsafe fn expression_tree_mutator<F: FnMut(NodeRef<'_>) -> Option<*mut pg_sys::Node>>(
+    node: *mut pg_sys::Node,
+    mut f: F,
+) -> *mut pg_sys::Node {
+    let mut f: &mut Mutator = &mut f;
+    mutate_node(node, (&mut f as *mut &mut Mutator).cast())
+}
+
+/// Return a modified copy of `query`, replacing each node in it that `f` returns
+/// `Some(replacement)` for, as [`expression_tree_mutator()`] does
+///
+/// This is the usual way to rewrite a `Query` from a `post_parse_analyze` or `planner` hook.
+///
+/// ### Safety
+///
+/// We cannot guarantee the provided `query` is a valid pointer to a `Query`, nor that the
+/// replacement nodes `f` returns are valid where they're placed
+pub unsafe fn query_tree_mutator<F: FnMut(NodeRef<'_>) -> Option<*mut pg_sys::Node>>(
+    query: *mut pg_sys::Query,
+    f: F,
+) -> *mut pg_sys::Query {
+    expression_tree_mutator(query.cast(), f).cast()
+}
+
+#[pg_guard]
+unsafe extern "C" fn mutate_node(
+    node: *mut pg_sys::Node,
+    context: crate::void_mut_ptr,
+) -> *mut pg_sys::Node {
+    let node = match NodeRef::from_pg(node) {
+        Some(node) => node,
+        None => return std::ptr::null_mut(),
+    };
+    let f = &mut *context.cast::<&mut Mutator>();
+
+    // the mutator functions are declared without arguments in C, and so in our bindings
+    let mutator = Some(std::mem::transmute::<
+        unsafe extern "C" fn(*mut pg_sys::Node, crate::void_mut_ptr) -> *mut pg_sys::Node,
+        unsafe extern "C" fn() -> *mut pg_sys::Node,
+    >(mutate_node));
+
+    match node {
+        NodeRef::Other(_) if node.tag() == pg_sys::NodeTag_T_List => {
+            pg_sys::expression_tree_mutator(node.as_ptr(), mutator, context)
+        }
+        _ => match f(node) {
+            Some(replacement) => replacement,
+            None => match node {
+                NodeRef::Query(query) => {
+                    pg_sys::query_tree_mutator(query as *const _ as *mut _, mutator, context, 0)
+                        .cast()
+                }
+                _ => pg_sys::expression_tree_mutator(node.as_ptr(), mutator, context),
+            },
+        },
+    }
+}
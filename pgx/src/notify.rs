@@ -0,0 +1,349 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Postgres' `LISTEN`/`NOTIFY`, without building SQL strings
+//!
+//! Any backend can send notifications with [`notify()`].  Like SQL's `NOTIFY`, they're only
+//! delivered once (and if) the sending transaction commits.
+//!
+//! Background workers can receive them with a [`PgListener`]:
+//!
+//! ```rust,no_run
+//! use pgx::bgworkers::{BackgroundWorker, SignalWakeFlags};
+//! use pgx::notify::PgListener;
+//!
+//! BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+//! BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+//!
+//! let mut listener = PgListener::new();
+//! listener.listen("jobs").expect("invalid channel name");
+//! for notification in listener.notifications(None) {
+//!     println!("{} sent {:?}", notification.sender_pid, notification.payload);
+//! }
+//! // the worker was asked to terminate
+//! ```
+use crate::bgworkers::BackgroundWorker;
+use crate::pg_sys;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::time::{Duration, Instant};
+
+/// The longest channel name, in bytes, Postgres allows
+pub const MAX_CHANNEL_LENGTH: usize = pg_sys::NAMEDATALEN as usize - 1;
+
+/// The longest payload, in bytes, Postgres allows
+pub const MAX_PAYLOAD_LENGTH: usize = (pg_sys::BLCKSZ - pg_sys::NAMEDATALEN - 128) as usize - 1;
+
+/// Describes why a channel name or payload can't be sent or listened to
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum NotifyError {
+    #[error("channel name cannot be empty")]
+    EmptyChannel,
+
+    #[error("channel name too long: {0} bytes")]
+    ChannelTooLong(usize),
+
+    #[error("payload string too long: {0} bytes")]
+    PayloadTooLong(usize),
+
+    #[error("channel name or payload contains a NUL byte")]
+    ContainsNul,
+}
+
+/// Send a notification with `payload` to the listeners of `channel`, like SQL's
+/// `NOTIFY channel, 'payload'`
+///
+/// The notification is only queued here, and is delivered when the current transaction commits,
+/// or never if it aborts.  Identical notifications sent within the same transaction are only
+/// delivered once.
+///
+/// On Postgres 14 and earlier, listeners are only woken up by a background worker's notifications
+/// once the worker next processes its own notifications, such as by waiting on
+/// [`PgListener::notifications()`].
+///
+/// ## Errors
+///
+/// Returns a [`NotifyError`] if `channel` is empty or too long, or `payload` is too long
+///
+/// ## Panics
+///
+/// If not called inside a transaction, such as from a background worker outside of
+/// [`BackgroundWorker::transaction()`]
+pub fn notify(channel: &str, payload: &str) -> Result<(), NotifyError> {
+    assert!(
+        unsafe { pg_sys::IsTransactionState() },
+        "notify() can only be called inside a transaction, and notifications are delivered when it commits"
+    );
+    let channel = channel_name(channel)?;
+    if payload.len() > MAX_PAYLOAD_LENGTH {
+        return Err(NotifyError::PayloadTooLong(payload.len()));
+    }
+    let payload = CString::new(payload).map_err(|_| NotifyError::ContainsNul)?;
+
+    unsafe {
+        pg_sys::Async_Notify(channel.as_ptr(), payload.as_ptr());
+    }
+    Ok(())
+}
+
+fn channel_name(channel: &str) -> Result<CString, NotifyError> {
+    match channel.len() {
+        0 => Err(NotifyError::EmptyChannel),
+        len if len > MAX_CHANNEL_LENGTH => Err(NotifyError::ChannelTooLong(len)),
+        _ => CString::new(channel).map_err(|_| NotifyError::ContainsNul),
+    }
+}
+
+/// A notification received by a [`PgListener`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// The channel the notification was sent to
+    pub channel: String,
+    /// The payload, which is empty if none was given
+    pub payload: String,
+    /// The process ID of the backend that sent the notification
+    pub sender_pid: i32,
+}
+
+/// Receives notifications in a background worker
+///
+/// The worker must be connected to a database, with
+/// [`BackgroundWorker::connect_worker_to_spi()`], and have attached its signal handlers.
+/// Notifications are only sent to listeners in the same database.
+pub struct PgListener {
+    channels: Vec<String>,
+}
+
+impl PgListener {
+    /// Create a listener that isn't listening to any channels yet
+    ///
+    /// ## Panics
+    ///
+    /// If not called from a background worker
+    pub fn new() -> Self {
+        unsafe {
+            assert!(
+                !pg_sys::MyBgworkerEntry.is_null(),
+                "PgListener can only be used from a registered background worker"
+            );
+        }
+        PgListener { channels: Vec::new() }
+    }
+
+    /// Start listening to `channel`, like SQL's `LISTEN channel`
+    ///
+    /// If called inside a transaction, listening starts when it commits.  Otherwise a transaction
+    /// is started, and committed, to do so.
+    pub fn listen(&mut self, channel: &str) -> Result<(), NotifyError> {
+        let name = channel_name(channel)?;
+        in_transaction(|| unsafe { pg_sys::Async_Listen(name.as_ptr()) });
+        if !self.channels.iter().any(|c| c == channel) {
+            self.channels.push(channel.to_string());
+        }
+        Ok(())
+    }
+
+    /// Stop listening to `channel`, like SQL's `UNLISTEN channel`
+    pub fn unlisten(&mut self, channel: &str) -> Result<(), NotifyError> {
+        let name = channel_name(channel)?;
+        in_transaction(|| unsafe { pg_sys::Async_Unlisten(name.as_ptr()) });
+        self.channels.retain(|c| c != channel);
+        Ok(())
+    }
+
+    /// Stop listening to all channels, like SQL's `UNLISTEN *`
+    pub fn unlisten_all(&mut self) {
+        in_transaction(|| unsafe { pg_sys::Async_UnlistenAll() });
+        self.channels.clear();
+    }
+
+    /// The channels this listener has listened to
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Iterate over notifications as they arrive, waiting on the worker's latch for each one
+    ///
+    /// The iterator ends when the worker receives a SIGTERM, or if a `timeout` is given, when no
+    /// notification arrives within it.
+    ///
+    /// ## Panics
+    ///
+    /// If iterated inside a transaction, as notifications are only received between them
+    pub fn notifications(&mut self, timeout: Option<Duration>) -> Notifications<'_> {
+        Notifications { _listener: self, timeout, terminated: false }
+    }
+}
+
+impl Default for PgListener {
+    fn default() -> Self {
+        PgListener::new()
+    }
+}
+
+fn in_transaction<F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe>(f: F) {
+    if unsafe { pg_sys::IsTransactionOrTransactionBlock() } {
+        f()
+    } else {
+        BackgroundWorker::transaction(f)
+    }
+}
+
+/// An iterator over the notifications a [`PgListener`] receives
+pub struct Notifications<'a> {
+    _listener: &'a mut PgListener,
+    timeout: Option<Duration>,
+    terminated: bool,
+}
+
+impl Notifications<'_> {
+    /// Did the iterator end because the worker received a SIGTERM?
+    pub fn terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl Iterator for Notifications<'_> {
+    type Item = Notification;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.terminated {
+            return None;
+        }
+        assert!(
+            unsafe { !pg_sys::IsTransactionOrTransactionBlock() },
+            "notifications can't be received inside a transaction"
+        );
+
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if let Some(notification) = RECEIVED.with(|received| received.borrow_mut().pop_front())
+            {
+                return Some(notification);
+            }
+
+            unsafe {
+                receive_notifications();
+            }
+            if RECEIVED.with(|received| !received.borrow().is_empty()) {
+                continue;
+            }
+
+            let timeout = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(timeout) if !timeout.is_zero() => Some(timeout),
+                    _ => return None,
+                },
+                None => None,
+            };
+            if !BackgroundWorker::wait_latch(timeout) {
+                self.terminated = true;
+                return None;
+            }
+        }
+    }
+}
+
+thread_local! {
+    static RECEIVED: RefCell<VecDeque<Notification>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Have Postgres read this backend's pending notifications from the notification queue
+///
+/// Postgres "sends" them to the frontend, which background workers don't have, so we stand in for
+/// one, the way parallel workers redirect their frontend to a shared memory queue.
+unsafe fn receive_notifications() {
+    struct Redirect {
+        comm_methods: *const pg_sys::PQcommMethods,
+        dest: pg_sys::CommandDest,
+        protocol: pg_sys::ProtocolVersion,
+    }
+
+    impl Drop for Redirect {
+        fn drop(&mut self) {
+            unsafe {
+                pg_sys::PqCommMethods = self.comm_methods;
+                pg_sys::whereToSendOutput = self.dest;
+                pg_sys::FrontendProtocol = self.protocol;
+            }
+        }
+    }
+
+    // restored when dropped, even if Postgres raises an error
+    let _redirect = Redirect {
+        comm_methods: pg_sys::PqCommMethods,
+        dest: pg_sys::whereToSendOutput,
+        protocol: pg_sys::FrontendProtocol,
+    };
+    pg_sys::PqCommMethods = &NOTIFICATION_COMM_METHODS;
+    pg_sys::whereToSendOutput = pg_sys::CommandDest_DestRemote;
+    // protocol version 3.0, so payloads are included
+    pg_sys::FrontendProtocol = 3 << 16;
+
+    // before Postgres 15, notifications sent by this backend are only processed after its
+    // transaction, by the main loop background workers don't run
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    pg_sys::ProcessCompletedNotifies();
+
+    if std::ptr::read_volatile(std::ptr::addr_of!(pg_sys::notifyInterruptPending)) != 0 {
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        pg_sys::ProcessNotifyInterrupt();
+        #[cfg(feature = "pg15")]
+        pg_sys::ProcessNotifyInterrupt(false);
+    }
+}
+
+static NOTIFICATION_COMM_METHODS: pg_sys::PQcommMethods = pg_sys::PQcommMethods {
+    comm_reset: Some(comm_reset),
+    flush: Some(flush),
+    flush_if_writable: Some(flush),
+    is_send_pending: Some(is_send_pending),
+    putmessage: Some(putmessage),
+    putmessage_noblock: Some(putmessage_noblock),
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+    startcopyout: Some(comm_reset),
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+    endcopyout: Some(endcopyout),
+};
+
+unsafe extern "C" fn comm_reset() {}
+
+unsafe extern "C" fn flush() -> c_int {
+    0
+}
+
+unsafe extern "C" fn is_send_pending() -> bool {
+    false
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+unsafe extern "C" fn endcopyout(_error_abort: bool) {}
+
+unsafe extern "C" fn putmessage_noblock(msgtype: c_char, s: *const c_char, len: usize) {
+    putmessage(msgtype, s, len);
+}
+
+/// Keep `NotificationResponse` messages, and ignore any others, such as notices
+unsafe extern "C" fn putmessage(msgtype: c_char, s: *const c_char, len: usize) -> c_int {
+    if msgtype as u8 == b'A' && len > 4 {
+        let message = std::slice::from_raw_parts(s as *const u8, len);
+        let (pid, strings) = message.split_at(4);
+        let mut strings = strings.split(|b| *b == 0).map(|s| String::from_utf8_lossy(s));
+
+        let notification = Notification {
+            sender_pid: i32::from_be_bytes(pid.try_into().unwrap()),
+            channel: strings.next().unwrap_or_default().into_owned(),
+            payload: strings.next().unwrap_or_default().into_owned(),
+        };
+        RECEIVED.with(|received| received.borrow_mut().push_back(notification));
+    }
+    0
+}
@@ -0,0 +1,694 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Access to Postgres' raw SQL parser, for inspecting the syntax of SQL without analyzing it
+//!
+//! Raw parse trees are what Postgres' grammar produces, before names are looked up in the
+//! catalogs.  They're built from different nodes than the analyzed `Query` trees
+//! [`crate::nodes::NodeRef`] views: a column is a `ColumnRef` rather than a `Var`, and a table is
+//! a `RangeVar` rather than a `RangeTblEntry`.
+//!
+//! ```rust,no_run
+//! use pgx::parser::{parse, RawNodeKind};
+//!
+//! let sql = "SELECT * FROM accounts;\nDELETE FROM accounts";
+//! for stmt in parse(sql).expect("invalid SQL") {
+//!     if let RawNodeKind::DeleteStmt(delete) = stmt.node().kind() {
+//!         if delete.whereClause.is_null() {
+//!             let location = stmt.location();
+//!             println!("unqualified DELETE at line {}", location.line);
+//!         }
+//!     }
+//! }
+//! ```
+use crate as pgx; // for #[pg_guard] support from within ourself
+use crate::pg_sys::panic::{CaughtError, ErrorReport};
+use crate::{is_a, pg_sys, PgList, PgSqlErrorCode, PgTryBuilder};
+use core::ffi::CStr;
+use pgx_macros::pg_guard;
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+/// Parse `sql` into its raw statements
+///
+/// The parse trees are allocated in the `CurrentMemoryContext` and are valid for as long as it is.
+///
+/// Syntax errors are returned rather than raised, so they don't abort the current transaction.
+/// Their [`ErrorReport::position()`] is the 1-based character position in `sql` of the error,
+/// which [`SourceLocation::from_position()`] converts to a line and column.
+pub fn parse(sql: &str) -> Result<Vec<RawStmt<'_>>, ErrorReport> {
+    let c_sql = CString::new(sql).map_err(|e| {
+        ErrorReport::new(
+            PgSqlErrorCode::ERRCODE_SYNTAX_ERROR,
+            "SQL contains a NUL byte",
+            pg_sys::function_name!(),
+        )
+        .set_position(sql[..e.nul_position()].chars().count() as u32 + 1)
+    })?;
+
+    let stmts = PgTryBuilder::new(|| unsafe {
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+        let stmts = pg_sys::raw_parser(c_sql.as_ptr());
+        #[cfg(any(feature = "pg14", feature = "pg15"))]
+        let stmts = pg_sys::raw_parser(c_sql.as_ptr(), pg_sys::RawParseMode_RAW_PARSE_DEFAULT);
+        Ok(stmts)
+    })
+    .catch_others(|e| match e {
+        CaughtError::PostgresError(ereport) => Err(ereport.error_report().clone()),
+        e => e.rethrow(),
+    })
+    .execute()?;
+
+    Ok(unsafe {
+        PgList::<pg_sys::RawStmt>::from_pg(stmts)
+            .iter_ptr()
+            .map(|stmt| RawStmt { sql, stmt: &*stmt })
+            .collect()
+    })
+}
+
+/// A location in a SQL string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The byte offset from the start of the SQL
+    pub offset: usize,
+    /// The 1-based line number
+    pub line: usize,
+    /// The 1-based character position in the line
+    pub column: usize,
+}
+
+impl SourceLocation {
+    /// The location of byte `offset` in `sql`, or `None` if it isn't the start of a character
+    pub fn new(sql: &str, offset: usize) -> Option<Self> {
+        let before = sql.get(..offset)?;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Some(SourceLocation {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        })
+    }
+
+    /// The location of the 1-based character `position` in `sql`, as an [`ErrorReport`] reports
+    /// it
+    pub fn from_position(sql: &str, position: u32) -> Option<Self> {
+        let index = (position as usize).checked_sub(1)?;
+        let offset = sql.char_indices().map(|(i, _)| i).chain(Some(sql.len())).nth(index)?;
+        SourceLocation::new(sql, offset)
+    }
+}
+
+/// One statement from [`parse()`]
+#[derive(Debug, Clone, Copy)]
+pub struct RawStmt<'a> {
+    sql: &'a str,
+    stmt: &'a pg_sys::RawStmt,
+}
+
+impl<'a> RawStmt<'a> {
+    /// The statement's parse tree
+    pub fn node(&self) -> RawNode<'a> {
+        RawNode { sql: self.sql, node: unsafe { &*self.stmt.stmt } }
+    }
+
+    /// The text of this statement, without any surrounding whitespace or trailing semicolon
+    pub fn sql(&self) -> &'a str {
+        let start = self.stmt.stmt_location as usize;
+        let sql = match self.stmt.stmt_len {
+            // the statement runs to the end of the string
+            0 => &self.sql[start..],
+            len => &self.sql[start..start + len as usize],
+        };
+        sql.trim()
+    }
+
+    /// Where this statement starts in the parsed SQL
+    pub fn location(&self) -> SourceLocation {
+        // Postgres considers whitespace after the previous statement's semicolon part of this one
+        let start = self.stmt.stmt_location as usize;
+        let whitespace = self.sql[start..].len() - self.sql[start..].trim_start().len();
+        SourceLocation::new(self.sql, start + whitespace)
+            .expect("statement location is not in the SQL")
+    }
+
+    /// Convert this statement back into SQL, as for [`RawNode::deparse()`]
+    pub fn deparse(&self) -> Option<String> {
+        self.node().deparse()
+    }
+
+    /// The underlying [pg_sys::RawStmt]
+    pub fn as_ptr(&self) -> *mut pg_sys::RawStmt {
+        self.stmt as *const _ as *mut _
+    }
+}
+
+/// A node in a raw parse tree
+#[derive(Debug, Clone, Copy)]
+pub struct RawNode<'a> {
+    sql: &'a str,
+    node: &'a pg_sys::Node,
+}
+
+/// A typed view of a [`RawNode`], for the node types a raw parse tree is mostly made of
+///
+/// Nodes of any other type are [`RawNodeKind::Other`].
+#[derive(Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub enum RawNodeKind<'a> {
+    SelectStmt(&'a pg_sys::SelectStmt),
+    InsertStmt(&'a pg_sys::InsertStmt),
+    UpdateStmt(&'a pg_sys::UpdateStmt),
+    DeleteStmt(&'a pg_sys::DeleteStmt),
+    ResTarget(&'a pg_sys::ResTarget),
+    ColumnRef(&'a pg_sys::ColumnRef),
+    A_Const(&'a pg_sys::A_Const),
+    A_Expr(&'a pg_sys::A_Expr),
+    BoolExpr(&'a pg_sys::BoolExpr),
+    FuncCall(&'a pg_sys::FuncCall),
+    TypeCast(&'a pg_sys::TypeCast),
+    RangeVar(&'a pg_sys::RangeVar),
+    NullTest(&'a pg_sys::NullTest),
+    SubLink(&'a pg_sys::SubLink),
+    Other(&'a pg_sys::Node),
+}
+
+impl<'a> RawNode<'a> {
+    /// The node's `NodeTag`
+    pub fn tag(&self) -> pg_sys::NodeTag {
+        self.node.type_
+    }
+
+    /// The node viewed as its type
+    pub fn kind(&self) -> RawNodeKind<'a> {
+        let node = self.node;
+        let ptr = self.as_ptr();
+        unsafe {
+            match node.type_ {
+                pg_sys::NodeTag_T_SelectStmt => RawNodeKind::SelectStmt(&*ptr.cast()),
+                pg_sys::NodeTag_T_InsertStmt => RawNodeKind::InsertStmt(&*ptr.cast()),
+                pg_sys::NodeTag_T_UpdateStmt => RawNodeKind::UpdateStmt(&*ptr.cast()),
+                pg_sys::NodeTag_T_DeleteStmt => RawNodeKind::DeleteStmt(&*ptr.cast()),
+                pg_sys::NodeTag_T_ResTarget => RawNodeKind::ResTarget(&*ptr.cast()),
+                pg_sys::NodeTag_T_ColumnRef => RawNodeKind::ColumnRef(&*ptr.cast()),
+                pg_sys::NodeTag_T_A_Const => RawNodeKind::A_Const(&*ptr.cast()),
+                pg_sys::NodeTag_T_A_Expr => RawNodeKind::A_Expr(&*ptr.cast()),
+                pg_sys::NodeTag_T_BoolExpr => RawNodeKind::BoolExpr(&*ptr.cast()),
+                pg_sys::NodeTag_T_FuncCall => RawNodeKind::FuncCall(&*ptr.cast()),
+                pg_sys::NodeTag_T_TypeCast => RawNodeKind::TypeCast(&*ptr.cast()),
+                pg_sys::NodeTag_T_RangeVar => RawNodeKind::RangeVar(&*ptr.cast()),
+                pg_sys::NodeTag_T_NullTest => RawNodeKind::NullTest(&*ptr.cast()),
+                pg_sys::NodeTag_T_SubLink => RawNodeKind::SubLink(&*ptr.cast()),
+                _ => RawNodeKind::Other(node),
+            }
+        }
+    }
+
+    /// View another node from the same parse tree, such as one of this node's fields, returning
+    /// `None` if it's NULL
+    ///
+    /// ### Safety
+    ///
+    /// We cannot guarantee the provided `node` is a valid pointer into the same parse tree
+    pub unsafe fn child(&self, node: *mut pg_sys::Node) -> Option<RawNode<'a>> {
+        Some(RawNode { sql: self.sql, node: node.as_ref()? })
+    }
+
+    /// View each node of a `List` from the same parse tree, such as a `SelectStmt`'s `targetList`
+    ///
+    /// ### Safety
+    ///
+    /// We cannot guarantee the provided `list` is a valid `List` of nodes from the same parse tree
+    pub unsafe fn children(&self, list: *mut pg_sys::List) -> Vec<RawNode<'a>> {
+        PgList::<pg_sys::Node>::from_pg(list)
+            .iter_ptr()
+            .filter_map(|node| self.child(node))
+            .collect()
+    }
+
+    /// Where this node is in the parsed SQL, if Postgres knows
+    pub fn location(&self) -> Option<SourceLocation> {
+        match unsafe { pg_sys::exprLocation(self.node) } {
+            location if location < 0 => None,
+            location => SourceLocation::new(self.sql, location as usize),
+        }
+    }
+
+    /// Call `f` on this node and on every node below it, stopping as soon as `f` returns `true`
+    ///
+    /// Only the nodes Postgres' `raw_expression_tree_walker()` knows are walked into: those of
+    /// `SELECT`, `INSERT`, `UPDATE` and `DELETE` statements and their expressions.  Other nodes,
+    /// such as utility statements like `CREATE TABLE`, are passed to `f` but their children aren't.
+    ///
+    /// Returns `true` if `f` did.
+    pub fn walk<F: FnMut(RawNode<'_>) -> bool>(&self, mut f: F) -> bool {
+        let mut context = WalkContext { sql: self.sql, f: &mut f };
+        unsafe { walk_raw_node(self.as_ptr(), (&mut context as *mut WalkContext).cast()) }
+    }
+
+    /// Convert this node back into SQL
+    ///
+    /// Only `SELECT` statements and the expressions most often found in them are supported:
+    /// column references, constants, operators, `AND`/`OR`/`NOT`, function calls, casts,
+    /// `IS [NOT] NULL`, and `EXISTS` and scalar sub-queries.  Returns `None` if the node, or any
+    /// node below it, isn't.
+    ///
+    /// The result isn't the original text:  expressions are fully parenthesized and names are
+    /// quoted as needed.
+    pub fn deparse(&self) -> Option<String> {
+        unsafe { deparse_node(self.as_ptr()) }
+    }
+
+    /// The underlying [pg_sys::Node]
+    pub fn as_ptr(&self) -> *mut pg_sys::Node {
+        self.node as *const _ as *mut _
+    }
+}
+
+struct WalkContext<'a, 'f> {
+    sql: &'a str,
+    f: &'f mut dyn FnMut(RawNode<'_>) -> bool,
+}
+
+#[pg_guard]
+unsafe extern "C" fn walk_raw_node(node: *mut pg_sys::Node, context: crate::void_mut_ptr) -> bool {
+    let walk_context = &mut *context.cast::<WalkContext>();
+    let node = match node.as_ref() {
+        Some(node) => RawNode { sql: walk_context.sql, node },
+        None => return false,
+    };
+
+    // the walker functions are declared without arguments in C, and so in our bindings
+    let walker = Some(std::mem::transmute::<
+        unsafe extern "C" fn(*mut pg_sys::Node, crate::void_mut_ptr) -> bool,
+        unsafe extern "C" fn() -> bool,
+    >(walk_raw_node));
+
+    // Lists aren't interesting on their own
+    (node.tag() != pg_sys::NodeTag_T_List && (walk_context.f)(node))
+        || (is_raw_walkable(node.tag())
+            && pg_sys::raw_expression_tree_walker(node.as_ptr(), walker, context))
+}
+
+/// Does `raw_expression_tree_walker()` know how to walk nodes tagged `tag`?  It raises an `ERROR`
+/// for any other node.
+fn is_raw_walkable(tag: pg_sys::NodeTag) -> bool {
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    if tag == pg_sys::NodeTag_T_Null {
+        return true;
+    }
+    #[cfg(feature = "pg15")]
+    if [pg_sys::NodeTag_T_Boolean, pg_sys::NodeTag_T_MergeStmt, pg_sys::NodeTag_T_MergeWhenClause]
+        .contains(&tag)
+    {
+        return true;
+    }
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    if [
+        pg_sys::NodeTag_T_PLAssignStmt,
+        pg_sys::NodeTag_T_CTESearchClause,
+        pg_sys::NodeTag_T_CTECycleClause,
+    ]
+    .contains(&tag)
+    {
+        return true;
+    }
+
+    [
+        pg_sys::NodeTag_T_SetToDefault,
+        pg_sys::NodeTag_T_CurrentOfExpr,
+        pg_sys::NodeTag_T_SQLValueFunction,
+        pg_sys::NodeTag_T_Integer,
+        pg_sys::NodeTag_T_Float,
+        pg_sys::NodeTag_T_String,
+        pg_sys::NodeTag_T_BitString,
+        pg_sys::NodeTag_T_ParamRef,
+        pg_sys::NodeTag_T_A_Const,
+        pg_sys::NodeTag_T_A_Star,
+        pg_sys::NodeTag_T_Alias,
+        pg_sys::NodeTag_T_RangeVar,
+        pg_sys::NodeTag_T_GroupingFunc,
+        pg_sys::NodeTag_T_SubLink,
+        pg_sys::NodeTag_T_CaseExpr,
+        pg_sys::NodeTag_T_RowExpr,
+        pg_sys::NodeTag_T_CoalesceExpr,
+        pg_sys::NodeTag_T_MinMaxExpr,
+        pg_sys::NodeTag_T_XmlExpr,
+        pg_sys::NodeTag_T_NullTest,
+        pg_sys::NodeTag_T_BooleanTest,
+        pg_sys::NodeTag_T_JoinExpr,
+        pg_sys::NodeTag_T_IntoClause,
+        pg_sys::NodeTag_T_List,
+        pg_sys::NodeTag_T_InsertStmt,
+        pg_sys::NodeTag_T_DeleteStmt,
+        pg_sys::NodeTag_T_UpdateStmt,
+        pg_sys::NodeTag_T_SelectStmt,
+        pg_sys::NodeTag_T_A_Expr,
+        pg_sys::NodeTag_T_BoolExpr,
+        pg_sys::NodeTag_T_ColumnRef,
+        pg_sys::NodeTag_T_FuncCall,
+        pg_sys::NodeTag_T_NamedArgExpr,
+        pg_sys::NodeTag_T_A_Indices,
+        pg_sys::NodeTag_T_A_Indirection,
+        pg_sys::NodeTag_T_A_ArrayExpr,
+        pg_sys::NodeTag_T_ResTarget,
+        pg_sys::NodeTag_T_MultiAssignRef,
+        pg_sys::NodeTag_T_TypeCast,
+        pg_sys::NodeTag_T_CollateClause,
+        pg_sys::NodeTag_T_SortBy,
+        pg_sys::NodeTag_T_WindowDef,
+        pg_sys::NodeTag_T_RangeSubselect,
+        pg_sys::NodeTag_T_RangeFunction,
+        pg_sys::NodeTag_T_RangeTableSample,
+        pg_sys::NodeTag_T_RangeTableFunc,
+        pg_sys::NodeTag_T_RangeTableFuncCol,
+        pg_sys::NodeTag_T_TypeName,
+        pg_sys::NodeTag_T_ColumnDef,
+        pg_sys::NodeTag_T_IndexElem,
+        pg_sys::NodeTag_T_GroupingSet,
+        pg_sys::NodeTag_T_LockingClause,
+        pg_sys::NodeTag_T_XmlSerialize,
+        pg_sys::NodeTag_T_WithClause,
+        pg_sys::NodeTag_T_InferClause,
+        pg_sys::NodeTag_T_OnConflictClause,
+        pg_sys::NodeTag_T_CommonTableExpr,
+    ]
+    .contains(&tag)
+}
+
+unsafe fn cstr<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+unsafe fn quote_identifier(ident: *const c_char) -> Option<String> {
+    cstr(ident)?;
+    cstr(pg_sys::quote_identifier(ident)).map(str::to_string)
+}
+
+unsafe fn quote_literal(literal: *const c_char) -> Option<String> {
+    cstr(literal)?;
+    cstr(pg_sys::quote_literal_cstr(literal)).map(str::to_string)
+}
+
+/// The C string of a `String` value node
+#[cfg(feature = "pg15")]
+pub(crate) unsafe fn string_value(node: *mut pg_sys::Node) -> Option<*const c_char> {
+    is_a(node, pg_sys::NodeTag_T_String).then(|| (*node.cast::<pg_sys::String>()).sval as _)
+}
+
+/// The C string of a `String` value node
+#[cfg(not(feature = "pg15"))]
+pub(crate) unsafe fn string_value(node: *mut pg_sys::Node) -> Option<*const c_char> {
+    is_a(node, pg_sys::NodeTag_T_String).then(|| (*node.cast::<pg_sys::Value>()).val.str_ as _)
+}
+
+/// A dotted name, such as a qualified function name, from a `List` of `String` value nodes
+unsafe fn deparse_name(names: *mut pg_sys::List) -> Option<String> {
+    let names = PgList::<pg_sys::Node>::from_pg(names)
+        .iter_ptr()
+        .map(|name| quote_identifier(string_value(name)?))
+        .collect::<Option<Vec<_>>>()?;
+    (!names.is_empty()).then(|| names.join("."))
+}
+
+unsafe fn deparse_list(list: *mut pg_sys::List, separator: &str) -> Option<String> {
+    let nodes = PgList::<pg_sys::Node>::from_pg(list)
+        .iter_ptr()
+        .map(|node| deparse_node(node))
+        .collect::<Option<Vec<_>>>()?;
+    Some(nodes.join(separator))
+}
+
+#[cfg(feature = "pg15")]
+unsafe fn deparse_const(constant: &pg_sys::A_Const) -> Option<String> {
+    if constant.isnull {
+        return Some("NULL".to_string());
+    }
+    let val = &constant.val;
+    match val.node.type_ {
+        pg_sys::NodeTag_T_Integer => Some(val.ival.ival.to_string()),
+        pg_sys::NodeTag_T_Float => cstr(val.fval.fval).map(str::to_string),
+        pg_sys::NodeTag_T_Boolean => Some(val.boolval.boolval.to_string()),
+        pg_sys::NodeTag_T_String => quote_literal(val.sval.sval),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "pg15"))]
+unsafe fn deparse_const(constant: &pg_sys::A_Const) -> Option<String> {
+    let val = &constant.val;
+    match val.type_ {
+        pg_sys::NodeTag_T_Integer => Some(val.val.ival.to_string()),
+        pg_sys::NodeTag_T_Float => cstr(val.val.str_).map(str::to_string),
+        pg_sys::NodeTag_T_String => quote_literal(val.val.str_),
+        pg_sys::NodeTag_T_Null => Some("NULL".to_string()),
+        _ => None,
+    }
+}
+
+unsafe fn deparse_type_name(type_name: &pg_sys::TypeName) -> Option<String> {
+    if type_name.setof || type_name.pct_type {
+        return None;
+    }
+    let mut sql = deparse_name(type_name.names)?;
+    if !type_name.typmods.is_null() {
+        sql.push_str(&format!("({})", deparse_list(type_name.typmods, ", ")?));
+    }
+    for _ in 0..PgList::<pg_sys::Node>::from_pg(type_name.arrayBounds).len() {
+        sql.push_str("[]");
+    }
+    Some(sql)
+}
+
+unsafe fn deparse_select(select: &pg_sys::SelectStmt) -> Option<String> {
+    if !select.intoClause.is_null()
+        || !select.withClause.is_null()
+        || !select.lockingClause.is_null()
+        || !select.windowClause.is_null()
+        || !select.valuesLists.is_null()
+    {
+        return None;
+    }
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    if select.groupDistinct {
+        return None;
+    }
+    #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+    if select.limitOption == pg_sys::LimitOption_LIMIT_OPTION_WITH_TIES {
+        return None;
+    }
+
+    let mut sql = match select.op {
+        pg_sys::SetOperation_SETOP_NONE => {
+            let mut sql = String::from("SELECT ");
+            if !select.distinctClause.is_null() {
+                let distinct = PgList::<pg_sys::Node>::from_pg(select.distinctClause);
+                // a plain DISTINCT is a list of one NULL
+                if distinct.iter_ptr().all(|node| node.is_null()) {
+                    sql.push_str("DISTINCT ");
+                } else {
+                    sql.push_str(&format!(
+                        "DISTINCT ON ({}) ",
+                        deparse_list(select.distinctClause, ", ")?
+                    ));
+                }
+            }
+            sql.push_str(&deparse_list(select.targetList, ", ")?);
+            if !select.fromClause.is_null() {
+                sql.push_str(&format!(" FROM {}", deparse_list(select.fromClause, ", ")?));
+            }
+            if !select.whereClause.is_null() {
+                sql.push_str(&format!(" WHERE {}", deparse_node(select.whereClause)?));
+            }
+            if !select.groupClause.is_null() {
+                sql.push_str(&format!(" GROUP BY {}", deparse_list(select.groupClause, ", ")?));
+            }
+            if !select.havingClause.is_null() {
+                sql.push_str(&format!(" HAVING {}", deparse_node(select.havingClause)?));
+            }
+            sql
+        }
+        op => {
+            let op = match op {
+                pg_sys::SetOperation_SETOP_UNION => "UNION",
+                pg_sys::SetOperation_SETOP_INTERSECT => "INTERSECT",
+                pg_sys::SetOperation_SETOP_EXCEPT => "EXCEPT",
+                _ => return None,
+            };
+            format!(
+                "({}) {}{} ({})",
+                deparse_select(select.larg.as_ref()?)?,
+                op,
+                if select.all { " ALL" } else { "" },
+                deparse_select(select.rarg.as_ref()?)?
+            )
+        }
+    };
+
+    if !select.sortClause.is_null() {
+        sql.push_str(&format!(" ORDER BY {}", deparse_list(select.sortClause, ", ")?));
+    }
+    if !select.limitCount.is_null() {
+        sql.push_str(&format!(" LIMIT {}", deparse_node(select.limitCount)?));
+    }
+    if !select.limitOffset.is_null() {
+        sql.push_str(&format!(" OFFSET {}", deparse_node(select.limitOffset)?));
+    }
+    Some(sql)
+}
+
+unsafe fn deparse_node(node: *mut pg_sys::Node) -> Option<String> {
+    let tag = node.as_ref()?.type_;
+    match tag {
+        pg_sys::NodeTag_T_SelectStmt => deparse_select(&*node.cast()),
+        pg_sys::NodeTag_T_ResTarget => {
+            let target = &*node.cast::<pg_sys::ResTarget>();
+            if !target.indirection.is_null() {
+                return None;
+            }
+            let mut sql = deparse_node(target.val)?;
+            if !target.name.is_null() {
+                sql.push_str(&format!(" AS {}", quote_identifier(target.name)?));
+            }
+            Some(sql)
+        }
+        pg_sys::NodeTag_T_ColumnRef => {
+            let column = &*node.cast::<pg_sys::ColumnRef>();
+            let fields = PgList::<pg_sys::Node>::from_pg(column.fields)
+                .iter_ptr()
+                .map(|field| {
+                    if is_a(field, pg_sys::NodeTag_T_A_Star) {
+                        Some("*".to_string())
+                    } else {
+                        quote_identifier(string_value(field)?)
+                    }
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(fields.join("."))
+        }
+        pg_sys::NodeTag_T_A_Const => deparse_const(&*node.cast()),
+        pg_sys::NodeTag_T_A_Expr => {
+            let expr = &*node.cast::<pg_sys::A_Expr>();
+            let names = PgList::<pg_sys::Node>::from_pg(expr.name);
+            if expr.kind != pg_sys::A_Expr_Kind_AEXPR_OP || names.len() != 1 {
+                return None;
+            }
+            let op = cstr(string_value(names.head()?)?)?;
+            let right = deparse_node(expr.rexpr)?;
+            match expr.lexpr.is_null() {
+                true => Some(format!("({} {})", op, right)),
+                false => Some(format!("({} {} {})", deparse_node(expr.lexpr)?, op, right)),
+            }
+        }
+        pg_sys::NodeTag_T_BoolExpr => {
+            let expr = &*node.cast::<pg_sys::BoolExpr>();
+            match expr.boolop {
+                pg_sys::BoolExprType_AND_EXPR => {
+                    Some(format!("({})", deparse_list(expr.args, " AND ")?))
+                }
+                pg_sys::BoolExprType_OR_EXPR => {
+                    Some(format!("({})", deparse_list(expr.args, " OR ")?))
+                }
+                pg_sys::BoolExprType_NOT_EXPR => {
+                    Some(format!("(NOT {})", deparse_list(expr.args, "")?))
+                }
+                _ => None,
+            }
+        }
+        pg_sys::NodeTag_T_FuncCall => {
+            let call = &*node.cast::<pg_sys::FuncCall>();
+            if !call.agg_order.is_null()
+                || !call.agg_filter.is_null()
+                || !call.over.is_null()
+                || call.agg_within_group
+                || call.func_variadic
+            {
+                return None;
+            }
+            let args = match call.agg_star {
+                true => "*".to_string(),
+                false => deparse_list(call.args, ", ")?,
+            };
+            let distinct = if call.agg_distinct { "DISTINCT " } else { "" };
+            Some(format!("{}({}{})", deparse_name(call.funcname)?, distinct, args))
+        }
+        pg_sys::NodeTag_T_TypeCast => {
+            let cast = &*node.cast::<pg_sys::TypeCast>();
+            Some(format!(
+                "({})::{}",
+                deparse_node(cast.arg)?,
+                deparse_type_name(cast.typeName.as_ref()?)?
+            ))
+        }
+        pg_sys::NodeTag_T_RangeVar => {
+            let range_var = &*node.cast::<pg_sys::RangeVar>();
+            if !range_var.catalogname.is_null() {
+                return None;
+            }
+            let mut sql = String::new();
+            if !range_var.inh {
+                sql.push_str("ONLY ");
+            }
+            if !range_var.schemaname.is_null() {
+                sql.push_str(&quote_identifier(range_var.schemaname)?);
+                sql.push('.');
+            }
+            sql.push_str(&quote_identifier(range_var.relname)?);
+            if let Some(alias) = range_var.alias.as_ref() {
+                if !alias.colnames.is_null() {
+                    return None;
+                }
+                sql.push_str(&format!(" AS {}", quote_identifier(alias.aliasname)?));
+            }
+            Some(sql)
+        }
+        pg_sys::NodeTag_T_NullTest => {
+            let test = &*node.cast::<pg_sys::NullTest>();
+            let not = match test.nulltesttype {
+                pg_sys::NullTestType_IS_NULL => "",
+                _ => "NOT ",
+            };
+            Some(format!("({} IS {}NULL)", deparse_node(test.arg.cast())?, not))
+        }
+        pg_sys::NodeTag_T_SubLink => {
+            let sublink = &*node.cast::<pg_sys::SubLink>();
+            match sublink.subLinkType {
+                pg_sys::SubLinkType_EXISTS_SUBLINK => {
+                    Some(format!("EXISTS ({})", deparse_node(sublink.subselect)?))
+                }
+                pg_sys::SubLinkType_EXPR_SUBLINK => {
+                    Some(format!("({})", deparse_node(sublink.subselect)?))
+                }
+                _ => None,
+            }
+        }
+        pg_sys::NodeTag_T_SortBy => {
+            let sort = &*node.cast::<pg_sys::SortBy>();
+            let mut sql = deparse_node(sort.node)?;
+            match sort.sortby_dir {
+                pg_sys::SortByDir_SORTBY_DEFAULT => {}
+                pg_sys::SortByDir_SORTBY_ASC => sql.push_str(" ASC"),
+                pg_sys::SortByDir_SORTBY_DESC => sql.push_str(" DESC"),
+                _ => return None,
+            }
+            match sort.sortby_nulls {
+                pg_sys::SortByNulls_SORTBY_NULLS_DEFAULT => {}
+                pg_sys::SortByNulls_SORTBY_NULLS_FIRST => sql.push_str(" NULLS FIRST"),
+                pg_sys::SortByNulls_SORTBY_NULLS_LAST => sql.push_str(" NULLS LAST"),
+                _ => return None,
+            }
+            Some(sql)
+        }
+        _ => None,
+    }
+}
@@ -32,6 +32,10 @@ pub use crate::trigger_support::{
 // Aggregate support
 pub use crate::aggregate::{Aggregate, FinalizeModify, ParallelOption};
 
+// Text search support
+#[cfg(feature = "cshim")]
+pub use crate::tsearch::{TsDictionary, TsParser, TsTokenType};
+
 pub use crate::pg_sys::oids::PgOid;
 pub use crate::pg_sys::pg_try::PgTryBuilder;
 pub use crate::pg_sys::utils::name_data_to_str;
@@ -44,6 +48,7 @@ pub use crate::spi::Spi;
 // Logging and Error support
 pub use crate::pg_sys::elog::PgLogLevel;
 pub use crate::pg_sys::errcodes::PgSqlErrorCode;
+pub use crate::pg_sys::panic::ToPgError;
 pub use crate::pg_sys::{
     check_for_interrupts, debug1, debug2, debug3, debug4, debug5, ereport, error, function_name,
     info, log, notice, warning, FATAL, PANIC,
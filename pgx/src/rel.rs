@@ -9,7 +9,8 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Provides a safe wrapper around Postgres' `pg_sys::RelationData` struct
 use crate::{
-    direct_function_call, name_data_to_str, pg_sys, FromDatum, IntoDatum, PgBox, PgTupleDesc,
+    direct_function_call, name_data_to_str, pg_sys, FromDatum, HeapScan, IndexScan, IntoDatum,
+    PgBox, PgSnapshot, PgTupleDesc, ScanKey,
 };
 use pgx_sql_entity_graph::metadata::{
     ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
@@ -287,6 +288,25 @@ impl PgRelation {
         rd_rel.relkind == pg_sys::RELKIND_TOASTVALUE as c_char
     }
 
+    /// Sequentially scan this table for the rows visible to `snapshot` that match every one of
+    /// the `keys`, returning them as [`PgHeapTuple`](crate::heap_tuple::PgHeapTuple)s
+    ///
+    /// Only tables using the `heap` access method can be scanned.
+    pub fn heap_scan<'a>(&'a self, snapshot: &'a PgSnapshot, keys: &[ScanKey]) -> HeapScan<'a> {
+        HeapScan::begin(self, snapshot, keys)
+    }
+
+    /// Scan this table through `index`, for the rows visible to `snapshot` whose index entries
+    /// match every one of the `keys`, returning them in index order
+    pub fn index_scan<'a>(
+        &'a self,
+        index: &'a PgRelation,
+        snapshot: &'a PgSnapshot,
+        keys: &[ScanKey],
+    ) -> IndexScan<'a> {
+        IndexScan::begin(self, index, snapshot, keys)
+    }
+
     /// ensures that the returned `PgRelation` is closed by Rust when it is dropped
     pub fn to_owned(mut self) -> Self {
         self.need_close = true;
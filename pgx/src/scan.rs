@@ -0,0 +1,365 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Scan tables and indexes directly, without going through SPI
+//!
+//! [`PgRelation::heap_scan()`] and [`PgRelation::index_scan()`] return iterators of the matching
+//! rows, as [`PgHeapTuple`](crate::heap_tuple::PgHeapTuple)s.  Rows can be filtered with [`ScanKey`]s, which compare a column
+//! against a Rust value using the operators of the column type's btree operator family.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgx::prelude::*;
+//! use pgx::{PgRelation, PgSnapshot, ScanKey};
+//!
+//! # let (table_oid, index_oid) = (pg_sys::InvalidOid, pg_sys::InvalidOid);
+//! let table = unsafe { PgRelation::with_lock(table_oid, pg_sys::AccessShareLock as _) };
+//! let index = unsafe { PgRelation::with_lock(index_oid, pg_sys::AccessShareLock as _) };
+//! let snapshot = PgSnapshot::transaction();
+//!
+//! // every row with `id = 42`, found through an index on `id`
+//! for row in table.index_scan(&index, &snapshot, &[ScanKey::equal(1, 42)]) {
+//!     let name = row.get_by_name::<String>("name").unwrap();
+//! }
+//! ```
+use crate::heap_tuple::PgHeapTuple;
+use crate::{pg_sys, AllocatedByRust, IntoDatum, PgRelation, PgSnapshot};
+use std::ptr::NonNull;
+
+/// The comparison a [`ScanKey`] makes, as a btree strategy number
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScanStrategy {
+    Less = pg_sys::BTLessStrategyNumber as isize,
+    LessOrEqual = pg_sys::BTLessEqualStrategyNumber as isize,
+    Equal = pg_sys::BTEqualStrategyNumber as isize,
+    GreaterOrEqual = pg_sys::BTGreaterEqualStrategyNumber as isize,
+    Greater = pg_sys::BTGreaterStrategyNumber as isize,
+}
+
+/// A condition on one column of a scan, like `column = value`
+///
+/// For heap scans, `attno` is the column's attribute number in the table.  For index scans, it's
+/// the column's position in the index.  Both start at 1.
+///
+/// The comparison operator is chosen from the btree operator family of the column's type (or of
+/// the index column's operator class) that takes the column's type on the left and the Rust
+/// value's type on the right.  Comparing against `NULL` matches no rows.
+#[derive(Debug, Clone)]
+pub struct ScanKey {
+    attno: pg_sys::AttrNumber,
+    strategy: ScanStrategy,
+    value: Option<pg_sys::Datum>,
+    value_type: pg_sys::Oid,
+}
+
+impl ScanKey {
+    pub fn new<T: IntoDatum>(attno: pg_sys::AttrNumber, strategy: ScanStrategy, value: T) -> Self {
+        ScanKey { attno, strategy, value: value.into_datum(), value_type: T::type_oid() }
+    }
+
+    /// `column < value`
+    pub fn less<T: IntoDatum>(attno: pg_sys::AttrNumber, value: T) -> Self {
+        Self::new(attno, ScanStrategy::Less, value)
+    }
+
+    /// `column <= value`
+    pub fn less_or_equal<T: IntoDatum>(attno: pg_sys::AttrNumber, value: T) -> Self {
+        Self::new(attno, ScanStrategy::LessOrEqual, value)
+    }
+
+    /// `column = value`
+    pub fn equal<T: IntoDatum>(attno: pg_sys::AttrNumber, value: T) -> Self {
+        Self::new(attno, ScanStrategy::Equal, value)
+    }
+
+    /// `column >= value`
+    pub fn greater_or_equal<T: IntoDatum>(attno: pg_sys::AttrNumber, value: T) -> Self {
+        Self::new(attno, ScanStrategy::GreaterOrEqual, value)
+    }
+
+    /// `column > value`
+    pub fn greater<T: IntoDatum>(attno: pg_sys::AttrNumber, value: T) -> Self {
+        Self::new(attno, ScanStrategy::Greater, value)
+    }
+
+    /// Fill in `entry` with this key, using the operator from `opfamily` for comparing `lefttype`
+    /// to our value
+    fn init(
+        &self,
+        entry: &mut pg_sys::ScanKeyData,
+        opfamily: pg_sys::Oid,
+        lefttype: pg_sys::Oid,
+        collation: pg_sys::Oid,
+    ) {
+        unsafe {
+            let opno = pg_sys::get_opfamily_member(
+                opfamily,
+                lefttype,
+                self.value_type,
+                self.strategy as i16,
+            );
+            if opno == pg_sys::InvalidOid {
+                panic!(
+                    "no {:?} operator for comparing type {:?} to type {:?} in operator family {:?}",
+                    self.strategy, lefttype, self.value_type, opfamily
+                );
+            }
+
+            let subtype =
+                if self.value_type == lefttype { pg_sys::InvalidOid } else { self.value_type };
+            let (flags, argument) = match self.value {
+                Some(datum) => (0, datum),
+                None => (pg_sys::SK_ISNULL as i32, pg_sys::Datum::from(0)),
+            };
+            pg_sys::ScanKeyEntryInitialize(
+                entry,
+                flags,
+                self.attno,
+                self.strategy as pg_sys::StrategyNumber,
+                subtype,
+                collation,
+                pg_sys::get_opcode(opno),
+                argument,
+            );
+        }
+    }
+}
+
+#[cfg(feature = "pg11")]
+type TableScanDescData = pg_sys::HeapScanDescData;
+#[cfg(not(feature = "pg11"))]
+type TableScanDescData = pg_sys::TableScanDescData;
+
+/// A sequential scan of a table, returned by [`PgRelation::heap_scan()`]
+///
+/// Each row is copied into the `CurrentMemoryContext` as it's returned.
+pub struct HeapScan<'a> {
+    relation: &'a PgRelation,
+    _snapshot: &'a PgSnapshot,
+    scan: NonNull<TableScanDescData>,
+    // the scan refers to these for as long as it's open
+    _keys: Vec<pg_sys::ScanKeyData>,
+}
+
+impl<'a> HeapScan<'a> {
+    pub(crate) fn begin(
+        relation: &'a PgRelation,
+        snapshot: &'a PgSnapshot,
+        keys: &[ScanKey],
+    ) -> Self {
+        if !relation.is_table() && !relation.is_matview() && !relation.is_toast_value() {
+            panic!("\"{}\" is not a table", relation.name());
+        }
+        #[cfg(not(feature = "pg11"))]
+        if relation.rd_tableam != unsafe { pg_sys::GetHeapamTableAmRoutine() } {
+            panic!("\"{}\" is not a heap table", relation.name());
+        }
+
+        let tupdesc = relation.tuple_desc();
+        let mut entries = vec![pg_sys::ScanKeyData::default(); keys.len()];
+        for (key, entry) in keys.iter().zip(entries.iter_mut()) {
+            let att = tupdesc.get((key.attno - 1) as usize).unwrap_or_else(|| {
+                panic!("no attribute number {} in \"{}\"", key.attno, relation.name())
+            });
+            let typcache = unsafe {
+                &*pg_sys::lookup_type_cache(att.atttypid, pg_sys::TYPECACHE_BTREE_OPFAMILY as i32)
+            };
+            if typcache.btree_opf == pg_sys::InvalidOid {
+                panic!("type {:?} has no default btree operator class", att.atttypid);
+            }
+            key.init(entry, typcache.btree_opf, typcache.btree_opintype, att.attcollation);
+        }
+
+        #[cfg(feature = "pg11")]
+        let scan = unsafe {
+            pg_sys::heap_beginscan(
+                relation.as_ptr(),
+                snapshot.as_ptr(),
+                entries.len() as _,
+                entries.as_mut_ptr(),
+            )
+        };
+        #[cfg(not(feature = "pg11"))]
+        let scan = unsafe {
+            pg_sys::heap_beginscan(
+                relation.as_ptr(),
+                snapshot.as_ptr(),
+                entries.len() as _,
+                entries.as_mut_ptr(),
+                std::ptr::null_mut(),
+                // the same options as `table_beginscan()`
+                pg_sys::ScanOptions_SO_TYPE_SEQSCAN
+                    | pg_sys::ScanOptions_SO_ALLOW_STRAT
+                    | pg_sys::ScanOptions_SO_ALLOW_SYNC
+                    | pg_sys::ScanOptions_SO_ALLOW_PAGEMODE,
+            )
+        };
+
+        HeapScan {
+            relation,
+            _snapshot: snapshot,
+            scan: NonNull::new(scan.cast()).expect("heap_beginscan returned NULL"),
+            _keys: entries,
+        }
+    }
+}
+
+impl<'a> Iterator for HeapScan<'a> {
+    type Item = PgHeapTuple<'a, AllocatedByRust>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            let tuple = pg_sys::heap_getnext(
+                self.scan.as_ptr().cast(),
+                pg_sys::ScanDirection_ForwardScanDirection,
+            );
+            if tuple.is_null() {
+                None
+            } else {
+                // the tuple lives in a shared buffer, and is only valid until the next call
+                Some(PgHeapTuple::from_heap_tuple(self.relation.tuple_desc(), tuple).into_owned())
+            }
+        }
+    }
+}
+
+impl Drop for HeapScan<'_> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::heap_endscan(self.scan.as_ptr().cast()) }
+    }
+}
+
+/// A scan of a table through one of its indexes, returned by [`PgRelation::index_scan()`]
+///
+/// Rows are returned in index order, and each is copied into the `CurrentMemoryContext` as it's
+/// returned.
+pub struct IndexScan<'a> {
+    relation: &'a PgRelation,
+    _index: &'a PgRelation,
+    _snapshot: &'a PgSnapshot,
+    scan: NonNull<pg_sys::IndexScanDescData>,
+    #[cfg(not(feature = "pg11"))]
+    slot: NonNull<pg_sys::TupleTableSlot>,
+    // the scan refers to these for as long as it's open
+    _keys: Vec<pg_sys::ScanKeyData>,
+}
+
+impl<'a> IndexScan<'a> {
+    pub(crate) fn begin(
+        relation: &'a PgRelation,
+        index: &'a PgRelation,
+        snapshot: &'a PgSnapshot,
+        keys: &[ScanKey],
+    ) -> Self {
+        match index.heap_relation() {
+            Some(heap) if heap.oid() == relation.oid() => {}
+            _ => panic!("\"{}\" is not an index on \"{}\"", index.name(), relation.name()),
+        }
+
+        let natts = index.tuple_desc().len();
+        let mut entries = vec![pg_sys::ScanKeyData::default(); keys.len()];
+        for (key, entry) in keys.iter().zip(entries.iter_mut()) {
+            if key.attno < 1 || key.attno as usize > natts {
+                panic!("no column number {} in index \"{}\"", key.attno, index.name());
+            }
+            // SAFETY: an index relation has an opfamily, opcintype, and collation for each column
+            let i = (key.attno - 1) as usize;
+            unsafe {
+                key.init(
+                    entry,
+                    *index.rd_opfamily.add(i),
+                    *index.rd_opcintype.add(i),
+                    *index.rd_indcollation.add(i),
+                );
+            }
+        }
+
+        unsafe {
+            let scan = pg_sys::index_beginscan(
+                relation.as_ptr(),
+                index.as_ptr(),
+                snapshot.as_ptr(),
+                entries.len() as _,
+                0,
+            );
+            pg_sys::index_rescan(
+                scan,
+                entries.as_mut_ptr(),
+                entries.len() as _,
+                std::ptr::null_mut(),
+                0,
+            );
+
+            IndexScan {
+                relation,
+                _index: index,
+                _snapshot: snapshot,
+                scan: NonNull::new(scan).expect("index_beginscan returned NULL"),
+                #[cfg(not(feature = "pg11"))]
+                slot: NonNull::new(pg_sys::table_slot_create(
+                    relation.as_ptr(),
+                    std::ptr::null_mut(),
+                ))
+                .expect("table_slot_create returned NULL"),
+                _keys: entries,
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for IndexScan<'a> {
+    type Item = PgHeapTuple<'a, AllocatedByRust>;
+
+    #[cfg(feature = "pg11")]
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            let tuple = pg_sys::index_getnext(
+                self.scan.as_ptr(),
+                pg_sys::ScanDirection_ForwardScanDirection,
+            );
+            if tuple.is_null() {
+                None
+            } else {
+                Some(PgHeapTuple::from_heap_tuple(self.relation.tuple_desc(), tuple).into_owned())
+            }
+        }
+    }
+
+    #[cfg(not(feature = "pg11"))]
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            if !pg_sys::index_getnext_slot(
+                self.scan.as_ptr(),
+                pg_sys::ScanDirection_ForwardScanDirection,
+                self.slot.as_ptr(),
+            ) {
+                return None;
+            }
+
+            let mut should_free = false;
+            let tuple = pg_sys::ExecFetchSlotHeapTuple(self.slot.as_ptr(), false, &mut should_free);
+            let row = PgHeapTuple::from_heap_tuple(self.relation.tuple_desc(), tuple).into_owned();
+            if should_free {
+                pg_sys::heap_freetuple(tuple);
+            }
+            Some(row)
+        }
+    }
+}
+
+impl Drop for IndexScan<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::index_endscan(self.scan.as_ptr());
+            #[cfg(not(feature = "pg11"))]
+            pg_sys::ExecDropSingleTupleTableSlot(self.slot.as_ptr());
+        }
+    }
+}
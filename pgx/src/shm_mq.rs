@@ -0,0 +1,310 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Typed message queues between backends and background workers, built on Postgres' `shm_mq`
+//!
+//! One side creates the queue, which allocates a dynamic shared memory segment to hold it, and
+//! passes the queue's [`ShmMqHandle`] to the other side -- typically as the `Datum` argument of a
+//! background worker.  The other side then attaches to the queue using that handle.  Messages are
+//! any type that implements `serde`'s `Serialize` and `Deserialize`, and are encoded as CBOR.
+//!
+//! ## Example
+//!
+//! A SQL function that launches a worker and returns whatever rows it streams back:
+//!
+//! ```rust,no_run
+//! use pgx::prelude::*;
+//! use pgx::bgworkers::*;
+//! use pgx::shm_mq::*;
+//!
+//! #[pg_extern]
+//! fn worker_rows() -> TableIterator<'static, (name!(id, i64), name!(label, String))> {
+//!     let mut receiver = ShmMqReceiver::<(i64, String)>::create(64 * 1024);
+//!     let worker = BackgroundWorkerBuilder::new("row streamer")
+//!         .set_library("my_extension")
+//!         .set_function("row_streamer_main")
+//!         .set_argument(Some(receiver.handle().into()))
+//!         .enable_shmem_access(None)
+//!         .set_notify_pid(unsafe { pg_sys::MyProcPid })
+//!         .load_dynamic();
+//!
+//!     // lets us notice if the worker dies before it attaches to the queue
+//!     receiver.attach_worker(&worker);
+//!
+//!     TableIterator::new(receiver)
+//! }
+//!
+//! #[pg_guard]
+//! #[no_mangle]
+//! pub extern "C" fn row_streamer_main(arg: pg_sys::Datum) {
+//!     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGTERM);
+//!
+//!     let mut sender = ShmMqSender::<(i64, String)>::attach(ShmMqHandle::from(arg));
+//!     for i in 0..10 {
+//!         sender.send(&(i, format!("row {}", i))).expect("the receiver went away");
+//!     }
+//! }
+//! ```
+use crate::bgworkers::DynamicBackgroundWorker;
+use crate::{ereport, pg_sys, PgSqlErrorCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+/// Errors that can occur when sending or receiving through a shared memory queue
+#[derive(thiserror::Error, Debug)]
+pub enum ShmMqError {
+    /// The process on the other side of the queue has detached, or exited before attaching
+    #[error("the other side of the shared memory queue has detached")]
+    Detached,
+
+    /// The message could not be encoded or decoded
+    #[error("failed to encode or decode a shared memory queue message: {0}")]
+    Codec(#[from] serde_cbor::Error),
+}
+
+/// The handle of the dynamic shared memory segment holding a queue.  This is what's passed to
+/// the other process so it can attach to the queue.
+///
+/// It converts to and from a [`pg_sys::Datum`], so it can be given to
+/// [`BackgroundWorkerBuilder::set_argument`](crate::bgworkers::BackgroundWorkerBuilder::set_argument).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ShmMqHandle(pg_sys::dsm_handle);
+
+impl From<ShmMqHandle> for pg_sys::Datum {
+    fn from(handle: ShmMqHandle) -> Self {
+        pg_sys::Datum::from(handle.0)
+    }
+}
+
+impl From<pg_sys::Datum> for ShmMqHandle {
+    fn from(datum: pg_sys::Datum) -> Self {
+        ShmMqHandle(datum.value() as pg_sys::dsm_handle)
+    }
+}
+
+/// Which end of the queue this process is
+#[derive(Copy, Clone)]
+enum Role {
+    Sender,
+    Receiver,
+}
+
+/// One process's attachment to a queue, shared by both [`ShmMqSender`] and [`ShmMqReceiver`]
+struct ShmMqEnd {
+    segment: *mut pg_sys::dsm_segment,
+    mqh: *mut pg_sys::shm_mq_handle,
+}
+
+impl ShmMqEnd {
+    fn create(size: usize, role: Role) -> Self {
+        let size = size.max(unsafe { pg_sys::shm_mq_minimum_size });
+        unsafe {
+            let segment = pg_sys::dsm_create(size, 0);
+            let mq = pg_sys::shm_mq_create(pg_sys::dsm_segment_address(segment), size);
+            Self::set_role(mq, role);
+            let mqh = pg_sys::shm_mq_attach(mq, segment, std::ptr::null_mut());
+            ShmMqEnd { segment, mqh }
+        }
+    }
+
+    fn attach(handle: ShmMqHandle, role: Role) -> Self {
+        unsafe {
+            let segment = pg_sys::dsm_attach(handle.0);
+            if segment.is_null() {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+                    "could not map dynamic shared memory segment for message queue"
+                );
+            }
+            let mq = pg_sys::dsm_segment_address(segment) as *mut pg_sys::shm_mq;
+            Self::set_role(mq, role);
+            let mqh = pg_sys::shm_mq_attach(mq, segment, std::ptr::null_mut());
+            ShmMqEnd { segment, mqh }
+        }
+    }
+
+    unsafe fn set_role(mq: *mut pg_sys::shm_mq, role: Role) {
+        match role {
+            Role::Sender => pg_sys::shm_mq_set_sender(mq, pg_sys::MyProc),
+            Role::Receiver => pg_sys::shm_mq_set_receiver(mq, pg_sys::MyProc),
+        }
+    }
+
+    fn handle(&self) -> ShmMqHandle {
+        ShmMqHandle(unsafe { pg_sys::dsm_segment_handle(self.segment) })
+    }
+
+    fn attach_worker(&mut self, worker: &DynamicBackgroundWorker) {
+        unsafe { pg_sys::shm_mq_set_handle(self.mqh, worker.handle) }
+    }
+
+    fn wait_for_attach(&mut self) -> Result<(), ShmMqError> {
+        match unsafe { pg_sys::shm_mq_wait_for_attach(self.mqh) } {
+            pg_sys::shm_mq_result_SHM_MQ_SUCCESS => Ok(()),
+            _ => Err(ShmMqError::Detached),
+        }
+    }
+}
+
+impl Drop for ShmMqEnd {
+    fn drop(&mut self) {
+        unsafe {
+            // detaching the segment also detaches the queue, which tells the other side we're gone
+            pg_sys::dsm_detach(self.segment);
+        }
+    }
+}
+
+/// The sending end of a shared memory queue carrying messages of type `T`
+pub struct ShmMqSender<T> {
+    end: ShmMqEnd,
+    __marker: PhantomData<T>,
+}
+
+impl<T: Serialize> ShmMqSender<T> {
+    /// Create a new queue of `size` bytes in a new dynamic shared memory segment, with this
+    /// process as the sender.  Pass [`ShmMqSender::handle()`] to the receiving process.
+    pub fn create(size: usize) -> Self {
+        ShmMqSender { end: ShmMqEnd::create(size, Role::Sender), __marker: PhantomData }
+    }
+
+    /// Attach to a queue created by another process, as its sender
+    pub fn attach(handle: ShmMqHandle) -> Self {
+        ShmMqSender { end: ShmMqEnd::attach(handle, Role::Sender), __marker: PhantomData }
+    }
+
+    /// The handle the receiving process needs to attach to this queue
+    pub fn handle(&self) -> ShmMqHandle {
+        self.end.handle()
+    }
+
+    /// Associate the background worker that's going to receive from this queue, so that sending
+    /// fails rather than waiting forever if the worker exits before attaching
+    pub fn attach_worker(&mut self, worker: &DynamicBackgroundWorker) {
+        self.end.attach_worker(worker)
+    }
+
+    /// Block until the receiver has attached to the queue
+    pub fn wait_for_attach(&mut self) -> Result<(), ShmMqError> {
+        self.end.wait_for_attach()
+    }
+
+    /// Send a message, blocking until there's room for it in the queue
+    pub fn send(&mut self, message: &T) -> Result<(), ShmMqError> {
+        let bytes = serde_cbor::to_vec(message)?;
+        match self.send_bytes(&bytes, false) {
+            pg_sys::shm_mq_result_SHM_MQ_SUCCESS => Ok(()),
+            _ => Err(ShmMqError::Detached),
+        }
+    }
+
+    /// Try to send a message without blocking.  Returns `Ok(false)` if the queue is full, in
+    /// which case the same message must be sent again before any other.
+    pub fn try_send(&mut self, message: &T) -> Result<bool, ShmMqError> {
+        let bytes = serde_cbor::to_vec(message)?;
+        match self.send_bytes(&bytes, true) {
+            pg_sys::shm_mq_result_SHM_MQ_SUCCESS => Ok(true),
+            pg_sys::shm_mq_result_SHM_MQ_WOULD_BLOCK => Ok(false),
+            _ => Err(ShmMqError::Detached),
+        }
+    }
+
+    fn send_bytes(&mut self, bytes: &[u8], nowait: bool) -> pg_sys::shm_mq_result {
+        unsafe {
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+            let result =
+                pg_sys::shm_mq_send(self.end.mqh, bytes.len(), bytes.as_ptr().cast(), nowait);
+
+            #[cfg(feature = "pg15")]
+            let result =
+                pg_sys::shm_mq_send(self.end.mqh, bytes.len(), bytes.as_ptr().cast(), nowait, true);
+
+            result
+        }
+    }
+}
+
+/// The receiving end of a shared memory queue carrying messages of type `T`
+///
+/// As an [`Iterator`], it blocks for each message and ends once the sender detaches.
+pub struct ShmMqReceiver<T> {
+    end: ShmMqEnd,
+    __marker: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> ShmMqReceiver<T> {
+    /// Create a new queue of `size` bytes in a new dynamic shared memory segment, with this
+    /// process as the receiver.  Pass [`ShmMqReceiver::handle()`] to the sending process.
+    pub fn create(size: usize) -> Self {
+        ShmMqReceiver { end: ShmMqEnd::create(size, Role::Receiver), __marker: PhantomData }
+    }
+
+    /// Attach to a queue created by another process, as its receiver
+    pub fn attach(handle: ShmMqHandle) -> Self {
+        ShmMqReceiver { end: ShmMqEnd::attach(handle, Role::Receiver), __marker: PhantomData }
+    }
+
+    /// The handle the sending process needs to attach to this queue
+    pub fn handle(&self) -> ShmMqHandle {
+        self.end.handle()
+    }
+
+    /// Associate the background worker that's going to send to this queue, so that receiving
+    /// ends rather than waiting forever if the worker exits before attaching
+    pub fn attach_worker(&mut self, worker: &DynamicBackgroundWorker) {
+        self.end.attach_worker(worker)
+    }
+
+    /// Block until the sender has attached to the queue
+    pub fn wait_for_attach(&mut self) -> Result<(), ShmMqError> {
+        self.end.wait_for_attach()
+    }
+
+    /// Receive the next message, blocking until one arrives.  Returns `Ok(None)` once the sender
+    /// has detached and every message it sent has been received.
+    pub fn recv(&mut self) -> Result<Option<T>, ShmMqError> {
+        match self.receive(false) {
+            Err(ShmMqError::Detached) => Ok(None),
+            other => other,
+        }
+    }
+
+    /// Receive the next message if one is waiting, without blocking.  Returns `Ok(None)` if the
+    /// queue is empty, and [`ShmMqError::Detached`] if it's empty and the sender has detached.
+    pub fn try_recv(&mut self) -> Result<Option<T>, ShmMqError> {
+        self.receive(true)
+    }
+
+    fn receive(&mut self, nowait: bool) -> Result<Option<T>, ShmMqError> {
+        let mut nbytes = 0;
+        let mut data = std::ptr::null_mut();
+        let result =
+            unsafe { pg_sys::shm_mq_receive(self.end.mqh, &mut nbytes, &mut data, nowait) };
+        match result {
+            pg_sys::shm_mq_result_SHM_MQ_SUCCESS => {
+                // SAFETY: Postgres gives us a pointer to `nbytes` bytes that remain valid until the
+                // next receive, and we decode them into an owned value right away
+                let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, nbytes) };
+                Ok(Some(serde_cbor::from_slice(bytes)?))
+            }
+            pg_sys::shm_mq_result_SHM_MQ_WOULD_BLOCK => Ok(None),
+            _ => Err(ShmMqError::Detached),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for ShmMqReceiver<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().expect("failed to receive from shared memory queue")
+    }
+}
@@ -228,6 +228,7 @@ unsafe impl PGXSharedMemory for isize {}
 unsafe impl PGXSharedMemory for f32 {}
 unsafe impl PGXSharedMemory for f64 {}
 unsafe impl<T> PGXSharedMemory for [T] where T: PGXSharedMemory + Default {}
+unsafe impl<T, const N: usize> PGXSharedMemory for [T; N] where T: PGXSharedMemory {}
 unsafe impl<A, B> PGXSharedMemory for (A, B)
 where
     A: PGXSharedMemory + Default,
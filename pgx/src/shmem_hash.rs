@@ -0,0 +1,807 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Hash tables that live in Postgres shared memory
+//!
+//! [`PgSharedHashMap`] is a fixed-capacity table created with `ShmemInitHash()` and protected by a
+//! single `LWLock`.  [`PgDsHashMap`] is a growable table backed by Postgres' `dshash` over dynamic
+//! shared memory (DSA), with per-partition locking.
+//!
+//! Both are registered with [`pg_shmem_init!()`](crate::pg_shmem_init) from `_PG_init()`, just like
+//! [`PgLwLock`](crate::PgLwLock) and [`PgAtomic`](crate::PgAtomic).
+use crate::lwlock::release_unless_elog_unwinding;
+use crate::memcxt::PgMemoryContexts;
+use crate::pg_sys;
+use crate::shmem::{PGXSharedMemory, PgSharedMemoryInitialization};
+use once_cell::sync::OnceCell;
+use std::ffi::CString;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::os::raw::{c_int, c_void};
+use std::panic::AssertUnwindSafe;
+use uuid::Uuid;
+
+/// Errors that can occur when modifying a shared memory hash map
+#[derive(thiserror::Error, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PgSharedHashMapError {
+    /// The map already contains its maximum number of entries
+    #[error("shared memory hash map is full")]
+    HashMapFull,
+}
+
+/// The layout of each entry as Postgres stores it.  Postgres requires the key to be first.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+/// Hash `K` using its Rust [`Hash`] implementation.  [`seahash`] is used because it is stable
+/// across processes, which is a requirement for a hash table shared between backends.
+unsafe fn hash_key<K: Hash>(key: *const c_void) -> u32 {
+    let mut hasher = seahash::SeaHasher::new();
+    (*key.cast::<K>()).hash(&mut hasher);
+    hasher.finish() as u32
+}
+
+unsafe extern "C" fn htab_hash<K: Hash>(key: *const c_void, _keysize: pg_sys::Size) -> u32 {
+    hash_key::<K>(key)
+}
+
+unsafe extern "C" fn htab_match<K: Eq>(
+    a: *const c_void,
+    b: *const c_void,
+    _keysize: pg_sys::Size,
+) -> c_int {
+    // Postgres expects zero to mean "equal"
+    (*a.cast::<K>() != *b.cast::<K>()) as c_int
+}
+
+unsafe extern "C" fn dshash_hash<K: Hash>(
+    key: *const c_void,
+    _size: usize,
+    _arg: *mut c_void,
+) -> pg_sys::dshash_hash {
+    hash_key::<K>(key)
+}
+
+unsafe extern "C" fn dshash_compare<K: Eq>(
+    a: *const c_void,
+    b: *const c_void,
+    _size: usize,
+    _arg: *mut c_void,
+) -> c_int {
+    (*a.cast::<K>() != *b.cast::<K>()) as c_int
+}
+
+/// A fixed-capacity hash map stored in Postgres shared memory
+///
+/// The map is created with Postgres' `ShmemInitHash()`, so its memory is reserved when the server
+/// starts and it can never hold more than `max_entries` entries.  Every operation is protected by
+/// a single `LWLock`.  Keys and values are copied into and out of shared memory, so both must be
+/// `Copy` and implement [`PGXSharedMemory`].  Keys are hashed and compared using their Rust
+/// [`Hash`] and [`Eq`] implementations.
+///
+/// > Extensions that use shared memory **must** be loaded via `postgresql.conf`'s
+/// > `shared_preload_libraries` configuration setting.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pgx::prelude::*;
+/// use pgx::{pg_shmem_init, PgSharedHashMap, PgSharedMemoryInitialization};
+///
+/// // at most 1024 query fingerprints and their call counts
+/// static STATS: PgSharedHashMap<u64, i64> = PgSharedHashMap::new(1024);
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(STATS);
+/// }
+///
+/// #[pg_extern]
+/// fn record_call(fingerprint: i64) -> i64 {
+///     let mut stats = STATS.exclusive();
+///     let calls = stats.get(&(fingerprint as u64)).unwrap_or_default() + 1;
+///     stats.insert(fingerprint as u64, calls).expect("stats map is full");
+///     calls
+/// }
+/// ```
+pub struct PgSharedHashMap<K, V> {
+    max_entries: usize,
+    name: OnceCell<&'static str>,
+    inner: OnceCell<PgSharedHashMapInner>,
+    __marker: PhantomData<(K, V)>,
+}
+
+unsafe impl<K: Send, V: Send> Send for PgSharedHashMap<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for PgSharedHashMap<K, V> {}
+
+struct PgSharedHashMapInner {
+    htab: *mut pg_sys::HTAB,
+    lock: *mut pg_sys::LWLock,
+}
+
+impl<K, V> PgSharedHashMap<K, V>
+where
+    K: PGXSharedMemory + Copy + Hash + Eq,
+    V: PGXSharedMemory + Copy,
+{
+    /// Create a new, unattached, map that can hold up to `max_entries` entries.  It is meant to
+    /// be declared as a `static` and then passed to `pg_shmem_init!()`
+    pub const fn new(max_entries: usize) -> Self {
+        PgSharedHashMap {
+            max_entries,
+            name: OnceCell::new(),
+            inner: OnceCell::new(),
+            __marker: PhantomData,
+        }
+    }
+
+    /// The maximum number of entries this map can hold
+    pub fn capacity(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Get the name of the shared memory segment (and `LWLock` tranche) backing this map
+    pub fn get_name(&self) -> &'static str {
+        self.name.get_or_init(|| Box::leak(Uuid::new_v4().to_string().into_boxed_str()))
+    }
+
+    /// Obtain a shared lock on the map, which allows reading entries
+    pub fn share(&self) -> PgSharedHashMapShareGuard<'_, K, V> {
+        let inner = self.inner();
+        unsafe {
+            pg_sys::LWLockAcquire(inner.lock, pg_sys::LWLockMode_LW_SHARED);
+        }
+        PgSharedHashMapShareGuard { map: self }
+    }
+
+    /// Obtain an exclusive lock on the map, which allows reading and modifying entries
+    pub fn exclusive(&self) -> PgSharedHashMapExclusiveGuard<'_, K, V> {
+        let inner = self.inner();
+        unsafe {
+            pg_sys::LWLockAcquire(inner.lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+        }
+        PgSharedHashMapExclusiveGuard { map: self }
+    }
+
+    /// Copy out the value associated with `key`, holding a shared lock for the duration
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.share().get(key)
+    }
+
+    /// Insert or replace the value for `key`, holding an exclusive lock for the duration.
+    /// Returns the previous value, if any.
+    pub fn insert(&self, key: K, value: V) -> Result<Option<V>, PgSharedHashMapError> {
+        self.exclusive().insert(key, value)
+    }
+
+    /// Remove `key` from the map, holding an exclusive lock for the duration.  Returns the value
+    /// it was associated with, if any.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.exclusive().remove(key)
+    }
+
+    /// How many entries are currently in the map?
+    pub fn len(&self) -> usize {
+        self.share().len()
+    }
+
+    /// Is the map empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy every entry out of the map, holding a shared lock for the duration
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.share().iter().map(|(k, v)| (*k, *v)).collect()
+    }
+
+    fn entry_size() -> usize {
+        std::mem::size_of::<Entry<K, V>>()
+    }
+}
+
+impl<K, V> PgSharedHashMap<K, V> {
+    fn inner(&self) -> &PgSharedHashMapInner {
+        self.inner.get().expect("PgSharedHashMap was not initialized with `pg_shmem_init!()`")
+    }
+}
+
+impl<K, V> PgSharedMemoryInitialization for PgSharedHashMap<K, V>
+where
+    K: PGXSharedMemory + Copy + Hash + Eq,
+    V: PGXSharedMemory + Copy,
+{
+    fn pg_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.get_name()).expect("CString::new failed");
+            pg_sys::RequestAddinShmemSpace(pg_sys::hash_estimate_size(
+                self.max_entries as _,
+                Self::entry_size(),
+            ));
+            pg_sys::RequestNamedLWLockTranche(name.as_ptr(), 1);
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.get_name()).expect("CString::new failed");
+            let mut hashctl = pg_sys::HASHCTL {
+                keysize: std::mem::size_of::<K>(),
+                entrysize: Self::entry_size(),
+                hash: Some(htab_hash::<K>),
+                match_: Some(htab_match::<K>),
+                ..Default::default()
+            };
+
+            let addin_shmem_init_lock: *mut pg_sys::LWLock =
+                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+            pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+
+            let htab = pg_sys::ShmemInitHash(
+                name.as_ptr(),
+                self.max_entries as _,
+                self.max_entries as _,
+                &mut hashctl,
+                (pg_sys::HASH_ELEM | pg_sys::HASH_FUNCTION | pg_sys::HASH_COMPARE) as _,
+            );
+            let lock = &mut (*pg_sys::GetNamedLWLockTranche(name.as_ptr())).lock as *mut _;
+
+            self.inner
+                .set(PgSharedHashMapInner { htab, lock })
+                .ok()
+                .expect("PgSharedHashMap was already initialized");
+            pg_sys::LWLockRelease(addin_shmem_init_lock);
+        }
+    }
+}
+
+/// Shared (read-only) access to a [`PgSharedHashMap`].  The lock is released on drop.
+pub struct PgSharedHashMapShareGuard<'a, K, V> {
+    map: &'a PgSharedHashMap<K, V>,
+}
+
+/// Exclusive (read-write) access to a [`PgSharedHashMap`].  The lock is released on drop.
+pub struct PgSharedHashMapExclusiveGuard<'a, K, V> {
+    map: &'a PgSharedHashMap<K, V>,
+}
+
+/// Look up `key`, returning a pointer to its entry in shared memory
+///
+/// SAFETY: the caller must hold the map's lock and `htab` must be valid
+unsafe fn htab_find<K, V>(htab: *mut pg_sys::HTAB, key: &K) -> *mut Entry<K, V> {
+    pg_sys::hash_search(
+        htab,
+        (key as *const K).cast(),
+        pg_sys::HASHACTION_HASH_FIND,
+        std::ptr::null_mut(),
+    )
+    .cast()
+}
+
+macro_rules! impl_read_methods {
+    ($guard:ident) => {
+        impl<'a, K, V> $guard<'a, K, V>
+        where
+            K: PGXSharedMemory + Copy + Hash + Eq,
+            V: PGXSharedMemory + Copy,
+        {
+            /// Copy out the value associated with `key`
+            pub fn get(&self, key: &K) -> Option<V> {
+                unsafe {
+                    htab_find::<K, V>(self.map.inner().htab, key).as_ref().map(|entry| entry.value)
+                }
+            }
+
+            /// Does the map contain `key`?
+            pub fn contains_key(&self, key: &K) -> bool {
+                unsafe { !htab_find::<K, V>(self.map.inner().htab, key).is_null() }
+            }
+
+            /// How many entries are currently in the map?
+            pub fn len(&self) -> usize {
+                unsafe { pg_sys::hash_get_num_entries(self.map.inner().htab) as usize }
+            }
+
+            /// Is the map empty?
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Iterate over every entry in the map, in no particular order
+            pub fn iter(&self) -> PgSharedHashMapIter<'_, K, V> {
+                PgSharedHashMapIter::new(self.map.inner().htab)
+            }
+        }
+
+        impl<K, V> Drop for $guard<'_, K, V> {
+            fn drop(&mut self) {
+                // SAFETY: self.lock is always valid
+                unsafe { release_unless_elog_unwinding(self.map.inner().lock) }
+            }
+        }
+    };
+}
+
+impl_read_methods!(PgSharedHashMapShareGuard);
+impl_read_methods!(PgSharedHashMapExclusiveGuard);
+
+impl<'a, K, V> PgSharedHashMapExclusiveGuard<'a, K, V>
+where
+    K: PGXSharedMemory + Copy + Hash + Eq,
+    V: PGXSharedMemory + Copy,
+{
+    /// Get a mutable reference to the value associated with `key`
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        unsafe {
+            htab_find::<K, V>(self.map.inner().htab, key).as_mut().map(|entry| &mut entry.value)
+        }
+    }
+
+    /// Insert or replace the value for `key`, returning the previous value, if any.
+    ///
+    /// Returns [`PgSharedHashMapError::HashMapFull`] if `key` is new and the map is already at
+    /// capacity.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, PgSharedHashMapError> {
+        if let Some(existing) = self.get_mut(&key) {
+            return Ok(Some(std::mem::replace(existing, value)));
+        }
+
+        if self.len() >= self.map.capacity() {
+            return Err(PgSharedHashMapError::HashMapFull);
+        }
+
+        unsafe {
+            let mut found = false;
+            let entry: *mut Entry<K, V> = pg_sys::hash_search(
+                self.map.inner().htab,
+                (&key as *const K).cast(),
+                pg_sys::HASHACTION_HASH_ENTER_NULL,
+                &mut found,
+            )
+            .cast();
+
+            match entry.as_mut() {
+                // shared memory is exhausted
+                None => Err(PgSharedHashMapError::HashMapFull),
+                Some(entry) => {
+                    // Postgres has already copied the key into the entry
+                    std::ptr::write(&mut entry.value, value);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Remove `key` from the map, returning the value it was associated with, if any
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        unsafe {
+            let entry: *mut Entry<K, V> = pg_sys::hash_search(
+                self.map.inner().htab,
+                (key as *const K).cast(),
+                pg_sys::HASHACTION_HASH_REMOVE,
+                std::ptr::null_mut(),
+            )
+            .cast();
+
+            // a removed entry's memory remains valid until the next insert, which can't happen
+            // while we hold the exclusive lock
+            entry.as_ref().map(|entry| entry.value)
+        }
+    }
+
+    /// Remove every entry for which `f` returns `false`
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        let mut status = pg_sys::HASH_SEQ_STATUS::default();
+        unsafe {
+            pg_sys::hash_seq_init(&mut status, self.map.inner().htab);
+            loop {
+                let entry = pg_sys::hash_seq_search(&mut status) as *mut Entry<K, V>;
+                let Some(entry) = entry.as_mut() else { break };
+
+                if !f(&entry.key, &mut entry.value) {
+                    // removing the entry just returned by hash_seq_search() is explicitly allowed
+                    pg_sys::hash_search(
+                        self.map.inner().htab,
+                        (&entry.key as *const K).cast(),
+                        pg_sys::HASHACTION_HASH_REMOVE,
+                        std::ptr::null_mut(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Remove every entry from the map
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false)
+    }
+}
+
+/// An iterator over the entries of a [`PgSharedHashMap`], borrowed from one of its lock guards
+pub struct PgSharedHashMapIter<'a, K, V> {
+    status: pg_sys::HASH_SEQ_STATUS,
+    done: bool,
+    __marker: PhantomData<&'a (K, V)>,
+}
+
+impl<'a, K, V> PgSharedHashMapIter<'a, K, V> {
+    fn new(htab: *mut pg_sys::HTAB) -> Self {
+        let mut status = pg_sys::HASH_SEQ_STATUS::default();
+        unsafe {
+            pg_sys::hash_seq_init(&mut status, htab);
+        }
+        PgSharedHashMapIter { status, done: false, __marker: PhantomData }
+    }
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for PgSharedHashMapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let entry = unsafe { pg_sys::hash_seq_search(&mut self.status) as *const Entry<K, V> };
+        match unsafe { entry.as_ref() } {
+            Some(entry) => Some((&entry.key, &entry.value)),
+            None => {
+                // hash_seq_search() terminates the scan itself once it's exhausted
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl<K, V> Drop for PgSharedHashMapIter<'_, K, V> {
+    fn drop(&mut self) {
+        if !self.done {
+            unsafe {
+                pg_sys::hash_seq_term(&mut self.status);
+            }
+        }
+    }
+}
+
+/// A growable hash map in Postgres dynamic shared memory, built on `dshash`
+///
+/// Unlike [`PgSharedHashMap`], a [`PgDsHashMap`] has no fixed capacity -- its entries are
+/// allocated from a dynamic shared memory area (DSA) that grows as needed.  The area and table
+/// are created lazily by the first backend that uses the map, and other backends attach to them
+/// on first use.  Locking is handled internally by `dshash`, per partition, so individual
+/// operations don't contend on a single lock.
+///
+/// Keys and values are copied into and out of shared memory, so both must be `Copy` and implement
+/// [`PGXSharedMemory`].  Keys are hashed and compared using their Rust [`Hash`] and [`Eq`]
+/// implementations.
+///
+/// `iter()` and `entries()` are only available on Postgres 15, the first version with
+/// `dshash_seq_init()`.  On earlier versions, keep the keys you'll need to visit somewhere else,
+/// such as in a [`PgSharedHashMap`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pgx::prelude::*;
+/// use pgx::{pg_shmem_init, PgDsHashMap, PgSharedMemoryInitialization};
+///
+/// static STATS: PgDsHashMap<u64, i64> = PgDsHashMap::new();
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(STATS);
+/// }
+///
+/// #[pg_extern]
+/// fn stats_get(fingerprint: i64) -> Option<i64> {
+///     STATS.get(&(fingerprint as u64))
+/// }
+/// ```
+pub struct PgDsHashMap<K, V> {
+    name: OnceCell<&'static str>,
+    control: OnceCell<PgDsHashMapControlPtr>,
+    attached: OnceCell<PgDsHashMapAttached>,
+    __marker: PhantomData<(K, V)>,
+}
+
+unsafe impl<K: Send, V: Send> Send for PgDsHashMap<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for PgDsHashMap<K, V> {}
+
+/// The fixed-size part of a [`PgDsHashMap`] that lives in the main shared memory segment and
+/// tells backends how to find the DSA area and table
+#[repr(C)]
+struct PgDsHashMapControl {
+    tranche_id: c_int,
+    area: pg_sys::dsa_handle,
+    table: pg_sys::dshash_table_handle,
+    created: bool,
+}
+
+struct PgDsHashMapControlPtr {
+    control: *mut PgDsHashMapControl,
+    lock: *mut pg_sys::LWLock,
+}
+
+/// This backend's attachment to the map's DSA area and table
+struct PgDsHashMapAttached {
+    table: *mut pg_sys::dshash_table,
+}
+
+impl<K, V> PgDsHashMap<K, V>
+where
+    K: PGXSharedMemory + Copy + Hash + Eq,
+    V: PGXSharedMemory + Copy,
+{
+    /// Create a new, unattached, map.  It is meant to be declared as a `static` and then passed to
+    /// `pg_shmem_init!()`
+    pub const fn new() -> Self {
+        PgDsHashMap {
+            name: OnceCell::new(),
+            control: OnceCell::new(),
+            attached: OnceCell::new(),
+            __marker: PhantomData,
+        }
+    }
+
+    /// Get the name of the shared memory segment (and `LWLock` tranche) backing this map
+    pub fn get_name(&self) -> &'static str {
+        self.name.get_or_init(|| Box::leak(Uuid::new_v4().to_string().into_boxed_str()))
+    }
+
+    /// Copy out the value associated with `key`
+    pub fn get(&self, key: &K) -> Option<V> {
+        let table = self.table();
+        unsafe {
+            let entry =
+                pg_sys::dshash_find(table, (key as *const K).cast(), false) as *mut Entry<K, V>;
+            let value = entry.as_ref().map(|entry| entry.value);
+            if !entry.is_null() {
+                pg_sys::dshash_release_lock(table, entry.cast());
+            }
+            value
+        }
+    }
+
+    /// Does the map contain `key`?
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Insert or replace the value for `key`, returning the previous value, if any
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.upsert(key, |previous| {
+            let previous = previous.copied();
+            (value, previous)
+        })
+    }
+
+    /// Atomically insert or update the value for `key`.  `f` is given the current value, if any,
+    /// and returns the value to store along with an arbitrary result that's passed back to the
+    /// caller.  The entry's partition is exclusively locked while `f` runs.
+    pub fn upsert<R, F: FnOnce(Option<&V>) -> (V, R)>(&self, key: K, f: F) -> R {
+        let table = self.table();
+        unsafe {
+            let mut found = false;
+            let entry = pg_sys::dshash_find_or_insert(table, (&key as *const K).cast(), &mut found)
+                as *mut Entry<K, V>;
+            assert!(!entry.is_null(), "dshash_find_or_insert() returned null");
+
+            let current = if found { Some(&(*entry).value) } else { None };
+            let (value, result) = match std::panic::catch_unwind(AssertUnwindSafe(|| f(current))) {
+                Ok(computed) => computed,
+                Err(e) => {
+                    if !found {
+                        // the new entry has no value, so it mustn't be left for other backends to
+                        // read.  Deleting it releases the partition lock, which expects interrupts
+                        // to still be held from taking it, but an `elog(ERROR)` resets that.  So
+                        // hold them for the release, and put the count back as we found it
+                        let holdoff = pg_sys::InterruptHoldoffCount;
+                        if holdoff == 0 {
+                            pg_sys::InterruptHoldoffCount += 1;
+                            pg_sys::dshash_delete_entry(table, entry.cast());
+                            pg_sys::InterruptHoldoffCount = holdoff;
+                        } else {
+                            pg_sys::dshash_delete_entry(table, entry.cast());
+                        }
+                    } else if pg_sys::InterruptHoldoffCount > 0 {
+                        // otherwise the lock is released by the transaction's abort
+                        pg_sys::dshash_release_lock(table, entry.cast());
+                    }
+                    std::panic::resume_unwind(e)
+                }
+            };
+            std::ptr::write(&mut (*entry).value, value);
+            pg_sys::dshash_release_lock(table, entry.cast());
+            result
+        }
+    }
+
+    /// Remove `key` from the map, returning the value it was associated with, if any
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let table = self.table();
+        unsafe {
+            let entry =
+                pg_sys::dshash_find(table, (key as *const K).cast(), true) as *mut Entry<K, V>;
+            let value = entry.as_ref().map(|entry| entry.value);
+            if !entry.is_null() {
+                // also releases the lock
+                pg_sys::dshash_delete_entry(table, entry.cast());
+            }
+            value
+        }
+    }
+
+    /// Iterate over every entry in the map, in no particular order.  Each entry's partition is
+    /// share-locked while the iterator is positioned within it.
+    #[cfg(feature = "pg15")]
+    pub fn iter(&self) -> PgDsHashMapIter<'_, K, V> {
+        let mut status = pg_sys::dshash_seq_status::default();
+        unsafe {
+            pg_sys::dshash_seq_init(&mut status, self.table(), false);
+        }
+        PgDsHashMapIter { status, done: false, __marker: PhantomData }
+    }
+
+    /// Copy every entry out of the map
+    #[cfg(feature = "pg15")]
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.iter().collect()
+    }
+
+    fn params(tranche_id: c_int) -> pg_sys::dshash_parameters {
+        pg_sys::dshash_parameters {
+            key_size: std::mem::size_of::<K>(),
+            entry_size: std::mem::size_of::<Entry<K, V>>(),
+            compare_function: Some(dshash_compare::<K>),
+            hash_function: Some(dshash_hash::<K>),
+            tranche_id,
+        }
+    }
+
+    /// Attach this backend to the map's table, creating it if we're the first to use it
+    fn table(&self) -> *mut pg_sys::dshash_table {
+        self.attached.get_or_init(|| unsafe { self.attach() }).table
+    }
+
+    unsafe fn attach(&self) -> PgDsHashMapAttached {
+        let PgDsHashMapControlPtr { control, lock } =
+            self.control.get().expect("PgDsHashMap was not initialized with `pg_shmem_init!()`");
+        let control = &mut **control;
+
+        // the area and table must outlive the current memory context and resource owner
+        PgMemoryContexts::TopMemoryContext.switch_to(|_| {
+            pg_sys::LWLockAcquire(*lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+
+            let name = PgMemoryContexts::TopMemoryContext.pstrdup(self.get_name());
+            pg_sys::LWLockRegisterTranche(control.tranche_id, name);
+
+            let params = Self::params(control.tranche_id);
+            let table = if control.created {
+                let area = pg_sys::dsa_attach(control.area);
+                pg_sys::dsa_pin_mapping(area);
+                pg_sys::dshash_attach(area, &params, control.table, std::ptr::null_mut())
+            } else {
+                let area = pg_sys::dsa_create(control.tranche_id);
+                pg_sys::dsa_pin(area);
+                pg_sys::dsa_pin_mapping(area);
+                let table = pg_sys::dshash_create(area, &params, std::ptr::null_mut());
+                control.area = pg_sys::dsa_get_handle(area);
+                control.table = pg_sys::dshash_get_hash_table_handle(table);
+                control.created = true;
+                table
+            };
+
+            pg_sys::LWLockRelease(*lock);
+            PgDsHashMapAttached { table }
+        })
+    }
+}
+
+impl<K, V> Default for PgDsHashMap<K, V>
+where
+    K: PGXSharedMemory + Copy + Hash + Eq,
+    V: PGXSharedMemory + Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> PgSharedMemoryInitialization for PgDsHashMap<K, V>
+where
+    K: PGXSharedMemory + Copy + Hash + Eq,
+    V: PGXSharedMemory + Copy,
+{
+    fn pg_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.get_name()).expect("CString::new failed");
+            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<PgDsHashMapControl>());
+            pg_sys::RequestNamedLWLockTranche(name.as_ptr(), 1);
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.get_name()).expect("CString::new failed");
+            let addin_shmem_init_lock: *mut pg_sys::LWLock =
+                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+            pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+
+            let mut found = false;
+            let control = pg_sys::ShmemInitStruct(
+                name.as_ptr(),
+                std::mem::size_of::<PgDsHashMapControl>(),
+                &mut found,
+            ) as *mut PgDsHashMapControl;
+            if !found {
+                std::ptr::write(
+                    control,
+                    PgDsHashMapControl {
+                        tranche_id: pg_sys::LWLockNewTrancheId(),
+                        area: 0,
+                        table: 0,
+                        created: false,
+                    },
+                );
+            }
+            let lock = &mut (*pg_sys::GetNamedLWLockTranche(name.as_ptr())).lock as *mut _;
+
+            self.control
+                .set(PgDsHashMapControlPtr { control, lock })
+                .ok()
+                .expect("PgDsHashMap was already initialized");
+            pg_sys::LWLockRelease(addin_shmem_init_lock);
+        }
+    }
+}
+
+/// An iterator over copies of the entries of a [`PgDsHashMap`]
+#[cfg(feature = "pg15")]
+pub struct PgDsHashMapIter<'a, K, V> {
+    status: pg_sys::dshash_seq_status,
+    done: bool,
+    __marker: PhantomData<&'a PgDsHashMap<K, V>>,
+}
+
+#[cfg(feature = "pg15")]
+impl<K: Copy, V: Copy> Iterator for PgDsHashMapIter<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let entry = unsafe { pg_sys::dshash_seq_next(&mut self.status) as *const Entry<K, V> };
+        match unsafe { entry.as_ref() } {
+            Some(entry) => Some((entry.key, entry.value)),
+            None => {
+                self.done = true;
+                unsafe { pg_sys::dshash_seq_term(&mut self.status) };
+                None
+            }
+        }
+    }
+}
+
+#[cfg(feature = "pg15")]
+impl<K, V> Drop for PgDsHashMapIter<'_, K, V> {
+    fn drop(&mut self) {
+        // like `release_unless_elog_unwinding()`, partition locks are released by the abort
+        // if we're unwinding from an `elog(ERROR)`
+        if !self.done && unsafe { pg_sys::InterruptHoldoffCount > 0 } {
+            unsafe { pg_sys::dshash_seq_term(&mut self.status) };
+        }
+    }
+}
@@ -0,0 +1,165 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Provides a safe wrapper around Postgres' MVCC `pg_sys::Snapshot`s
+use crate::pg_sys::{self, AsPgCStr};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::ptr::NonNull;
+
+/// A registered MVCC snapshot, which decides which rows are visible to scans and queries
+///
+/// A `PgSnapshot` is registered with `RegisterSnapshot()` when it's created and unregistered when
+/// it's dropped, so the rows it can see won't be vacuumed away while it exists.  Registrations
+/// belong to the current resource owner, so a `PgSnapshot` shouldn't outlive the transaction it
+/// was taken in.
+///
+/// ## Examples
+///
+/// Count the rows that are visible now, even if the current statement's snapshot is older:
+///
+/// ```rust,no_run
+/// use pgx::prelude::*;
+/// use pgx::PgSnapshot;
+///
+/// let latest = PgSnapshot::latest();
+/// let count = latest.with_active(|| Spi::get_one::<i64>("SELECT count(*) FROM accounts"));
+/// ```
+pub struct PgSnapshot {
+    snapshot: NonNull<pg_sys::SnapshotData>,
+    // registrations belong to this backend's resource owners
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl PgSnapshot {
+    /// Register `snapshot`, which Postgres copies first if it's one of its static snapshots
+    ///
+    /// ## Safety
+    ///
+    /// `snapshot` must be a valid MVCC snapshot
+    pub unsafe fn from_pg(snapshot: pg_sys::Snapshot) -> Self {
+        PgSnapshot {
+            snapshot: NonNull::new(pg_sys::RegisterSnapshot(snapshot))
+                .expect("RegisterSnapshot returned NULL"),
+            _not_send: std::marker::PhantomData,
+        }
+    }
+
+    /// The snapshot at the top of the active snapshot stack, which the currently running
+    /// statement uses, or `None` if there's no active snapshot
+    pub fn active() -> Option<Self> {
+        unsafe {
+            if pg_sys::ActiveSnapshotSet() {
+                Some(PgSnapshot::from_pg(pg_sys::GetActiveSnapshot()))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// The snapshot for a new statement in the current transaction
+    ///
+    /// In `READ COMMITTED` transactions, this is a new snapshot.  In `REPEATABLE READ` and
+    /// `SERIALIZABLE` transactions, this is the snapshot taken by the transaction's first
+    /// statement.
+    pub fn transaction() -> Self {
+        unsafe { PgSnapshot::from_pg(pg_sys::GetTransactionSnapshot()) }
+    }
+
+    /// A new snapshot, regardless of the transaction's isolation level
+    ///
+    /// This can see rows that were committed after the transaction snapshot was taken, which
+    /// is what's wanted for things like checking foreign keys.
+    pub fn latest() -> Self {
+        unsafe { PgSnapshot::from_pg(pg_sys::GetLatestSnapshot()) }
+    }
+
+    /// Import a snapshot that another transaction exported with [`PgSnapshot::export()`], making
+    /// it this transaction's snapshot, and return it
+    ///
+    /// This is the same as `SET TRANSACTION SNAPSHOT`, so it has the same restrictions:  the
+    /// current transaction must be `REPEATABLE READ` or `SERIALIZABLE` and can't have run any
+    /// queries yet.  It's meant for background workers that need to see the same rows as the
+    /// backend that started them.
+    pub fn import(id: &str) -> Self {
+        unsafe {
+            let id = id.as_pg_cstr();
+            pg_sys::ImportSnapshot(id);
+            pg_sys::pfree(id.cast());
+        }
+        PgSnapshot::transaction()
+    }
+
+    /// Export this snapshot so other transactions can [`PgSnapshot::import()`] it, returning the
+    /// identifier to import it by
+    ///
+    /// The snapshot can be imported until the current transaction ends.
+    pub fn export(&self) -> String {
+        unsafe {
+            let id = pg_sys::ExportSnapshot(self.as_ptr());
+            let exported = core::ffi::CStr::from_ptr(id).to_string_lossy().into_owned();
+            pg_sys::pfree(id.cast());
+            exported
+        }
+    }
+
+    /// Run `f` with this snapshot pushed onto the active snapshot stack
+    ///
+    /// Read-only SPI queries, which are all SPI queries until something is modified through SPI
+    /// in the current transaction, see the rows visible to the active snapshot.
+    pub fn with_active<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        unsafe {
+            pg_sys::PushActiveSnapshot(self.as_ptr());
+        }
+        let result = catch_unwind(AssertUnwindSafe(f));
+        unsafe {
+            // after a Postgres ERROR, SPI may have left its own snapshots on top of ours.  Aborting
+            // the transaction pops those, along with ours
+            if pg_sys::ActiveSnapshotSet() && pg_sys::GetActiveSnapshot() == self.as_ptr() {
+                pg_sys::PopActiveSnapshot();
+            }
+        }
+        result.unwrap_or_else(|e| resume_unwind(e))
+    }
+
+    /// The lowest transaction id that was still running when the snapshot was taken.  Everything
+    /// older is visible, or aborted.
+    pub fn xmin(&self) -> pg_sys::TransactionId {
+        unsafe { self.snapshot.as_ref() }.xmin
+    }
+
+    /// One past the highest transaction id that had finished when the snapshot was taken.
+    /// Everything this new, or newer, is invisible.
+    pub fn xmax(&self) -> pg_sys::TransactionId {
+        unsafe { self.snapshot.as_ref() }.xmax
+    }
+
+    /// The command id within the current transaction the snapshot was taken at.  Changes the
+    /// transaction made with this command id, or later ones, are invisible.
+    pub fn command_id(&self) -> pg_sys::CommandId {
+        unsafe { self.snapshot.as_ref() }.curcid
+    }
+
+    /// The wrapped `pg_sys::Snapshot`, which stays valid for as long as this `PgSnapshot` exists
+    pub fn as_ptr(&self) -> pg_sys::Snapshot {
+        self.snapshot.as_ptr()
+    }
+}
+
+impl Clone for PgSnapshot {
+    /// Registers the same snapshot again
+    fn clone(&self) -> Self {
+        unsafe { PgSnapshot::from_pg(self.as_ptr()) }
+    }
+}
+
+impl Drop for PgSnapshot {
+    fn drop(&mut self) {
+        unsafe { pg_sys::UnregisterSnapshot(self.as_ptr()) }
+    }
+}
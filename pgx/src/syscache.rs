@@ -0,0 +1,749 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Safe, typed wrappers around rows from Postgres' system catalog caches
+//!
+//! Each wrapper holds a pin on its syscache entry, acquired with `SearchSysCache1()` or
+//! `SearchSysCache2()`, which is released with `ReleaseSysCache()` when the wrapper is dropped.
+//! Syscache pins belong to the current resource owner, so wrappers shouldn't outlive the
+//! transaction they were looked up in.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use pgx::prelude::*;
+//! use pgx::syscache::{PgNamespace, PgProc};
+//!
+//! # let fn_oid = pg_sys::InvalidOid;
+//! let proc = PgProc::lookup(fn_oid).expect("no such function");
+//! let namespace = PgNamespace::lookup(proc.namespace_oid()).expect("no such namespace");
+//! info!("{}.{} takes {} arguments", namespace.name(), proc.name(), proc.arg_types().len());
+//! ```
+use crate::pg_sys::GETSTRUCT;
+use crate::{name_data_to_str, pg_sys, FromDatum};
+use pgx_pg_sys::AsPgCStr;
+use std::os::raw::c_char;
+use std::ptr::NonNull;
+
+/// A pinned syscache tuple, released when dropped
+struct SysCacheTuple {
+    cache_id: pg_sys::SysCacheIdentifier,
+    tuple: NonNull<pg_sys::HeapTupleData>,
+}
+
+impl SysCacheTuple {
+    fn search1(cache_id: pg_sys::SysCacheIdentifier, key1: pg_sys::Datum) -> Option<Self> {
+        let tuple = unsafe { pg_sys::SearchSysCache1(cache_id as _, key1) };
+        NonNull::new(tuple).map(|tuple| SysCacheTuple { cache_id, tuple })
+    }
+
+    fn search2(
+        cache_id: pg_sys::SysCacheIdentifier,
+        key1: pg_sys::Datum,
+        key2: pg_sys::Datum,
+    ) -> Option<Self> {
+        let tuple = unsafe { pg_sys::SearchSysCache2(cache_id as _, key1, key2) };
+        NonNull::new(tuple).map(|tuple| SysCacheTuple { cache_id, tuple })
+    }
+
+    /// The tuple's fixed-width fields, as the catalog's `FormData_pg_*` struct
+    ///
+    /// ## Safety
+    ///
+    /// `T` must be the `FormData_pg_*` struct of the catalog this tuple was looked up in
+    unsafe fn form<T>(&self) -> &T {
+        &*GETSTRUCT(self.tuple.as_ptr()).cast::<T>()
+    }
+
+    /// Read a variable-width or nullable column, which isn't part of the `FormData_pg_*` struct
+    ///
+    /// ## Safety
+    ///
+    /// `attnum` must be a column of the catalog this tuple was looked up in, of a type `T` can be
+    /// read from
+    unsafe fn attr<T: FromDatum>(&self, attnum: u32) -> Option<T> {
+        let mut is_null = false;
+        let datum = pg_sys::SysCacheGetAttr(
+            self.cache_id as _,
+            self.tuple.as_ptr(),
+            attnum as pg_sys::AttrNumber,
+            &mut is_null,
+        );
+        T::from_datum(datum, is_null)
+    }
+}
+
+impl Drop for SysCacheTuple {
+    fn drop(&mut self) {
+        unsafe { pg_sys::ReleaseSysCache(self.tuple.as_ptr()) }
+    }
+}
+
+#[inline]
+fn valid_oid(oid: pg_sys::Oid) -> Option<pg_sys::Oid> {
+    (oid != pg_sys::InvalidOid).then_some(oid)
+}
+
+#[inline]
+fn as_char(c: c_char) -> char {
+    c as u8 as char
+}
+
+/// The kind of a function, from `pg_proc.prokind`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProcKind {
+    Function,
+    Procedure,
+    Aggregate,
+    Window,
+}
+
+/// The volatility of a function, from `pg_proc.provolatile`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProcVolatility {
+    Immutable,
+    Stable,
+    Volatile,
+}
+
+/// Whether a function can run in parallel mode, from `pg_proc.proparallel`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProcParallel {
+    Safe,
+    Restricted,
+    Unsafe,
+}
+
+/// A row of `pg_proc`, describing a function, procedure, or aggregate
+pub struct PgProc {
+    oid: pg_sys::Oid,
+    tuple: SysCacheTuple,
+}
+
+impl PgProc {
+    /// Look up a function by its oid, with the `PROCOID` syscache
+    pub fn lookup(oid: pg_sys::Oid) -> Option<Self> {
+        SysCacheTuple::search1(pg_sys::SysCacheIdentifier_PROCOID, oid.into())
+            .map(|tuple| PgProc { oid, tuple })
+    }
+
+    fn form(&self) -> &pg_sys::FormData_pg_proc {
+        unsafe { self.tuple.form() }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.oid
+    }
+
+    pub fn name(&self) -> &str {
+        name_data_to_str(&self.form().proname)
+    }
+
+    pub fn namespace_oid(&self) -> pg_sys::Oid {
+        self.form().pronamespace
+    }
+
+    pub fn owner(&self) -> pg_sys::Oid {
+        self.form().proowner
+    }
+
+    /// The oid of the function's implementation language, in `pg_language`
+    pub fn language_oid(&self) -> pg_sys::Oid {
+        self.form().prolang
+    }
+
+    /// The estimated execution cost, in units of `cpu_operator_cost`
+    pub fn cost(&self) -> f32 {
+        self.form().procost
+    }
+
+    /// The estimated number of result rows, for set-returning functions
+    pub fn rows(&self) -> Option<f32> {
+        let rows = self.form().prorows;
+        (rows != 0.0).then_some(rows)
+    }
+
+    /// The element type of the variadic argument, if the function has one
+    pub fn variadic_type(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().provariadic)
+    }
+
+    pub fn kind(&self) -> ProcKind {
+        match self.form().prokind as u8 {
+            b'p' => ProcKind::Procedure,
+            b'a' => ProcKind::Aggregate,
+            b'w' => ProcKind::Window,
+            _ => ProcKind::Function,
+        }
+    }
+
+    pub fn volatility(&self) -> ProcVolatility {
+        match self.form().provolatile as u8 {
+            b'i' => ProcVolatility::Immutable,
+            b's' => ProcVolatility::Stable,
+            _ => ProcVolatility::Volatile,
+        }
+    }
+
+    pub fn parallel(&self) -> ProcParallel {
+        match self.form().proparallel as u8 {
+            b's' => ProcParallel::Safe,
+            b'r' => ProcParallel::Restricted,
+            _ => ProcParallel::Unsafe,
+        }
+    }
+
+    /// Does the function run with the privileges of its owner?
+    pub fn is_security_definer(&self) -> bool {
+        self.form().prosecdef
+    }
+
+    pub fn is_leakproof(&self) -> bool {
+        self.form().proleakproof
+    }
+
+    /// Does the function return NULL when any argument is NULL, without being called?
+    pub fn is_strict(&self) -> bool {
+        self.form().proisstrict
+    }
+
+    pub fn returns_set(&self) -> bool {
+        self.form().proretset
+    }
+
+    /// The number of arguments with default values
+    pub fn num_arg_defaults(&self) -> usize {
+        self.form().pronargdefaults as usize
+    }
+
+    pub fn return_type(&self) -> pg_sys::Oid {
+        self.form().prorettype
+    }
+
+    /// The types of the input arguments, not including `OUT` arguments
+    pub fn arg_types(&self) -> &[pg_sys::Oid] {
+        let argtypes = &self.form().proargtypes;
+        unsafe { argtypes.values.as_slice(argtypes.dim1 as usize) }
+    }
+
+    /// The names of all arguments, including `OUT` arguments, if any are named
+    ///
+    /// Unnamed arguments have an empty name.
+    pub fn arg_names(&self) -> Option<Vec<String>> {
+        unsafe { self.tuple.attr(pg_sys::Anum_pg_proc_proargnames) }
+    }
+
+    /// The function's source code, or the name of its C symbol for `LANGUAGE c` functions
+    pub fn source(&self) -> Option<String> {
+        unsafe { self.tuple.attr(pg_sys::Anum_pg_proc_prosrc) }
+    }
+}
+
+/// The kind of a type, from `pg_type.typtype`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TypeKind {
+    Base,
+    Composite,
+    Domain,
+    Enum,
+    Pseudo,
+    Range,
+    Multirange,
+}
+
+/// A row of `pg_type`, describing a data type
+pub struct PgType {
+    oid: pg_sys::Oid,
+    tuple: SysCacheTuple,
+}
+
+impl PgType {
+    /// Look up a type by its oid, with the `TYPEOID` syscache
+    pub fn lookup(oid: pg_sys::Oid) -> Option<Self> {
+        SysCacheTuple::search1(pg_sys::SysCacheIdentifier_TYPEOID, oid.into())
+            .map(|tuple| PgType { oid, tuple })
+    }
+
+    fn form(&self) -> &pg_sys::FormData_pg_type {
+        unsafe { self.tuple.form() }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.oid
+    }
+
+    pub fn name(&self) -> &str {
+        name_data_to_str(&self.form().typname)
+    }
+
+    pub fn namespace_oid(&self) -> pg_sys::Oid {
+        self.form().typnamespace
+    }
+
+    pub fn owner(&self) -> pg_sys::Oid {
+        self.form().typowner
+    }
+
+    /// The size of the type in bytes, or `-1` for varlena types and `-2` for cstrings
+    pub fn typlen(&self) -> i16 {
+        self.form().typlen
+    }
+
+    /// Are values of this type passed by value, rather than by reference?
+    pub fn is_by_value(&self) -> bool {
+        self.form().typbyval
+    }
+
+    pub fn kind(&self) -> TypeKind {
+        match self.form().typtype as u8 {
+            b'c' => TypeKind::Composite,
+            b'd' => TypeKind::Domain,
+            b'e' => TypeKind::Enum,
+            b'p' => TypeKind::Pseudo,
+            b'r' => TypeKind::Range,
+            b'm' => TypeKind::Multirange,
+            _ => TypeKind::Base,
+        }
+    }
+
+    /// The type's category, such as `'N'` for numeric types or `'S'` for string types
+    pub fn category(&self) -> char {
+        as_char(self.form().typcategory)
+    }
+
+    /// Is this the preferred type within its category?
+    pub fn is_preferred(&self) -> bool {
+        self.form().typispreferred
+    }
+
+    /// Is the type defined, rather than a placeholder for a not-yet-defined type?
+    pub fn is_defined(&self) -> bool {
+        self.form().typisdefined
+    }
+
+    /// The character separating values of this type in array input
+    pub fn delimiter(&self) -> char {
+        as_char(self.form().typdelim)
+    }
+
+    /// The `pg_class` entry of a composite type
+    pub fn relation_oid(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().typrelid)
+    }
+
+    /// The element type, if this is an array type
+    pub fn element_type(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().typelem)
+    }
+
+    /// The array type with this type as its element type, if there is one
+    pub fn array_type(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().typarray)
+    }
+
+    pub fn input_function(&self) -> pg_sys::Oid {
+        self.form().typinput
+    }
+
+    pub fn output_function(&self) -> pg_sys::Oid {
+        self.form().typoutput
+    }
+
+    pub fn receive_function(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().typreceive)
+    }
+
+    pub fn send_function(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().typsend)
+    }
+
+    /// The storage alignment: `'c'`, `'s'`, `'i'`, or `'d'`
+    pub fn alignment(&self) -> char {
+        as_char(self.form().typalign)
+    }
+
+    /// The default TOAST strategy: `'p'`, `'e'`, `'m'`, or `'x'`
+    pub fn storage(&self) -> char {
+        as_char(self.form().typstorage)
+    }
+
+    /// Does this domain have a `NOT NULL` constraint?
+    pub fn is_not_null(&self) -> bool {
+        self.form().typnotnull
+    }
+
+    /// The type this domain is based on
+    pub fn base_type(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().typbasetype)
+    }
+
+    /// The typmod this domain applies to its base type, or `-1`
+    pub fn typmod(&self) -> i32 {
+        self.form().typtypmod
+    }
+
+    /// The number of array dimensions of this domain, if it's over an array
+    pub fn ndims(&self) -> i32 {
+        self.form().typndims
+    }
+
+    pub fn collation(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().typcollation)
+    }
+}
+
+/// A row of `pg_class`, describing a table, index, sequence, view, or other relation
+///
+/// Unlike [`PgRelation`](crate::PgRelation), looking up a `PgClass` doesn't open the relation,
+/// so no lock on it is needed.
+pub struct PgClass {
+    oid: pg_sys::Oid,
+    tuple: SysCacheTuple,
+}
+
+impl PgClass {
+    /// Look up a relation by its oid, with the `RELOID` syscache
+    pub fn lookup(oid: pg_sys::Oid) -> Option<Self> {
+        SysCacheTuple::search1(pg_sys::SysCacheIdentifier_RELOID, oid.into())
+            .map(|tuple| PgClass { oid, tuple })
+    }
+
+    fn form(&self) -> &pg_sys::FormData_pg_class {
+        unsafe { self.tuple.form() }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.oid
+    }
+
+    pub fn name(&self) -> &str {
+        name_data_to_str(&self.form().relname)
+    }
+
+    pub fn namespace_oid(&self) -> pg_sys::Oid {
+        self.form().relnamespace
+    }
+
+    /// The composite type describing the relation's rows, if it has one
+    pub fn row_type(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().reltype)
+    }
+
+    pub fn owner(&self) -> pg_sys::Oid {
+        self.form().relowner
+    }
+
+    /// The access method of an index or table, if the relation has one
+    pub fn access_method(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().relam)
+    }
+
+    pub fn tablespace(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().reltablespace)
+    }
+
+    /// The size of the relation in pages, as of the last `VACUUM` or `ANALYZE`
+    pub fn pages(&self) -> i32 {
+        self.form().relpages
+    }
+
+    /// The number of live rows, as of the last `VACUUM` or `ANALYZE`, or `None` if the relation
+    /// has never been vacuumed or analyzed.  Before Postgres 14 that's indistinguishable from
+    /// being empty, and is `Some(0.0)`.
+    pub fn reltuples(&self) -> Option<f32> {
+        let reltuples = self.form().reltuples;
+        (reltuples >= 0.0).then_some(reltuples)
+    }
+
+    pub fn toast_relation(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().reltoastrelid)
+    }
+
+    pub fn has_index(&self) -> bool {
+        self.form().relhasindex
+    }
+
+    /// Is the relation shared across all databases in the cluster?
+    pub fn is_shared(&self) -> bool {
+        self.form().relisshared
+    }
+
+    /// `'p'` for permanent relations, `'u'` for unlogged, or `'t'` for temporary
+    pub fn persistence(&self) -> char {
+        as_char(self.form().relpersistence)
+    }
+
+    /// The `RELKIND_*` of the relation, such as `'r'` for ordinary tables
+    pub fn kind(&self) -> char {
+        as_char(self.form().relkind)
+    }
+
+    /// The number of user columns
+    pub fn natts(&self) -> usize {
+        self.form().relnatts as usize
+    }
+
+    pub fn is_partition(&self) -> bool {
+        self.form().relispartition
+    }
+
+    pub fn is_table(&self) -> bool {
+        self.form().relkind == pg_sys::RELKIND_RELATION as c_char
+    }
+
+    pub fn is_index(&self) -> bool {
+        self.form().relkind == pg_sys::RELKIND_INDEX as c_char
+    }
+
+    pub fn is_view(&self) -> bool {
+        self.form().relkind == pg_sys::RELKIND_VIEW as c_char
+    }
+
+    pub fn is_matview(&self) -> bool {
+        self.form().relkind == pg_sys::RELKIND_MATVIEW as c_char
+    }
+
+    pub fn is_sequence(&self) -> bool {
+        self.form().relkind == pg_sys::RELKIND_SEQUENCE as c_char
+    }
+
+    /// Look up one of this relation's columns by its attribute number, starting at 1
+    pub fn attribute(&self, attnum: i16) -> Option<PgAttribute> {
+        PgAttribute::lookup(self.oid, attnum)
+    }
+}
+
+/// A row of `pg_attribute`, describing a column of a relation
+pub struct PgAttribute {
+    tuple: SysCacheTuple,
+}
+
+impl PgAttribute {
+    /// Look up a relation's column by its attribute number, with the `ATTNUM` syscache
+    ///
+    /// User columns are numbered from 1.  System columns have negative numbers.
+    pub fn lookup(relid: pg_sys::Oid, attnum: i16) -> Option<Self> {
+        SysCacheTuple::search2(pg_sys::SysCacheIdentifier_ATTNUM, relid.into(), attnum.into())
+            .map(|tuple| PgAttribute { tuple })
+    }
+
+    fn form(&self) -> &pg_sys::FormData_pg_attribute {
+        unsafe { self.tuple.form() }
+    }
+
+    /// The relation this column belongs to
+    pub fn relation_oid(&self) -> pg_sys::Oid {
+        self.form().attrelid
+    }
+
+    pub fn name(&self) -> &str {
+        name_data_to_str(&self.form().attname)
+    }
+
+    pub fn type_oid(&self) -> pg_sys::Oid {
+        self.form().atttypid
+    }
+
+    /// A copy of the column type's `typlen`
+    pub fn attlen(&self) -> i16 {
+        self.form().attlen
+    }
+
+    pub fn num(&self) -> i16 {
+        self.form().attnum
+    }
+
+    pub fn ndims(&self) -> i32 {
+        self.form().attndims
+    }
+
+    pub fn typmod(&self) -> i32 {
+        self.form().atttypmod
+    }
+
+    /// A copy of the column type's `typbyval`
+    pub fn is_by_value(&self) -> bool {
+        self.form().attbyval
+    }
+
+    /// A copy of the column type's `typalign`
+    pub fn alignment(&self) -> char {
+        as_char(self.form().attalign)
+    }
+
+    /// The column's TOAST strategy: `'p'`, `'e'`, `'m'`, or `'x'`
+    pub fn storage(&self) -> char {
+        as_char(self.form().attstorage)
+    }
+
+    pub fn is_not_null(&self) -> bool {
+        self.form().attnotnull
+    }
+
+    pub fn has_default(&self) -> bool {
+        self.form().atthasdef
+    }
+
+    /// `'a'` for `GENERATED ALWAYS AS IDENTITY`, `'d'` for `GENERATED BY DEFAULT AS IDENTITY`
+    pub fn identity(&self) -> Option<char> {
+        let identity = self.form().attidentity;
+        (identity != 0).then(|| as_char(identity))
+    }
+
+    /// `'s'` for stored generated columns
+    #[cfg(not(feature = "pg11"))]
+    pub fn generated(&self) -> Option<char> {
+        let generated = self.form().attgenerated;
+        (generated != 0).then(|| as_char(generated))
+    }
+
+    pub fn is_dropped(&self) -> bool {
+        self.form().attisdropped
+    }
+
+    /// Is the column defined locally, rather than only inherited?
+    pub fn is_local(&self) -> bool {
+        self.form().attislocal
+    }
+
+    /// The number of direct ancestors this column is inherited from
+    pub fn inherit_count(&self) -> i32 {
+        self.form().attinhcount
+    }
+
+    pub fn collation(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().attcollation)
+    }
+}
+
+/// A row of `pg_namespace`, describing a schema
+pub struct PgNamespace {
+    oid: pg_sys::Oid,
+    tuple: SysCacheTuple,
+}
+
+impl PgNamespace {
+    /// Look up a schema by its oid, with the `NAMESPACEOID` syscache
+    pub fn lookup(oid: pg_sys::Oid) -> Option<Self> {
+        SysCacheTuple::search1(pg_sys::SysCacheIdentifier_NAMESPACEOID, oid.into())
+            .map(|tuple| PgNamespace { oid, tuple })
+    }
+
+    /// Look up a schema by its name
+    pub fn lookup_by_name(name: &str) -> Option<Self> {
+        let name = name.as_pg_cstr();
+        let oid = unsafe {
+            let oid = pg_sys::get_namespace_oid(name, true);
+            pg_sys::pfree(name.cast());
+            oid
+        };
+        valid_oid(oid).and_then(PgNamespace::lookup)
+    }
+
+    fn form(&self) -> &pg_sys::FormData_pg_namespace {
+        unsafe { self.tuple.form() }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.oid
+    }
+
+    pub fn name(&self) -> &str {
+        name_data_to_str(&self.form().nspname)
+    }
+
+    pub fn owner(&self) -> pg_sys::Oid {
+        self.form().nspowner
+    }
+}
+
+/// A row of `pg_operator`, describing an operator
+pub struct PgOperator {
+    oid: pg_sys::Oid,
+    tuple: SysCacheTuple,
+}
+
+impl PgOperator {
+    /// Look up an operator by its oid, with the `OPEROID` syscache
+    pub fn lookup(oid: pg_sys::Oid) -> Option<Self> {
+        SysCacheTuple::search1(pg_sys::SysCacheIdentifier_OPEROID, oid.into())
+            .map(|tuple| PgOperator { oid, tuple })
+    }
+
+    fn form(&self) -> &pg_sys::FormData_pg_operator {
+        unsafe { self.tuple.form() }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.oid
+    }
+
+    pub fn name(&self) -> &str {
+        name_data_to_str(&self.form().oprname)
+    }
+
+    pub fn namespace_oid(&self) -> pg_sys::Oid {
+        self.form().oprnamespace
+    }
+
+    pub fn owner(&self) -> pg_sys::Oid {
+        self.form().oprowner
+    }
+
+    /// `'b'` for infix operators or `'l'` for prefix operators
+    pub fn kind(&self) -> char {
+        as_char(self.form().oprkind)
+    }
+
+    /// Does the operator support merge joins?
+    pub fn can_merge(&self) -> bool {
+        self.form().oprcanmerge
+    }
+
+    /// Does the operator support hash joins?
+    pub fn can_hash(&self) -> bool {
+        self.form().oprcanhash
+    }
+
+    /// The type of the left operand, which prefix operators don't have
+    pub fn left_type(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().oprleft)
+    }
+
+    pub fn right_type(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().oprright)
+    }
+
+    pub fn result_type(&self) -> pg_sys::Oid {
+        self.form().oprresult
+    }
+
+    pub fn commutator(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().oprcom)
+    }
+
+    pub fn negator(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().oprnegate)
+    }
+
+    /// The function implementing the operator
+    pub fn function(&self) -> pg_sys::Oid {
+        self.form().oprcode
+    }
+
+    /// The restriction selectivity estimator, if any
+    pub fn restrict_function(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().oprrest)
+    }
+
+    /// The join selectivity estimator, if any
+    pub fn join_function(&self) -> Option<pg_sys::Oid> {
+        valid_oid(self.form().oprjoin)
+    }
+}
@@ -0,0 +1,305 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Route [`tracing`](::tracing) spans and events into the Postgres log
+//!
+//! [`PgLogLayer`] is a `tracing-subscriber` layer that reports each event through `ereport`, at a
+//! [`PgLogLevel`] chosen by the event's level.  The fields of the spans the event happened in are
+//! attached as `CONTEXT` lines, innermost span first, just like Postgres' own error context.
+//!
+//! Events Postgres would discard because of `log_min_messages` and `client_min_messages` are
+//! filtered out before their fields are ever formatted.
+//!
+//! Postgres can only be called from the thread that loaded the extension, so events from other
+//! threads are ignored.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use pgx::prelude::*;
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     pgx::tracing::install();
+//! }
+//!
+//! #[pg_extern]
+//! fn import(file: &str) {
+//!     let _span = tracing::info_span!("import", file).entered();
+//!     tracing::warn!(row = 42, "skipping malformed row");
+//!     // WARNING:  skipping malformed row row=42
+//!     // CONTEXT:  import file=...
+//! }
+//! ```
+use crate::{pg_sys, PgLogLevel, PgSqlErrorCode};
+use ::tracing::field::{Field, Visit};
+use ::tracing::span::{Attributes, Id, Record};
+use ::tracing::subscriber::Interest;
+use ::tracing::{Event, Level, Metadata, Subscriber};
+use pg_sys::panic::ErrorReport;
+use std::fmt::{Debug, Write};
+use std::thread::ThreadId;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Install a [`PgLogLayer`], with its default level mapping, as the global `tracing` subscriber
+///
+/// This should be called once, from `_PG_init()`.  It panics if a global subscriber has already
+/// been installed.
+pub fn install() {
+    PgLogLayer::new().install()
+}
+
+/// A `tracing-subscriber` [`Layer`] that reports events through `ereport`
+///
+/// By default, tracing levels map to Postgres levels as follows:
+///
+/// | tracing | Postgres  |
+/// |---------|-----------|
+/// | `ERROR` | `WARNING` |
+/// | `WARN`  | `WARNING` |
+/// | `INFO`  | `LOG`     |
+/// | `DEBUG` | `DEBUG1`  |
+/// | `TRACE` | `DEBUG2`  |
+///
+/// Events can't be mapped to `ERROR` or above, as that would abort the transaction from inside
+/// the `tracing` machinery.  Return an error instead.
+pub struct PgLogLayer {
+    /// The Postgres level for each tracing level, indexed by `level_index()`
+    levels: [PgLogLevel; 5],
+    thread: ThreadId,
+}
+
+impl Default for PgLogLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PgLogLayer {
+    /// Create a layer that reports events raised on the current thread, which must be the thread
+    /// Postgres runs on
+    pub fn new() -> Self {
+        PgLogLayer {
+            levels: [
+                PgLogLevel::DEBUG2,
+                PgLogLevel::DEBUG1,
+                PgLogLevel::LOG,
+                PgLogLevel::WARNING,
+                PgLogLevel::WARNING,
+            ],
+            thread: std::thread::current().id(),
+        }
+    }
+
+    /// Report events at the tracing `level` as `pg_level`
+    ///
+    /// ## Panics
+    ///
+    /// If `pg_level` is [`PgLogLevel::ERROR`] or above
+    pub fn level(mut self, level: Level, pg_level: PgLogLevel) -> Self {
+        assert!(
+            pg_level < PgLogLevel::ERROR,
+            "tracing events cannot be reported as {:?}",
+            pg_level
+        );
+        self.levels[level_index(&level)] = pg_level;
+        self
+    }
+
+    /// A subscriber made of this layer and a [`tracing_error::ErrorLayer`], so that
+    /// [`SpanTrace`](tracing_error::SpanTrace)s can be captured
+    pub fn subscriber(self) -> impl Subscriber + Send + Sync + 'static {
+        tracing_subscriber::registry().with(tracing_error::ErrorLayer::default()).with(self)
+    }
+
+    /// Install [`PgLogLayer::subscriber`] as the global `tracing` subscriber
+    ///
+    /// This should be called once, from `_PG_init()`.  It panics if a global subscriber has already
+    /// been installed.
+    pub fn install(self) {
+        ::tracing::subscriber::set_global_default(self.subscriber())
+            .expect("a global tracing subscriber has already been installed");
+    }
+
+    fn pg_level(&self, level: &Level) -> PgLogLevel {
+        self.levels[level_index(level)]
+    }
+
+    fn on_postgres_thread(&self) -> bool {
+        std::thread::current().id() == self.thread
+    }
+}
+
+fn level_index(level: &Level) -> usize {
+    match *level {
+        Level::TRACE => 0,
+        Level::DEBUG => 1,
+        Level::INFO => 2,
+        Level::WARN => 3,
+        _ => 4, // Level::ERROR
+    }
+}
+
+/// The formatted fields of a span, stored in its extensions
+struct SpanFields(String);
+
+impl<S> Layer<S> for PgLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if metadata.is_event() {
+            // the log level GUCs can change at any time, so ask `enabled()` for every event
+            Interest::sometimes()
+        } else {
+            Interest::always()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        !metadata.is_event()
+            || (self.on_postgres_thread()
+                && message_level_is_interesting(self.pg_level(metadata.level())))
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span must exist in on_new_span");
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanFields(visitor.fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span must exist in on_record");
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+            let mut visitor = FieldVisitor { fields: std::mem::take(fields), ..Default::default() };
+            values.record(&mut visitor);
+            *fields = visitor.fields;
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !self.on_postgres_thread() {
+            return;
+        }
+
+        let metadata = event.metadata();
+        let level = self.pg_level(metadata.level());
+        let sqlerrcode = if level == PgLogLevel::WARNING {
+            PgSqlErrorCode::ERRCODE_WARNING
+        } else {
+            PgSqlErrorCode::ERRCODE_SUCCESSFUL_COMPLETION
+        };
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let mut message = visitor.message.unwrap_or_default();
+        if !visitor.fields.is_empty() {
+            if !message.is_empty() {
+                message.push(' ');
+            }
+            message.push_str(&visitor.fields);
+        }
+
+        let funcname = metadata.module_path().unwrap_or_else(|| metadata.target());
+        let mut report = ErrorReport::new(sqlerrcode, message, funcname).set_location(
+            metadata.file().unwrap_or("<unknown>"),
+            metadata.line().unwrap_or(0),
+            Some(funcname),
+        );
+
+        if let Some(scope) = ctx.event_scope(event) {
+            let context = scope
+                .map(|span| {
+                    let extensions = span.extensions();
+                    match extensions.get::<SpanFields>() {
+                        Some(SpanFields(fields)) if !fields.is_empty() => {
+                            format!("{} {}", span.name(), fields)
+                        }
+                        _ => span.name().to_string(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            if !context.is_empty() {
+                report = report.set_context(context.join("\n"));
+            }
+        }
+
+        report.report(level);
+    }
+}
+
+/// Formats an event's `message` field, and every other field as `name=value`
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: String,
+}
+
+impl FieldVisitor {
+    fn push(&mut self, field: &Field, value: std::fmt::Arguments<'_>) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            if !self.fields.is_empty() {
+                self.fields.push(' ');
+            }
+            let _ = write!(self.fields, "{}={}", field.name(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, format_args!("{}", value))
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.push(field, format_args!("{:?}", value))
+    }
+}
+
+/// Would Postgres send a message at `level` to either the server log or the client?
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+fn message_level_is_interesting(level: PgLogLevel) -> bool {
+    unsafe { pg_sys::message_level_is_interesting(level as i32) }
+}
+
+/// Would Postgres send a message at `level` to either the server log or the client?
+///
+/// This mirrors `message_level_is_interesting()` from Postgres 14's `elog.c`, which earlier
+/// versions don't have.
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+fn message_level_is_interesting(level: PgLogLevel) -> bool {
+    let elevel = level as i32;
+    if elevel >= pg_sys::ERROR as i32 {
+        return true;
+    }
+
+    unsafe {
+        // is_log_level_output()
+        let log_min_level = pg_sys::log_min_messages;
+        let to_server = if level == PgLogLevel::LOG || level == PgLogLevel::LOG_SERVER_ONLY {
+            log_min_level == pg_sys::LOG as i32 || log_min_level <= pg_sys::ERROR as i32
+        } else if log_min_level == pg_sys::LOG as i32 {
+            elevel >= pg_sys::FATAL as i32
+        } else {
+            elevel >= log_min_level
+        };
+
+        // should_output_to_client()
+        let to_client = pg_sys::whereToSendOutput == pg_sys::CommandDest_DestRemote
+            && level != PgLogLevel::LOG_SERVER_ONLY
+            && (elevel >= pg_sys::client_min_messages || level == PgLogLevel::INFO);
+
+        to_server || to_client
+    }
+}
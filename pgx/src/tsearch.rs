@@ -0,0 +1,280 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+/*!
+
+Custom [text search](https://www.postgresql.org/docs/current/textsearch.html) parsers and dictionaries.
+
+A parser splits a document into typed tokens, and dictionaries then normalize each token into
+lexemes, as mapped by a text search configuration.
+
+Parsers are created by implementing [`TsParser`] for a type and decorating the implementation with
+[`#[pg_ts_parser]`](pgx_macros::pg_ts_parser), and dictionaries by implementing [`TsDictionary`] and
+decorating it with [`#[pg_ts_dictionary]`](pgx_macros::pg_ts_dictionary).
+
+# Example
+
+```rust,no_run
+use pgx::prelude::*;
+
+pub struct WordParser {
+    text: String,
+    offset: usize,
+}
+
+#[pg_ts_parser]
+impl TsParser for WordParser {
+    const TOKEN_TYPES: &'static [TsTokenType] = &[TsTokenType::new(1, "word", "Word")];
+
+    fn start(text: &str) -> Self {
+        WordParser { text: text.to_string(), offset: 0 }
+    }
+
+    fn next_token(&mut self) -> Option<(i32, &str)> {
+        let rest = &self.text[self.offset..];
+        let start = rest.find(|c: char| !c.is_whitespace())?;
+        let len = rest[start..].find(char::is_whitespace).unwrap_or(rest.len() - start);
+        self.offset += start + len;
+        Some((1, &rest[start..start + len]))
+    }
+}
+
+pub struct UpperDictionary;
+
+#[pg_ts_dictionary]
+impl TsDictionary for UpperDictionary {
+    fn init(_options: &[(String, String)]) -> Self {
+        UpperDictionary
+    }
+
+    fn lexize(&self, token: &str) -> Option<Vec<String>> {
+        Some(vec![token.to_uppercase()])
+    }
+}
+```
+
+This creates SQL like so:
+
+```sql
+-- src/lib.rs:8
+-- my_extension::WordParser
+CREATE FUNCTION "word_parser_start"(internal, integer) RETURNS internal
+    STRICT LANGUAGE c AS 'MODULE_PATHNAME', 'word_parser_start_wrapper';
+CREATE FUNCTION "word_parser_gettoken"(internal, internal, internal) RETURNS internal
+    STRICT LANGUAGE c AS 'MODULE_PATHNAME', 'word_parser_gettoken_wrapper';
+CREATE FUNCTION "word_parser_end"(internal) RETURNS void
+    STRICT LANGUAGE c AS 'MODULE_PATHNAME', 'word_parser_end_wrapper';
+CREATE FUNCTION "word_parser_lextype"(internal) RETURNS internal
+    STRICT LANGUAGE c AS 'MODULE_PATHNAME', 'word_parser_lextype_wrapper';
+CREATE TEXT SEARCH PARSER "word_parser" (
+    START = "word_parser_start",
+    GETTOKEN = "word_parser_gettoken",
+    END = "word_parser_end",
+    LEXTYPES = "word_parser_lextype"
+);
+
+-- src/lib.rs:26
+-- my_extension::UpperDictionary
+CREATE FUNCTION "upper_dictionary_init"(internal) RETURNS internal
+    STRICT LANGUAGE c AS 'MODULE_PATHNAME', 'upper_dictionary_init_wrapper';
+CREATE FUNCTION "upper_dictionary_lexize"(internal, internal, internal, internal) RETURNS internal
+    STRICT LANGUAGE c AS 'MODULE_PATHNAME', 'upper_dictionary_lexize_wrapper';
+CREATE TEXT SEARCH TEMPLATE "upper_dictionary" (
+    INIT = "upper_dictionary_init",
+    LEXIZE = "upper_dictionary_lexize"
+);
+CREATE TEXT SEARCH DICTIONARY "upper_dictionary" (
+    TEMPLATE = "upper_dictionary"
+);
+```
+
+They can then be used in a configuration:
+
+```sql
+CREATE TEXT SEARCH CONFIGURATION words (PARSER = word_parser);
+ALTER TEXT SEARCH CONFIGURATION words ADD MAPPING FOR word WITH upper_dictionary;
+SELECT to_tsvector('words', 'hello world'); -- 'HELLO':1 'WORLD':2
+```
+
+*/
+use crate::{pg_getarg_datum_raw, pg_return_void, pg_sys, PgList, PgMemoryContexts};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+
+/// A type of token a [`TsParser`] produces, as listed by SQL's `ts_token_type()`
+///
+/// Configurations map token types, by their `alias`, to dictionaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TsTokenType {
+    /// The token type's id, which must be greater than zero
+    pub id: i32,
+    pub alias: &'static str,
+    pub description: &'static str,
+}
+
+impl TsTokenType {
+    pub const fn new(id: i32, alias: &'static str, description: &'static str) -> Self {
+        TsTokenType { id, alias, description }
+    }
+}
+
+/// A text search parser, created with [`#[pg_ts_parser]`](pgx_macros::pg_ts_parser)
+///
+/// Each document is parsed by a new value of the implementing type, created by
+/// [`TsParser::start()`], which is dropped once the document's tokens have been read.
+///
+/// The parser has no headline function, so `ts_headline()` can't be used with it.
+pub trait TsParser: Sized {
+    /// The name of the parser, which defaults to the implementing type's name in snake case
+    const NAME: &'static str;
+
+    /// The types of tokens the parser produces
+    const TOKEN_TYPES: &'static [TsTokenType];
+
+    /// Start parsing the document `text`
+    fn start(text: &str) -> Self;
+
+    /// The document's next token, as the `id` of its [`TsTokenType`] and its text, or `None` if
+    /// there are no more
+    fn next_token(&mut self) -> Option<(i32, &str)>;
+}
+
+/// A text search dictionary template, created with
+/// [`#[pg_ts_dictionary]`](pgx_macros::pg_ts_dictionary)
+///
+/// A dictionary of the same name is created from the template, with [`TsDictionary::OPTIONS`].
+/// Others can be created with different options, with `CREATE TEXT SEARCH DICTIONARY`.
+///
+/// Postgres caches each dictionary's value of the implementing type for the rest of the session,
+/// or until the dictionary is altered.
+pub trait TsDictionary: Sized {
+    /// The name of the template and its dictionary, which defaults to the implementing type's name
+    /// in snake case
+    const NAME: &'static str;
+
+    /// The options of the dictionary created with the template
+    const OPTIONS: &'static [(&'static str, &'static str)] = &[];
+
+    /// Create the dictionary from its options, as given to `CREATE TEXT SEARCH DICTIONARY`
+    ///
+    /// Option names are folded to lower case by Postgres.  Unknown options should raise an error.
+    fn init(options: &[(String, String)]) -> Self;
+
+    /// Normalize `token` into lexemes
+    ///
+    /// Returns `None` if the dictionary doesn't recognize the token, so the next dictionary in the
+    /// configuration is tried, or an empty `Vec` if the token is a stop word.  Each lexeme is an
+    /// alternative normalization of the token.
+    fn lexize(&self, token: &str) -> Option<Vec<String>>;
+}
+
+#[doc(hidden)]
+pub unsafe fn ts_parser_start<P: TsParser>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let text = pg_getarg_datum_raw(fcinfo, 0).cast_mut_ptr::<u8>();
+    let len = pg_getarg_datum_raw(fcinfo, 1).value() as c_int as usize;
+    let text = String::from_utf8_lossy(std::slice::from_raw_parts(text, len));
+
+    // dropped by the `end` function, or if it's never called, with the caller's memory context
+    let parser =
+        PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(Some(P::start(&text)));
+    pg_sys::Datum::from(parser)
+}
+
+#[doc(hidden)]
+pub unsafe fn ts_parser_gettoken<P: TsParser>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let parser = pg_getarg_datum_raw(fcinfo, 0).cast_mut_ptr::<Option<P>>();
+    let token = pg_getarg_datum_raw(fcinfo, 1).cast_mut_ptr::<*mut c_char>();
+    let token_len = pg_getarg_datum_raw(fcinfo, 2).cast_mut_ptr::<c_int>();
+
+    let parser = (*parser).as_mut().expect("text search parser has already ended");
+    match parser.next_token() {
+        Some((token_type, text)) => {
+            assert!(token_type > 0, "text search token types must be greater than zero");
+            // Postgres holds on to the token until the document is parsed, which might be longer
+            // than the parser lends it to us for
+            let copy = pg_sys::palloc(text.len() + 1) as *mut c_char;
+            std::ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, copy, text.len());
+            *copy.add(text.len()) = 0;
+            *token = copy;
+            *token_len = text.len() as c_int;
+            pg_sys::Datum::from(token_type)
+        }
+        None => pg_sys::Datum::from(0),
+    }
+}
+
+#[doc(hidden)]
+pub unsafe fn ts_parser_end<P: TsParser>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let parser = pg_getarg_datum_raw(fcinfo, 0).cast_mut_ptr::<Option<P>>();
+    *parser = None;
+    pg_return_void()
+}
+
+#[doc(hidden)]
+pub unsafe fn ts_parser_lextype<P: TsParser>(_fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    // terminated by an entry with a `lexid` of zero
+    let descrs =
+        pg_sys::palloc0(std::mem::size_of::<pg_sys::LexDescr>() * (P::TOKEN_TYPES.len() + 1))
+            as *mut pg_sys::LexDescr;
+    for (i, token_type) in P::TOKEN_TYPES.iter().enumerate() {
+        let descr = descrs.add(i);
+        (*descr).lexid = token_type.id;
+        (*descr).alias = PgMemoryContexts::CurrentMemoryContext.pstrdup(token_type.alias);
+        (*descr).descr = PgMemoryContexts::CurrentMemoryContext.pstrdup(token_type.description);
+    }
+    pg_sys::Datum::from(descrs)
+}
+
+#[doc(hidden)]
+pub unsafe fn ts_dictionary_init<D: TsDictionary>(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let list = PgList::<pg_sys::DefElem>::from_pg(
+        pg_getarg_datum_raw(fcinfo, 0).cast_mut_ptr::<pg_sys::List>(),
+    );
+    let options = list
+        .iter_ptr()
+        .map(|defel| {
+            let name = CStr::from_ptr((*defel).defname).to_string_lossy().into_owned();
+            let value = CStr::from_ptr(pg_sys::defGetString(defel)).to_string_lossy().into_owned();
+            (name, value)
+        })
+        .collect::<Vec<_>>();
+
+    // Postgres calls this in the dictionary's own memory context, which is deleted along with
+    // the dictionary's cache entry
+    let dictionary =
+        PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(D::init(&options));
+    pg_sys::Datum::from(dictionary)
+}
+
+#[doc(hidden)]
+pub unsafe fn ts_dictionary_lexize<D: TsDictionary>(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let dictionary = pg_getarg_datum_raw(fcinfo, 0).cast_mut_ptr::<D>();
+    let token = pg_getarg_datum_raw(fcinfo, 1).cast_mut_ptr::<u8>();
+    let len = pg_getarg_datum_raw(fcinfo, 2).value() as c_int as usize;
+    let token = String::from_utf8_lossy(std::slice::from_raw_parts(token, len));
+
+    match (*dictionary).lexize(&token) {
+        // a null pointer, not a SQL NULL, for an unrecognized token
+        None => pg_sys::Datum::from(0),
+        Some(lexemes) => {
+            // terminated by an entry with a null `lexeme`
+            let res = pg_sys::palloc0(std::mem::size_of::<pg_sys::TSLexeme>() * (lexemes.len() + 1))
+                as *mut pg_sys::TSLexeme;
+            for (i, lexeme) in lexemes.iter().enumerate() {
+                let res = res.add(i);
+                (*res).nvariant = (i + 1) as u16;
+                (*res).lexeme = PgMemoryContexts::CurrentMemoryContext.pstrdup(lexeme);
+            }
+            pg_sys::Datum::from(res)
+        }
+    }
+}
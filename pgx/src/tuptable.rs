@@ -0,0 +1,324 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Provides a safe wrapper around Postgres' `TupleTableSlot`, the executor's container for tuples
+use crate::heap_tuple::{PgHeapTuple, PgHeapTupleError};
+use crate::{
+    pg_sys, FromDatum, IntoDatum, PgMemoryContexts, PgTupleDesc, TryFromDatumError, WhoAllocated,
+};
+use std::marker::PhantomData;
+use std::num::NonZeroUsize;
+use std::ptr::NonNull;
+
+/// A [`pg_sys::TupleTableSlot`], which holds one tuple at a time in whatever form it was stored in
+///
+/// Executor hooks, custom scans, and table access methods are handed slots by Postgres, which can
+/// be wrapped with [`PgTupleSlot::from_pg()`].  Slots created with [`PgTupleSlot::new()`] are
+/// dropped along with the `PgTupleSlot`.
+///
+/// Attributes are only deformed from the stored tuple as they're asked for, so getting the first
+/// attribute of a wide tuple doesn't pay for the rest.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgx::prelude::*;
+/// use pgx::{PgTupleDesc, PgTupleSlot};
+/// use std::num::NonZeroUsize;
+///
+/// let tupdesc = PgTupleDesc::for_composite_type("dog").unwrap();
+/// let mut slot = PgTupleSlot::new(&tupdesc);
+/// slot.store_datums(["Brandy".into_datum(), 42.into_datum()]).unwrap();
+///
+/// let age = slot.get::<i32>(NonZeroUsize::new(2).unwrap());
+/// assert_eq!(age, Ok(Some(42)));
+/// ```
+pub struct PgTupleSlot<'a> {
+    slot: NonNull<pg_sys::TupleTableSlot>,
+    owned: bool,
+    _tupdesc: PhantomData<&'a pg_sys::TupleDescData>,
+}
+
+impl<'a> PgTupleSlot<'a> {
+    /// Create a new, empty, slot for tuples described by `tupdesc`
+    ///
+    /// On Postgres 12 and later, this is a "virtual" slot, which any form of tuple can be stored in.
+    pub fn new(tupdesc: &'a PgTupleDesc<'_>) -> Self {
+        unsafe {
+            #[cfg(feature = "pg11")]
+            let slot = pg_sys::MakeSingleTupleTableSlot(tupdesc.as_ptr());
+            #[cfg(not(feature = "pg11"))]
+            let slot = pg_sys::MakeSingleTupleTableSlot(tupdesc.as_ptr(), &pg_sys::TTSOpsVirtual);
+
+            PgTupleSlot {
+                slot: NonNull::new(slot).expect("MakeSingleTupleTableSlot returned NULL"),
+                owned: true,
+                _tupdesc: PhantomData,
+            }
+        }
+    }
+
+    /// Wrap a slot provided by Postgres, which is not dropped with the returned `PgTupleSlot`
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe as we cannot guarantee that the provided `slot` is valid, nor that
+    /// it and its tuple descriptor live for `'a`
+    pub unsafe fn from_pg(slot: *mut pg_sys::TupleTableSlot) -> Self {
+        PgTupleSlot {
+            slot: NonNull::new(slot).expect("slot is NULL"),
+            owned: false,
+            _tupdesc: PhantomData,
+        }
+    }
+
+    /// The tuple descriptor describing this slot's tuples
+    pub fn tuple_desc(&self) -> PgTupleDesc<'a> {
+        unsafe { PgTupleDesc::from_pg_unchecked(self.slot.as_ref().tts_tupleDescriptor) }
+    }
+
+    /// The number of attributes in this slot's tuples
+    pub fn natts(&self) -> usize {
+        unsafe { (*self.slot.as_ref().tts_tupleDescriptor).natts as usize }
+    }
+
+    /// Does this slot not hold a tuple?
+    pub fn is_empty(&self) -> bool {
+        let slot = unsafe { self.slot.as_ref() };
+        #[cfg(feature = "pg11")]
+        {
+            slot.tts_isempty
+        }
+        #[cfg(not(feature = "pg11"))]
+        {
+            slot.tts_flags as u32 & pg_sys::TTS_FLAG_EMPTY != 0
+        }
+    }
+
+    /// Empty the slot, freeing the tuple it holds if the slot owns it
+    pub fn clear(&mut self) {
+        unsafe {
+            #[cfg(feature = "pg11")]
+            pg_sys::ExecClearTuple(self.as_ptr());
+            #[cfg(not(feature = "pg11"))]
+            {
+                let slot = self.as_ptr();
+                let clear = (*(*slot).tts_ops).clear.expect("slot has no clear function");
+                pg_sys::ffi::pg_guard_ffi_boundary(|| clear(slot));
+            }
+        }
+    }
+
+    /// Copy the slot's tuple, and any by-reference values it points to, into the slot's own
+    /// memory, so it no longer depends on a buffer or on memory owned by someone else
+    pub fn materialize(&mut self) {
+        if self.is_empty() {
+            return;
+        }
+        unsafe {
+            #[cfg(feature = "pg11")]
+            pg_sys::ExecMaterializeSlot(self.as_ptr());
+            #[cfg(not(feature = "pg11"))]
+            {
+                let slot = self.as_ptr();
+                let materialize =
+                    (*(*slot).tts_ops).materialize.expect("slot has no materialize function");
+                pg_sys::ffi::pg_guard_ffi_boundary(|| materialize(slot));
+            }
+        }
+    }
+
+    /// Retrieve the raw Datum of the specified attribute, deforming the stored tuple as far as
+    /// that attribute if it hasn't been yet.  Returns `None` if the attribute is NULL.
+    ///
+    /// Attribute numbers start at 1, not 0.
+    ///
+    /// ## Panics
+    ///
+    /// If the slot is empty or `attno` is greater than the number of attributes
+    pub fn get_datum(&self, attno: NonZeroUsize) -> Option<pg_sys::Datum> {
+        if self.is_empty() {
+            panic!("cannot get attributes from an empty slot");
+        }
+        if attno.get() > self.natts() {
+            panic!("attribute number {} is out of range", attno);
+        }
+
+        let slot = self.slot.as_ptr();
+        unsafe {
+            #[cfg(feature = "pg11")]
+            {
+                let mut is_null = false;
+                let datum = pg_sys::slot_getattr(slot, attno.get() as _, &mut is_null);
+                (!is_null).then(|| datum)
+            }
+            #[cfg(not(feature = "pg11"))]
+            {
+                if attno.get() > (*slot).tts_nvalid as usize {
+                    pg_sys::slot_getsomeattrs_int(slot, attno.get() as _);
+                }
+                let i = attno.get() - 1;
+                (!*(*slot).tts_isnull.add(i)).then(|| *(*slot).tts_values.add(i))
+            }
+        }
+    }
+
+    /// Retrieve the value of the specified attribute, by index.
+    ///
+    /// Attribute numbers start at 1, not 0.
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::NoSuchAttributeNumber`] if the attribute does not exist
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if the Rust type of the `value` is not
+    /// compatible with the attribute's Postgres type
+    ///
+    /// ## Panics
+    ///
+    /// If the slot is empty
+    pub fn get<T: FromDatum + IntoDatum + 'static>(
+        &self,
+        attno: NonZeroUsize,
+    ) -> Result<Option<T>, TryFromDatumError> {
+        let tupdesc = self.tuple_desc();
+        let att =
+            tupdesc.get(attno.get() - 1).ok_or(TryFromDatumError::NoSuchAttributeNumber(attno))?;
+        match self.get_datum(attno) {
+            None => Ok(None),
+            Some(datum) => unsafe {
+                match T::type_oid() {
+                    record @ pg_sys::RECORDOID => T::try_from_datum(datum, false, record),
+                    _ => T::try_from_datum(datum, false, att.type_oid().value()),
+                }
+            },
+        }
+    }
+
+    /// Retrieve the value of the specified attribute, by name.
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::NoSuchAttributeName`] if the attribute does not exist
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if the Rust type of the `value` is not
+    /// compatible with the attribute's Postgres type
+    ///
+    /// ## Panics
+    ///
+    /// If the slot is empty
+    pub fn get_by_name<T: FromDatum + IntoDatum + 'static>(
+        &self,
+        attname: &str,
+    ) -> Result<Option<T>, TryFromDatumError> {
+        let attno = self
+            .tuple_desc()
+            .iter()
+            .position(|att| att.name() == attname)
+            .ok_or_else(|| TryFromDatumError::NoSuchAttributeName(attname.to_owned()))?;
+        self.get(NonZeroUsize::new(attno + 1).unwrap())
+    }
+
+    /// Replace the slot's tuple with one made of `datums`, which are copied into the slot's memory
+    ///
+    /// ## Errors
+    /// - [`PgHeapTupleError::IncorrectAttributeCount`] if the number of items in the iterator
+    /// does not match the number of attributes in the slot's tuple descriptor.
+    pub fn store_datums<I: IntoIterator<Item = Option<pg_sys::Datum>>>(
+        &mut self,
+        datums: I,
+    ) -> Result<(), PgHeapTupleError> {
+        let (datums, nulls): (Vec<_>, Vec<_>) =
+            datums.into_iter().map(|datum| (datum.unwrap_or(0.into()), datum.is_none())).unzip();
+        if datums.len() != self.natts() {
+            return Err(PgHeapTupleError::IncorrectAttributeCount(datums.len(), self.natts()));
+        }
+
+        self.clear();
+        unsafe {
+            let slot = self.slot.as_mut();
+            std::ptr::copy_nonoverlapping(datums.as_ptr(), slot.tts_values, datums.len());
+            std::ptr::copy_nonoverlapping(nulls.as_ptr(), slot.tts_isnull, nulls.len());
+            pg_sys::ExecStoreVirtualTuple(slot);
+        }
+        self.materialize();
+        Ok(())
+    }
+
+    /// Replace the slot's tuple with a copy of `tuple`
+    ///
+    /// ## Errors
+    /// - [`PgHeapTupleError::IncorrectAttributeCount`] if `tuple` doesn't have the same number of
+    /// attributes as the slot's tuple descriptor.
+    pub fn store_heap_tuple<AllocatedBy: WhoAllocated>(
+        &mut self,
+        tuple: &PgHeapTuple<'_, AllocatedBy>,
+    ) -> Result<(), PgHeapTupleError> {
+        if tuple.len() != self.natts() {
+            return Err(PgHeapTupleError::IncorrectAttributeCount(tuple.len(), self.natts()));
+        }
+
+        unsafe {
+            let slot = self.as_ptr();
+            let copy = PgMemoryContexts::For((*slot).tts_mcxt)
+                .switch_to(|_| pg_sys::heap_copytuple(tuple.as_ptr()));
+
+            // the slot frees the copy when it's cleared
+            #[cfg(feature = "pg11")]
+            pg_sys::ExecStoreTuple(copy, slot, pg_sys::InvalidBuffer as _, true);
+            #[cfg(not(feature = "pg11"))]
+            pg_sys::ExecForceStoreHeapTuple(copy, slot, true);
+        }
+        Ok(())
+    }
+
+    /// Copy the slot's tuple into a new [`PgHeapTuple`], or return `None` if the slot is empty
+    pub fn to_heap_tuple(&self) -> Option<PgHeapTuple<'a, crate::AllocatedByRust>> {
+        if self.is_empty() {
+            return None;
+        }
+
+        unsafe {
+            let slot = self.as_ptr();
+            #[cfg(feature = "pg11")]
+            let copy = pg_sys::ExecCopySlotTuple(slot);
+            #[cfg(not(feature = "pg11"))]
+            let copy = {
+                let copy_heap_tuple = (*(*slot).tts_ops)
+                    .copy_heap_tuple
+                    .expect("slot has no copy_heap_tuple function");
+                pg_sys::ffi::pg_guard_ffi_boundary(|| copy_heap_tuple(slot))
+            };
+            Some(PgHeapTuple::from_rust_heap_tuple(self.tuple_desc(), copy))
+        }
+    }
+
+    /// The wrapped [`pg_sys::TupleTableSlot`]
+    pub fn as_ptr(&self) -> *mut pg_sys::TupleTableSlot {
+        self.slot.as_ptr()
+    }
+
+    /// Consume this `PgTupleSlot`, returning the wrapped [`pg_sys::TupleTableSlot`] without dropping
+    /// it.  Its memory is freed along with the `MemoryContext` it was created in.
+    ///
+    /// A slot created with [`PgTupleSlot::new()`] keeps its tuple descriptor pinned, if the
+    /// descriptor is reference counted, such as one from the relcache or typcache.  Whoever takes
+    /// ownership of the slot must release it with [`pg_sys::ExecDropSingleTupleTableSlot()`], which
+    /// releases that pin too, otherwise Postgres warns of a leaked `TupleDesc` reference when the
+    /// transaction ends.
+    pub fn into_pg(self) -> *mut pg_sys::TupleTableSlot {
+        let slot = self.as_ptr();
+        std::mem::forget(self);
+        slot
+    }
+}
+
+impl Drop for PgTupleSlot<'_> {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe { pg_sys::ExecDropSingleTupleTableSlot(self.as_ptr()) }
+        }
+    }
+}
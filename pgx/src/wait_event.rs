@@ -0,0 +1,263 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Wait for the process latch, postmaster death, and socket readiness at the same time
+//!
+//! A [`WaitEventSet`] is a safe wrapper around Postgres' `WaitEventSet` API.  It's built with a
+//! [`WaitEventSetBuilder`] and can be used from background workers and regular backends alike.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use pgx::bgworkers::BackgroundWorker;
+//! use pgx::wait_event::{WaitEventSetBuilder, WaitEventSource, WaitEvents};
+//! use std::os::unix::net::UnixStream;
+//! use std::time::Duration;
+//!
+//! let socket = UnixStream::connect("/tmp/my_service.sock").unwrap();
+//! let mut set = WaitEventSetBuilder::new()
+//!     .latch()
+//!     .postmaster_death()
+//!     .socket(&socket, WaitEvents::SOCKET_READABLE)
+//!     .build();
+//!
+//! loop {
+//!     for event in set.wait(Some(Duration::from_secs(1))) {
+//!         match event.source {
+//!             WaitEventSource::PostmasterDeath => return,
+//!             WaitEventSource::Latch if BackgroundWorker::sigterm_received() => return,
+//!             WaitEventSource::Socket(_) => { /* read from the socket */ }
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+use crate::pg_sys;
+use std::os::raw::c_int;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::Duration;
+
+bitflags! {
+    /// The events a [`WaitEventSet`] member can wait for, and that can be reported as having occurred
+    pub struct WaitEvents: u32 {
+        const LATCH_SET         = pg_sys::WL_LATCH_SET;
+        const SOCKET_READABLE   = pg_sys::WL_SOCKET_READABLE;
+        const SOCKET_WRITEABLE  = pg_sys::WL_SOCKET_WRITEABLE;
+        const POSTMASTER_DEATH  = pg_sys::WL_POSTMASTER_DEATH;
+    }
+}
+
+/// What a [`WaitEventSet`] member waits on
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WaitEventSource {
+    /// This process' latch, `MyLatch`
+    Latch,
+    /// The death of the postmaster
+    PostmasterDeath,
+    /// A socket, by its file descriptor
+    Socket(RawFd),
+}
+
+/// An event reported by [`WaitEventSet::wait`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct OccurredEvent {
+    /// The position of the member in the set, in the order it was added to the builder
+    pub position: usize,
+    pub source: WaitEventSource,
+    /// Which of the events the member was waiting for have occurred
+    pub events: WaitEvents,
+}
+
+/// A builder for a [`WaitEventSet`]
+///
+/// Members are numbered in the order they're added, which is the `position` reported for them in
+/// [`OccurredEvent`] and used by [`WaitEventSet::modify_socket`].
+pub struct WaitEventSetBuilder {
+    members: Vec<(WaitEventSource, WaitEvents)>,
+    wait_event_info: u32,
+}
+
+impl Default for WaitEventSetBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaitEventSetBuilder {
+    pub fn new() -> Self {
+        WaitEventSetBuilder { members: Vec::new(), wait_event_info: pg_sys::PG_WAIT_EXTENSION }
+    }
+
+    /// Wake up when this process' latch is set.  The latch is reset, and pending interrupts are
+    /// processed, before [`WaitEventSet::wait`] returns
+    pub fn latch(mut self) -> Self {
+        self.members.push((WaitEventSource::Latch, WaitEvents::LATCH_SET));
+        self
+    }
+
+    /// Wake up if the postmaster dies.  This is ignored when not running under a postmaster, such
+    /// as in single-user mode
+    pub fn postmaster_death(mut self) -> Self {
+        self.members.push((WaitEventSource::PostmasterDeath, WaitEvents::POSTMASTER_DEATH));
+        self
+    }
+
+    /// Wake up when `socket` is ready for any of `events`, which may only contain
+    /// [`WaitEvents::SOCKET_READABLE`] and [`WaitEvents::SOCKET_WRITEABLE`].  The socket must stay
+    /// open for as long as the [`WaitEventSet`] exists.
+    ///
+    /// A socket that's already a member isn't added again, as Postgres can't wait on the same one
+    /// twice.  Instead it also waits for `events`, and keeps its original position.
+    pub fn socket<S: AsRawFd + ?Sized>(mut self, socket: &S, events: WaitEvents) -> Self {
+        assert!(
+            (WaitEvents::SOCKET_READABLE | WaitEvents::SOCKET_WRITEABLE).contains(events)
+                && !events.is_empty(),
+            "sockets can only wait for readable and/or writeable events"
+        );
+        let source = WaitEventSource::Socket(socket.as_raw_fd());
+        match self.members.iter_mut().find(|(member, _)| *member == source) {
+            Some((_, existing)) => *existing |= events,
+            None => self.members.push((source, events)),
+        }
+        self
+    }
+
+    /// The wait event reported in `pg_stat_activity` while waiting.  Defaults to
+    /// `pg_sys::PG_WAIT_EXTENSION`
+    pub fn wait_event_info(mut self, wait_event_info: u32) -> Self {
+        self.wait_event_info = wait_event_info;
+        self
+    }
+
+    /// Create the [`WaitEventSet`]
+    pub fn build(self) -> WaitEventSet {
+        unsafe {
+            // the set is freed by our `Drop` impl, so it must outlive any transient memory context
+            let set = pg_sys::CreateWaitEventSet(pg_sys::TopMemoryContext, self.members.len() as _);
+            let mut positions = Vec::with_capacity(self.members.len());
+            for &(source, events) in &self.members {
+                let pos = match source {
+                    WaitEventSource::Latch => pg_sys::AddWaitEventToSet(
+                        set,
+                        events.bits(),
+                        pg_sys::PGINVALID_SOCKET,
+                        pg_sys::MyLatch,
+                        std::ptr::null_mut(),
+                    ),
+                    WaitEventSource::PostmasterDeath if !pg_sys::IsUnderPostmaster => -1,
+                    WaitEventSource::PostmasterDeath => pg_sys::AddWaitEventToSet(
+                        set,
+                        events.bits(),
+                        pg_sys::PGINVALID_SOCKET,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                    ),
+                    WaitEventSource::Socket(fd) => pg_sys::AddWaitEventToSet(
+                        set,
+                        events.bits(),
+                        fd,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                    ),
+                };
+                positions.push(pos);
+            }
+
+            WaitEventSet {
+                set,
+                members: self.members,
+                positions,
+                wait_event_info: self.wait_event_info,
+            }
+        }
+    }
+}
+
+/// A set of events to wait for, created with a [`WaitEventSetBuilder`]
+pub struct WaitEventSet {
+    set: *mut pg_sys::WaitEventSet,
+    members: Vec<(WaitEventSource, WaitEvents)>,
+    /// The position Postgres assigned each member, or -1 if it wasn't added
+    positions: Vec<c_int>,
+    wait_event_info: u32,
+}
+
+impl WaitEventSet {
+    /// Block until at least one member's events occur, or until `timeout` elapses.  An empty
+    /// result means the timeout was reached.  `None` waits forever.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Vec<OccurredEvent> {
+        let timeout = match timeout {
+            Some(t) => t.as_millis().try_into().expect("timeout is too large"),
+            None => -1,
+        };
+        let mut occurred = vec![pg_sys::WaitEvent::default(); self.members.len().max(1)];
+
+        let n = unsafe {
+            pg_sys::WaitEventSetWait(
+                self.set,
+                timeout,
+                occurred.as_mut_ptr(),
+                occurred.len() as _,
+                self.wait_event_info,
+            )
+        };
+
+        let events = occurred[..n as usize]
+            .iter()
+            .filter_map(|event| {
+                let position = self.positions.iter().position(|&p| p == event.pos)?;
+                Some(OccurredEvent {
+                    position,
+                    source: self.members[position].0,
+                    events: WaitEvents::from_bits_truncate(event.events),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if events.iter().any(|e| e.source == WaitEventSource::Latch) {
+            unsafe {
+                pg_sys::ResetLatch(pg_sys::MyLatch);
+            }
+            pg_sys::check_for_interrupts!();
+        }
+        events
+    }
+
+    /// Change the events the socket at `position` waits for
+    pub fn modify_socket(&mut self, position: usize, events: WaitEvents) {
+        let (source, current) = &mut self.members[position];
+        assert!(
+            matches!(source, WaitEventSource::Socket(_)),
+            "the member at position {} is not a socket",
+            position
+        );
+        assert!(
+            (WaitEvents::SOCKET_READABLE | WaitEvents::SOCKET_WRITEABLE).contains(events)
+                && !events.is_empty(),
+            "sockets can only wait for readable and/or writeable events"
+        );
+        unsafe {
+            pg_sys::ModifyWaitEvent(
+                self.set,
+                self.positions[position],
+                events.bits(),
+                std::ptr::null_mut(),
+            );
+        }
+        *current = events;
+    }
+}
+
+impl Drop for WaitEventSet {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::FreeWaitEventSet(self.set);
+        }
+    }
+}